@@ -0,0 +1,27 @@
+//! Convert a dump of the USB stream (or a trace file from the SD card) produced by
+//! `libdragon::trace` into a Chrome trace-event JSON file.
+//!
+//! Usage: `n64trace <dump> <output.json>`
+
+use std::{env, process::ExitCode};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} <dump> <output.json>", args[0]);
+        return ExitCode::FAILURE;
+    }
+    match libdragon_build::trace::convert(&args[1], &args[2]) {
+        Ok(trace) => {
+            println!("{} events written to {}", trace.events.len(), args[2]);
+            if trace.skipped > 0 {
+                println!("{} invalid lines skipped", trace.skipped);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}: {}", args[1], error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Host-side inspection of `.font64` files built by `mkfont`.
+//!
+//! The parser itself is shared with the `libdragon` crate (see `libdragon::font64`), so the
+//! coverage computed here matches what the N64 sees at runtime.
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+pub use crate::font64::{Atlas, FontData, FontError, Glyph, Range};
+use crate::Result;
+
+/// A character that a font cannot render, with the string it was found in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingChar {
+    /// Index of the string in the list passed to [check_strings]
+    pub string: usize,
+    /// The character that has no glyph
+    pub c:      char,
+}
+
+/// Convert a parser error to an [std::io::Error]
+pub fn to_io_error(error: FontError) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid font file: {:?}", error),
+    )
+}
+
+/// A `.font64` file loaded in memory
+pub struct FontFile {
+    data: Vec<u8>,
+}
+
+impl FontFile {
+    /// Read and validate a font file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let data = fs::read(path)?;
+        FontData::parse(&data).map_err(to_io_error)?;
+        Ok(Self { data })
+    }
+
+    /// Access the parsed font
+    pub fn font(&self) -> FontData<'_> {
+        // validated in load()
+        FontData::parse(&self.data).unwrap()
+    }
+}
+
+/// Check that every string can be rendered with the font at `path`.
+///
+/// Returns the list of characters without a glyph (each distinct character is reported once
+/// per string). An empty list means all the strings are renderable.
+pub fn check_strings<'s, P, I>(path: P, strings: I) -> Result<Vec<MissingChar>>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = &'s str>,
+{
+    let file = FontFile::load(path)?;
+    let font = file.font();
+    let mut missing = Vec::new();
+    for (index, text) in strings.into_iter().enumerate() {
+        let mut seen: Vec<char> = Vec::new();
+        for c in font.missing_chars(text) {
+            if !seen.contains(&c) {
+                seen.push(c);
+                missing.push(MissingChar { string: index, c });
+            }
+        }
+    }
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixtures(extension: &str) -> Vec<PathBuf> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .map(|entries| entries.filter_map(|e| Some(e.ok()?.path())).collect())
+            .unwrap_or_default();
+        files.retain(|p| p.extension().is_some_and(|e| e == extension));
+        files.sort();
+        assert!(
+            !files.is_empty(),
+            "no .{} fixtures, see tests/fixtures/README.md",
+            extension
+        );
+        files
+    }
+
+    #[test]
+    #[ignore = "needs fonts built by mkfont, see tests/fixtures/README.md"]
+    fn mkfont_fixtures() {
+        for path in fixtures("font64") {
+            let file = FontFile::load(&path).unwrap();
+            let font = file.font();
+            assert!(font.ascent() > 0 && font.descent() <= 0, "{:?}", path);
+            assert_eq!(font.missing_chars("Hello, World! 0123456789").count(), 0);
+
+            // every glyph must lie within its atlas sprite (width and height are the first two
+            // fields of sprite_t)
+            let atlases: Vec<&[u8]> = font.atlases().map(|a| font.atlas_data(a)).collect();
+            for c in (0x21u8..0x7F).map(char::from) {
+                let glyph = font.glyph_for(c).unwrap();
+                let sprite = atlases[glyph.natlas as usize];
+                let width = u16::from_be_bytes([sprite[0], sprite[1]]) as i32;
+                let height = u16::from_be_bytes([sprite[2], sprite[3]]) as i32;
+                assert!(
+                    glyph.s as i32 + glyph.width() <= width,
+                    "{:?} {:?}",
+                    path,
+                    c
+                );
+                assert!(
+                    glyph.t as i32 + glyph.height() <= height,
+                    "{:?} {:?}",
+                    path,
+                    c
+                );
+                assert!(glyph.xadvance > 0.0);
+            }
+            assert!(font.line_width("AVAVA", 0.0) > 0.0);
+        }
+    }
+}
@@ -2,6 +2,88 @@ use execute::Execute;
 use glob::glob;
 use std::{env, fs::File, io::Write, path::PathBuf, process::Command};
 
+pub mod font;
+#[path = "../../libdragon/src/font64.rs"]
+pub mod font64;
+#[path = "../../libdragon/src/debug/image.rs"]
+mod image;
+mod json;
+pub mod model64;
+/// The escape codes of the text engine, used by [font64] to skip them
+#[path = "../../libdragon/src/rdpq"]
+pub mod rdpq {
+    pub mod escape;
+}
+pub mod screenshot;
+pub mod tiled;
+/// The `.tilemap` format, shared with `libdragon::tilemap`
+#[path = "../../libdragon/src/tilemap"]
+pub mod tilemap {
+    pub mod format;
+    #[cfg(test)]
+    pub mod layer;
+}
+pub mod trace;
+
+// Core-only modules of libdragon, compiled here so that they are tested on the host
+extern crate alloc;
+#[cfg(test)]
+#[path = "../../libdragon/src/anim/clip.rs"]
+pub mod anim_clip;
+#[cfg(test)]
+#[path = "../../libdragon/src/cull"]
+pub mod cull {
+    pub mod frustum;
+}
+#[cfg(test)]
+#[path = "../../libdragon/src/debug/hud"]
+pub mod hud {
+    pub mod stats;
+}
+#[cfg(test)]
+#[path = "../../libdragon/src/fixed.rs"]
+pub mod fixed;
+#[cfg(test)]
+#[path = "../../libdragon/src/frame"]
+pub mod frame {
+    pub mod timestep;
+}
+#[cfg(test)]
+#[path = "../../libdragon/src/math.rs"]
+pub mod math;
+#[cfg(test)]
+#[path = "../../libdragon/src/rdpq/mesh"]
+pub mod mesh {
+    pub mod clip;
+}
+#[cfg(test)]
+#[path = "../../libdragon/src/model64/timeline.rs"]
+pub mod model64_timeline;
+#[cfg(test)]
+#[path = "../../libdragon/src/mpeg2"]
+pub mod mpeg2 {
+    pub mod stream;
+}
+#[cfg(test)]
+#[path = "../../libdragon/src/palette/effects.rs"]
+pub mod palette_effects;
+#[cfg(test)]
+#[path = "../../libdragon/src/particles"]
+pub mod particles {
+    pub mod simulation;
+}
+#[cfg(test)]
+#[path = "../../libdragon/src/pixel"]
+pub mod pixel {
+    pub mod codec;
+    pub mod view;
+}
+#[cfg(test)]
+#[path = "../../libdragon/src/yuv"]
+pub mod yuv {
+    pub mod layout;
+}
+
 pub type Result<T> = std::io::Result<T>;
 
 #[derive(Debug, Clone, Default)]
@@ -0,0 +1,737 @@
+//! Conversion of [glTF 2.0](https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html) models,
+//! in the binary (`.glb`) or JSON (`.gltf`) format, to a [Model].
+//!
+//! Every node, mesh, skin and animation of the file is converted. Vertex positions and texture
+//! coordinates are stored as [DataType::HalfFixed], colors as [DataType::UnsignedByte] and
+//! normals as [DataType::Byte]. Skinned vertices get the joint with the largest weight as their
+//! matrix index. If the scene has several root nodes, a root node without a name is added as
+//! their parent. Morph targets and sparse accessors are not supported.
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use super::{
+    Animation, Attribute, DataType, Indices, Joint, Mesh, Mode, Model, Node, Primitive, Skin,
+    Target, Track, Transform,
+};
+use crate::{
+    json::{json_num, Json},
+    Result,
+};
+
+fn invalid(msg: &str) -> Error { Error::new(ErrorKind::InvalidData, msg.to_owned()) }
+
+/// Number of fractional bits of [DataType::HalfFixed] values, see
+/// `gl::VertexHalfFixedPrecisionN64`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Precision {
+    /// The largest precision that fits the values of each primitive
+    #[default]
+    Auto,
+    /// A fixed number of fractional bits, from 0 to 15
+    Bits(u32),
+}
+
+/// How the textures of the primitives are referenced in [Primitive::texture]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum TextureRefs {
+    /// No texture references
+    None,
+    /// Index of the image of the base color texture in the glTF file
+    #[default]
+    ImageIndex,
+    /// Index in this list of the name of the image of the base color texture, or of its file
+    /// name without extension if it has no name. Images that are not in the list are an error.
+    ByName(Vec<String>),
+}
+
+/// Reduction of the number of animation keyframes
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum AnimCompression {
+    /// Keep all the keyframes
+    #[default]
+    None,
+    /// Remove the keyframes that interpolating their neighbours reproduces with an error of at
+    /// most `max_error` on each component
+    Lossy { max_error: f32 },
+}
+
+/// Conversion options
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Options {
+    pub vertex_precision:   Precision,
+    pub texcoord_precision: Precision,
+    pub textures:           TextureRefs,
+    pub anim_compression:   AnimCompression,
+}
+
+/// Load a glTF file, choosing the format from the file extension (`.glb` or `.gltf`)
+pub fn load<P: AsRef<Path>>(path: P, options: &Options) -> Result<Model> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    if path.extension().is_some_and(|e| e == "glb") {
+        parse_glb(&fs::read(path)?, dir, options)
+    } else {
+        parse_gltf(&fs::read_to_string(path)?, dir, options)
+    }
+}
+
+/// Parse a binary glTF file. External buffers are looked up relative to `dir`.
+pub fn parse_glb(data: &[u8], dir: &Path, options: &Options) -> Result<Model> {
+    let word = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or_else(|| invalid("truncated GLB file"))
+    };
+    if !data.starts_with(b"glTF") || word(4)? != 2 {
+        return Err(invalid("not a glTF 2.0 binary file"));
+    }
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset < data.len().min(word(8)? as usize) {
+        let (len, kind) = (word(offset)? as usize, word(offset + 4)?);
+        let chunk = data
+            .get(offset + 8..offset + 8 + len)
+            .ok_or_else(|| invalid("truncated GLB file"))?;
+        match kind {
+            0x4E4F_534A => json = Some(chunk),
+            0x004E_4942 => bin = Some(chunk.to_vec()),
+            _ => {}
+        }
+        offset += 8 + len;
+    }
+    let json = json.ok_or_else(|| invalid("GLB file without JSON chunk"))?;
+    let text = std::str::from_utf8(json).map_err(|_| invalid("invalid UTF-8 in glTF JSON"))?;
+    Gltf::new(Json::parse(text)?, dir, bin)?.convert(options)
+}
+
+/// Parse a glTF file in the JSON format. External buffers are looked up relative to `dir`.
+pub fn parse_gltf(text: &str, dir: &Path, options: &Options) -> Result<Model> {
+    Gltf::new(Json::parse(text)?, dir, None)?.convert(options)
+}
+
+struct Gltf {
+    root:    Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+// An accessor read as floats (or integers, exactly), `components` per element
+struct Accessor {
+    components: usize,
+    values:     Vec<f64>,
+}
+
+impl Accessor {
+    fn len(&self) -> usize { self.values.len() / self.components }
+
+    fn element(&self, i: usize) -> &[f64] {
+        &self.values[i * self.components..(i + 1) * self.components]
+    }
+}
+
+fn array<'a>(value: &'a Json, key: &str) -> &'a [Json] {
+    value.get(key).and_then(Json::as_array).unwrap_or(&[])
+}
+
+fn optional_num(value: &Json, key: &str) -> Result<Option<usize>> {
+    value.get(key).map(|_| json_num(value, key)).transpose()
+}
+
+fn floats<const N: usize>(value: &Json, key: &str, default: [f32; N]) -> Result<[f32; N]> {
+    let Some(values) = value.get(key) else {
+        return Ok(default);
+    };
+    let values = values
+        .as_array()
+        .filter(|a| a.len() == N)
+        .ok_or_else(|| invalid(&format!("invalid {}", key)))?;
+    let mut out = default;
+    for (out, v) in out.iter_mut().zip(values) {
+        *out = v
+            .as_f64()
+            .ok_or_else(|| invalid(&format!("invalid {}", key)))? as f32;
+    }
+    Ok(out)
+}
+
+fn base64(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return Err(invalid("invalid base64 buffer")),
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+impl Gltf {
+    fn new(root: Json, dir: &Path, mut bin: Option<Vec<u8>>) -> Result<Self> {
+        let mut buffers = Vec::new();
+        for buffer in array(&root, "buffers") {
+            let data = match buffer.get("uri").and_then(Json::as_str) {
+                Some(uri) if uri.starts_with("data:") => {
+                    let (_, data) = uri
+                        .split_once(";base64,")
+                        .ok_or_else(|| invalid("unsupported data URI in glTF buffer"))?;
+                    base64(data)?
+                }
+                Some(uri) => fs::read(dir.join(uri))?,
+                None => bin
+                    .take()
+                    .ok_or_else(|| invalid("glTF buffer without data"))?,
+            };
+            if data.len() < json_num::<usize>(buffer, "byteLength")? {
+                return Err(invalid("glTF buffer shorter than its byteLength"));
+            }
+            buffers.push(data);
+        }
+        Ok(Self { root, buffers })
+    }
+
+    fn item(&self, key: &str, index: usize) -> Result<&Json> {
+        array(&self.root, key)
+            .get(index)
+            .ok_or_else(|| invalid(&format!("invalid index in glTF {}", key)))
+    }
+
+    fn accessor(&self, index: usize) -> Result<Accessor> {
+        let accessor = self.item("accessors", index)?;
+        if accessor.get("sparse").is_some() {
+            return Err(invalid("sparse glTF accessors are not supported"));
+        }
+        let count: usize = json_num(accessor, "count")?;
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            Some("MAT4") => 16,
+            _ => return Err(invalid("unsupported glTF accessor type")),
+        };
+        let component_type: u32 = json_num(accessor, "componentType")?;
+        let normalized = accessor.get("normalized").and_then(Json::as_bool) == Some(true);
+        let (size, max) = match component_type {
+            5120 => (1, 127.0),
+            5121 => (1, 255.0),
+            5122 => (2, 32767.0),
+            5123 => (2, 65535.0),
+            5125 => (4, 1.0),
+            5126 => (4, 1.0),
+            _ => return Err(invalid("unsupported glTF component type")),
+        };
+        let Some(view) = optional_num(accessor, "bufferView")? else {
+            // no buffer view: all zeros
+            return Ok(Accessor {
+                components,
+                values: vec![0.0; count * components],
+            });
+        };
+        let view = self.item("bufferViews", view)?;
+        let buffer = self
+            .buffers
+            .get(json_num::<usize>(view, "buffer")?)
+            .ok_or_else(|| invalid("invalid glTF buffer index"))?;
+        let start = optional_num(view, "byteOffset")?.unwrap_or(0)
+            + optional_num(accessor, "byteOffset")?.unwrap_or(0);
+        let stride = optional_num(view, "byteStride")?.unwrap_or(size * components);
+
+        let mut values = Vec::with_capacity(count * components);
+        for i in 0..count {
+            for c in 0..components {
+                let at = start + i * stride + c * size;
+                let b = buffer
+                    .get(at..at + size)
+                    .ok_or_else(|| invalid("glTF accessor out of its buffer"))?;
+                let v = match component_type {
+                    5120 => b[0] as i8 as f64,
+                    5121 => b[0] as f64,
+                    5122 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    5123 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                };
+                values.push(if normalized { (v / max).max(-1.0) } else { v });
+            }
+        }
+        Ok(Accessor { components, values })
+    }
+
+    fn convert(&self, options: &Options) -> Result<Model> {
+        let mut model = Model::default();
+        for node in array(&self.root, "nodes") {
+            model.nodes.push(Node {
+                name:      node.get("name").and_then(Json::as_str).map(str::to_owned),
+                mesh:      optional_num(node, "mesh")?,
+                skin:      optional_num(node, "skin")?,
+                transform: node_transform(node)?,
+                parent:    None,
+                children:  array(node, "children")
+                    .iter()
+                    .map(|c| {
+                        c.as_f64()
+                            .filter(|c| c.fract() == 0.0 && *c >= 0.0)
+                            .map(|c| c as usize)
+                            .ok_or_else(|| invalid("invalid glTF node child"))
+                    })
+                    .collect::<Result<_>>()?,
+            });
+        }
+        for n in 0..model.nodes.len() {
+            for c in model.nodes[n].children.clone() {
+                let child = model
+                    .nodes
+                    .get_mut(c)
+                    .ok_or_else(|| invalid("invalid glTF node child"))?;
+                child.parent = Some(n);
+            }
+        }
+
+        let scene = optional_num(&self.root, "scene")?.unwrap_or(0);
+        let roots: Vec<usize> = match array(&self.root, "scenes").get(scene) {
+            Some(scene) => array(scene, "nodes")
+                .iter()
+                .filter_map(|n| n.as_f64().map(|n| n as usize))
+                .collect(),
+            None => (0..model.nodes.len())
+                .filter(|&n| model.nodes[n].parent.is_none())
+                .collect(),
+        };
+        if let [root] = roots[..] {
+            model.root_node = root;
+        } else {
+            model.root_node = model.nodes.len();
+            for &r in &roots {
+                model.nodes[r].parent = Some(model.root_node);
+            }
+            model.nodes.push(Node {
+                children: roots,
+                ..Default::default()
+            });
+        }
+
+        for mesh in array(&self.root, "meshes") {
+            let primitives = array(mesh, "primitives")
+                .iter()
+                .map(|p| self.primitive(p, options))
+                .collect::<Result<_>>()?;
+            model.meshes.push(Mesh { primitives });
+        }
+
+        for skin in array(&self.root, "skins") {
+            let matrices = optional_num(skin, "inverseBindMatrices")?
+                .map(|a| self.accessor(a))
+                .transpose()?;
+            let mut joints = Vec::new();
+            for (i, joint) in array(skin, "joints").iter().enumerate() {
+                let mut inverse_bind_matrix = [0.0; 16];
+                for (c, v) in inverse_bind_matrix.iter_mut().enumerate() {
+                    *v = match &matrices {
+                        Some(m) if m.components == 16 && i < m.len() => m.element(i)[c] as f32,
+                        Some(_) => return Err(invalid("invalid glTF inverseBindMatrices")),
+                        None => (c % 5 == 0) as u32 as f32,
+                    };
+                }
+                joints.push(Joint {
+                    node: joint
+                        .as_f64()
+                        .map(|n| n as usize)
+                        .filter(|&n| n < model.nodes.len())
+                        .ok_or_else(|| invalid("invalid glTF joint"))?,
+                    inverse_bind_matrix,
+                });
+            }
+            model.skins.push(Skin { joints });
+        }
+
+        for anim in array(&self.root, "animations") {
+            model.anims.push(self.animation(anim, options)?);
+        }
+
+        let meshes = model.meshes.len();
+        let skins = model.skins.len();
+        if model
+            .nodes
+            .iter()
+            .any(|n| n.mesh.is_some_and(|m| m >= meshes) || n.skin.is_some_and(|s| s >= skins))
+        {
+            return Err(invalid("invalid mesh or skin index in glTF node"));
+        }
+        Ok(model)
+    }
+
+    fn primitive(&self, primitive: &Json, options: &Options) -> Result<Primitive> {
+        let mode = match optional_num(primitive, "mode")? {
+            Some(mode) => Mode::from_gl(mode as u32).ok_or_else(|| invalid("invalid glTF mode"))?,
+            None => Mode::Triangles,
+        };
+        let attributes = primitive
+            .get("attributes")
+            .ok_or_else(|| invalid("glTF primitive without attributes"))?;
+        let attribute = |name: &str| {
+            optional_num(attributes, name)?
+                .map(|a| self.accessor(a))
+                .transpose()
+        };
+        let position =
+            attribute("POSITION")?.ok_or_else(|| invalid("glTF primitive without POSITION"))?;
+        let count = position.len();
+        let check = |a: Option<Accessor>| match a {
+            Some(a) if a.len() != count => Err(invalid("glTF attributes of different lengths")),
+            a => Ok(a),
+        };
+
+        let vertex_precision = precision(options.vertex_precision, &position.values);
+        let texcoord = check(attribute("TEXCOORD_0")?)?;
+        let texcoord_precision = texcoord
+            .as_ref()
+            .map_or(0, |t| precision(options.texcoord_precision, &t.values));
+        let color = check(attribute("COLOR_0")?)?.map(|c| {
+            let values = (0..count).flat_map(|i| {
+                let c = c.element(i);
+                [c[0], c[1], c[2], c.get(3).copied().unwrap_or(1.0)]
+            });
+            attribute_data(4, DataType::UnsignedByte, values.map(|v| v * 255.0))
+        });
+        let normal = check(attribute("NORMAL")?)?
+            .map(|n| attribute_data(3, DataType::Byte, n.values.iter().map(|v| v * 127.0)));
+        let joints = check(attribute("JOINTS_0")?)?;
+        let weights = check(attribute("WEIGHTS_0")?)?;
+        let mtx_index = match (joints, weights) {
+            (Some(joints), Some(weights)) => {
+                let values = (0..count).map(|i| {
+                    let w = weights.element(i);
+                    let strongest = (0..w.len())
+                        .max_by(|&a, &b| w[a].total_cmp(&w[b]))
+                        .unwrap_or(0);
+                    joints.element(i)[strongest]
+                });
+                Some(attribute_data(1, DataType::UnsignedByte, values))
+            }
+            _ => None,
+        };
+        let scale = (1u32 << vertex_precision) as f64;
+        let texcoord_scale = (1u32 << texcoord_precision) as f64;
+
+        let indices = match optional_num(primitive, "indices")? {
+            Some(i) => {
+                let accessor = self.accessor(i)?;
+                let indices: Vec<u32> = accessor.values.iter().map(|&i| i as u32).collect();
+                if indices.iter().any(|&i| i as usize >= count) {
+                    return Err(invalid("glTF index out of range"));
+                }
+                Some(Indices::from_u32(&indices, count))
+            }
+            None => None,
+        };
+
+        Ok(Primitive {
+            mode,
+            position: Some(attribute_data(
+                3,
+                DataType::HalfFixed,
+                position.values.iter().map(|v| v * scale),
+            )),
+            color,
+            texcoord: texcoord.map(|t| {
+                attribute_data(
+                    2,
+                    DataType::HalfFixed,
+                    t.values.iter().map(|v| v * texcoord_scale),
+                )
+            }),
+            normal,
+            mtx_index,
+            vertex_precision,
+            texcoord_precision,
+            num_vertices: count as u32,
+            indices,
+            texture: self.texture(primitive, &options.textures)?,
+        })
+    }
+
+    fn texture(&self, primitive: &Json, refs: &TextureRefs) -> Result<Option<u32>> {
+        if *refs == TextureRefs::None {
+            return Ok(None);
+        }
+        let Some(material) = optional_num(primitive, "material")? else {
+            return Ok(None);
+        };
+        let material = self.item("materials", material)?;
+        let texture = material
+            .get("pbrMetallicRoughness")
+            .and_then(|pbr| pbr.get("baseColorTexture"));
+        let Some(texture) = texture else {
+            return Ok(None);
+        };
+        let texture = self.item("textures", json_num(texture, "index")?)?;
+        let Some(image) = optional_num(texture, "source")? else {
+            return Ok(None);
+        };
+        match refs {
+            TextureRefs::None => Ok(None),
+            TextureRefs::ImageIndex => Ok(Some(image as u32)),
+            TextureRefs::ByName(names) => {
+                let image = self.item("images", image)?;
+                let name = match image.get("name").and_then(Json::as_str) {
+                    Some(name) => name,
+                    None => image
+                        .get("uri")
+                        .and_then(Json::as_str)
+                        .and_then(|uri| Path::new(uri).file_stem())
+                        .and_then(|stem| stem.to_str())
+                        .ok_or_else(|| invalid("glTF image without name"))?,
+                };
+                names
+                    .iter()
+                    .position(|n| n == name)
+                    .map(|i| Some(i as u32))
+                    .ok_or_else(|| invalid(&format!("texture {} is not in the list", name)))
+            }
+        }
+    }
+
+    fn animation(&self, anim: &Json, options: &Options) -> Result<Animation> {
+        let samplers = array(anim, "samplers");
+        let mut animation = Animation {
+            name:     anim
+                .get("name")
+                .and_then(Json::as_str)
+                .unwrap_or_default()
+                .to_owned(),
+            duration: 0.0,
+            tracks:   Vec::new(),
+        };
+        for channel in array(anim, "channels") {
+            let target = channel
+                .get("target")
+                .ok_or_else(|| invalid("glTF animation channel without target"))?;
+            let target_kind = match target.get("path").and_then(Json::as_str) {
+                Some("translation") => Target::Position,
+                Some("rotation") => Target::Rotation,
+                Some("scale") => Target::Scale,
+                // morph target weights
+                _ => continue,
+            };
+            let Some(node) = optional_num(target, "node")? else {
+                continue;
+            };
+            let sampler = samplers
+                .get(json_num::<usize>(channel, "sampler")?)
+                .ok_or_else(|| invalid("invalid glTF animation sampler"))?;
+            let input = self.accessor(json_num(sampler, "input")?)?;
+            let output = self.accessor(json_num(sampler, "output")?)?;
+            let interpolation = sampler.get("interpolation").and_then(Json::as_str);
+            // cubic splines store (in-tangent, value, out-tangent): only the values are used
+            let (per_key, value_at) = match interpolation {
+                Some("CUBICSPLINE") => (3, 1),
+                _ => (1, 0),
+            };
+            let components = if target_kind == Target::Rotation {
+                4
+            } else {
+                3
+            };
+            if output.components != components || output.len() != input.len() * per_key {
+                return Err(invalid("glTF animation sampler of the wrong size"));
+            }
+
+            let mut keys: Vec<(f32, [f32; 4])> = Vec::new();
+            for i in 0..input.len() {
+                let v = output.element(i * per_key + value_at);
+                let value = [v[0], v[1], v[2], v.get(3).copied().unwrap_or(0.0)].map(|v| v as f32);
+                let time = input.values[i] as f32;
+                // a step holds the previous value until the time of the next key
+                if interpolation == Some("STEP") {
+                    if let Some(&(_, previous)) = keys.last() {
+                        keys.push((time, previous));
+                    }
+                }
+                keys.push((time, value));
+                animation.duration = animation.duration.max(time);
+            }
+            if let AnimCompression::Lossy { max_error } = options.anim_compression {
+                keys = simplify(&keys, target_kind, max_error);
+            }
+            animation.tracks.push(Track {
+                node,
+                target: target_kind,
+                keys,
+            });
+        }
+        Ok(animation)
+    }
+}
+
+fn node_transform(node: &Json) -> Result<Transform> {
+    let Some(_) = node.get("matrix") else {
+        return Ok(Transform {
+            position: floats(node, "translation", [0.0; 3])?,
+            rotation: floats(node, "rotation", [0.0, 0.0, 0.0, 1.0])?,
+            scale:    floats(node, "scale", [1.0; 3])?,
+        });
+    };
+    // column-major affine matrix without shear
+    let m = floats(node, "matrix", [0.0; 16])?;
+    let column = |c: usize| [m[c * 4], m[c * 4 + 1], m[c * 4 + 2]];
+    let length = |v: [f32; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    let mut scale = [length(column(0)), length(column(1)), length(column(2))];
+    let (x, y, z) = (column(0), column(1), column(2));
+    let determinant = x[0] * (y[1] * z[2] - y[2] * z[1]) - y[0] * (x[1] * z[2] - x[2] * z[1])
+        + z[0] * (x[1] * y[2] - x[2] * y[1]);
+    if determinant < 0.0 {
+        scale[0] = -scale[0];
+    }
+    let r = |row: usize, col: usize| {
+        if scale[col] == 0.0 {
+            (row == col) as u32 as f32
+        } else {
+            m[col * 4 + row] / scale[col]
+        }
+    };
+    let trace = r(0, 0) + r(1, 1) + r(2, 2);
+    let rotation = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [
+            (r(2, 1) - r(1, 2)) / s,
+            (r(0, 2) - r(2, 0)) / s,
+            (r(1, 0) - r(0, 1)) / s,
+            0.25 * s,
+        ]
+    } else if r(0, 0) > r(1, 1) && r(0, 0) > r(2, 2) {
+        let s = (1.0 + r(0, 0) - r(1, 1) - r(2, 2)).sqrt() * 2.0;
+        [
+            0.25 * s,
+            (r(0, 1) + r(1, 0)) / s,
+            (r(0, 2) + r(2, 0)) / s,
+            (r(2, 1) - r(1, 2)) / s,
+        ]
+    } else if r(1, 1) > r(2, 2) {
+        let s = (1.0 + r(1, 1) - r(0, 0) - r(2, 2)).sqrt() * 2.0;
+        [
+            (r(0, 1) + r(1, 0)) / s,
+            0.25 * s,
+            (r(1, 2) + r(2, 1)) / s,
+            (r(0, 2) - r(2, 0)) / s,
+        ]
+    } else {
+        let s = (1.0 + r(2, 2) - r(0, 0) - r(1, 1)).sqrt() * 2.0;
+        [
+            (r(0, 2) + r(2, 0)) / s,
+            (r(1, 2) + r(2, 1)) / s,
+            0.25 * s,
+            (r(1, 0) - r(0, 1)) / s,
+        ]
+    };
+    Ok(Transform {
+        position: [m[12], m[13], m[14]],
+        rotation,
+        scale,
+    })
+}
+
+fn precision(precision: Precision, values: &[f64]) -> u32 {
+    match precision {
+        Precision::Bits(bits) => bits.min(15),
+        Precision::Auto => {
+            let max = values.iter().fold(0.0f64, |max, v| max.max(v.abs()));
+            (0..=15)
+                .rev()
+                .find(|&bits| max * (1u32 << bits) as f64 <= 32767.0)
+                .unwrap_or(0)
+        }
+    }
+}
+
+// Encode big-endian values, rounded and clamped to the range of the type
+fn attribute_data<I>(size: u32, data_type: DataType, values: I) -> Attribute
+where
+    I: IntoIterator<Item = f64>,
+{
+    let mut data = Vec::new();
+    for v in values {
+        let v = v.round();
+        match data_type {
+            DataType::Byte => data.push(v.clamp(-128.0, 127.0) as i8 as u8),
+            DataType::UnsignedByte => data.push(v.clamp(0.0, 255.0) as u8),
+            _ => data.extend_from_slice(&(v.clamp(-32768.0, 32767.0) as i16).to_be_bytes()),
+        }
+    }
+    Attribute {
+        size,
+        data_type,
+        data,
+    }
+}
+
+fn interpolate(a: [f32; 4], b: [f32; 4], t: f32, target: Target) -> [f32; 4] {
+    let mut b = b;
+    // shortest path between rotations
+    if target == Target::Rotation && a.iter().zip(&b).map(|(a, b)| a * b).sum::<f32>() < 0.0 {
+        b = b.map(|c| -c);
+    }
+    let mut out = [0.0; 4];
+    for (out, (a, b)) in out.iter_mut().zip(a.iter().zip(b)) {
+        *out = a + (b - a) * t;
+    }
+    if target == Target::Rotation {
+        let norm = out.iter().map(|c| c * c).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            out = out.map(|c| c / norm);
+        }
+    }
+    out
+}
+
+fn distance(a: [f32; 4], b: [f32; 4], target: Target) -> f32 {
+    let d = |b: [f32; 4]| {
+        a.iter()
+            .zip(b)
+            .fold(0.0f32, |d, (a, b)| d.max((a - b).abs()))
+    };
+    match target {
+        Target::Rotation => d(b).min(d(b.map(|c| -c))),
+        _ => d(b),
+    }
+}
+
+// Drop the keys that interpolating the kept keys reproduces within `max_error`
+fn simplify(keys: &[(f32, [f32; 4])], target: Target, max_error: f32) -> Vec<(f32, [f32; 4])> {
+    let Some(&first) = keys.first() else {
+        return Vec::new();
+    };
+    let mut kept = vec![first];
+    let mut anchor = 0;
+    for end in 2..keys.len() {
+        let (t0, v0) = keys[anchor];
+        let (t1, v1) = keys[end];
+        let fits = keys[anchor + 1..end].iter().all(|&(t, v)| {
+            let f = if t1 > t0 { (t - t0) / (t1 - t0) } else { 0.0 };
+            distance(interpolate(v0, v1, f, target), v, target) <= max_error
+        });
+        if !fits {
+            anchor = end - 1;
+            kept.push(keys[anchor]);
+        }
+    }
+    if keys.len() > 1 {
+        kept.push(keys[keys.len() - 1]);
+    }
+    kept
+}
@@ -0,0 +1,1121 @@
+//! Reading and writing of the `.model64` format loaded by `libdragon::model64`, and conversion
+//! from glTF (see [gltf]).
+//!
+//! It can be used instead of the external `mkmodel` tool from a build script, and to inspect
+//! `.model64` files in tests:
+//!
+//! ```no_run
+//! // build.rs
+//! use libdragon_build::model64;
+//!
+//! let options = model64::Options::default();
+//! model64::convert("assets/rust.glb", "filesystem/rust.model64", &options)?;
+//!
+//! let model = model64::Model::load("filesystem/rust.model64")?;
+//! assert_eq!(model.meshes.len(), 2);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! # File layout
+//!
+//! The file is the in-memory image of the structures of `model64_internal.h`: all values are
+//! big-endian 32-bit words unless noted, and pointers are stored as offsets from the start of
+//! the file (0 for `NULL`), which the loader relocates.
+//!
+//! | Structure     | Size | Fields |
+//! |---------------|------|--------|
+//! | header        | 76   | magic (`MDLH`), version, header, mesh, primitive, node, skin and animation sizes, num_nodes, nodes, root_node, num_skins, skins, num_meshes, meshes, num_anims, anims, max_tracks, anim_data_handle |
+//! | `attribute_t` | 16   | size (components), type (GL type), stride, pointer |
+//! | `primitive_t` | 112  | mode, position, color, texcoord, normal and mtx_index attributes, vertex_precision, texcoord_precision, index_type, num_vertices, num_indices, texture, indices |
+//! | `mesh_t`      | 8    | num_primitives, primitives |
+//! | `node_t`      | 64   | name, mesh, skin, position (3 floats), rotation (4 floats), scale (3 floats), parent, num_children, children (array of words) |
+//! | `skin_t`      | 8    | num_joints, joints |
+//! | joint         | 68   | node, inverse bind matrix (16 floats, column-major) |
+//! | animation     | 40   | name, duration, position offset and scale, scale offset and scale, num_tracks, tracks (array of 16-bit track ids), num_keyframes, keyframes |
+//! | keyframe      | 16   | time, time_req, track (16 bits), data (3 × 16 bits) |
+//!
+//! Indices that don't exist (no parent, no texture) are stored as `0xFFFFFFFF`. Vertex
+//! attributes are interleaved in a single buffer per primitive.
+//!
+//! The keyframes are not loaded with the model but streamed while animations play, so they
+//! are stored at the end of the file, after everything else, and their offset is not relocated.
+//! A track id is `target << 14 | node` with target 0 for position, 1 for rotation and 2 for
+//! scale. Keyframes are sorted by the time at which they are needed (`time_req`, the time of
+//! the previous keyframe of the same track) then by track. Positions and scales are quantized
+//! to 16 bits as `offset + value * scale`, and rotations are packed with the smallest-three
+//! method: the 2-bit index of the largest component then the 3 others as 15-bit values.
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use crate::Result;
+
+pub mod gltf;
+pub use gltf::Options;
+
+/// Magic number of `.model64` files (`MDLH`)
+pub const MAGIC: u32 = 0x4D44_4C48;
+/// Version of the format written by this module
+pub const VERSION: u32 = 1;
+
+const HEADER_SIZE: u32 = 76;
+const ATTRIBUTE_SIZE: u32 = 16;
+const PRIMITIVE_SIZE: u32 = 112;
+const MESH_SIZE: u32 = 8;
+const NODE_SIZE: u32 = 64;
+const SKIN_SIZE: u32 = 8;
+const JOINT_SIZE: u32 = 68;
+const ANIM_SIZE: u32 = 40;
+const KEYFRAME_SIZE: u32 = 16;
+
+const NONE: u32 = 0xFFFF_FFFF;
+const MAX_TRACK_NODES: usize = 1 << 14;
+
+fn invalid(msg: &str) -> Error { Error::new(ErrorKind::InvalidData, msg.to_owned()) }
+
+/// Type of the components of a vertex attribute, like `gl::DataType`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DataType {
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Float,
+    Double,
+    /// Signed 16-bit fixed point, with the precision of the [Primitive]
+    HalfFixed,
+}
+
+#[rustfmt::skip]
+const DATA_TYPES: [(DataType, u32, usize); 9] = [
+    (DataType::Byte,          0x1400, 1),
+    (DataType::UnsignedByte,  0x1401, 1),
+    (DataType::Short,         0x1402, 2),
+    (DataType::UnsignedShort, 0x1403, 2),
+    (DataType::Int,           0x1404, 4),
+    (DataType::UnsignedInt,   0x1405, 4),
+    (DataType::Float,         0x1406, 4),
+    (DataType::Double,        0x140A, 8),
+    (DataType::HalfFixed,     0x6900, 2),
+];
+
+impl DataType {
+    /// The GL enum value
+    pub fn to_gl(self) -> u32 { DATA_TYPES.iter().find(|t| t.0 == self).unwrap().1 }
+
+    /// The type with the GL enum value `value`
+    pub fn from_gl(value: u32) -> Option<Self> {
+        DATA_TYPES.iter().find(|t| t.1 == value).map(|t| t.0)
+    }
+
+    /// Size of a component in bytes
+    pub fn size(self) -> usize { DATA_TYPES.iter().find(|t| t.0 == self).unwrap().2 }
+}
+
+/// How the vertices of a [Primitive] are assembled. The values are the GL enum values, which
+/// are also the glTF ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Mode {
+    Points        = 0,
+    Lines         = 1,
+    LineLoop      = 2,
+    LineStrip     = 3,
+    Triangles     = 4,
+    TriangleStrip = 5,
+    TriangleFan   = 6,
+}
+
+impl Mode {
+    /// The mode with the GL enum value `value`
+    pub fn from_gl(value: u32) -> Option<Self> {
+        [
+            Self::Points,
+            Self::Lines,
+            Self::LineLoop,
+            Self::LineStrip,
+            Self::Triangles,
+            Self::TriangleStrip,
+            Self::TriangleFan,
+        ]
+        .into_iter()
+        .find(|m| *m as u32 == value)
+    }
+}
+
+/// A vertex attribute: `size` components of type `data_type` per vertex
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub size:      u32,
+    pub data_type: DataType,
+    /// Big-endian components of all the vertices, without padding
+    pub data:      Vec<u8>,
+}
+
+impl Attribute {
+    /// Size of the attribute of a vertex, in bytes
+    pub fn element_size(&self) -> usize { self.size as usize * self.data_type.size() }
+
+    /// Number of vertices
+    pub fn len(&self) -> usize { self.data.len() / self.element_size().max(1) }
+
+    /// Returns true if there are no vertices
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// The attribute of vertex `i` converted to floats, with the missing components set to
+    /// `(0, 0, 0, 1)`, like `libdragon::model64::VertexAttribute::get`. Integers are not
+    /// normalized, and [DataType::HalfFixed] components are divided by `2^precision`.
+    pub fn get(&self, i: usize, precision: u32) -> [f32; 4] {
+        let mut out = [0.0, 0.0, 0.0, 1.0];
+        let size = self.data_type.size();
+        let element = &self.data[i * self.element_size()..(i + 1) * self.element_size()];
+        for (out, b) in out.iter_mut().zip(element.chunks_exact(size)) {
+            *out = match self.data_type {
+                DataType::Byte => b[0] as i8 as f32,
+                DataType::UnsignedByte => b[0] as f32,
+                DataType::Short => i16::from_be_bytes([b[0], b[1]]) as f32,
+                DataType::UnsignedShort => u16::from_be_bytes([b[0], b[1]]) as f32,
+                DataType::HalfFixed => {
+                    i16::from_be_bytes([b[0], b[1]]) as f32 / (1u32 << precision) as f32
+                }
+                DataType::Int => i32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                DataType::UnsignedInt => u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                DataType::Float => f32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+                DataType::Double => f64::from_be_bytes(b.try_into().unwrap()) as f32,
+            };
+        }
+        out
+    }
+}
+
+/// Vertex indices of a [Primitive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Indices {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// Number of indices
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U8(i) => i.len(),
+            Self::U16(i) => i.len(),
+            Self::U32(i) => i.len(),
+        }
+    }
+
+    /// Returns true if there are no indices
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Index `i`, widened to `u32`
+    pub fn get(&self, i: usize) -> Option<u32> {
+        match self {
+            Self::U8(v) => v.get(i).map(|&x| x as u32),
+            Self::U16(v) => v.get(i).map(|&x| x as u32),
+            Self::U32(v) => v.get(i).copied(),
+        }
+    }
+
+    /// The smallest index type that can address `vertex_count` vertices
+    pub fn from_u32(indices: &[u32], vertex_count: usize) -> Self {
+        if vertex_count <= 0x100 {
+            Self::U8(indices.iter().map(|&i| i as u8).collect())
+        } else if vertex_count <= 0x1_0000 {
+            Self::U16(indices.iter().map(|&i| i as u16).collect())
+        } else {
+            Self::U32(indices.to_vec())
+        }
+    }
+
+    fn gl_type(&self) -> u32 {
+        match self {
+            Self::U8(_) => DataType::UnsignedByte.to_gl(),
+            Self::U16(_) => DataType::UnsignedShort.to_gl(),
+            Self::U32(_) => DataType::UnsignedInt.to_gl(),
+        }
+    }
+}
+
+/// A set of vertices drawn in one call
+#[derive(Debug, Clone, PartialEq)]
+pub struct Primitive {
+    pub mode:               Mode,
+    pub position:           Option<Attribute>,
+    pub color:              Option<Attribute>,
+    pub texcoord:           Option<Attribute>,
+    pub normal:             Option<Attribute>,
+    /// Index of the joint of the skin moving each vertex
+    pub mtx_index:          Option<Attribute>,
+    /// Fractional bits of [DataType::HalfFixed] positions
+    pub vertex_precision:   u32,
+    /// Fractional bits of [DataType::HalfFixed] texture coordinates
+    pub texcoord_precision: u32,
+    pub num_vertices:       u32,
+    pub indices:            Option<Indices>,
+    /// Reference to the texture of the primitive, see [gltf::TextureRefs]
+    pub texture:            Option<u32>,
+}
+
+impl Primitive {
+    fn attributes(&self) -> [&Option<Attribute>; 5] {
+        [
+            &self.position,
+            &self.color,
+            &self.texcoord,
+            &self.normal,
+            &self.mtx_index,
+        ]
+    }
+
+    /// Minimum and maximum of the vertex positions, or `None` if there are none
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let position = self.position.as_ref()?;
+        let mut points = (0..position.len()).map(|i| position.get(i, self.vertex_precision));
+        let first = points.next()?;
+        let (mut min, mut max) = (
+            [first[0], first[1], first[2]],
+            [first[0], first[1], first[2]],
+        );
+        for p in points {
+            for c in 0..3 {
+                min[c] = min[c].min(p[c]);
+                max[c] = max[c].max(p[c]);
+            }
+        }
+        Some((min, max))
+    }
+}
+
+/// A list of primitives, drawn together
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mesh {
+    pub primitives: Vec<Primitive>,
+}
+
+/// Local transform of a [Node]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub position: [f32; 3],
+    /// Quaternion (x, y, z, w)
+    pub rotation: [f32; 4],
+    pub scale:    [f32; 3],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale:    [1.0; 3],
+        }
+    }
+}
+
+/// A node of the hierarchy
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Node {
+    pub name:      Option<String>,
+    /// Index in [Model::meshes]
+    pub mesh:      Option<usize>,
+    /// Index in [Model::skins]
+    pub skin:      Option<usize>,
+    pub transform: Transform,
+    pub parent:    Option<usize>,
+    pub children:  Vec<usize>,
+}
+
+/// A joint of a [Skin]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+    /// Index of the node moving the joint
+    pub node:                usize,
+    /// Column-major matrix from the mesh space to the joint space
+    pub inverse_bind_matrix: [f32; 16],
+}
+
+/// The joints used by a skinned mesh
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Skin {
+    pub joints: Vec<Joint>,
+}
+
+/// The property of a node animated by a [Track]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    Position = 0,
+    Rotation = 1,
+    Scale    = 2,
+}
+
+/// The keyframes of one property of a node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub node:   usize,
+    pub target: Target,
+    /// (time, value) sorted by time. Positions and scales use the first 3 components of the
+    /// value, rotations are quaternions (x, y, z, w).
+    pub keys:   Vec<(f32, [f32; 4])>,
+}
+
+/// A named animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub name:     String,
+    /// Length in seconds
+    pub duration: f32,
+    pub tracks:   Vec<Track>,
+}
+
+/// The content of a `.model64` file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Model {
+    pub nodes:     Vec<Node>,
+    /// Index of the root of the hierarchy in [Model::nodes]
+    pub root_node: usize,
+    pub meshes:    Vec<Mesh>,
+    pub skins:     Vec<Skin>,
+    pub anims:     Vec<Animation>,
+}
+
+/// Convert a glTF file (`.gltf` or `.glb`) to a `.model64` file
+pub fn convert<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    options: &Options,
+) -> Result<()> {
+    gltf::load(input, options)?.write(output)
+}
+
+impl Model {
+    /// Read a `.model64` file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> { Self::parse(&fs::read(path)?) }
+
+    /// Index of the first node named `name`
+    pub fn find_node(&self, name: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|n| n.name.as_deref() == Some(name))
+    }
+
+    /// Decode a `.model64` file
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let r = Reader { data };
+        if r.u32(0)? != MAGIC {
+            return Err(invalid("not a model64 file"));
+        }
+        if r.u32(4)? != VERSION {
+            return Err(invalid("unsupported model64 version"));
+        }
+        let sizes = [
+            HEADER_SIZE,
+            MESH_SIZE,
+            PRIMITIVE_SIZE,
+            NODE_SIZE,
+            SKIN_SIZE,
+            ANIM_SIZE,
+        ];
+        for (i, size) in sizes.into_iter().enumerate() {
+            if r.u32(8 + i * 4)? != size {
+                return Err(invalid("unexpected structure size in model64 header"));
+            }
+        }
+        let (num_nodes, nodes_ptr) = (r.u32(32)?, r.u32(36)?);
+        let root_node = r.u32(40)? as usize;
+        let (num_skins, skins_ptr) = (r.u32(44)?, r.u32(48)?);
+        let (num_meshes, meshes_ptr) = (r.u32(52)?, r.u32(56)?);
+        let (num_anims, anims_ptr) = (r.u32(60)?, r.u32(64)?);
+
+        let table_index = |ptr: u32, base: u32, size: u32, count: u32| -> Result<Option<usize>> {
+            if ptr == 0 {
+                return Ok(None);
+            }
+            let offset = ptr.wrapping_sub(base);
+            if ptr < base || offset / size * size != offset || offset / size >= count {
+                return Err(invalid("invalid pointer in model64 file"));
+            }
+            Ok(Some((offset / size) as usize))
+        };
+        let optional_index = |value: u32, count: u32| -> Result<Option<usize>> {
+            match value {
+                NONE => Ok(None),
+                v if v < count => Ok(Some(v as usize)),
+                _ => Err(invalid("invalid index in model64 file")),
+            }
+        };
+
+        let mut meshes = Vec::new();
+        for m in 0..num_meshes {
+            let at = element(meshes_ptr, m, MESH_SIZE)?;
+            let (count, ptr) = (r.u32(at)?, r.u32(at + 4)?);
+            let primitives = (0..count)
+                .map(|p| r.primitive(element(ptr, p, PRIMITIVE_SIZE)?))
+                .collect::<Result<_>>()?;
+            meshes.push(Mesh { primitives });
+        }
+
+        let mut nodes = Vec::new();
+        for n in 0..num_nodes {
+            let at = element(nodes_ptr, n, NODE_SIZE)?;
+            let floats = (0..10)
+                .map(|i| r.f32(at + 12 + i * 4))
+                .collect::<Result<Vec<_>>>()?;
+            let (num_children, children_ptr) = (r.u32(at + 56)?, r.u32(at + 60)?);
+            let children = (0..num_children as usize)
+                .map(|i| optional_index(r.u32(children_ptr as usize + i * 4)?, num_nodes))
+                .map(|c| c?.ok_or_else(|| invalid("invalid child in model64 file")))
+                .collect::<Result<_>>()?;
+            nodes.push(Node {
+                name: r.string(r.u32(at)?)?,
+                mesh: table_index(r.u32(at + 4)?, meshes_ptr, MESH_SIZE, num_meshes)?,
+                skin: table_index(r.u32(at + 8)?, skins_ptr, SKIN_SIZE, num_skins)?,
+                transform: Transform {
+                    position: [floats[0], floats[1], floats[2]],
+                    rotation: [floats[3], floats[4], floats[5], floats[6]],
+                    scale:    [floats[7], floats[8], floats[9]],
+                },
+                parent: optional_index(r.u32(at + 52)?, num_nodes)?,
+                children,
+            });
+        }
+        if num_nodes > 0 && root_node >= nodes.len() {
+            return Err(invalid("invalid root node in model64 file"));
+        }
+
+        let mut skins = Vec::new();
+        for s in 0..num_skins {
+            let at = element(skins_ptr, s, SKIN_SIZE)?;
+            let (count, ptr) = (r.u32(at)?, r.u32(at + 4)?);
+            let mut joints = Vec::new();
+            for j in 0..count {
+                let at = element(ptr, j, JOINT_SIZE)?;
+                let mut inverse_bind_matrix = [0.0; 16];
+                for (i, v) in inverse_bind_matrix.iter_mut().enumerate() {
+                    *v = r.f32(at + 4 + i * 4)?;
+                }
+                joints.push(Joint {
+                    node: optional_index(r.u32(at)?, num_nodes)?
+                        .ok_or_else(|| invalid("invalid joint in model64 file"))?,
+                    inverse_bind_matrix,
+                });
+            }
+            skins.push(Skin { joints });
+        }
+
+        let anims = (0..num_anims)
+            .map(|a| r.animation(element(anims_ptr, a, ANIM_SIZE)?))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            nodes,
+            root_node,
+            meshes,
+            skins,
+            anims,
+        })
+    }
+
+    /// Encode the model in the `.model64` format
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        if self.nodes.len() > MAX_TRACK_NODES {
+            return Err(invalid("too many nodes for model64"));
+        }
+        let num_primitives: usize = self.meshes.iter().map(|m| m.primitives.len()).sum();
+        let num_joints: usize = self.skins.iter().map(|s| s.joints.len()).sum();
+
+        // fixed-size tables, followed by the variable-size data
+        let nodes_ptr = HEADER_SIZE;
+        let meshes_ptr = nodes_ptr + self.nodes.len() as u32 * NODE_SIZE;
+        let primitives_ptr = meshes_ptr + self.meshes.len() as u32 * MESH_SIZE;
+        let skins_ptr = primitives_ptr + num_primitives as u32 * PRIMITIVE_SIZE;
+        let joints_ptr = skins_ptr + self.skins.len() as u32 * SKIN_SIZE;
+        let anims_ptr = joints_ptr + num_joints as u32 * JOINT_SIZE;
+        let mut out = Writer {
+            data: Vec::new(),
+            base: anims_ptr + self.anims.len() as u32 * ANIM_SIZE,
+        };
+        let ptr = |present: bool, ptr: u32| if present { ptr } else { 0 };
+
+        let mut w = Writer {
+            data: Vec::new(),
+            base: 0,
+        };
+        let max_tracks = self.anims.iter().map(|a| a.tracks.len()).max().unwrap_or(0);
+        for v in [
+            MAGIC,
+            VERSION,
+            HEADER_SIZE,
+            MESH_SIZE,
+            PRIMITIVE_SIZE,
+            NODE_SIZE,
+            SKIN_SIZE,
+            ANIM_SIZE,
+            self.nodes.len() as u32,
+            ptr(!self.nodes.is_empty(), nodes_ptr),
+            self.root_node as u32,
+            self.skins.len() as u32,
+            ptr(!self.skins.is_empty(), skins_ptr),
+            self.meshes.len() as u32,
+            ptr(!self.meshes.is_empty(), meshes_ptr),
+            self.anims.len() as u32,
+            ptr(!self.anims.is_empty(), anims_ptr),
+            max_tracks as u32,
+            0,
+        ] {
+            w.u32(v);
+        }
+
+        for node in &self.nodes {
+            let children = out.align(4);
+            for &c in &node.children {
+                out.u32(c as u32);
+            }
+            w.u32(out.string(node.name.as_deref()));
+            w.u32(node.mesh.map_or(0, |m| meshes_ptr + m as u32 * MESH_SIZE));
+            w.u32(node.skin.map_or(0, |s| skins_ptr + s as u32 * SKIN_SIZE));
+            let t = &node.transform;
+            for v in t.position.iter().chain(&t.rotation).chain(&t.scale) {
+                w.f32(*v);
+            }
+            w.u32(node.parent.map_or(NONE, |p| p as u32));
+            w.u32(node.children.len() as u32);
+            w.u32(ptr(!node.children.is_empty(), children));
+        }
+
+        let mut primitive_index = 0;
+        for mesh in &self.meshes {
+            w.u32(mesh.primitives.len() as u32);
+            w.u32(ptr(
+                !mesh.primitives.is_empty(),
+                primitives_ptr + primitive_index * PRIMITIVE_SIZE,
+            ));
+            primitive_index += mesh.primitives.len() as u32;
+        }
+
+        for primitive in self.meshes.iter().flat_map(|m| &m.primitives) {
+            write_primitive(&mut w, &mut out, primitive)?;
+        }
+
+        let mut joint_index = 0;
+        for skin in &self.skins {
+            w.u32(skin.joints.len() as u32);
+            w.u32(ptr(
+                !skin.joints.is_empty(),
+                joints_ptr + joint_index * JOINT_SIZE,
+            ));
+            joint_index += skin.joints.len() as u32;
+        }
+        for joint in self.skins.iter().flat_map(|s| &s.joints) {
+            w.u32(joint.node as u32);
+            for v in joint.inverse_bind_matrix {
+                w.f32(v);
+            }
+        }
+
+        // the keyframes go after everything else, see the module documentation
+        let mut keyframes = Vec::new();
+        for anim in &self.anims {
+            let encoded = EncodedAnimation::new(anim)?;
+            let tracks = out.align(2);
+            for track in &anim.tracks {
+                out.data.extend_from_slice(&track_id(track).to_be_bytes());
+            }
+            w.u32(out.string(Some(&anim.name)));
+            w.f32(anim.duration);
+            for v in [encoded.position.0, encoded.position.1] {
+                w.f32(v);
+            }
+            for v in [encoded.scale.0, encoded.scale.1] {
+                w.f32(v);
+            }
+            w.u32(anim.tracks.len() as u32);
+            w.u32(ptr(!anim.tracks.is_empty(), tracks));
+            w.u32(encoded.keyframes.len() as u32);
+            keyframes.push((w.data.len(), encoded.keyframes));
+            w.u32(0);
+        }
+
+        debug_assert_eq!(w.data.len() as u32, out.base);
+        w.data.append(&mut out.data);
+        w.align(8);
+        for (at, keys) in keyframes {
+            let offset = w.data.len() as u32;
+            w.data[at..at + 4].copy_from_slice(&ptr(!keys.is_empty(), offset).to_be_bytes());
+            for key in keys {
+                w.data.extend_from_slice(&key);
+            }
+        }
+        Ok(w.data)
+    }
+
+    /// Write the model as a `.model64` file
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> { fs::write(path, self.to_bytes()?) }
+}
+
+fn write_primitive(w: &mut Writer, out: &mut Writer, primitive: &Primitive) -> Result<()> {
+    let count = primitive.num_vertices as usize;
+    let attributes = primitive.attributes();
+    for a in attributes.iter().filter_map(|a| a.as_ref()) {
+        if a.size == 0 || a.size > 4 || a.data.len() != count * a.element_size() {
+            return Err(invalid("vertex attribute doesn't match the vertex count"));
+        }
+    }
+
+    // interleaved vertices, each attribute aligned to its component size
+    let mut stride = 0;
+    let mut offsets = [0; 5];
+    for (offset, a) in offsets.iter_mut().zip(attributes) {
+        if let Some(a) = a {
+            stride = align(stride, a.data_type.size());
+            *offset = stride;
+            stride += a.element_size();
+        }
+    }
+    let max_align = attributes
+        .iter()
+        .filter_map(|a| a.as_ref().map(|a| a.data_type.size()))
+        .max()
+        .unwrap_or(1);
+    let stride = align(stride, max_align);
+    let vertices = out.align(8);
+    let mut buffer = vec![0; stride * count];
+    for (offset, a) in offsets.iter().zip(attributes) {
+        if let Some(a) = a {
+            let size = a.element_size();
+            for (i, element) in a.data.chunks_exact(size).enumerate() {
+                let at = i * stride + offset;
+                buffer[at..at + size].copy_from_slice(element);
+            }
+        }
+    }
+    out.data.extend_from_slice(&buffer);
+
+    w.u32(primitive.mode as u32);
+    for (offset, a) in offsets.iter().zip(attributes) {
+        match a {
+            Some(a) if count > 0 => {
+                w.u32(a.size);
+                w.u32(a.data_type.to_gl());
+                w.u32(stride as u32);
+                w.u32(vertices + *offset as u32);
+            }
+            _ => (0..ATTRIBUTE_SIZE / 4).for_each(|_| w.u32(0)),
+        }
+    }
+    w.u32(primitive.vertex_precision);
+    w.u32(primitive.texcoord_precision);
+    w.u32(primitive.indices.as_ref().map_or(0, Indices::gl_type));
+    w.u32(primitive.num_vertices);
+    w.u32(primitive.indices.as_ref().map_or(0, |i| i.len() as u32));
+    w.u32(primitive.texture.unwrap_or(NONE));
+    let indices = match &primitive.indices {
+        Some(indices) if !indices.is_empty() => {
+            let ptr = out.align(4);
+            for i in 0..indices.len() {
+                let index = indices.get(i).unwrap();
+                if index >= primitive.num_vertices {
+                    return Err(invalid("vertex index out of range"));
+                }
+                match indices {
+                    Indices::U8(_) => out.data.push(index as u8),
+                    Indices::U16(_) => out.data.extend_from_slice(&(index as u16).to_be_bytes()),
+                    Indices::U32(_) => out.u32(index),
+                }
+            }
+            ptr
+        }
+        _ => 0,
+    };
+    w.u32(indices);
+    Ok(())
+}
+
+// Offset of element `index` of a table of `size`-byte structures at `ptr`
+fn element(ptr: u32, index: u32, size: u32) -> Result<usize> {
+    index
+        .checked_mul(size)
+        .and_then(|offset| offset.checked_add(ptr))
+        .map(|at| at as usize)
+        .ok_or_else(|| invalid("invalid pointer in model64 file"))
+}
+
+fn align(value: usize, alignment: usize) -> usize { value.div_ceil(alignment) * alignment }
+
+fn track_id(track: &Track) -> u16 { ((track.target as u16) << 14) | track.node as u16 }
+
+// Animation data in the encoding of the file
+struct EncodedAnimation {
+    // offset and scale of the quantized positions and scales
+    position:  (f32, f32),
+    scale:     (f32, f32),
+    keyframes: Vec<[u8; KEYFRAME_SIZE as usize]>,
+}
+
+impl EncodedAnimation {
+    fn new(anim: &Animation) -> Result<Self> {
+        let range = |target| {
+            let values = anim
+                .tracks
+                .iter()
+                .filter(|t| t.target == target)
+                .flat_map(|t| t.keys.iter().flat_map(|(_, v)| &v[..3]));
+            let (min, max) = values.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+                (lo.min(v), hi.max(v))
+            });
+            if min > max {
+                (0.0, 0.0)
+            } else {
+                (min, (max - min) / 65535.0)
+            }
+        };
+        let position = range(Target::Position);
+        let scale = range(Target::Scale);
+
+        let mut keyframes = Vec::new();
+        for track in &anim.tracks {
+            if track.node >= MAX_TRACK_NODES {
+                return Err(invalid("animated node index too large for model64"));
+            }
+            let mut time_req = 0.0f32;
+            for &(time, value) in &track.keys {
+                let data = match track.target {
+                    Target::Position => quantize(value, position),
+                    Target::Scale => quantize(value, scale),
+                    Target::Rotation => pack_quat(value),
+                };
+                let mut key = [0; KEYFRAME_SIZE as usize];
+                key[0..4].copy_from_slice(&time.to_be_bytes());
+                key[4..8].copy_from_slice(&time_req.to_be_bytes());
+                key[8..10].copy_from_slice(&track_id(track).to_be_bytes());
+                for (i, d) in data.iter().enumerate() {
+                    key[10 + i * 2..12 + i * 2].copy_from_slice(&d.to_be_bytes());
+                }
+                keyframes.push((time_req, track_id(track), key));
+                time_req = time;
+            }
+        }
+        // stable, so that the keyframes of a track stay in order
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        Ok(Self {
+            position,
+            scale,
+            keyframes: keyframes.into_iter().map(|k| k.2).collect(),
+        })
+    }
+}
+
+fn quantize(value: [f32; 4], (offset, scale): (f32, f32)) -> [u16; 3] {
+    let q = |v: f32| {
+        if scale == 0.0 {
+            0
+        } else {
+            ((v - offset) / scale).round().clamp(0.0, 65535.0) as u16
+        }
+    };
+    [q(value[0]), q(value[1]), q(value[2])]
+}
+
+fn dequantize(data: [u16; 3], (offset, scale): (f32, f32)) -> [f32; 4] {
+    let d = |q: u16| offset + q as f32 * scale;
+    [d(data[0]), d(data[1]), d(data[2]), 0.0]
+}
+
+fn pack_quat(q: [f32; 4]) -> [u16; 3] {
+    let norm = q.iter().map(|c| c * c).sum::<f32>().sqrt();
+    let mut q = q.map(|c| if norm > 0.0 { c / norm } else { 0.0 });
+    let largest = (0..4)
+        .max_by(|&a, &b| q[a].abs().total_cmp(&q[b].abs()))
+        .unwrap();
+    // q and -q are the same rotation: make the dropped component positive
+    if q[largest] < 0.0 {
+        q = q.map(|c| -c);
+    }
+    let mut bits = (largest as u64) << 45;
+    let mut shift = 30;
+    for (i, c) in q.iter().enumerate() {
+        if i != largest {
+            let c = c / std::f32::consts::SQRT_2 * 2.0;
+            let v = ((c * 0.5 + 0.5) * 32767.0).round().clamp(0.0, 32767.0) as u64;
+            bits |= v << shift;
+            shift -= 15;
+        }
+    }
+    [(bits >> 32) as u16, (bits >> 16) as u16, bits as u16]
+}
+
+fn unpack_quat(data: [u16; 3]) -> [f32; 4] {
+    let bits = ((data[0] as u64) << 32) | ((data[1] as u64) << 16) | data[2] as u64;
+    let largest = ((bits >> 45) & 3) as usize;
+    let mut q = [0.0; 4];
+    let mut shift = 30;
+    for (i, c) in q.iter_mut().enumerate() {
+        if i != largest {
+            let v = ((bits >> shift) & 0x7FFF) as f32 / 32767.0;
+            *c = (v * 2.0 - 1.0) * std::f32::consts::FRAC_1_SQRT_2;
+            shift -= 15;
+        }
+    }
+    q[largest] = (1.0 - q.iter().map(|c| c * c).sum::<f32>()).max(0.0).sqrt();
+    q
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8]> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| invalid("truncated model64 file"))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16> {
+        Ok(u16::from_be_bytes(
+            self.bytes(offset, 2)?.try_into().unwrap(),
+        ))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32> {
+        Ok(u32::from_be_bytes(
+            self.bytes(offset, 4)?.try_into().unwrap(),
+        ))
+    }
+
+    fn f32(&self, offset: usize) -> Result<f32> { Ok(f32::from_bits(self.u32(offset)?)) }
+
+    fn string(&self, ptr: u32) -> Result<Option<String>> {
+        if ptr == 0 {
+            return Ok(None);
+        }
+        let rest = self
+            .data
+            .get(ptr as usize..)
+            .ok_or_else(|| invalid("truncated model64 file"))?;
+        let len = rest
+            .iter()
+            .position(|&c| c == 0)
+            .ok_or_else(|| invalid("unterminated string in model64 file"))?;
+        String::from_utf8(rest[..len].to_vec())
+            .map(Some)
+            .map_err(|_| invalid("invalid UTF-8 in model64 file"))
+    }
+
+    fn attribute(&self, at: usize, count: usize) -> Result<Option<Attribute>> {
+        let (size, type_) = (self.u32(at)?, self.u32(at + 4)?);
+        let (stride, ptr) = (self.u32(at + 8)? as usize, self.u32(at + 12)? as usize);
+        if size == 0 || ptr == 0 {
+            return Ok(None);
+        }
+        let data_type =
+            DataType::from_gl(type_).ok_or_else(|| invalid("invalid attribute type"))?;
+        if size > 4 {
+            return Err(invalid("invalid attribute size"));
+        }
+        let size_bytes = size as usize * data_type.size();
+        let stride = if stride == 0 { size_bytes } else { stride };
+        // the last vertex must be in the file, which bounds the allocation below
+        if count > 0 {
+            let last = (count - 1)
+                .checked_mul(stride)
+                .and_then(|offset| offset.checked_add(ptr))
+                .ok_or_else(|| invalid("truncated model64 file"))?;
+            self.bytes(last, size_bytes)?;
+        }
+        let mut data = Vec::with_capacity(count * size_bytes);
+        for i in 0..count {
+            data.extend_from_slice(self.bytes(ptr + i * stride, size_bytes)?);
+        }
+        Ok(Some(Attribute {
+            size,
+            data_type,
+            data,
+        }))
+    }
+
+    fn primitive(&self, at: usize) -> Result<Primitive> {
+        let num_vertices = self.u32(at + 96)?;
+        let count = num_vertices as usize;
+        let attribute = |i: usize| self.attribute(at + 4 + i * ATTRIBUTE_SIZE as usize, count);
+        let index_type = self.u32(at + 92)?;
+        let (num_indices, ptr) = (self.u32(at + 100)? as usize, self.u32(at + 108)? as usize);
+        let indices = if num_indices == 0 || ptr == 0 {
+            None
+        } else {
+            Some(match DataType::from_gl(index_type) {
+                Some(DataType::UnsignedByte) => Indices::U8(self.bytes(ptr, num_indices)?.to_vec()),
+                Some(DataType::UnsignedShort) => Indices::U16(
+                    (0..num_indices)
+                        .map(|i| self.u16(ptr + i * 2))
+                        .collect::<Result<_>>()?,
+                ),
+                Some(DataType::UnsignedInt) => Indices::U32(
+                    (0..num_indices)
+                        .map(|i| self.u32(ptr + i * 4))
+                        .collect::<Result<_>>()?,
+                ),
+                _ => return Err(invalid("invalid index type")),
+            })
+        };
+        Ok(Primitive {
+            mode: Mode::from_gl(self.u32(at)?).ok_or_else(|| invalid("invalid primitive mode"))?,
+            position: attribute(0)?,
+            color: attribute(1)?,
+            texcoord: attribute(2)?,
+            normal: attribute(3)?,
+            mtx_index: attribute(4)?,
+            vertex_precision: self.u32(at + 84)?,
+            texcoord_precision: self.u32(at + 88)?,
+            num_vertices,
+            indices,
+            texture: Some(self.u32(at + 104)?).filter(|&t| t != NONE),
+        })
+    }
+
+    fn animation(&self, at: usize) -> Result<Animation> {
+        let position = (self.f32(at + 8)?, self.f32(at + 12)?);
+        let scale = (self.f32(at + 16)?, self.f32(at + 20)?);
+        let (num_tracks, tracks_ptr) = (self.u32(at + 24)? as usize, self.u32(at + 28)? as usize);
+        let (num_keyframes, keyframes) = (self.u32(at + 32)? as usize, self.u32(at + 36)? as usize);
+
+        let mut tracks = Vec::new();
+        for i in 0..num_tracks {
+            let id = self.u16(tracks_ptr + i * 2)?;
+            tracks.push(Track {
+                node:   (id & 0x3FFF) as usize,
+                target: match id >> 14 {
+                    0 => Target::Position,
+                    1 => Target::Rotation,
+                    2 => Target::Scale,
+                    _ => return Err(invalid("invalid animation track")),
+                },
+                keys:   Vec::new(),
+            });
+        }
+        for i in 0..num_keyframes {
+            let at = keyframes + i * KEYFRAME_SIZE as usize;
+            let id = self.u16(at + 8)?;
+            let track = tracks
+                .iter_mut()
+                .find(|t| track_id(t) == id)
+                .ok_or_else(|| invalid("keyframe of an unknown track"))?;
+            let data = [self.u16(at + 10)?, self.u16(at + 12)?, self.u16(at + 14)?];
+            let value = match track.target {
+                Target::Position => dequantize(data, position),
+                Target::Scale => dequantize(data, scale),
+                Target::Rotation => unpack_quat(data),
+            };
+            track.keys.push((self.f32(at)?, value));
+        }
+        Ok(Animation {
+            name: self.string(self.u32(at)?)?.unwrap_or_default(),
+            duration: self.f32(at + 4)?,
+            tracks,
+        })
+    }
+}
+
+// Variable-size data following the fixed-size tables, at file offset `base`
+struct Writer {
+    data: Vec<u8>,
+    base: u32,
+}
+
+impl Writer {
+    fn u32(&mut self, v: u32) { self.data.extend_from_slice(&v.to_be_bytes()); }
+
+    fn f32(&mut self, v: f32) { self.u32(v.to_bits()); }
+
+    // pad to `alignment` and return the file offset of the next byte
+    fn align(&mut self, alignment: usize) -> u32 {
+        let len = align(self.base as usize + self.data.len(), alignment) - self.base as usize;
+        self.data.resize(len, 0);
+        self.base + len as u32
+    }
+
+    // a NUL-terminated string, returns its file offset or 0 for `None`
+    fn string(&mut self, s: Option<&str>) -> u32 {
+        let Some(s) = s else {
+            return 0;
+        };
+        let ptr = self.base + self.data.len() as u32;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn rust_glb() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../libdragon-examples/importglb/assets/rust.glb")
+    }
+
+    #[test]
+    fn round_trip() {
+        let model = gltf::load(rust_glb(), &Options::default()).unwrap();
+        assert!(!model.meshes.is_empty());
+        let bytes = model.to_bytes().unwrap();
+        let parsed = Model::parse(&bytes).unwrap();
+        assert_eq!(parsed.nodes, model.nodes);
+        assert_eq!(parsed.meshes.len(), model.meshes.len());
+        assert_eq!(parsed.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn invalid_files() {
+        let bytes = gltf::load(rust_glb(), &Options::default())
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        let error = |bytes: &[u8]| Model::parse(bytes).unwrap_err().to_string();
+        assert_eq!(error(&bytes[..40]), "truncated model64 file");
+        assert_eq!(error(b"MDLX"), "not a model64 file");
+
+        // a table past the end of the address space
+        let mut huge = bytes.clone();
+        huge[32..40].copy_from_slice(&[0, 0, 0, 2, 0xFF, 0xFF, 0xFF, 0xF0]);
+        assert_eq!(error(&huge), "truncated model64 file");
+        assert_eq!(element(0xFFFF_FFF0, 0, NODE_SIZE).unwrap(), 0xFFFF_FFF0);
+        assert!(element(0xFFFF_FFF0, 1, NODE_SIZE).is_err());
+        assert!(element(0, u32::MAX, NODE_SIZE).is_err());
+
+        // a primitive claiming more vertices than the file can hold
+        let primitives = u32::from_be_bytes(bytes[56..60].try_into().unwrap()) as usize + 4;
+        let primitive = u32::from_be_bytes(bytes[primitives..primitives + 4].try_into().unwrap());
+        let num_vertices = primitive as usize + 96;
+        let mut huge = bytes;
+        huge[num_vertices..num_vertices + 4].copy_from_slice(&[0x7F, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(error(&huge), "truncated model64 file");
+    }
+
+    #[test]
+    #[ignore = "needs models built by mkmodel, see tests/fixtures/README.md"]
+    fn mkmodel_fixtures() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rust.model64");
+        let model = Model::load(&path).unwrap();
+        let converted = gltf::load(rust_glb(), &Options::default()).unwrap();
+        let names = |m: &Model| m.nodes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&model), names(&converted));
+        assert_eq!(model.root_node, converted.root_node);
+        assert_eq!(model.meshes.len(), converted.meshes.len());
+        for (a, b) in model.meshes.iter().zip(&converted.meshes) {
+            assert_eq!(a.primitives.len(), b.primitives.len());
+            for (a, b) in a.primitives.iter().zip(&b.primitives) {
+                assert_eq!((a.mode, a.num_vertices), (b.mode, b.num_vertices));
+                assert_eq!(a.bounds(), b.bounds());
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Host-side decoding of the screenshots produced by `libdragon::debug::screenshot`.
+//!
+//! The N64 encoder writes PNG files with uncompressed (stored) deflate blocks and 24-bit BMP
+//! files; this module decodes exactly those, so that screenshots received over USB or copied
+//! from the SD card can be checked or converted without pulling an image library. The encoders
+//! themselves are shared with the N64 side and re-exported here.
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use crate::{image::crc32, Result};
+
+pub use crate::image::{encode_bmp, encode_png, DATATYPE_SCREENSHOT_FILE};
+
+/// A decoded screenshot: 8-bit RGBA pixels, row-major, top row first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width:  u32,
+    pub height: u32,
+    pub rgba:   Vec<u8>,
+}
+
+impl Image {
+    /// RGBA value of the pixel at (`x`, `y`)
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [
+            self.rgba[i],
+            self.rgba[i + 1],
+            self.rgba[i + 2],
+            self.rgba[i + 3],
+        ]
+    }
+}
+
+fn invalid(msg: &str) -> Error { Error::new(ErrorKind::InvalidData, msg.to_owned()) }
+
+/// Decode a screenshot file, detecting the format from its signature
+pub fn decode(data: &[u8]) -> Result<Image> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        decode_png(data)
+    } else if data.starts_with(b"BM") {
+        decode_bmp(data)
+    } else {
+        Err(invalid("unknown screenshot format"))
+    }
+}
+
+/// Read and decode a screenshot file
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Image> { decode(&fs::read(path)?) }
+
+fn be32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("truncated file"))
+}
+
+fn le32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("truncated file"))
+}
+
+/// Decode an 8-bit RGBA PNG made of stored deflate blocks
+pub fn decode_png(data: &[u8]) -> Result<Image> {
+    let mut offset = 8;
+    let (mut width, mut height) = (0, 0);
+    let mut zlib = Vec::new();
+    loop {
+        let len = be32(data, offset)? as usize;
+        let chunk = data
+            .get(offset + 4..offset + 8 + len)
+            .ok_or_else(|| invalid("truncated chunk"))?;
+        if crc32(chunk) != be32(data, offset + 8 + len)? {
+            return Err(invalid("bad chunk CRC"));
+        }
+        let (kind, body) = chunk.split_at(4);
+        match kind {
+            b"IHDR" => {
+                width = be32(body, 0)?;
+                height = be32(body, 4)?;
+                if body.get(8..13) != Some(&[8, 6, 0, 0, 0][..]) {
+                    return Err(invalid("only non-interlaced 8-bit RGBA PNG is supported"));
+                }
+            }
+            b"IDAT" => zlib.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        offset += 12 + len;
+    }
+
+    // zlib header, then stored blocks only
+    if zlib.len() < 6 || zlib[0] & 0x0F != 8 {
+        return Err(invalid("bad zlib header"));
+    }
+    let mut raw = Vec::new();
+    let mut pos = 2;
+    loop {
+        let header = *zlib
+            .get(pos)
+            .ok_or_else(|| invalid("truncated deflate stream"))?;
+        if header & 0x06 != 0 {
+            return Err(invalid("compressed deflate blocks are not supported"));
+        }
+        let b = zlib
+            .get(pos + 1..pos + 5)
+            .ok_or_else(|| invalid("truncated deflate stream"))?;
+        let len = u16::from_le_bytes([b[0], b[1]]);
+        if len != !u16::from_le_bytes([b[2], b[3]]) {
+            return Err(invalid("bad stored block length"));
+        }
+        let block = zlib
+            .get(pos + 5..pos + 5 + len as usize)
+            .ok_or_else(|| invalid("truncated deflate stream"))?;
+        raw.extend_from_slice(block);
+        pos += 5 + len as usize;
+        if header & 1 != 0 {
+            break;
+        }
+    }
+
+    let row_len = width as usize * 4;
+    if raw.len() != (row_len + 1) * height as usize {
+        return Err(invalid("unexpected image data size"));
+    }
+    let mut rgba = Vec::with_capacity(row_len * height as usize);
+    for row in raw.chunks(row_len + 1) {
+        if row[0] != 0 {
+            return Err(invalid("only filter type 0 is supported"));
+        }
+        rgba.extend_from_slice(&row[1..]);
+    }
+    Ok(Image {
+        width,
+        height,
+        rgba,
+    })
+}
+
+/// Decode an uncompressed 24-bit or 32-bit BMP
+pub fn decode_bmp(data: &[u8]) -> Result<Image> {
+    let pixel_offset = le32(data, 10)? as usize;
+    let width = le32(data, 18)? as i32;
+    let height = le32(data, 22)? as i32;
+    let bpp = data
+        .get(28..30)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid("truncated file"))?;
+    if le32(data, 30)? != 0 || (bpp != 24 && bpp != 32) || width < 0 {
+        return Err(invalid("only uncompressed 24/32-bit BMP is supported"));
+    }
+
+    // a negative height means rows are stored top-down
+    let (width, bottom_up) = (width as usize, height > 0);
+    let height = height.unsigned_abs() as usize;
+    let bytes = bpp as usize / 8;
+    let row_len = (width * bytes + 3) & !3;
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let src_y = if bottom_up { height - 1 - y } else { y };
+        let start = pixel_offset + src_y * row_len;
+        let row = data
+            .get(start..start + width * bytes)
+            .ok_or_else(|| invalid("truncated pixel data"))?;
+        for p in row.chunks(bytes) {
+            let a = if bytes == 4 { p[3] } else { 0xFF };
+            rgba.extend_from_slice(&[p[2], p[1], p[0], a]);
+        }
+    }
+    Ok(Image {
+        width: width as u32,
+        height: height as u32,
+        rgba,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::adler32;
+
+    fn gradient(width: u32, height: u32, opaque: bool) -> Vec<u8> {
+        let mut rgba = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let a = if opaque { 0xFF } else { (x ^ y) as u8 };
+                rgba.extend_from_slice(&[x as u8, y as u8, (x + y) as u8, a]);
+            }
+        }
+        rgba
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        // long enough to need the modulo reduction
+        assert_eq!(adler32(&[0xFF; 20000]), 0x9F51_D664);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        for (width, height) in [(1, 1), (3, 2), (13, 7), (320, 240), (0, 0)] {
+            let rgba = gradient(width, height, false);
+            let png = encode_png(&rgba, width, height);
+            let image = decode(&png).unwrap();
+            assert_eq!(
+                image,
+                Image {
+                    width,
+                    height,
+                    rgba
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn bmp_round_trip() {
+        for (width, height) in [(1, 1), (3, 2), (13, 7), (320, 240)] {
+            // 24-bit BMP drops the alpha channel
+            let rgba = gradient(width, height, true);
+            let bmp = encode_bmp(&rgba, width, height);
+            assert_eq!(
+                bmp.len(),
+                54 + ((width as usize * 3 + 3) & !3) * height as usize
+            );
+            let image = decode(&bmp).unwrap();
+            assert_eq!(
+                image,
+                Image {
+                    width,
+                    height,
+                    rgba
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn png_corruption_is_detected() {
+        let rgba = gradient(4, 4, false);
+        let mut png = encode_png(&rgba, 4, 4);
+        let last = png.len() - 20;
+        png[last] ^= 1;
+        assert!(decode(&png).is_err());
+        assert!(decode(&png[..30]).is_err());
+        assert!(decode(b"GIF89a").is_err());
+    }
+}
@@ -0,0 +1,725 @@
+//! Conversion of maps made with the [Tiled](https://www.mapeditor.org) editor to the `.tilemap`
+//! format read by `libdragon::tilemap`.
+//!
+//! Both the XML (`.tmx`) and JSON (`.tmj`/`.json`) formats are supported, for orthogonal,
+//! finite maps using a single tileset (embedded or external). Tile layers keep their name,
+//! visibility, parallax factor and offset; tile animations defined in the tileset are exported
+//! too. Tile data can be stored as CSV, XML, JSON arrays or uncompressed base64.
+//!
+//! ```no_run
+//! // build.rs
+//! libdragon_build::tiled::convert("assets/level1.tmx", "filesystem/level1.tilemap")?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use crate::{
+    json::{json_num, Json},
+    tilemap::format,
+    Result,
+};
+
+pub use format::EMPTY;
+
+// Tiled stores flipping and rotation in the upper bits of the global tile IDs
+const GID_FLAGS: u32 = 0xF000_0000;
+
+fn invalid(msg: &str) -> Error { Error::new(ErrorKind::InvalidData, msg.to_owned()) }
+
+/// A tile layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub name:     String,
+    pub width:    u16,
+    pub height:   u16,
+    /// Tile indices in row-major order, [EMPTY] for no tile
+    pub cells:    Vec<u16>,
+    pub visible:  bool,
+    pub parallax: (f32, f32),
+    pub offset:   (f32, f32),
+}
+
+/// An animated tile: the tile index and its frames (tile index, duration in milliseconds)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Animation {
+    pub tile:   u16,
+    pub frames: Vec<(u16, u16)>,
+}
+
+/// A map ready to be written as a `.tilemap` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Map {
+    pub tile_width:  u16,
+    pub tile_height: u16,
+    /// Tile layers, from back to front
+    pub layers:      Vec<Layer>,
+    pub animations:  Vec<Animation>,
+}
+
+impl Map {
+    /// Load a Tiled map, choosing the format from the file extension (`.tmx` or JSON)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        if path.extension().is_some_and(|e| e == "tmx") {
+            Self::parse_tmx(&text, dir)
+        } else {
+            Self::parse_json(&text, dir)
+        }
+    }
+
+    /// Parse a map in Tiled's XML format. External tilesets are looked up relative to `dir`.
+    pub fn parse_tmx(text: &str, dir: &Path) -> Result<Self> {
+        let mut map = None;
+        let mut firstgid = None;
+        let mut layer: Option<Layer> = None;
+        let mut data: Option<(String, String)> = None;
+        let mut gids = Vec::new();
+        let mut tileset = TilesetState::default();
+
+        for event in xml_events(text)? {
+            match event {
+                Xml::Start { name, attrs } => match name.as_str() {
+                    "map" => {
+                        check_map(
+                            attr(&attrs, "orientation"),
+                            attr(&attrs, "infinite") == Some("1"),
+                        )?;
+                        map = Some(Map {
+                            tile_width:  attr_num(&attrs, "tilewidth")?,
+                            tile_height: attr_num(&attrs, "tileheight")?,
+                            layers:      Vec::new(),
+                            animations:  Vec::new(),
+                        });
+                    }
+                    "tileset" if layer.is_none() && firstgid.is_none() => {
+                        firstgid = Some(attr_num::<u32>(&attrs, "firstgid")?);
+                        if let Some(source) = attr(&attrs, "source") {
+                            tileset.animations = load_tileset_animations(&dir.join(source))?;
+                        }
+                    }
+                    "tileset" if layer.is_none() => {
+                        return Err(invalid("only maps with a single tileset are supported"));
+                    }
+                    "tile" if layer.is_none() => tileset.tile = Some(attr_num(&attrs, "id")?),
+                    "frame" => {
+                        tileset.frame(attr_num(&attrs, "tileid")?, attr_num(&attrs, "duration")?)?
+                    }
+                    "layer" => {
+                        layer = Some(Layer {
+                            name:     attr(&attrs, "name").unwrap_or("").to_owned(),
+                            width:    attr_num(&attrs, "width")?,
+                            height:   attr_num(&attrs, "height")?,
+                            cells:    Vec::new(),
+                            visible:  attr(&attrs, "visible") != Some("0"),
+                            parallax: (
+                                attr_num_or(&attrs, "parallaxx", 1.0)?,
+                                attr_num_or(&attrs, "parallaxy", 1.0)?,
+                            ),
+                            offset:   (
+                                attr_num_or(&attrs, "offsetx", 0.0)?,
+                                attr_num_or(&attrs, "offsety", 0.0)?,
+                            ),
+                        });
+                        gids.clear();
+                    }
+                    "data" => {
+                        if attr(&attrs, "compression").is_some_and(|c| !c.is_empty()) {
+                            return Err(invalid("compressed tile data is not supported"));
+                        }
+                        let encoding = attr(&attrs, "encoding").unwrap_or("").to_owned();
+                        data = Some((encoding, String::new()));
+                    }
+                    "tile" => gids.push(attr_num_or(&attrs, "gid", 0)?),
+                    "chunk" => return Err(invalid("infinite maps are not supported")),
+                    _ => {}
+                },
+                Xml::Text(text) => {
+                    if let Some((_, ref mut body)) = data {
+                        body.push_str(&text);
+                    }
+                }
+                Xml::End(name) => match name.as_str() {
+                    "data" => {
+                        let (encoding, body) = data.take().unwrap_or_default();
+                        match encoding.as_str() {
+                            "csv" => {
+                                for value in body.split(',') {
+                                    gids.push(parse_num(value.trim())?);
+                                }
+                            }
+                            "base64" => gids.extend(base64_gids(&body)?),
+                            "" => {}
+                            _ => return Err(invalid("unknown tile data encoding")),
+                        }
+                    }
+                    "layer" => {
+                        let mut l = layer.take().ok_or_else(|| invalid("unexpected </layer>"))?;
+                        l.cells = to_cells(&gids, firstgid, l.width, l.height)?;
+                        map.as_mut()
+                            .ok_or_else(|| invalid("missing <map>"))?
+                            .layers
+                            .push(l);
+                    }
+                    "tile" => tileset.end_tile(),
+                    _ => {}
+                },
+            }
+        }
+
+        let mut map = map.ok_or_else(|| invalid("missing <map>"))?;
+        map.animations = tileset.animations;
+        Ok(map)
+    }
+
+    /// Parse a map in Tiled's JSON format. External tilesets are looked up relative to `dir`.
+    pub fn parse_json(text: &str, dir: &Path) -> Result<Self> {
+        let root = Json::parse(text)?;
+        check_map(
+            root.get("orientation").and_then(Json::as_str),
+            root.get("infinite").and_then(Json::as_bool) == Some(true),
+        )?;
+
+        let tilesets = root.get("tilesets").and_then(Json::as_array).unwrap_or(&[]);
+        if tilesets.len() > 1 {
+            return Err(invalid("only maps with a single tileset are supported"));
+        }
+        let (firstgid, animations) = match tilesets.first() {
+            Some(tileset) => {
+                let firstgid = json_num(tileset, "firstgid")?;
+                let animations = match tileset.get("source").and_then(Json::as_str) {
+                    Some(source) => load_tileset_animations(&dir.join(source))?,
+                    None => json_animations(tileset)?,
+                };
+                (Some(firstgid), animations)
+            }
+            None => (None, Vec::new()),
+        };
+
+        let mut map = Map {
+            tile_width: json_num(&root, "tilewidth")?,
+            tile_height: json_num(&root, "tileheight")?,
+            layers: Vec::new(),
+            animations,
+        };
+        let layers = root.get("layers").and_then(Json::as_array).unwrap_or(&[]);
+        json_layers(layers, firstgid, &mut map.layers)?;
+        Ok(map)
+    }
+
+    /// Encode the map in the `.tilemap` format.
+    ///
+    /// Panics if the map has more than 65535 layers or animations, or if a layer name or an
+    /// animation does not fit in the format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        format::Header {
+            tile_width:     self.tile_width,
+            tile_height:    self.tile_height,
+            num_layers:     u16::try_from(self.layers.len()).expect("too many layers"),
+            num_animations: u16::try_from(self.animations.len()).expect("too many animations"),
+        }
+        .write(&mut out);
+        for layer in &self.layers {
+            format::LayerHeader {
+                name:     &layer.name,
+                width:    layer.width,
+                height:   layer.height,
+                visible:  layer.visible,
+                parallax: layer.parallax,
+                offset:   layer.offset,
+            }
+            .write(&mut out);
+            format::write_cells(&layer.cells, &mut out);
+        }
+        for anim in &self.animations {
+            format::write_animation(anim.tile, &anim.frames, &mut out);
+        }
+        out
+    }
+
+    /// Write the map as a `.tilemap` file
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> { fs::write(path, self.to_bytes()) }
+}
+
+/// Convert a Tiled map to a `.tilemap` file
+pub fn convert<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> Result<()> {
+    Map::load(input)?.write(output)
+}
+
+fn check_map(orientation: Option<&str>, infinite: bool) -> Result<()> {
+    if orientation.is_some_and(|o| o != "orthogonal") {
+        return Err(invalid("only orthogonal maps are supported"));
+    }
+    if infinite {
+        return Err(invalid("infinite maps are not supported"));
+    }
+    Ok(())
+}
+
+fn to_cells(gids: &[u32], firstgid: Option<u32>, width: u16, height: u16) -> Result<Vec<u16>> {
+    if gids.len() != width as usize * height as usize {
+        return Err(invalid("tile data does not match the layer size"));
+    }
+    gids.iter()
+        .map(|&gid| {
+            if gid == 0 {
+                return Ok(EMPTY);
+            }
+            if gid & GID_FLAGS != 0 {
+                return Err(invalid("flipped or rotated tiles are not supported"));
+            }
+            let firstgid = firstgid.ok_or_else(|| invalid("tile used without a tileset"))?;
+            match gid.checked_sub(firstgid) {
+                Some(index) if index < EMPTY as u32 => Ok(index as u16),
+                _ => Err(invalid("invalid tile ID")),
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct TilesetState {
+    tile:       Option<u16>,
+    frames:     Vec<(u16, u16)>,
+    animations: Vec<Animation>,
+}
+
+impl TilesetState {
+    fn frame(&mut self, tile: u16, duration: u16) -> Result<()> {
+        if self.tile.is_none() {
+            return Err(invalid("<frame> outside of a <tile>"));
+        }
+        self.frames.push((tile, duration));
+        Ok(())
+    }
+
+    fn end_tile(&mut self) {
+        if let Some(tile) = self.tile.take() {
+            if !self.frames.is_empty() {
+                self.animations.push(Animation {
+                    tile,
+                    frames: std::mem::take(&mut self.frames),
+                });
+            }
+        }
+    }
+}
+
+fn load_tileset_animations(path: &Path) -> Result<Vec<Animation>> {
+    let text = fs::read_to_string(path)?;
+    if path.extension().is_some_and(|e| e == "tsx") {
+        let mut tileset = TilesetState::default();
+        for event in xml_events(&text)? {
+            match event {
+                Xml::Start { name, attrs } if name == "tile" => {
+                    tileset.tile = Some(attr_num(&attrs, "id")?);
+                }
+                Xml::Start { name, attrs } if name == "frame" => {
+                    tileset.frame(attr_num(&attrs, "tileid")?, attr_num(&attrs, "duration")?)?;
+                }
+                Xml::End(name) if name == "tile" => tileset.end_tile(),
+                _ => {}
+            }
+        }
+        Ok(tileset.animations)
+    } else {
+        json_animations(&Json::parse(&text)?)
+    }
+}
+
+fn json_animations(tileset: &Json) -> Result<Vec<Animation>> {
+    let mut animations = Vec::new();
+    for tile in tileset.get("tiles").and_then(Json::as_array).unwrap_or(&[]) {
+        let Some(frames) = tile.get("animation").and_then(Json::as_array) else {
+            continue;
+        };
+        animations.push(Animation {
+            tile:   json_num(tile, "id")?,
+            frames: frames
+                .iter()
+                .map(|f| Ok((json_num(f, "tileid")?, json_num(f, "duration")?)))
+                .collect::<Result<_>>()?,
+        });
+    }
+    Ok(animations)
+}
+
+fn json_layers(layers: &[Json], firstgid: Option<u32>, out: &mut Vec<Layer>) -> Result<()> {
+    for layer in layers {
+        match layer.get("type").and_then(Json::as_str) {
+            Some("tilelayer") => {}
+            Some("group") => {
+                let children = layer.get("layers").and_then(Json::as_array).unwrap_or(&[]);
+                json_layers(children, firstgid, out)?;
+                continue;
+            }
+            _ => continue,
+        }
+        if layer.get("chunks").is_some() {
+            return Err(invalid("infinite maps are not supported"));
+        }
+        if layer
+            .get("compression")
+            .and_then(Json::as_str)
+            .is_some_and(|c| !c.is_empty())
+        {
+            return Err(invalid("compressed tile data is not supported"));
+        }
+        let gids = match layer.get("data") {
+            Some(Json::String(s)) => base64_gids(s)?,
+            Some(Json::Array(values)) => values
+                .iter()
+                .map(|v| v.as_f64().map(|n| n as u32))
+                .collect::<Option<_>>()
+                .ok_or_else(|| invalid("invalid tile data"))?,
+            _ => return Err(invalid("missing tile data")),
+        };
+        let (width, height) = (json_num(layer, "width")?, json_num(layer, "height")?);
+        let num_or = |key: &str, default: f32| {
+            layer
+                .get(key)
+                .and_then(Json::as_f64)
+                .map_or(default, |n| n as f32)
+        };
+        out.push(Layer {
+            name: layer
+                .get("name")
+                .and_then(Json::as_str)
+                .unwrap_or("")
+                .to_owned(),
+            width,
+            height,
+            cells: to_cells(&gids, firstgid, width, height)?,
+            visible: layer.get("visible").and_then(Json::as_bool) != Some(false),
+            parallax: (num_or("parallaxx", 1.0), num_or("parallaxy", 1.0)),
+            offset: (num_or("offsetx", 0.0), num_or("offsety", 0.0)),
+        });
+    }
+    Ok(())
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str) -> Result<T> {
+    s.parse().map_err(|_| invalid("invalid number"))
+}
+
+fn base64_gids(text: &str) -> Result<Vec<u32>> {
+    let mut bytes = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return Err(invalid("invalid base64 tile data")),
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    if bytes.len() % 4 != 0 {
+        return Err(invalid("invalid base64 tile data"));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+// XML
+
+enum Xml {
+    Start {
+        name:  String,
+        attrs: Vec<(String, String)>,
+    },
+    End(String),
+    Text(String),
+}
+
+fn attr<'a>(attrs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+fn attr_num<T: std::str::FromStr>(attrs: &[(String, String)], key: &str) -> Result<T> {
+    parse_num(attr(attrs, key).ok_or_else(|| invalid(&format!("missing attribute {}", key)))?)
+}
+
+fn attr_num_or<T: std::str::FromStr>(
+    attrs: &[(String, String)],
+    key: &str,
+    default: T,
+) -> Result<T> {
+    attr(attrs, key).map_or(Ok(default), parse_num)
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// A minimal XML tokenizer, enough for the files written by Tiled. Self-closing elements produce
+// a start and an end event.
+fn xml_events(text: &str) -> Result<Vec<Xml>> {
+    let mut events = Vec::new();
+    let mut rest = text;
+    while let Some(lt) = rest.find('<') {
+        if lt > 0 {
+            events.push(Xml::Text(unescape(&rest[..lt])));
+        }
+        rest = &rest[lt..];
+        let skip = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<?") {
+            Some("?>")
+        } else if rest.starts_with("<!") {
+            Some(">")
+        } else {
+            None
+        };
+        if let Some(end) = skip {
+            let pos = rest
+                .find(end)
+                .ok_or_else(|| invalid("unterminated XML markup"))?;
+            rest = &rest[pos + end.len()..];
+            continue;
+        }
+
+        let gt = rest
+            .find('>')
+            .ok_or_else(|| invalid("unterminated XML tag"))?;
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+        if let Some(name) = tag.strip_prefix('/') {
+            events.push(Xml::End(name.trim().to_owned()));
+            continue;
+        }
+        let (tag, self_closing) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let name_end = tag
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(tag.len());
+        let name = tag[..name_end].to_owned();
+        let mut attrs = Vec::new();
+        let mut a = tag[name_end..].trim_start();
+        while !a.is_empty() {
+            let eq = a
+                .find('=')
+                .ok_or_else(|| invalid("invalid XML attribute"))?;
+            let key = a[..eq].trim().to_owned();
+            let value = a[eq + 1..].trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|&c| c == '"' || c == '\'')
+                .ok_or_else(|| invalid("invalid XML attribute"))?;
+            let end = value[1..]
+                .find(quote)
+                .ok_or_else(|| invalid("invalid XML attribute"))?;
+            attrs.push((key, unescape(&value[1..end + 1])));
+            a = value[end + 2..].trim_start();
+        }
+        events.push(Xml::Start {
+            name: name.clone(),
+            attrs,
+        });
+        if self_closing {
+            events.push(Xml::End(name));
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="2" height="2"
+     tilewidth="16" tileheight="8" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="8" tilecount="4" columns="2">
+  <image source="tiles.png" width="32" height="16"/>
+  <tile id="2">
+   <animation>
+    <frame tileid="2" duration="100"/>
+    <frame tileid="3" duration="250"/>
+   </animation>
+  </tile>
+ </tileset>
+ <layer id="1" name="back &amp; sky" width="2" height="2" parallaxx="0.5" offsety="-4">
+  <data encoding="csv">
+1,0,
+3,2
+</data>
+ </layer>
+ <!-- a <layer> in a comment -->
+ <layer id="2" name="front" width="2" height="2" visible="0">
+  <data encoding="base64">
+   AQAAAAAAAAADAAAAAgAAAA==
+  </data>
+ </layer>
+ <layer id="3" name="xml" width="2" height="2">
+  <data><tile gid="4"/><tile/><tile gid="1"/><tile/></data>
+ </layer>
+</map>
+"#;
+
+    const JSON: &str = r#"{
+ "orientation": "orthogonal", "infinite": false, "tilewidth": 16, "tileheight": 8,
+ "tilesets": [{"firstgid": 1, "tiles": [
+  {"id": 2, "animation": [{"tileid": 2, "duration": 100}, {"tileid": 3, "duration": 250}]}
+ ]}],
+ "layers": [
+  {"type": "tilelayer", "name": "back & sky", "width": 2, "height": 2, "data": [1, 0, 3, 2],
+   "parallaxx": 0.5, "offsety": -4},
+  {"type": "group", "name": "group", "layers": [
+   {"type": "tilelayer", "name": "front", "width": 2, "height": 2, "visible": false,
+    "encoding": "base64", "data": "AQAAAAAAAAADAAAAAgAAAA=="},
+   {"type": "objectgroup", "name": "objects", "objects": []}
+  ]},
+  {"type": "tilelayer", "name": "xml", "width": 2, "height": 2, "data": [4, 0, 1, 0]}
+ ]
+}"#;
+
+    fn expected() -> Map {
+        let layer = |name: &str, cells: [u16; 4], visible| Layer {
+            name: name.to_owned(),
+            width: 2,
+            height: 2,
+            cells: cells.to_vec(),
+            visible,
+            parallax: (1.0, 1.0),
+            offset: (0.0, 0.0),
+        };
+        let mut back = layer("back & sky", [0, EMPTY, 2, 1], true);
+        back.parallax.0 = 0.5;
+        back.offset.1 = -4.0;
+        Map {
+            tile_width:  16,
+            tile_height: 8,
+            layers:      vec![
+                back,
+                layer("front", [0, EMPTY, 2, 1], false),
+                layer("xml", [3, EMPTY, 0, EMPTY], true),
+            ],
+            animations:  vec![Animation {
+                tile:   2,
+                frames: vec![(2, 100), (3, 250)],
+            }],
+        }
+    }
+
+    #[test]
+    fn tmx() {
+        assert_eq!(Map::parse_tmx(TMX, Path::new("")).unwrap(), expected());
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(Map::parse_json(JSON, Path::new("")).unwrap(), expected());
+    }
+
+    #[test]
+    fn external_tilesets() {
+        let dir = std::env::temp_dir().join(format!("tiled-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("tiles.tsx"),
+            r#"<tileset name="tiles"><tile id="2"><animation>
+<frame tileid="2" duration="100"/><frame tileid="3" duration="250"/>
+</animation></tile></tileset>"#,
+        )
+        .unwrap();
+        let tmx = r#"<map orientation="orthogonal" tilewidth="16" tileheight="8">
+ <tileset firstgid="1" source="tiles.tsx"/>
+ <layer name="l" width="1" height="1"><data encoding="csv">3</data></layer>
+</map>"#;
+        let map = Map::parse_tmx(tmx, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(map.unwrap().animations, expected().animations);
+    }
+
+    #[test]
+    fn to_bytes() {
+        let map = expected();
+        let bytes = map.to_bytes();
+        let mut r = format::Reader::new(&bytes);
+        let header = format::Header::read(&mut r).unwrap();
+        assert_eq!((header.tile_width, header.tile_height), (16, 8));
+        assert_eq!((header.num_layers, header.num_animations), (3, 1));
+        for layer in &map.layers {
+            let h = format::LayerHeader::read(&mut r).unwrap();
+            assert_eq!(h.name, layer.name);
+            assert_eq!((h.width, h.height, h.visible), (2, 2, layer.visible));
+            assert_eq!((h.parallax, h.offset), (layer.parallax, layer.offset));
+            let mut cells = vec![0; h.len()];
+            format::read_cells(&mut r, &mut cells).unwrap();
+            assert_eq!(cells, layer.cells);
+        }
+        let mut frames = Vec::new();
+        let tile = format::read_animation(&mut r, |i, d| frames.push((i, d))).unwrap();
+        assert_eq!((tile, frames), (2, vec![(2, 100), (3, 250)]));
+        assert!(r.bytes(1).is_err());
+    }
+
+    #[test]
+    fn unsupported_maps() {
+        let error = |tmx: String| {
+            let e = Map::parse_tmx(&tmx, Path::new("")).unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::InvalidData);
+            e.to_string()
+        };
+        let infinite = TMX.replace(r#"infinite="0""#, r#"infinite="1""#);
+        assert_eq!(error(infinite), "infinite maps are not supported");
+        let iso = TMX.replace("orthogonal", "isometric");
+        assert_eq!(error(iso), "only orthogonal maps are supported");
+        let flipped = TMX.replace("3,2\n", "3,2147483650\n");
+        assert_eq!(error(flipped), "flipped or rotated tiles are not supported");
+        let compressed = TMX.replace(
+            r#"encoding="base64""#,
+            r#"encoding="base64" compression="zlib""#,
+        );
+        assert_eq!(error(compressed), "compressed tile data is not supported");
+        let short = TMX.replace("3,2\n", "3\n");
+        assert_eq!(error(short), "tile data does not match the layer size");
+        let tilesets = TMX.replace(" </tileset>\n", " </tileset>\n <tileset firstgid=\"5\"/>\n");
+        assert_eq!(
+            error(tilesets),
+            "only maps with a single tileset are supported"
+        );
+        let base64 = TMX.replace("AQAAAAAAAAADAAAAAgAAAA==", "AQAAAA*=");
+        assert_eq!(error(base64), "invalid base64 tile data");
+    }
+
+    #[test]
+    fn convert_file() {
+        let dir = std::env::temp_dir().join(format!("tiled-convert-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("map.tmj"), JSON).unwrap();
+        convert(dir.join("map.tmj"), dir.join("map.tilemap")).unwrap();
+        let bytes = fs::read(dir.join("map.tilemap"));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(bytes.unwrap(), expected().to_bytes());
+    }
+}
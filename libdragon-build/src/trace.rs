@@ -0,0 +1,209 @@
+//! Host-side conversion of the traces produced by `libdragon::trace` into Chrome trace-event
+//! JSON files, to be opened in Perfetto or `chrome://tracing`.
+//!
+//! The input is either a dump of the USB stream (the raw bytes sent by `usb_write`, with their
+//! `DMA@` framing), from which the trace packets are extracted, or a trace file written by the
+//! N64 directly, e.g. on the SD card. Events cut by the end of a dump are skipped.
+
+use std::{fs, path::Path};
+
+use crate::{json::Json, Result};
+
+/// The event format of `libdragon::trace`
+#[path = "../../libdragon/src/trace/format.rs"]
+pub mod format;
+pub use format::TRACE_MAGIC;
+
+// Framing of the libdragon USB protocol
+const USB_HEADER: &[u8; 4] = b"DMA@";
+const USB_FOOTER: &[u8; 4] = b"CMPH";
+
+/// The events of a trace, each one a JSON object
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub events:  Vec<String>,
+    /// Number of lines that were not valid events, e.g. cut by the end of the dump
+    pub skipped: usize,
+}
+
+impl Trace {
+    /// The trace as a JSON object, in the format expected by the viewers
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"traceEvents\":[\n");
+        out.push_str(&self.events.join(",\n"));
+        out.push_str("\n],\"displayTimeUnit\":\"ms\"}\n");
+        out
+    }
+}
+
+/// Extract the trace data from a dump of the USB stream: the payloads of the packets that
+/// start with [TRACE_MAGIC], concatenated. Data without any USB packet is returned as-is, as
+/// it is a trace file already.
+pub fn extract_usb(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut found = false;
+    let mut pos = 0;
+    while let Some(start) = find(&data[pos..], USB_HEADER).map(|i| pos + i) {
+        let Some(header) = data.get(start + 4..start + 8) else {
+            break;
+        };
+        found = true;
+        // 8-bit data type, then 24-bit size
+        let size = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let payload_start = start + 8;
+        let payload_end = (payload_start + size).min(data.len());
+        let payload = &data[payload_start..payload_end];
+        if let Some(trace) = payload.strip_prefix(TRACE_MAGIC) {
+            out.extend_from_slice(trace);
+        }
+        // the payload can be padded before the footer
+        pos = match find(&data[payload_end..], USB_FOOTER) {
+            Some(i) if i < 16 => payload_end + i + USB_FOOTER.len(),
+            _ => payload_end,
+        };
+    }
+    if found {
+        out
+    } else {
+        data.to_vec()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Parse trace data in the JSON array format written by the N64, one event per line
+pub fn parse(data: &[u8]) -> Trace {
+    let mut trace = Trace::default();
+    for line in String::from_utf8_lossy(data).lines() {
+        let line = line.trim().trim_end_matches(',');
+        if line.is_empty() || line == "[" || line == "]" {
+            continue;
+        }
+        match Json::parse(line) {
+            Ok(Json::Object(_)) => trace.events.push(line.to_owned()),
+            _ => trace.skipped += 1,
+        }
+    }
+    trace
+}
+
+/// Read a USB dump or trace file and write it as a JSON trace
+pub fn convert<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> Result<Trace> {
+    let trace = parse(&extract_usb(&fs::read(input)?));
+    fs::write(output, trace.to_json())?;
+    Ok(trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format::*, *};
+    use std::borrow::Cow;
+
+    // Frame a payload like usb_write does: header, 8-bit type and 24-bit size, payload, padding
+    // and footer
+    fn usb_packet(data_type: u8, payload: &[u8], padding: usize) -> Vec<u8> {
+        let mut out = USB_HEADER.to_vec();
+        out.push(data_type);
+        out.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+        out.extend_from_slice(payload);
+        out.resize(out.len() + padding, 0);
+        out.extend_from_slice(USB_FOOTER);
+        out
+    }
+
+    // A trace serialized on the N64 side, as sent by trace::flush: the header, then 300 events
+    // from a buffer of 200 (so 100 are dropped)
+    fn n64_trace() -> Vec<u8> {
+        let mut buffer = TraceBuffer::new(200);
+        for i in 0..300u64 {
+            let kind = match i % 3 {
+                0 => EventKind::Span { duration: 100 },
+                1 => EventKind::Instant,
+                _ => EventKind::Counter { value: i as f32 },
+            };
+            buffer.push(Event {
+                name: Cow::Owned(format!("event \"{}\"", i)),
+                track: Track::ALL[i as usize % 3],
+                kind,
+                timestamp: i * 1000,
+            });
+        }
+        let mut json = String::new();
+        write_json_header(&mut json);
+        json.push_str(&buffer.take(300_000, 46_875_000));
+        json.into_bytes()
+    }
+
+    // The USB dump of `trace`, with a text packet from another source in the middle
+    fn usb_dump(trace: &[u8]) -> Vec<u8> {
+        let mut dump = Vec::new();
+        let mut count = 0;
+        for_each_packet(trace, |packet| {
+            dump.extend(usb_packet(2, packet, count % 4));
+            if count == 0 {
+                dump.extend(usb_packet(1, b"DMA@ printed text", 3));
+            }
+            count += 1;
+        });
+        assert!(count > 2, "the trace should span several packets");
+        dump
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = n64_trace();
+        assert_eq!(extract_usb(&usb_dump(&data)), data);
+
+        let trace = parse(&extract_usb(&usb_dump(&data)));
+        assert_eq!(trace.skipped, 0);
+        // 3 track names, the dropped events report and the events left in the buffer
+        assert_eq!(trace.events.len(), 3 + 1 + 200);
+        let event = |i: usize| Json::parse(&trace.events[i]).unwrap();
+        assert_eq!(
+            event(3).get("name").and_then(Json::as_str),
+            Some("trace: 100 events dropped")
+        );
+        let last = event(trace.events.len() - 1);
+        assert_eq!(
+            last.get("name").and_then(Json::as_str),
+            Some("event \"299\"")
+        );
+        assert_eq!(last.get("ph").and_then(Json::as_str), Some("C"));
+        assert_eq!(last.get("tid").and_then(Json::as_f64), Some(2.0));
+
+        let json = Json::parse(&trace.to_json()).unwrap();
+        let events = json.get("traceEvents").and_then(Json::as_array).unwrap();
+        assert_eq!(events.len(), trace.events.len());
+    }
+
+    #[test]
+    fn cut_dump() {
+        let data = n64_trace();
+        let dump = usb_dump(&data);
+        // the last packet is cut in the middle of an event
+        let cut = &dump[..dump.len() - 60];
+        let extracted = extract_usb(cut);
+        assert!(data.starts_with(&extracted));
+        let trace = parse(&extracted);
+        assert_eq!(trace.skipped, 1);
+        assert_eq!(trace.events.len(), 3 + 1 + 200 - 1);
+
+        // a header cut before its size
+        let mut cut = usb_dump(&data);
+        cut.extend_from_slice(&USB_HEADER[..]);
+        cut.push(2);
+        assert_eq!(extract_usb(&cut), data);
+    }
+
+    #[test]
+    fn trace_file() {
+        // written directly to a file, without the closing bracket
+        let data = n64_trace();
+        assert_eq!(extract_usb(&data), data);
+        assert_eq!(parse(&data).events.len(), 3 + 1 + 200);
+        let closed = [&data[..], b"]\n"].concat();
+        assert_eq!(parse(&closed).skipped, 0);
+    }
+}
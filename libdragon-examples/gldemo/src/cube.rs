@@ -4,7 +4,10 @@ use libdragon::*;
 
 pub struct Cube;
 
-static CUBE_SIZE: f32 = 3.0;
+const CUBE_SIZE: f32 = 3.0;
+
+const BOUNDS: math::Aabb =
+    math::Aabb::new(math::Vec3::splat(-CUBE_SIZE), math::Vec3::splat(CUBE_SIZE));
 
 #[rustfmt::skip]
 static VERTICES: [Vertex; 24] = [
@@ -58,27 +61,42 @@ impl Cube {
     pub fn new() -> Self { Self {} }
 
     fn draw(&self) {
-        gl::EnableClientState(gl::VERTEX_ARRAY);
-        gl::EnableClientState(gl::TEXTURE_COORD_ARRAY);
-        gl::EnableClientState(gl::NORMAL_ARRAY);
-        gl::EnableClientState(gl::COLOR_ARRAY);
+        gl::EnableClientState(gl::ClientArray::Vertex);
+        gl::EnableClientState(gl::ClientArray::TextureCoord);
+        gl::EnableClientState(gl::ClientArray::Normal);
+        gl::EnableClientState(gl::ClientArray::Color);
 
         let stride = core::mem::size_of::<Vertex>();
-        gl::VertexPointer(3, gl::FLOAT, stride, addr_of!(VERTICES[0].position));
-        gl::TexCoordPointer(2, gl::FLOAT, stride, addr_of!(VERTICES[0].texcoord));
-        gl::NormalPointer(gl::FLOAT, stride, addr_of!(VERTICES[0].normal));
-        gl::ColorPointer(4, gl::UNSIGNED_BYTE, stride, addr_of!(VERTICES[0].color));
+        gl::VertexPointer(
+            3,
+            gl::DataType::Float,
+            stride,
+            addr_of!(VERTICES[0].position),
+        );
+        gl::TexCoordPointer(
+            2,
+            gl::DataType::Float,
+            stride,
+            addr_of!(VERTICES[0].texcoord),
+        );
+        gl::NormalPointer(gl::DataType::Float, stride, addr_of!(VERTICES[0].normal));
+        gl::ColorPointer(
+            4,
+            gl::DataType::UnsignedByte,
+            stride,
+            addr_of!(VERTICES[0].color),
+        );
         gl::DrawElements(
-            gl::TRIANGLES,
+            gl::PrimitiveMode::Triangles,
             INDICES.len(),
-            gl::UNSIGNED_SHORT,
+            gl::IndexType::UnsignedShort,
             addr_of!(INDICES[0]),
         );
 
-        gl::DisableClientState(gl::VERTEX_ARRAY);
-        gl::DisableClientState(gl::TEXTURE_COORD_ARRAY);
-        gl::DisableClientState(gl::NORMAL_ARRAY);
-        gl::DisableClientState(gl::COLOR_ARRAY);
+        gl::DisableClientState(gl::ClientArray::Vertex);
+        gl::DisableClientState(gl::ClientArray::TextureCoord);
+        gl::DisableClientState(gl::ClientArray::Normal);
+        gl::DisableClientState(gl::ClientArray::Color);
     }
 
     pub fn render(&self) {
@@ -86,16 +104,25 @@ impl Cube {
         gl::PushMatrix();
         gl::Translatef(0.0, -1.0, 0.0);
 
+        // Skip the cube when the camera doesn't see it
+        if !cull::Frustum::current().intersects_aabb(&BOUNDS) {
+            gl::PopMatrix();
+            return;
+        }
+
         // Apply vertex color as material color.
         // Because the cube has colors set per vertex, we can color each face seperately
-        gl::Enable(gl::COLOR_MATERIAL);
+        gl::Enable(gl::Capability::ColorMaterial);
 
         // Apply to ambient and diffuse material properties
-        gl::ColorMaterial(gl::FRONT_AND_BACK, gl::AMBIENT_AND_DIFFUSE);
+        gl::ColorMaterial(
+            gl::Face::FrontAndBack,
+            gl::MaterialParameter::AmbientAndDiffuse,
+        );
 
         self.draw();
 
-        gl::Disable(gl::COLOR_MATERIAL);
+        gl::Disable(gl::Capability::ColorMaterial);
 
         gl::PopMatrix();
     }
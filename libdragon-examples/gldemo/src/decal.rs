@@ -6,7 +6,7 @@ impl Decal {
     pub fn new() -> Self { Self {} }
 
     fn draw_quad(&self) {
-        gl::Begin(gl::TRIANGLE_STRIP);
+        gl::Begin(gl::PrimitiveMode::TriangleStrip);
         gl::Normal3f(0.0, 1.0, 0.0);
         gl::TexCoord2f(0.0, 0.0);
         gl::Vertex3f(-0.5, 0.0, -0.5);
@@ -29,21 +29,21 @@ impl Decal {
         gl::Scalef(3.0, 3.0, 3.0);
 
         // Decals are drawn with the depth func set to gl::EQUAL. Note that gl::PolygonOffset is not supported on N64.
-        gl::DepthFunc(gl::EQUAL);
+        gl::DepthFunc(gl::CompareFunc::Equal);
 
         // Disable writing to depth buffer, because the depth value will be the same anyway
-        gl::DepthMask(gl::FALSE);
+        gl::DepthMask(false);
 
         // Apply vertex color as material color.
         // This time, we set one vertex color for the entire model.
-        gl::Enable(gl::COLOR_MATERIAL);
+        gl::Enable(gl::Capability::ColorMaterial);
         gl::Color4f(1.0, 0.4, 0.2, 0.5);
 
         self.draw_quad();
 
-        gl::Disable(gl::COLOR_MATERIAL);
-        gl::DepthMask(gl::TRUE);
-        gl::DepthFunc(gl::LESS);
+        gl::Disable(gl::Capability::ColorMaterial);
+        gl::DepthMask(true);
+        gl::DepthFunc(gl::CompareFunc::Less);
 
         gl::PopMatrix();
     }
@@ -30,6 +30,7 @@ mod prim_test;
 use prim_test::PrimTest;
 
 #[repr(C)]
+#[derive(Copy, Clone)]
 struct Vertex {
     position: [f32; 3],
     texcoord: [f32; 2],
@@ -37,6 +38,9 @@ struct Vertex {
     color:    u32,
 }
 
+unsafe impl gl::Pod for Vertex {}
+gl_vertex!(Vertex { position => position, texcoord => tex_coord, normal => normal });
+
 static ENVIRONMENT_COLOR: [f32; 4] = [0.1, 0.03, 0.2, 1.0];
 
 #[rustfmt::skip]
@@ -98,7 +102,7 @@ impl<'a> App<'a> {
         let near_plane = 1.0;
         let far_plane = 50.0;
 
-        gl::MatrixMode(gl::PROJECTION);
+        gl::MatrixMode(gl::MatrixMode::Projection);
         gl::LoadIdentity();
         gl::Frustum(
             -near_plane * aspect_ratio,
@@ -109,45 +113,61 @@ impl<'a> App<'a> {
             far_plane,
         );
 
-        gl::MatrixMode(gl::MODELVIEW);
+        gl::MatrixMode(gl::MatrixMode::Modelview);
         gl::LoadIdentity();
 
-        gl::LightModelfv(gl::LIGHT_MODEL_AMBIENT, &ENVIRONMENT_COLOR);
-        gl::LightModeli(gl::LIGHT_MODEL_LOCAL_VIEWER, gl::TRUE);
+        gl::LightModelfv(gl::LightModelParameter::Ambient, &ENVIRONMENT_COLOR);
+        gl::LightModeli(gl::LightModelParameter::LocalViewer, gl::TRUE);
 
         let light_radius = 10.0;
-        for i in 0..8 as i32 {
-            gl::Enable(gl::LIGHT0 + i);
-            gl::Lightfv(gl::LIGHT0 + i, gl::DIFFUSE, &LIGHT_DIFFUSE[i as usize]);
-            gl::Lightf(gl::LIGHT0 + i, gl::LINEAR_ATTENUATION, 2.0 / light_radius);
+        for (i, &light) in gl::Light::ALL.iter().enumerate() {
+            gl::Enable(light.into());
+            gl::Lightfv(light, gl::LightParameter::Diffuse, &LIGHT_DIFFUSE[i]);
+            gl::Lightf(
+                light,
+                gl::LightParameter::LinearAttenuation,
+                2.0 / light_radius,
+            );
             gl::Lightf(
-                gl::LIGHT0 + i,
-                gl::QUADRATIC_ATTENUATION,
+                light,
+                gl::LightParameter::QuadraticAttenuation,
                 1.0 / (light_radius * light_radius),
             );
         }
 
         let mat_diffuse = [1.0, 1.0, 1.0, 1.0];
-        gl::Materialfv(gl::FRONT_AND_BACK, gl::AMBIENT_AND_DIFFUSE, &mat_diffuse);
+        gl::Materialfv(
+            gl::Face::FrontAndBack,
+            gl::MaterialParameter::AmbientAndDiffuse,
+            &mat_diffuse,
+        );
 
-        gl::Fogf(gl::FOG_START, 5.0);
-        gl::Fogf(gl::FOG_END, 20.0);
-        gl::Fogfv(gl::FOG_COLOR, &ENVIRONMENT_COLOR);
+        gl::Fogf(gl::FogParameter::Start, 5.0);
+        gl::Fogf(gl::FogParameter::End, 20.0);
+        gl::Fogfv(gl::FogParameter::Color, &ENVIRONMENT_COLOR);
 
-        gl::Enable(gl::MULTISAMPLE_ARB);
+        gl::Enable(gl::Capability::Multisample);
 
         let mut textures = [0u32; 4];
         gl::GenTextures(&mut textures);
 
         let min_filter = gl::LINEAR; // gl::LINEAR_MIPMAP_LINEAR
         for i in 0..4 {
-            gl::BindTexture(gl::TEXTURE_2D, textures[i]);
+            gl::BindTexture(gl::TextureTarget::Texture2D, textures[i]);
 
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter);
+            gl::TexParameteri(
+                gl::TextureTarget::Texture2D,
+                gl::TextureParameter::MagFilter,
+                gl::NEAREST,
+            );
+            gl::TexParameteri(
+                gl::TextureTarget::Texture2D,
+                gl::TextureParameter::MinFilter,
+                min_filter,
+            );
 
             gl::SpriteTextureN64(
-                gl::TEXTURE_2D,
+                gl::TextureTarget::Texture2D,
                 &sprites[i],
                 TexParms {
                     s: TexParmsST {
@@ -187,8 +207,8 @@ impl<'a> App<'a> {
     fn set_light_positions(&self, rotation: f32) {
         gl::PushMatrix();
         gl::Rotatef(rotation * 5.43, 0.0, 1.0, 0.0);
-        for i in 0..8 as i32 {
-            gl::Lightfv(gl::LIGHT0 + i, gl::POSITION, &LIGHT_POS[i as usize]);
+        for (i, &light) in gl::Light::ALL.iter().enumerate() {
+            gl::Lightfv(light, gl::LightParameter::Position, &LIGHT_POS[i]);
         }
         gl::PopMatrix();
     }
@@ -206,21 +226,24 @@ impl<'a> App<'a> {
             ENVIRONMENT_COLOR[2],
             ENVIRONMENT_COLOR[3],
         );
-        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        gl::Clear(gl::ClearMask::COLOR | gl::ClearMask::DEPTH);
 
-        gl::MatrixMode(gl::MODELVIEW);
+        gl::MatrixMode(gl::MatrixMode::Modelview);
         self.camera.transform();
 
         let rotation = (self.animation as f32) * 0.5;
         self.set_light_positions(rotation);
 
-        gl::Enable(gl::LIGHTING);
-        gl::Enable(gl::NORMALIZE);
-        gl::Enable(gl::DEPTH_TEST);
-        gl::Enable(gl::CULL_FACE);
+        gl::Enable(gl::Capability::Lighting);
+        gl::Enable(gl::Capability::Normalize);
+        gl::Enable(gl::Capability::DepthTest);
+        gl::Enable(gl::Capability::CullFace);
 
-        gl::Enable(gl::TEXTURE_2D);
-        gl::BindTexture(gl::TEXTURE_2D, self.textures[self.texture_index]);
+        gl::Enable(gl::Capability::Texture2D);
+        gl::BindTexture(
+            gl::TextureTarget::Texture2D,
+            self.textures[self.texture_index],
+        );
 
         self.plane.render();
         self.decal.render();
@@ -228,18 +251,18 @@ impl<'a> App<'a> {
         self.skinned.render(&self.camera, self.animation as f32);
 
         gl::BindTexture(
-            gl::TEXTURE_2D,
+            gl::TextureTarget::Texture2D,
             self.textures[(self.texture_index + 1) % self.textures.len()],
         );
         self.sphere.render(rotation);
 
-        gl::Disable(gl::TEXTURE_2D);
-        gl::Disable(gl::LIGHTING);
+        gl::Disable(gl::Capability::Texture2D);
+        gl::Disable(gl::Capability::Lighting);
         self.prim_test.render(rotation);
 
         // Draw a primitive with GL_RDPQ_TEXTURING_N64
-        gl::Enable(gl::RDPQ_TEXTURING_N64);
-        gl::Enable(gl::RDPQ_MATERIAL_N64);
+        gl::Enable(gl::Capability::RdpqTexturing);
+        gl::Enable(gl::Capability::RdpqMaterial);
 
         // When rendering with GL_RDPQ_TEXTURING_N64 we need to manualy specify the
         // tile size and if a 0.5 offset should be used since the ucode itself cannot
@@ -264,7 +287,7 @@ impl<'a> App<'a> {
         rdpq::set_mode_standard();
         rdpq::mode_filter(rdpq::Filter::Bilinear);
 
-        gl::Begin(gl::TRIANGLE_FAN);
+        gl::Begin(gl::PrimitiveMode::TriangleFan);
         gl::TexCoord2f(0.0, 0.0);
         gl::Vertex3f(-5.5, 1.0, -1.0);
 
@@ -278,8 +301,8 @@ impl<'a> App<'a> {
         gl::Vertex3f(-3.5, 1.0, -1.0);
         gl::End();
 
-        gl::Disable(gl::RDPQ_TEXTURING_N64);
-        gl::Disable(gl::RDPQ_MATERIAL_N64);
+        gl::Disable(gl::Capability::RdpqTexturing);
+        gl::Disable(gl::Capability::RdpqMaterial);
 
         gl::context_end();
 
@@ -319,7 +342,7 @@ extern "C" fn main() -> ! {
 
     joypad::init();
 
-    let mut shade_model = gl::SMOOTH;
+    let mut shade_model = gl::Shading::Smooth;
     let mut fog_enabled = false;
 
     loop {
@@ -342,10 +365,10 @@ extern "C" fn main() -> ! {
         }
 
         if pressed.r {
-            shade_model = if shade_model == gl::SMOOTH {
-                gl::FLAT
+            shade_model = if shade_model == gl::Shading::Smooth {
+                gl::Shading::Flat
             } else {
-                gl::SMOOTH
+                gl::Shading::Smooth
             };
             gl::ShadeModel(shade_model);
         }
@@ -353,9 +376,9 @@ extern "C" fn main() -> ! {
         if pressed.l {
             fog_enabled = !fog_enabled;
             if fog_enabled {
-                gl::Enable(gl::FOG)
+                gl::Enable(gl::Capability::Fog)
             } else {
-                gl::Disable(gl::FOG)
+                gl::Disable(gl::Capability::Fog)
             }
         }
 
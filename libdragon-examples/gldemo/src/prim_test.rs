@@ -6,7 +6,7 @@ impl PrimTest {
     pub fn new() -> Self { Self {} }
 
     fn points(&self) {
-        gl::Begin(gl::POINTS);
+        gl::Begin(gl::PrimitiveMode::Points);
         gl::Vertex2f(-1.0, -1.0);
         gl::Vertex2f(1.0, -1.0);
         gl::Vertex2f(1.0, 1.0);
@@ -15,7 +15,7 @@ impl PrimTest {
     }
 
     fn lines(&self) {
-        gl::Begin(gl::LINES);
+        gl::Begin(gl::PrimitiveMode::Lines);
         gl::Vertex2f(-1.0, -1.0);
         gl::Vertex2f(1.0, -1.0);
         gl::Vertex2f(-1.0, 0.0);
@@ -26,7 +26,7 @@ impl PrimTest {
     }
 
     fn line_strip(&self) {
-        gl::Begin(gl::LINE_STRIP);
+        gl::Begin(gl::PrimitiveMode::LineStrip);
         gl::Vertex2f(-1.0, -1.0);
         gl::Vertex2f(1.0, -1.0);
         gl::Vertex2f(1.0, 1.0);
@@ -35,7 +35,7 @@ impl PrimTest {
     }
 
     fn line_loop(&self) {
-        gl::Begin(gl::LINE_LOOP);
+        gl::Begin(gl::PrimitiveMode::LineLoop);
         gl::Vertex2f(-1.0, -1.0);
         gl::Vertex2f(1.0, -1.0);
         gl::Vertex2f(1.0, 1.0);
@@ -44,7 +44,7 @@ impl PrimTest {
     }
 
     fn triangles(&self) {
-        gl::Begin(gl::TRIANGLES);
+        gl::Begin(gl::PrimitiveMode::Triangles);
         gl::Vertex2f(-1.0, -1.0);
         gl::Vertex2f(0.0, -1.0);
         gl::Vertex2f(-1.0, 0.0);
@@ -55,7 +55,7 @@ impl PrimTest {
     }
 
     fn triangle_strip(&self) {
-        gl::Begin(gl::TRIANGLE_STRIP);
+        gl::Begin(gl::PrimitiveMode::TriangleStrip);
         gl::Vertex2f(-1.0, -1.0);
         gl::Vertex2f(1.0, -1.0);
         gl::Vertex2f(-1.0, 1.0);
@@ -64,7 +64,7 @@ impl PrimTest {
     }
 
     fn triangle_fan(&self) {
-        gl::Begin(gl::TRIANGLE_FAN);
+        gl::Begin(gl::PrimitiveMode::TriangleFan);
         gl::Vertex2f(0.0, 0.0);
         gl::Vertex2f(-1.0, 0.0);
         gl::Vertex2f(0.0, -1.0);
@@ -75,7 +75,7 @@ impl PrimTest {
     }
 
     fn quads(&self) {
-        gl::Begin(gl::QUADS);
+        gl::Begin(gl::PrimitiveMode::Quads);
         gl::Vertex2f(-1.0, -1.0);
         gl::Vertex2f(0.0, -1.0);
         gl::Vertex2f(0.0, 0.0);
@@ -88,7 +88,7 @@ impl PrimTest {
     }
 
     fn quad_strip(&self) {
-        gl::Begin(gl::QUAD_STRIP);
+        gl::Begin(gl::PrimitiveMode::QuadStrip);
         gl::Vertex2f(-1.0, -1.0);
         gl::Vertex2f(1.0, -1.0);
         gl::Vertex2f(-0.50, 0.0);
@@ -99,7 +99,7 @@ impl PrimTest {
     }
 
     fn polygon(&self) {
-        gl::Begin(gl::POLYGON);
+        gl::Begin(gl::PrimitiveMode::Polygon);
         gl::Vertex2f(-1.0, 0.0);
         gl::Vertex2f(-0.750, -0.750);
         gl::Vertex2f(0.0, -1.0);
@@ -171,23 +171,23 @@ impl PrimTest {
         gl::Rotatef(-rotation * 2.46, 0.0, 1.0, 0.0);
 
         // Configure alpha blending (transparency)
-        gl::Enable(gl::BLEND);
-        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl::Enable(gl::Capability::Blend);
+        gl::BlendFunc(gl::BlendFactor::SrcAlpha, gl::BlendFactor::OneMinusSrcAlpha);
 
         // Set a constant alpha for all vertices
         gl::Color4f(1.0, 1.0, 1.0, 0.4);
 
         // We want to see back faces as well
-        gl::Disable(gl::CULL_FACE);
+        gl::Disable(gl::Capability::CullFace);
 
         // Transparent polygons should not write to the depth buffer
-        gl::DepthMask(gl::FALSE);
+        gl::DepthMask(false);
 
         self.prim_test();
 
-        gl::DepthMask(gl::TRUE);
-        gl::Enable(gl::CULL_FACE);
-        gl::Disable(gl::BLEND);
+        gl::DepthMask(true);
+        gl::Enable(gl::Capability::CullFace);
+        gl::Disable(gl::Capability::Blend);
 
         gl::PopMatrix();
     }
@@ -29,32 +29,42 @@ impl Skinned {
     pub fn new() -> Self { Self {} }
 
     fn draw(&self) {
-        gl::Enable(gl::MATRIX_PALETTE_ARB);
+        gl::Enable(gl::Capability::MatrixPalette);
 
-        gl::EnableClientState(gl::VERTEX_ARRAY);
-        gl::EnableClientState(gl::TEXTURE_COORD_ARRAY);
-        gl::EnableClientState(gl::NORMAL_ARRAY);
-        gl::EnableClientState(gl::MATRIX_INDEX_ARRAY_ARB);
+        gl::EnableClientState(gl::ClientArray::Vertex);
+        gl::EnableClientState(gl::ClientArray::TextureCoord);
+        gl::EnableClientState(gl::ClientArray::Normal);
+        gl::EnableClientState(gl::ClientArray::MatrixIndex);
 
         let stride = core::mem::size_of::<SkinnedVertex>();
-        gl::VertexPointer(3, gl::FLOAT, stride, addr_of!(VERTICES[0].position));
-        gl::TexCoordPointer(2, gl::FLOAT, stride, addr_of!(VERTICES[0].texcoord));
-        gl::NormalPointer(gl::FLOAT, stride, addr_of!(VERTICES[0].normal));
+        gl::VertexPointer(
+            3,
+            gl::DataType::Float,
+            stride,
+            addr_of!(VERTICES[0].position),
+        );
+        gl::TexCoordPointer(
+            2,
+            gl::DataType::Float,
+            stride,
+            addr_of!(VERTICES[0].texcoord),
+        );
+        gl::NormalPointer(gl::DataType::Float, stride, addr_of!(VERTICES[0].normal));
         gl::MatrixIndexPointerARB(
             1,
-            gl::UNSIGNED_BYTE,
+            gl::DataType::UnsignedByte,
             stride,
             addr_of!(VERTICES[0].mtx_index),
         );
 
-        gl::DrawArrays(gl::TRIANGLE_STRIP, 0, VERTICES.len());
+        gl::DrawArrays(gl::PrimitiveMode::TriangleStrip, 0, VERTICES.len());
 
-        gl::DisableClientState(gl::VERTEX_ARRAY);
-        gl::DisableClientState(gl::TEXTURE_COORD_ARRAY);
-        gl::DisableClientState(gl::NORMAL_ARRAY);
-        gl::DisableClientState(gl::MATRIX_INDEX_ARRAY_ARB);
+        gl::DisableClientState(gl::ClientArray::Vertex);
+        gl::DisableClientState(gl::ClientArray::TextureCoord);
+        gl::DisableClientState(gl::ClientArray::Normal);
+        gl::DisableClientState(gl::ClientArray::MatrixIndex);
 
-        gl::Disable(gl::MATRIX_PALETTE_ARB);
+        gl::Disable(gl::Capability::MatrixPalette);
     }
 
     pub fn render(&self, _camera: &Camera, animation: f32) {
@@ -66,23 +76,23 @@ impl Skinned {
 
         // Set bone transforms. Note that because there is no matrix stack in palette mode, we need
         // to apply the camera transform and model transform as well for each bone.
-        gl::MatrixMode(gl::MATRIX_PALETTE_ARB);
+        gl::MatrixMode(gl::MatrixMode::MatrixPalette);
 
         // Set transform of first bone
         gl::CurrentPaletteMatrixARB(0);
-        gl::CopyMatrixN64(gl::MODELVIEW);
+        gl::CopyMatrixN64(gl::MatrixMode::Modelview);
         gl::Rotatef((animation * 0.1).sin() * 45.0, 0.0, 0.0, 1.0);
 
         // Set transform of second bone
         gl::CurrentPaletteMatrixARB(1);
-        gl::CopyMatrixN64(gl::MODELVIEW);
+        gl::CopyMatrixN64(gl::MatrixMode::Modelview);
         gl::Rotatef(-(animation * 0.1).sin() * 45.0, 0.0, 0.0, 1.0);
 
-        gl::MatrixMode(gl::MODELVIEW);
+        gl::MatrixMode(gl::MatrixMode::Modelview);
 
-        gl::Disable(gl::CULL_FACE);
+        gl::Disable(gl::Capability::CullFace);
         self.draw();
-        gl::Enable(gl::CULL_FACE);
+        gl::Enable(gl::Capability::CullFace);
 
         gl::PopMatrix();
     }
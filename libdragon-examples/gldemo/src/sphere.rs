@@ -30,28 +30,32 @@ impl Sphere {
         gl::GenVertexArrays(&mut sphere_array);
         gl::BindVertexArray(sphere_array[0]);
 
-        gl::EnableClientState(gl::VERTEX_ARRAY);
-        gl::EnableClientState(gl::TEXTURE_COORD_ARRAY);
-        gl::EnableClientState(gl::NORMAL_ARRAY);
+        gl::EnableClientState(gl::ClientArray::Vertex);
+        gl::EnableClientState(gl::ClientArray::TextureCoord);
+        gl::EnableClientState(gl::ClientArray::Normal);
 
-        gl::BindBufferARB(gl::ARRAY_BUFFER_ARB, sphere_buffers[0]);
+        gl::BindBufferARB(gl::BufferTarget::Array, sphere_buffers[0]);
 
         let stride = core::mem::size_of::<Vertex>();
         gl::VertexPointer(
             3,
-            gl::FLOAT,
+            gl::DataType::Float,
             stride,
             offset_of!(Vertex, position) as *const f32,
         );
         gl::TexCoordPointer(
             2,
-            gl::FLOAT,
+            gl::DataType::Float,
             stride,
             offset_of!(Vertex, texcoord) as *const f32,
         );
-        gl::NormalPointer(gl::FLOAT, stride, offset_of!(Vertex, normal) as *const f32);
+        gl::NormalPointer(
+            gl::DataType::Float,
+            stride,
+            offset_of!(Vertex, normal) as *const f32,
+        );
 
-        gl::BindBufferARB(gl::ARRAY_BUFFER_ARB, 0);
+        gl::BindBufferARB(gl::BufferTarget::Array, 0);
 
         gl::BindVertexArray(0);
 
@@ -101,15 +105,15 @@ impl Sphere {
         let sphere_vertex_count = self.sphere_rings * self.sphere_segments + 2;
 
         let vtsize = core::mem::size_of::<Vertex>();
-        gl::BindBufferARB(gl::ARRAY_BUFFER_ARB, self.sphere_buffers[0]);
+        gl::BindBufferARB(gl::BufferTarget::Array, self.sphere_buffers[0]);
         gl::BufferDataARB(
-            gl::ARRAY_BUFFER_ARB,
+            gl::BufferTarget::Array,
             sphere_vertex_count * vtsize,
             ::core::ptr::null::<u8>(),
-            gl::STATIC_DRAW_ARB,
+            gl::BufferUsage::StaticDraw,
         );
 
-        let vertex_data = gl::MapBufferARB(gl::ARRAY_BUFFER_ARB, gl::WRITE_ONLY_ARB);
+        let vertex_data = gl::MapBufferARB(gl::BufferTarget::Array, gl::BufferAccess::WriteOnly);
         let vertices: &mut [Vertex] =
             unsafe { ::core::slice::from_raw_parts_mut(vertex_data, sphere_vertex_count) };
 
@@ -123,23 +127,24 @@ impl Sphere {
 
         vertices[sphere_vertex_count - 1] = self.make_sphere_vertex(self.sphere_rings + 1, 0);
 
-        gl::UnmapBufferARB(gl::ARRAY_BUFFER_ARB);
-        gl::BindBufferARB(gl::ARRAY_BUFFER_ARB, 0);
+        gl::UnmapBufferARB(gl::BufferTarget::Array);
+        gl::BindBufferARB(gl::BufferTarget::Array, 0);
 
         let fan_index_count = self.sphere_segments + 2;
         let ring_index_count = self.sphere_segments * 6;
 
         self.sphere_index_count = fan_index_count * 2 + ring_index_count * (self.sphere_rings - 1);
 
-        gl::BindBufferARB(gl::ELEMENT_ARRAY_BUFFER_ARB, self.sphere_buffers[1]);
+        gl::BindBufferARB(gl::BufferTarget::ElementArray, self.sphere_buffers[1]);
         gl::BufferDataARB(
-            gl::ELEMENT_ARRAY_BUFFER_ARB,
+            gl::BufferTarget::ElementArray,
             self.sphere_index_count * ::core::mem::size_of::<u16>(),
             ::core::ptr::null::<u8>(),
-            gl::STATIC_DRAW_ARB,
+            gl::BufferUsage::StaticDraw,
         );
 
-        let index_data = gl::MapBufferARB(gl::ELEMENT_ARRAY_BUFFER_ARB, gl::WRITE_ONLY_ARB);
+        let index_data =
+            gl::MapBufferARB(gl::BufferTarget::ElementArray, gl::BufferAccess::WriteOnly);
         let indices: &mut [u16] =
             unsafe { ::core::slice::from_raw_parts_mut(index_data, self.sphere_index_count) };
 
@@ -171,39 +176,39 @@ impl Sphere {
             }
         }
 
-        gl::UnmapBufferARB(gl::ELEMENT_ARRAY_BUFFER_ARB);
-        gl::BindBufferARB(gl::ELEMENT_ARRAY_BUFFER_ARB, 0);
+        gl::UnmapBufferARB(gl::BufferTarget::ElementArray);
+        gl::BindBufferARB(gl::BufferTarget::ElementArray, 0);
 
-        gl::NewList(self.sphere_list, gl::COMPILE);
+        gl::NewList(self.sphere_list, gl::ListMode::Compile);
         self.draw_sphere_internal();
         gl::EndList();
     }
 
     fn draw_sphere_internal(&self) {
-        gl::BindBufferARB(gl::ELEMENT_ARRAY_BUFFER_ARB, self.sphere_buffers[1]);
+        gl::BindBufferARB(gl::BufferTarget::ElementArray, self.sphere_buffers[1]);
         gl::BindVertexArray(self.sphere_array);
 
         gl::DrawElements(
-            gl::TRIANGLE_FAN,
+            gl::PrimitiveMode::TriangleFan,
             self.sphere_segments + 2,
-            gl::UNSIGNED_SHORT,
+            gl::IndexType::UnsignedShort,
             0 as *const u8,
         );
         gl::DrawElements(
-            gl::TRIANGLE_FAN,
+            gl::PrimitiveMode::TriangleFan,
             self.sphere_segments + 2,
-            gl::UNSIGNED_SHORT,
+            gl::IndexType::UnsignedShort,
             ((self.sphere_segments + 2) * ::core::mem::size_of::<u16>()) as *const u8,
         );
         gl::DrawElements(
-            gl::TRIANGLES,
+            gl::PrimitiveMode::Triangles,
             (self.sphere_rings - 1) * (self.sphere_segments * 6),
-            gl::UNSIGNED_SHORT,
+            gl::IndexType::UnsignedShort,
             ((self.sphere_segments + 2) * 2 * ::core::mem::size_of::<u16>()) as *const u8,
         );
 
         gl::BindVertexArray(0);
-        gl::BindBufferARB(gl::ELEMENT_ARRAY_BUFFER_ARB, 0);
+        gl::BindBufferARB(gl::BufferTarget::ElementArray, 0);
     }
 
     fn draw(&self) { gl::CallList(self.sphere_list); }
@@ -218,9 +223,9 @@ impl Sphere {
         gl::Rotatef(rotation * 1.71, 0.0, 1.0, 0.0);
 
         // We want to see back faces instead of front faces, because the camera will be inside the sphere
-        gl::CullFace(gl::FRONT);
+        gl::CullFace(gl::Face::Front);
         self.draw();
-        gl::CullFace(gl::BACK);
+        gl::CullFace(gl::Face::Back);
 
         gl::PopMatrix();
     }
@@ -22,7 +22,7 @@ impl<'a> App<'a> {
         let near_plane = 1.0;
         let far_plane = 50.0;
 
-        gl::MatrixMode(gl::PROJECTION);
+        gl::MatrixMode(gl::MatrixMode::Projection);
         gl::LoadIdentity();
         gl::Frustum(
             -near_plane * aspect_ratio,
@@ -33,17 +33,21 @@ impl<'a> App<'a> {
             far_plane,
         );
 
-        gl::MatrixMode(gl::MODELVIEW);
+        gl::MatrixMode(gl::MatrixMode::Modelview);
         gl::LoadIdentity();
 
         let mat_diffuse = [1.0, 1.0, 1.0, 1.0];
-        gl::Materialfv(gl::FRONT_AND_BACK, gl::AMBIENT_AND_DIFFUSE, &mat_diffuse);
+        gl::Materialfv(
+            gl::Face::FrontAndBack,
+            gl::MaterialParameter::AmbientAndDiffuse,
+            &mat_diffuse,
+        );
 
-        gl::Fogf(gl::FOG_START, 5.0);
-        gl::Fogf(gl::FOG_END, 20.0);
-        gl::Fogfv(gl::FOG_COLOR, &[1.0, 1.0, 1.0, 1.0]);
+        gl::Fogf(gl::FogParameter::Start, 5.0);
+        gl::Fogf(gl::FogParameter::End, 20.0);
+        gl::Fogfv(gl::FogParameter::Color, &[1.0, 1.0, 1.0, 1.0]);
 
-        gl::Enable(gl::MULTISAMPLE_ARB);
+        gl::Enable(gl::Capability::Multisample);
 
         rspq::profile_start();
 
@@ -66,27 +70,27 @@ impl<'a> App<'a> {
         gl::context_begin();
 
         gl::ClearColor(1.0, 1.0, 1.0, 1.0);
-        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        gl::Clear(gl::ClearMask::COLOR | gl::ClearMask::DEPTH);
 
-        gl::MatrixMode(gl::MODELVIEW);
+        gl::MatrixMode(gl::MatrixMode::Modelview);
         self.camera.transform();
 
-        //gl::Enable(gl::LIGHTING);
-        gl::Enable(gl::NORMALIZE);
-        gl::Enable(gl::DEPTH_TEST);
-        gl::Enable(gl::CULL_FACE);
+        //gl::Enable(gl::Capability::Lighting);
+        gl::Enable(gl::Capability::Normalize);
+        gl::Enable(gl::Capability::DepthTest);
+        gl::Enable(gl::Capability::CullFace);
         gl::PushMatrix();
         gl::Rotatef(90.0, 1.0, 0.0, 0.0);
-        gl::Enable(gl::COLOR_MATERIAL);
+        gl::Enable(gl::Capability::ColorMaterial);
         gl::Color4f(0.0, 0.0, 0.0, 0.5);
         for i in 0..self.model.get_mesh_count() {
             self.model.get_mesh(i).draw();
         }
-        gl::Disable(gl::COLOR_MATERIAL);
+        gl::Disable(gl::Capability::ColorMaterial);
         gl::PopMatrix();
 
-        //gl::Disable(gl::TEXTURE_2D);
-        //gl::Disable(gl::LIGHTING);
+        //gl::Disable(gl::Capability::Texture2D);
+        //gl::Disable(gl::Capability::Lighting);
 
         gl::context_end();
 
@@ -126,7 +130,7 @@ extern "C" fn main() -> ! {
 
     joypad::init();
 
-    let mut shade_model = gl::SMOOTH;
+    let mut shade_model = gl::Shading::Smooth;
     let mut fog_enabled = false;
 
     loop {
@@ -149,10 +153,10 @@ extern "C" fn main() -> ! {
         }
 
         if pressed.r {
-            shade_model = if shade_model == gl::SMOOTH {
-                gl::FLAT
+            shade_model = if shade_model == gl::Shading::Smooth {
+                gl::Shading::Flat
             } else {
-                gl::SMOOTH
+                gl::Shading::Smooth
             };
             gl::ShadeModel(shade_model);
         }
@@ -160,9 +164,9 @@ extern "C" fn main() -> ! {
         if pressed.l {
             fog_enabled = !fog_enabled;
             if fog_enabled {
-                gl::Enable(gl::FOG)
+                gl::Enable(gl::Capability::Fog)
             } else {
-                gl::Disable(gl::FOG)
+                gl::Disable(gl::Capability::Fog)
             }
         }
 
@@ -56,62 +56,59 @@ impl<'a> App<'a> {
         let tiles_sprite = Sprite::load(dfs::PathBuf::from("rom:/tiles.sprite")).unwrap();
 
         // Create a block for the background, so that we can replay it later.
-        rspq::Block::begin();
-
-        // Check if the sprite was compiled with a paletted format. Normally
-        // we should know this beforehand, but for this demo we pretend we don't
-        // know. This also shows how rdpq can transparently work in both modes.
-        let mut tlut = false;
-        let format = tiles_sprite.get_format();
-        if format == TexFormat::Ci4 || format == TexFormat::Ci8 {
-            // If the sprite is paletted, turn on palette mode and load the
-            // palette in TMEM. We use the mode stack for demonstration,
-            // so that we show how a block can temporarily change the current
-            // render mode, and then restore it at the end.
-            rdpq::mode_push();
-            rdpq::mode_tlut(rdpq::Tlut::Rgba16);
-            unsafe {
-                rdpq::tex_upload_tlut(tiles_sprite.get_palette(), 0, 16);
+        let tiles_block = rspq::Block::record(|_| {
+            // Check if the sprite was compiled with a paletted format. Normally
+            // we should know this beforehand, but for this demo we pretend we don't
+            // know. This also shows how rdpq can transparently work in both modes.
+            let mut tlut = false;
+            let format = tiles_sprite.get_format();
+            if format == TexFormat::Ci4 || format == TexFormat::Ci8 {
+                // If the sprite is paletted, turn on palette mode and load the
+                // palette in TMEM. We use the mode stack for demonstration,
+                // so that we show how a block can temporarily change the current
+                // render mode, and then restore it at the end.
+                rdpq::mode_push();
+                rdpq::mode_tlut(rdpq::Tlut::Rgba16);
+                rdpq::tex_upload_tlut(tiles_sprite.get_palette().unwrap(), 0, 16);
+                tlut = true;
             }
-            tlut = true;
-        }
-
-        let tile_width = tiles_sprite.width() / (tiles_sprite.hslices() as u16);
-        let tile_height = tiles_sprite.height() / (tiles_sprite.vslices() as u16);
 
-        // block off tiles_surf so that it's dropped before the move of tiles_sprite above
-        {
-            let tiles_surf = tiles_sprite.get_pixels();
-            for ty in (0..display_height).step_by(tile_height as usize) {
-                for tx in (0..display_width).step_by(tile_width as usize) {
-                    let s = (rng.next_u32() % 2) * 32;
-                    let t = (rng.next_u32() % 2) * 32;
-                    rdpq::tex_upload_sub(
-                        rdpq::Tile(0),
-                        &tiles_surf,
-                        None,
-                        s as i32,
-                        t as i32,
-                        (s + 32) as i32,
-                        (t + 32) as i32,
-                    );
-                    rdpq::texture_rectangle(
-                        rdpq::Tile(0),
-                        tx as i32,
-                        ty as i32,
-                        (tx + 32) as i32,
-                        (ty + 32) as i32,
-                        s as i32,
-                        t as i32,
-                    );
+            let tile_width = tiles_sprite.width() / (tiles_sprite.hslices() as u16);
+            let tile_height = tiles_sprite.height() / (tiles_sprite.vslices() as u16);
+
+            // block off tiles_surf so that it's dropped before the move of tiles_sprite above
+            {
+                let tiles_surf = tiles_sprite.get_pixels();
+                for ty in (0..display_height).step_by(tile_height as usize) {
+                    for tx in (0..display_width).step_by(tile_width as usize) {
+                        let s = (rng.next_u32() % 2) * 32;
+                        let t = (rng.next_u32() % 2) * 32;
+                        rdpq::tex_upload_sub(
+                            rdpq::Tile(0),
+                            &tiles_surf,
+                            None,
+                            s as i32,
+                            t as i32,
+                            (s + 32) as i32,
+                            (t + 32) as i32,
+                        );
+                        rdpq::texture_rectangle(
+                            rdpq::Tile(0),
+                            tx as i32,
+                            ty as i32,
+                            (tx + 32) as i32,
+                            (ty + 32) as i32,
+                            s as i32,
+                            t as i32,
+                        );
+                    }
                 }
             }
-        }
 
-        if tlut {
-            rdpq::mode_pop();
-        }
-        let tiles_block = rspq::Block::end();
+            if tlut {
+                rdpq::mode_pop();
+            }
+        });
 
         Self {
             brew_sprite,
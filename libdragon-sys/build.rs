@@ -243,6 +243,32 @@ async fn main() -> Result<()> {
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
 
+    // Private headers (e.g. model64_internal.h) needed to inspect opaque structs. They are bound
+    // separately, into `libdragon_sys::internal`, and only with the `internal` feature: their
+    // layouts are tied to the libdragon submodule and not covered by its public API.
+    if env::var_os("CARGO_FEATURE_INTERNAL").is_some() {
+        println!("cargo:rerun-if-changed=internal.h");
+        let internal_bindings = bindgen::Builder::default()
+            .clang_arg(format!(
+                "-I{}/mips64-libdragon-elf/include",
+                toolchain_dir.display()
+            ))
+            .clang_arg(format!("-I{}/src", libdragon_dir.display()))
+            .clang_args(&["-target", "mips-nintendo64-none", "-mabi=n32", "-DN64"])
+            .header("internal.h")
+            .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+            .parse_callbacks(Box::new(Cb {}))
+            .use_core()
+            // only the private declarations, everything else comes from the public bindings
+            .allowlist_file(".*_internal\\.h")
+            .allowlist_recursively(false)
+            .generate()
+            .expect("Unable to generate the internal bindings");
+        internal_bindings
+            .write_to_file(out_path.join("internal_bindings.rs"))
+            .expect("Couldn't write internal bindings!");
+    }
+
     // Compile the static_fns file
     // The compile arguments are taken from n64.mk, so they need to be kept in sync.
     let static_fns_obj_path = out_dir.clone().join("static_fns.o");
@@ -0,0 +1,7 @@
+// Private libdragon headers, bound only with the `internal` feature.
+//
+// These structs are not part of libdragon's public API and change without notice. The layouts
+// match the `preview` branch checked out in the `libdragon` submodule, and must be checked again
+// whenever the submodule is updated.
+#include <libdragon.h>
+#include <model64_internal.h>
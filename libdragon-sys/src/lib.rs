@@ -12,3 +12,16 @@
 #![allow(clippy::ptr_offset_with_cast)]
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+/// Bindings to private libdragon headers (e.g. `model64_internal.h`), generated with the
+/// `internal` feature.
+///
+/// These structs are not part of libdragon's public API: their layouts match the `preview` branch
+/// checked out in the `libdragon` submodule, and may change whenever it is updated.
+#[cfg(feature = "internal")]
+#[doc(hidden)]
+pub mod internal {
+    use super::*;
+
+    include!(concat!(env!("OUT_DIR"), "/internal_bindings.rs"));
+}
@@ -0,0 +1,358 @@
+//! Sprite sheet animation clips and their playback, independent of rendering.
+//!
+//! This module only depends on `core` and `alloc`. It is shared with `libdragon-build`, so the
+//! timing logic behind [anim](crate::anim) can be tested on a development host.
+
+use alloc::vec::Vec;
+
+/// How a [Clip] behaves when it reaches its last frame
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum PlayMode {
+    /// Play the frames once and stop on the last one
+    Once,
+    /// Restart from the first frame
+    #[default]
+    Loop,
+    /// Play the frames forwards then backwards, repeatedly (end frames are not shown twice)
+    PingPong,
+}
+
+/// A single frame of a [Clip]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frame {
+    /// Index of the tile in the sprite sheet, counted left to right, top to bottom
+    pub index:    u16,
+    /// How long the frame is displayed, in seconds
+    pub duration: f32,
+}
+
+/// An animation clip: a list of sprite sheet frames with their durations.
+///
+/// ```rust
+/// let walk = anim::Clip::uniform(&[0, 1, 2, 3], 0.1, anim::PlayMode::Loop);
+/// let mut animator = anim::Animator::new(&walk);
+/// loop {
+///     animator.update(1.0 / 60.0);
+///     animator.draw(&sheet, x, y, rdpq::BlitParms::default());
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Clip {
+    frames: Vec<Frame>,
+    mode:   PlayMode,
+}
+
+impl Clip {
+    /// Create an empty clip. Add frames with [Clip::frame].
+    pub fn new(mode: PlayMode) -> Self {
+        Self {
+            frames: Vec::new(),
+            mode,
+        }
+    }
+
+    /// Create a clip where every frame lasts `duration` seconds
+    pub fn uniform(indices: &[u16], duration: f32, mode: PlayMode) -> Self {
+        let mut clip = Self::new(mode);
+        for &index in indices {
+            clip.frame(index, duration);
+        }
+        clip
+    }
+
+    /// Create a clip playing `count` consecutive tiles starting at `first`, at `fps` frames per
+    /// second.
+    ///
+    /// Panics if the last tile index does not fit in a `u16`.
+    pub fn range(first: u16, count: u16, fps: f32, mode: PlayMode) -> Self {
+        let end = first as u32 + count as u32;
+        assert!(end <= 1 << 16, "tile range out of bounds");
+        let mut clip = Self::new(mode);
+        for index in first as u32..end {
+            clip.frame(index as u16, 1.0 / fps);
+        }
+        clip
+    }
+
+    /// Append a frame
+    pub fn frame(&mut self, index: u16, duration: f32) -> &mut Self {
+        assert!(duration > 0.0, "frame duration must be positive");
+        self.frames.push(Frame { index, duration });
+        self
+    }
+
+    /// The frames of the clip
+    #[inline]
+    pub fn frames(&self) -> &[Frame] { &self.frames }
+    /// The play mode of the clip
+    #[inline]
+    pub fn mode(&self) -> PlayMode { self.mode }
+
+    /// Duration of a single pass over the frames, in seconds
+    pub fn duration(&self) -> f32 { self.frames.iter().map(|f| f.duration).sum() }
+
+    /// Duration after which the animation repeats (infinite for [PlayMode::Once])
+    pub fn period(&self) -> f32 {
+        match self.mode {
+            PlayMode::Once => f32::INFINITY,
+            PlayMode::Loop => self.duration(),
+            PlayMode::PingPong => {
+                let n = self.frames.len();
+                let back: f32 = if n > 2 {
+                    self.frames[1..n - 1].iter().map(|f| f.duration).sum()
+                } else {
+                    0.0
+                };
+                self.duration() + back
+            }
+        }
+    }
+
+    /// Returns true if a [PlayMode::Once] clip has reached its end at `time`
+    pub fn is_finished(&self, time: f32) -> bool {
+        self.mode == PlayMode::Once && time >= self.duration()
+    }
+
+    /// Position (in [Clip::frames]) of the frame shown at `time` seconds from the start
+    pub fn position_at(&self, time: f32) -> usize {
+        let n = self.frames.len();
+        if n <= 1 {
+            return 0;
+        }
+        let time = time.max(0.0);
+        let total = self.duration();
+        let t = match self.mode {
+            PlayMode::Once if time >= total => return n - 1,
+            PlayMode::Once => time,
+            PlayMode::Loop | PlayMode::PingPong => time % self.period(),
+        };
+
+        if t < total {
+            return Self::find(self.frames.iter().enumerate(), t).unwrap_or(n - 1);
+        }
+        // ping-pong backwards pass, from the second to last frame down to the second one
+        let back = self.frames.iter().enumerate().skip(1).take(n - 2).rev();
+        Self::find(back, t - total).unwrap_or(1)
+    }
+
+    fn find<'f>(mut frames: impl Iterator<Item = (usize, &'f Frame)>, mut t: f32) -> Option<usize> {
+        frames.find_map(|(i, f)| {
+            if t < f.duration {
+                Some(i)
+            } else {
+                t -= f.duration;
+                None
+            }
+        })
+    }
+
+    /// Sprite sheet index of the frame shown at `time` seconds from the start
+    pub fn index_at(&self, time: f32) -> u16 {
+        self.frames
+            .get(self.position_at(time))
+            .map_or(0, |f| f.index)
+    }
+}
+
+/// Plays a [Clip], advanced by delta time.
+#[derive(Debug, Clone)]
+pub struct Animator<'a> {
+    clip:    &'a Clip,
+    time:    f32,
+    speed:   f32,
+    playing: bool,
+}
+
+impl<'a> Animator<'a> {
+    /// Start playing `clip` from its first frame
+    pub fn new(clip: &'a Clip) -> Self {
+        Self {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            playing: true,
+        }
+    }
+
+    /// Switch to another clip, restarting from its first frame. Does nothing if `clip` is
+    /// already playing, so it can be called every frame.
+    pub fn play(&mut self, clip: &'a Clip) {
+        if !core::ptr::eq(self.clip, clip) {
+            self.clip = clip;
+            self.time = 0.0;
+        }
+        self.playing = true;
+    }
+
+    /// Advance the animation by `dt` seconds (scaled by the speed, which can be negative to play
+    /// backwards)
+    pub fn update(&mut self, dt: f32) {
+        if !self.playing {
+            return;
+        }
+        self.time += dt * self.speed;
+        // keep the time bounded so that long-running loops don't lose precision
+        let period = self.clip.period();
+        if period.is_finite() && period > 0.0 {
+            // like rem_euclid, which is not available in core
+            self.time %= period;
+            if self.time < 0.0 {
+                self.time += period;
+            }
+        } else {
+            self.time = self.time.clamp(0.0, self.clip.duration());
+        }
+    }
+
+    /// Restart the current clip
+    pub fn reset(&mut self) { self.time = 0.0; }
+    /// Pause the animation on the current frame
+    pub fn pause(&mut self) { self.playing = false; }
+    /// Resume a paused animation
+    pub fn resume(&mut self) { self.playing = true; }
+    /// Returns true if the animation is not paused
+    pub fn is_playing(&self) -> bool { self.playing }
+    /// Playback speed multiplier (1.0 = normal speed, negative values play backwards)
+    pub fn set_speed(&mut self, speed: f32) { self.speed = speed; }
+
+    /// The clip being played
+    pub fn clip(&self) -> &'a Clip { self.clip }
+    /// Time elapsed since the clip started, in seconds
+    pub fn time(&self) -> f32 { self.time }
+    /// Returns true if a [PlayMode::Once] clip has reached its last frame
+    pub fn is_finished(&self) -> bool { self.clip.is_finished(self.time) }
+    /// Sprite sheet index of the current frame
+    pub fn current_frame(&self) -> u16 { self.clip.index_at(self.time) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // frames of 0.25s, exactly representable so that comparisons are exact
+    fn clip(mode: PlayMode) -> Clip { Clip::uniform(&[10, 11, 12, 13], 0.25, mode) }
+
+    fn positions(clip: &Clip, times: &[f32]) -> Vec<usize> {
+        times.iter().map(|&t| clip.position_at(t)).collect()
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(clip(PlayMode::Loop).duration(), 1.0);
+        assert_eq!(clip(PlayMode::Loop).period(), 1.0);
+        assert_eq!(clip(PlayMode::PingPong).period(), 1.5);
+        assert_eq!(clip(PlayMode::Once).period(), f32::INFINITY);
+        assert_eq!(
+            Clip::uniform(&[1, 2], 0.5, PlayMode::PingPong).period(),
+            1.0
+        );
+    }
+
+    #[test]
+    fn loop_and_once() {
+        let times = [-1.0, 0.0, 0.24, 0.25, 0.8, 1.0, 1.3, 7.9];
+        assert_eq!(
+            positions(&clip(PlayMode::Loop), &times),
+            [0, 0, 0, 1, 3, 0, 1, 3]
+        );
+        assert_eq!(
+            positions(&clip(PlayMode::Once), &times),
+            [0, 0, 0, 1, 3, 3, 3, 3]
+        );
+        assert!(!clip(PlayMode::Once).is_finished(0.99));
+        assert!(clip(PlayMode::Once).is_finished(1.0));
+        assert!(!clip(PlayMode::Loop).is_finished(100.0));
+        assert_eq!(clip(PlayMode::Loop).index_at(0.6), 12);
+    }
+
+    #[test]
+    fn ping_pong_skips_end_frames_going_back() {
+        let clip = clip(PlayMode::PingPong);
+        let times: Vec<f32> = (0..12).map(|i| i as f32 * 0.25 + 0.1).collect();
+        assert_eq!(
+            positions(&clip, &times),
+            [0, 1, 2, 3, 2, 1, 0, 1, 2, 3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn degenerate_clips() {
+        let empty = Clip::new(PlayMode::Loop);
+        assert_eq!((empty.position_at(3.0), empty.index_at(3.0)), (0, 0));
+        let single = Clip::uniform(&[7], 0.5, PlayMode::PingPong);
+        assert_eq!(single.index_at(12.3), 7);
+    }
+
+    #[test]
+    fn range() {
+        let clip = Clip::range(4, 3, 10.0, PlayMode::Loop);
+        let indices: Vec<u16> = clip.frames().iter().map(|f| f.index).collect();
+        assert_eq!(indices, [4, 5, 6]);
+        assert_eq!(clip.frames()[0].duration, 0.1);
+        let last = Clip::range(u16::MAX - 1, 2, 10.0, PlayMode::Loop);
+        assert_eq!(last.frames().last().unwrap().index, u16::MAX);
+        assert!(Clip::range(9, 0, 10.0, PlayMode::Loop).frames().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "tile range out of bounds")]
+    fn range_overflow() { Clip::range(u16::MAX, 2, 10.0, PlayMode::Loop); }
+
+    #[test]
+    fn animator_wraps() {
+        let clip = clip(PlayMode::Loop);
+        let mut animator = Animator::new(&clip);
+        for _ in 0..5 {
+            animator.update(0.25);
+        }
+        assert_eq!((animator.time(), animator.current_frame()), (0.25, 11));
+        animator.pause();
+        animator.update(0.25);
+        assert_eq!(animator.current_frame(), 11);
+        animator.resume();
+        animator.update(0.25);
+        assert_eq!(animator.current_frame(), 12);
+    }
+
+    #[test]
+    fn animator_plays_backwards() {
+        let clip = clip(PlayMode::Loop);
+        let mut animator = Animator::new(&clip);
+        animator.set_speed(-1.0);
+        let frames: Vec<u16> = (0..6)
+            .map(|_| {
+                animator.update(0.25);
+                animator.current_frame()
+            })
+            .collect();
+        assert_eq!(frames, [13, 12, 11, 10, 13, 12]);
+        assert!(animator.time() >= 0.0 && animator.time() < 1.0);
+    }
+
+    #[test]
+    fn animator_once_is_bounded() {
+        let clip = clip(PlayMode::Once);
+        let mut animator = Animator::new(&clip);
+        animator.update(10.0);
+        assert_eq!(animator.time(), 1.0);
+        assert!(animator.is_finished());
+        assert_eq!(animator.current_frame(), 13);
+        animator.set_speed(-2.0);
+        animator.update(0.25);
+        assert_eq!(animator.current_frame(), 12);
+        animator.update(10.0);
+        assert_eq!((animator.time(), animator.current_frame()), (0.0, 10));
+    }
+
+    #[test]
+    fn play_switches_clips() {
+        let (walk, run) = (clip(PlayMode::Loop), clip(PlayMode::PingPong));
+        let mut animator = Animator::new(&walk);
+        animator.update(0.5);
+        animator.play(&walk);
+        assert_eq!(animator.time(), 0.5);
+        animator.play(&run);
+        assert_eq!(animator.time(), 0.0);
+        assert!(core::ptr::eq(animator.clip(), &run));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::*;
+
+use rdpq::BlitParms;
+use sprite::Sprite;
+
+mod clip;
+
+pub use clip::*;
+
+impl Animator<'_> {
+    /// Draw the current frame. See [draw_frame].
+    pub fn draw(&self, sheet: &Sprite, x: f32, y: f32, parms: BlitParms) {
+        draw_frame(sheet, self.current_frame(), x, y, parms);
+    }
+}
+
+/// Rectangle `(s0, t0, width, height)` of tile `index` within a sprite sheet, using the
+/// sprite's [hslices](Sprite::hslices) and [vslices](Sprite::vslices).
+pub fn tile_rect(sheet: &Sprite, index: u16) -> (i32, i32, i32, i32) {
+    let hslices = (sheet.hslices() as i32).max(1);
+    let vslices = (sheet.vslices() as i32).max(1);
+    let width = sheet.width() as i32 / hslices;
+    let height = sheet.height() as i32 / vslices;
+    let index = index as i32 % (hslices * vslices);
+    (
+        (index % hslices) * width,
+        (index / hslices) * height,
+        width,
+        height,
+    )
+}
+
+/// Blit tile `index` of a sprite sheet through [rdpq::sprite_blit].
+///
+/// The source rectangle of `parms` is replaced by the tile rectangle; flipping, scaling and
+/// rotation (around `parms.cx`/`parms.cy`, relative to the tile) are applied as usual.
+pub fn draw_frame(sheet: &Sprite, index: u16, x: f32, y: f32, parms: BlitParms) {
+    let (s0, t0, width, height) = tile_rect(sheet, index);
+    let parms = BlitParms {
+        s0,
+        t0,
+        width,
+        height,
+        ..parms
+    };
+    rdpq::sprite_blit(sheet, x, y, parms);
+}
@@ -0,0 +1,298 @@
+//! Planes, spheres and frustums, and the visibility tests between them.
+//!
+//! This module only depends on `core` and [math](crate::math). It is shared with
+//! `libdragon-build`, so the culling tests can be run on a development host.
+
+use crate::math::{self, Aabb, Mat4, Vec3, Vec4};
+
+/// A plane: the points `p` with `normal.dot(p) + d == 0`. The side the normal points to is the
+/// inside.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub d:      f32,
+}
+
+impl Plane {
+    /// Create a plane from its equation
+    #[inline]
+    pub const fn new(normal: Vec3, d: f32) -> Self { Self { normal, d } }
+
+    /// The plane `(x, y, z) . p + w == 0`, normalized so that [Plane::distance] is a distance
+    pub fn from_vec4(v: Vec4) -> Self {
+        let normal = v.truncate();
+        let length = normal.length();
+        if length == 0.0 {
+            return Self::new(normal, v.w);
+        }
+        Self::new(normal / length, v.w / length)
+    }
+
+    /// Signed distance from the plane to `p`: positive inside, negative outside
+    #[inline]
+    pub fn distance(&self, p: Vec3) -> f32 { self.normal.dot(p) + self.d }
+}
+
+/// A bounding sphere
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Sphere {
+    /// Create a sphere from its center and radius
+    #[inline]
+    pub const fn new(center: Vec3, radius: f32) -> Self { Self { center, radius } }
+
+    /// The sphere circumscribing `aabb`
+    pub fn from_aabb(aabb: &Aabb) -> Self { Self::new(aabb.center(), aabb.half_extents().length()) }
+
+    /// A sphere containing this sphere transformed by `m`, which must be an affine transform.
+    /// The radius is scaled by the largest scale factor of `m`.
+    pub fn transform(&self, m: &Mat4) -> Self {
+        let scale = (0..3)
+            .map(|i| m.cols[i].truncate().length_squared())
+            .fold(0.0, f32::max);
+        Self::new(
+            m.transform_point3(self.center),
+            self.radius * math::sqrt(scale),
+        )
+    }
+}
+
+/// Result of testing a volume against a [Frustum]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Visibility {
+    /// Entirely outside, it can be skipped
+    Outside,
+    /// Partially inside (or close to a corner of the frustum, the tests are conservative)
+    Intersecting,
+    /// Entirely inside
+    Inside,
+}
+
+/// The six planes bounding what a projection can see, normals pointing inwards
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extract the frustum of a combined `projection * modelview` matrix, in the coordinate
+    /// space the matrix is applied to.
+    pub fn from_matrix(m: &Mat4) -> Self {
+        // a point is visible when -w <= x, y, z <= w in clip space
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+        Self {
+            planes: [
+                Plane::from_vec4(r3 + r0),
+                Plane::from_vec4(r3 - r0),
+                Plane::from_vec4(r3 + r1),
+                Plane::from_vec4(r3 - r1),
+                Plane::from_vec4(r3 + r2),
+                Plane::from_vec4(r3 - r2),
+            ],
+        }
+    }
+
+    /// Extract the frustum of `projection` in the coordinate space of `modelview`: pass the
+    /// camera matrix to get it in world space, or the camera and object transforms to get it in
+    /// object space.
+    pub fn from_matrices(projection: &Mat4, modelview: &Mat4) -> Self {
+        Self::from_matrix(&(*projection * *modelview))
+    }
+
+    /// Returns true if `p` is inside the frustum or on its boundary
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        self.planes.iter().all(|plane| plane.distance(p) >= 0.0)
+    }
+
+    /// Test a sphere against the frustum
+    pub fn test_sphere(&self, sphere: &Sphere) -> Visibility {
+        let mut visibility = Visibility::Inside;
+        for plane in &self.planes {
+            let distance = plane.distance(sphere.center);
+            if distance < -sphere.radius {
+                return Visibility::Outside;
+            }
+            if distance < sphere.radius {
+                visibility = Visibility::Intersecting;
+            }
+        }
+        visibility
+    }
+
+    /// Test an axis-aligned box against the frustum
+    pub fn test_aabb(&self, aabb: &Aabb) -> Visibility {
+        let mut visibility = Visibility::Inside;
+        for plane in &self.planes {
+            // the corners furthest along and against the normal
+            let n = plane.normal;
+            let pick = |positive: bool, min: f32, max: f32| if positive { max } else { min };
+            let far = Vec3::new(
+                pick(n.x >= 0.0, aabb.min.x, aabb.max.x),
+                pick(n.y >= 0.0, aabb.min.y, aabb.max.y),
+                pick(n.z >= 0.0, aabb.min.z, aabb.max.z),
+            );
+            let near = Vec3::new(
+                pick(n.x < 0.0, aabb.min.x, aabb.max.x),
+                pick(n.y < 0.0, aabb.min.y, aabb.max.y),
+                pick(n.z < 0.0, aabb.min.z, aabb.max.z),
+            );
+            if plane.distance(far) < 0.0 {
+                return Visibility::Outside;
+            }
+            if plane.distance(near) < 0.0 {
+                visibility = Visibility::Intersecting;
+            }
+        }
+        visibility
+    }
+
+    /// Returns true if the sphere is at least partially inside the frustum
+    #[inline]
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.test_sphere(sphere) != Visibility::Outside
+    }
+
+    /// Returns true if the box is at least partially inside the frustum
+    #[inline]
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.test_aabb(aabb) != Visibility::Outside
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Visibility::*;
+
+    // 90° field of view, looking down -z from the origin
+    fn frustum() -> Frustum {
+        Frustum::from_matrix(&Mat4::perspective(
+            core::f32::consts::FRAC_PI_2,
+            1.0,
+            1.0,
+            100.0,
+        ))
+    }
+
+    fn cube(center: Vec3, half: f32) -> Aabb {
+        Aabb::new(
+            center - Vec3::new(half, half, half),
+            center + Vec3::new(half, half, half),
+        )
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn planes() {
+        let f = frustum();
+        let s = core::f32::consts::FRAC_1_SQRT_2;
+        assert_close(f.planes[0].normal, Vec3::new(s, 0.0, -s));
+        assert_close(f.planes[1].normal, Vec3::new(-s, 0.0, -s));
+        assert_close(f.planes[4].normal, Vec3::new(0.0, 0.0, -1.0));
+        assert!((f.planes[4].d + 1.0).abs() < 1e-5);
+        assert!((f.planes[5].d - 100.0).abs() < 1e-3);
+
+        let plane = Plane::from_vec4(Vec4::new(0.0, 2.0, 0.0, 4.0));
+        assert_eq!(plane, Plane::new(Vec3::new(0.0, 1.0, 0.0), 2.0));
+        assert_eq!(plane.distance(Vec3::new(5.0, -3.0, 1.0)), -1.0);
+        let degenerate = Vec4::new(0.0, 0.0, 0.0, 1.0);
+        assert_eq!(Plane::from_vec4(degenerate).d, 1.0);
+    }
+
+    #[test]
+    fn points() {
+        let f = frustum();
+        assert!(f.contains_point(Vec3::new(0.0, 0.0, -10.0)));
+        assert!(f.contains_point(Vec3::new(9.9, -9.9, -10.0)));
+        assert!(!f.contains_point(Vec3::new(10.1, 0.0, -10.0)));
+        assert!(!f.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+        assert!(!f.contains_point(Vec3::new(0.0, 0.0, -0.5)));
+        assert!(!f.contains_point(Vec3::new(0.0, 0.0, -101.0)));
+    }
+
+    #[test]
+    fn spheres() {
+        let f = frustum();
+        let sphere = |x: f32, z: f32, r: f32| f.test_sphere(&Sphere::new(Vec3::new(x, 0.0, z), r));
+        assert_eq!(sphere(0.0, -10.0, 1.0), Inside);
+        assert_eq!(sphere(10.5, -10.0, 1.0), Intersecting);
+        assert_eq!(sphere(13.0, -10.0, 1.0), Outside);
+        assert_eq!(sphere(0.0, -1.5, 1.0), Intersecting);
+        assert_eq!(sphere(0.0, 5.0, 1.0), Outside);
+        assert_eq!(sphere(0.0, -100.5, 1.0), Intersecting);
+        assert!(f.intersects_sphere(&Sphere::new(Vec3::new(0.0, 0.0, -50.0), 1000.0)));
+        assert!(!f.intersects_sphere(&Sphere::new(Vec3::new(0.0, 0.0, -200.0), 10.0)));
+    }
+
+    #[test]
+    fn boxes() {
+        let f = frustum();
+        assert_eq!(f.test_aabb(&cube(Vec3::new(0.0, 0.0, -10.0), 1.0)), Inside);
+        assert_eq!(
+            f.test_aabb(&cube(Vec3::new(0.0, 0.0, -1.0), 1.0)),
+            Intersecting
+        );
+        assert_eq!(
+            f.test_aabb(&cube(Vec3::new(10.0, 10.0, -10.0), 1.0)),
+            Intersecting
+        );
+        assert_eq!(f.test_aabb(&cube(Vec3::new(0.0, 0.0, 5.0), 1.0)), Outside);
+        assert_eq!(
+            f.test_aabb(&cube(Vec3::new(0.0, -20.0, -10.0), 1.0)),
+            Outside
+        );
+        // a box covering the whole frustum
+        assert_eq!(
+            f.test_aabb(&cube(Vec3::new(0.0, 0.0, 0.0), 1000.0)),
+            Intersecting
+        );
+        // the test is conservative: a box outside a corner of the frustum (here beyond the far
+        // plane and right of the right plane), but partially on the inner side of every plane,
+        // is not rejected
+        let corner = Aabb::new(Vec3::new(101.0, -1.0, -110.0), Vec3::new(110.0, 1.0, -99.0));
+        assert!(!f.contains_point(corner.center()));
+        assert_eq!(f.test_aabb(&corner), Intersecting);
+        assert!(f.intersects_aabb(&corner));
+    }
+
+    #[test]
+    fn object_space() {
+        // camera at z = 5 looking at the origin
+        let view = Mat4::look_at(
+            Vec3::new(0.0, 0.0, 5.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let projection = Mat4::perspective(core::f32::consts::FRAC_PI_2, 1.0, 1.0, 100.0);
+        let f = Frustum::from_matrices(&projection, &view);
+        assert!(f.contains_point(Vec3::new(0.0, 0.0, 0.0)));
+        assert!(!f.contains_point(Vec3::new(0.0, 0.0, 4.5)));
+        assert!(f.contains_point(Vec3::new(0.0, 0.0, -94.0)));
+        assert!(!f.contains_point(Vec3::new(0.0, 0.0, -96.0)));
+    }
+
+    #[test]
+    fn sphere_bounds() {
+        let aabb = Aabb::new(Vec3::new(-1.0, 0.0, 2.0), Vec3::new(1.0, 4.0, 6.0));
+        let sphere = Sphere::from_aabb(&aabb);
+        assert_eq!(sphere.center, Vec3::new(0.0, 2.0, 4.0));
+        assert_eq!(sphere.radius, 3.0);
+
+        let m = Mat4::from_scale_rotation_translation(
+            Vec3::new(1.0, 2.0, 0.5),
+            math::Quat::IDENTITY,
+            Vec3::new(10.0, 0.0, 0.0),
+        );
+        let moved = sphere.transform(&m);
+        assert_close(moved.center, Vec3::new(10.0, 4.0, 2.0));
+        assert!((moved.radius - 6.0).abs() < 1e-5);
+    }
+}
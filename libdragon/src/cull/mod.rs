@@ -0,0 +1,98 @@
+//! View frustum culling: skip drawing objects that are entirely off-screen.
+//!
+//! A [Frustum] is extracted from a projection and modelview matrix, either given
+//! ([Frustum::from_matrices]) or read from GL ([Frustum::current]), and its planes are expressed
+//! in the coordinate space of the modelview matrix, i.e. the object space. Spheres and
+//! axis-aligned boxes in that space are then tested against it with plain math, so culling code
+//! can be run and tested on the host.
+//!
+//! ```rust
+//! // after setting up the camera and the object transform
+//! let frustum = cull::Frustum::current();
+//! if frustum.intersects_aabb(&bounds) {
+//!     draw_object();
+//! }
+//! ```
+//!
+//! With the `internal` feature, `ModelBounds` computes the bounding box of every node of a
+//! [Model64](model64::Model64) once at load time and draws only the nodes that are visible.
+
+use crate::*;
+#[cfg(feature = "internal")]
+use math::Aabb;
+use math::Mat4;
+#[cfg(feature = "internal")]
+use model64::{Model64, Model64Node};
+
+mod frustum;
+pub use frustum::{Frustum, Plane, Sphere, Visibility};
+
+impl Frustum {
+    /// The frustum of the current GL projection and modelview matrices, in object space.
+    ///
+    /// Rust: reads the matrices with [gl::GetFloatv].
+    pub fn current() -> Self {
+        let mut modelview = [0f32; 16];
+        let mut projection = [0f32; 16];
+        gl::GetFloatv(gl::MODELVIEW_MATRIX, &mut modelview);
+        gl::GetFloatv(gl::PROJECTION_MATRIX, &mut projection);
+        Self::from_matrices(
+            &Mat4::from_cols_array(&projection),
+            &Mat4::from_cols_array(&modelview),
+        )
+    }
+}
+
+/// Bounding boxes of the nodes of a [Model64], computed once from the vertex data instead of
+/// at every frame like [Model64Node::bounding_box] does.
+#[cfg(feature = "internal")]
+#[derive(Debug, Clone, Default)]
+pub struct ModelBounds {
+    nodes: Vec<Option<Aabb>>,
+}
+
+#[cfg(feature = "internal")]
+impl ModelBounds {
+    /// Compute the bounds of all the nodes of `model`
+    pub fn new(model: &Model64) -> Self {
+        Self {
+            nodes: model.nodes().map(|node| node.bounding_box()).collect(),
+        }
+    }
+
+    /// Bounding box of the mesh of node `index` in the node coordinate space, or `None` if it
+    /// has no mesh
+    #[inline]
+    pub fn node(&self, index: usize) -> Option<Aabb> { self.nodes.get(index).copied().flatten() }
+
+    /// Bounding box of the mesh of `node` in the model coordinate space, using the current
+    /// transforms of the nodes (see [Model64Node::get_node_world_matrix])
+    pub fn node_in_model(&self, node: &Model64Node) -> Option<Aabb> {
+        Some(
+            self.node(node.index())?
+                .transform(&node.get_node_world_matrix()),
+        )
+    }
+
+    /// Returns true if the mesh of `node` is at least partially inside `frustum`, which must be
+    /// in the model coordinate space (see [Frustum::current]). Nodes without a mesh are never
+    /// visible.
+    pub fn is_node_visible(&self, node: &Model64Node, frustum: &Frustum) -> bool {
+        self.node_in_model(node)
+            .is_some_and(|aabb| frustum.intersects_aabb(&aabb))
+    }
+
+    /// Draw the nodes of `model` that are visible in `frustum`, which must be in the model
+    /// coordinate space. Returns the number of nodes drawn.
+    ///
+    /// Rust: draws with [Model64Node::draw_subtree] from the root node.
+    pub fn draw_visible(&self, model: &Model64, frustum: &Frustum) -> usize {
+        let mut drawn = 0;
+        model.root().draw_subtree(|node| {
+            let visible = self.is_node_visible(node, frustum);
+            drawn += visible as usize;
+            visible
+        });
+        drawn
+    }
+}
@@ -0,0 +1,327 @@
+//! On-screen profiler overlay showing the RSP busy time per overlay, the RDP busy time, the CPU
+//! frame time and the heap usage, as bars and rolling graphs.
+//!
+//! ```rust
+//! rdpq::text_register_font(1, &font);
+//! rspq::profile_start();
+//! let mut hud = debug::hud::Hud::new(debug::hud::HudConfig::default());
+//! loop {
+//!     joypad::poll();
+//!     hud.update();
+//!     let disp = display::get();
+//!     rdpq::attach_clear(&disp, None);
+//!     draw_game();
+//!     hud.draw();
+//!     rdpq::detach_show();
+//! }
+//! ```
+//!
+//! The overlay is toggled by holding the buttons of [HudConfig::combo] (by default L, R and Z).
+//!
+//! The RSP and RDP rows use the data of [rspq::profile_get_data], which is only recorded when
+//! libdragon is built with `RSPQ_PROFILE=1`; they stay empty otherwise. The aggregation of the
+//! samples is done by [History] and [ProfileSummary], which don't touch the hardware.
+
+use crate::*;
+
+use graphics::Color;
+use joypad::Buttons;
+
+mod stats;
+pub use stats::{
+    bar_length, combo_triggered, History, Label, ProfileSummary, SlotUsage, RCP_FREQUENCY,
+};
+
+/// Number of samples kept by the rolling graphs of [Hud]
+pub const GRAPH_SAMPLES: usize = 64;
+
+impl ProfileSummary {
+    /// Summarize the data returned by [rspq::profile_get_data]
+    pub fn from_data(data: &rspq::ProfileData) -> Self {
+        let slots = data
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(i, slot)| slot.is_used() || *i < rspq::consts::PROFILE_CSLOT_COUNT);
+        let slots = slots.map(|(_, slot)| {
+            let name = if slot.is_used() { slot.name() } else { "" };
+            (name, slot.total_ticks)
+        });
+        Self::from_ticks(
+            slots,
+            rspq::consts::PROFILE_CSLOT_COUNT,
+            data.total_ticks,
+            data.rdp_busy_ticks,
+            data.frame_count,
+        )
+    }
+}
+
+/// Returns true when all the buttons of `combo` are `held` and at least one of them was just
+/// `pressed`, so that the combo triggers once. An empty combo never triggers.
+///
+/// See [combo_triggered] for the same check on button masks.
+pub fn combo_pressed(combo: &Buttons, held: &Buttons, pressed: &Buttons) -> bool {
+    combo_triggered(button_mask(combo), button_mask(held), button_mask(pressed))
+}
+
+#[rustfmt::skip]
+fn button_mask(b: &Buttons) -> u16 {
+    [
+        b.a, b.b, b.z, b.start, b.d_up, b.d_down, b.d_left, b.d_right,
+        b.y, b.x, b.l, b.r, b.c_up, b.c_down, b.c_left, b.c_right,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |mask, (i, &held)| mask | ((held as u16) << i))
+}
+
+/// Configuration of a [Hud]
+#[derive(Debug, Copy, Clone)]
+pub struct HudConfig {
+    /// Font used for the labels, registered with [rdpq::text_register_font] (default: 1)
+    pub font_id:     u8,
+    /// Left edge of the overlay, in pixels (default: 8)
+    pub x:           i32,
+    /// Top edge of the overlay, in pixels (default: 8)
+    pub y:           i32,
+    /// Height of a row, in pixels (default: 10)
+    pub row_height:  i32,
+    /// Width of the labels, in pixels (default: 72)
+    pub label_width: i32,
+    /// Width of a full bar, in pixels (default: 64)
+    pub bar_width:   u32,
+    /// Controller port the combo is read from (default: 0)
+    pub port:        usize,
+    /// Buttons toggling the overlay (default: L, R and Z)
+    pub combo:       Buttons,
+    /// Frame time shown as a full bar, in microseconds (default: 1/60 s)
+    pub budget_us:   f32,
+    /// Number of frames the profiler data is averaged over (default: 8)
+    pub interval:    u32,
+    /// Show the overlay from the start (default: false)
+    pub visible:     bool,
+}
+
+impl Default for HudConfig {
+    fn default() -> Self {
+        Self {
+            font_id:     1,
+            x:           8,
+            y:           8,
+            row_height:  10,
+            label_width: 72,
+            bar_width:   64,
+            port:        0,
+            combo:       Buttons {
+                l: true,
+                r: true,
+                z: true,
+                ..Default::default()
+            },
+            budget_us:   1_000_000.0 / 60.0,
+            interval:    8,
+            visible:     false,
+        }
+    }
+}
+
+/// The profiler overlay. See the [module documentation](self).
+pub struct Hud {
+    config:     HudConfig,
+    visible:    bool,
+    frames:     u32,
+    last:       Option<u64>,
+    cpu:        History<GRAPH_SAMPLES>,
+    rsp:        History<GRAPH_SAMPLES>,
+    rdp:        History<GRAPH_SAMPLES>,
+    heap:       History<GRAPH_SAMPLES>,
+    heap_stats: HeapStats,
+    summary:    ProfileSummary,
+}
+
+impl Hud {
+    pub fn new(config: HudConfig) -> Self {
+        Self {
+            config,
+            visible: config.visible,
+            frames: 0,
+            last: None,
+            cpu: History::new(),
+            rsp: History::new(),
+            rdp: History::new(),
+            heap: History::new(),
+            heap_stats: HeapStats::default(),
+            summary: ProfileSummary::default(),
+        }
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool { self.visible }
+
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) { self.visible = visible; }
+
+    /// The latest profiler summary
+    #[inline]
+    pub fn summary(&self) -> &ProfileSummary { &self.summary }
+
+    /// CPU frame times, in microseconds
+    #[inline]
+    pub fn cpu_history(&self) -> &History<GRAPH_SAMPLES> { &self.cpu }
+
+    /// Call once per frame, after [joypad::poll]: checks the combo, measures the CPU frame time
+    /// and the heap, and marks the frame to the profiler with [rspq::profile_next_frame].
+    /// Every [HudConfig::interval] frames, the profiler data is summarized and reset.
+    ///
+    /// Rust: the CPU frame time is the time between two calls, measured with [ticks::get].
+    pub fn update(&mut self) {
+        let port = joypad::Port::get(self.config.port);
+        let (held, pressed) = (port.get_buttons_held(), port.get_buttons_pressed());
+        if combo_pressed(&self.config.combo, &held, &pressed) {
+            self.visible = !self.visible;
+        }
+
+        let now = ticks::get();
+        if let Some(last) = self.last {
+            let us = (now - last) as f32 * 1_000_000.0 / ticks::per_second() as f32;
+            self.cpu.push(us);
+        }
+        self.last = Some(now);
+
+        self.heap_stats = get_heap_stats();
+        self.heap.push(self.heap_stats.used as f32);
+
+        rspq::profile_next_frame();
+        self.frames += 1;
+        if self.frames >= self.config.interval.max(1) {
+            self.frames = 0;
+            self.summary = ProfileSummary::from_data(&rspq::profile_get_data());
+            self.rsp.push(self.summary.rsp_busy);
+            self.rdp.push(self.summary.rdp_busy);
+            rspq::profile_reset();
+        }
+    }
+
+    /// Draw the overlay with rdpq on the attached surface, if it is visible. The render mode is
+    /// saved and restored with [rdpq::mode_push]/[rdpq::mode_pop].
+    pub fn draw(&self) {
+        if !self.visible {
+            return;
+        }
+        let HudConfig {
+            x,
+            y,
+            row_height,
+            label_width,
+            bar_width,
+            ..
+        } = self.config;
+        let graph_x = x + label_width + bar_width as i32 + 4;
+        let width = graph_x + GRAPH_SAMPLES as i32 - x;
+        let slot_rows = self
+            .summary
+            .slots
+            .iter()
+            .filter(|slot| !slot.control)
+            .count();
+        let height = (4 + slot_rows) as i32 * row_height;
+
+        rdpq::mode_push();
+        rdpq::set_mode_standard();
+        rdpq::mode_combiner(rdpq::consts::COMBINER_FLAT);
+        rdpq::mode_blender(rdpq::consts::BLENDER_MULTIPLY);
+        rdpq::set_prim_color(graphics::rgba32(0, 0, 0, 0xA0));
+        rdpq::fill_rectangle(x - 2, y - 2, x + width + 2, y + height + 2);
+
+        for (i, row) in self.rows().enumerate() {
+            let top = y + i as i32 * row_height + 1;
+            let bottom = top + row_height - 2;
+            let bar_x = x + label_width;
+            rdpq::set_prim_color(graphics::rgba32(0x40, 0x40, 0x40, 0xFF));
+            rdpq::fill_rectangle(bar_x, top, bar_x + bar_width as i32, bottom);
+            rdpq::set_prim_color(row.color);
+            let length = bar_length(row.value, 1.0, bar_width) as i32;
+            if length > 0 {
+                rdpq::fill_rectangle(bar_x, top, bar_x + length, bottom);
+            }
+            if let Some((history, max)) = row.graph {
+                let first = graph_x + (GRAPH_SAMPLES - history.len()) as i32;
+                for (j, sample) in history.iter().enumerate() {
+                    let length = bar_length(sample, max, (row_height - 2) as u32) as i32;
+                    if length > 0 {
+                        let column = first + j as i32;
+                        rdpq::fill_rectangle(column, bottom - length, column + 1, bottom);
+                    }
+                }
+            }
+        }
+        rdpq::mode_pop();
+
+        for (i, row) in self.rows().enumerate() {
+            let baseline = y + (i as i32 + 1) * row_height - 2;
+            rdpq::text_printn(
+                rdpq::TextParms::default(),
+                self.config.font_id,
+                x as f32,
+                baseline as f32,
+                row.label.as_str().as_bytes(),
+            );
+        }
+    }
+
+    /// The rows of the overlay: CPU, RSP, RDP, heap, then the RSP time of each overlay
+    fn rows(&self) -> impl Iterator<Item = Row<'_>> + '_ {
+        let budget_us = self.config.budget_us;
+        let heap_total = self.heap_stats.total.max(1) as f32;
+        let fixed = [
+            Row {
+                label: RowLabel::new(format_args!("CPU {:5.1}ms", self.cpu.last() / 1000.0)),
+                value: self.cpu.last() / budget_us,
+                graph: Some((&self.cpu, self.cpu.max().max(budget_us))),
+                color: graphics::rgba32(0x40, 0xE0, 0x40, 0xFF),
+            },
+            Row {
+                label: RowLabel::new(format_args!("RSP {:5.1}%", self.summary.rsp_busy * 100.0)),
+                value: self.summary.rsp_busy,
+                graph: Some((&self.rsp, 1.0)),
+                color: graphics::rgba32(0x40, 0xA0, 0xFF, 0xFF),
+            },
+            Row {
+                label: RowLabel::new(format_args!("RDP {:5.1}%", self.summary.rdp_busy * 100.0)),
+                value: self.summary.rdp_busy,
+                graph: Some((&self.rdp, 1.0)),
+                color: graphics::rgba32(0xFF, 0x80, 0x40, 0xFF),
+            },
+            Row {
+                label: RowLabel::new(format_args!("HEAP {}K", self.heap_stats.used / 1024)),
+                value: self.heap_stats.used as f32 / heap_total,
+                graph: Some((&self.heap, heap_total)),
+                color: graphics::rgba32(0xE0, 0x40, 0xE0, 0xFF),
+            },
+        ];
+        let slots = self
+            .summary
+            .slots
+            .iter()
+            .filter(|slot| !slot.control)
+            .map(|slot| Row {
+                label: RowLabel::new(format_args!(" {:.9}", slot.name)),
+                value: slot.busy,
+                graph: None,
+                color: graphics::rgba32(0x40, 0xA0, 0xFF, 0xFF),
+            });
+        fixed.into_iter().chain(slots)
+    }
+}
+
+/// Label of a [Row], long enough for the 10 columns of text that fit before the bars
+type RowLabel = Label<16>;
+
+#[derive(Copy, Clone)]
+struct Row<'a> {
+    label: RowLabel,
+    value: f32,
+    graph: Option<(&'a History<GRAPH_SAMPLES>, f32)>,
+    color: Color,
+}
@@ -0,0 +1,328 @@
+//! Aggregation of the samples shown by [Hud](super::Hud), independent of the hardware.
+//!
+//! This module only depends on `core` and `alloc`. It is shared with `libdragon-build`, so it
+//! can be tested on a development host.
+
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+
+/// Frequency of the RCP clock, which the profiler ticks count
+pub const RCP_FREQUENCY: u32 = 62_500_000;
+
+/// A rolling window of the last `N` samples of a value
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct History<const N: usize> {
+    samples: [f32; N],
+    next:    usize,
+    len:     usize,
+}
+
+impl<const N: usize> Default for History<N> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<const N: usize> History<N> {
+    pub const fn new() -> Self {
+        Self {
+            samples: [0.0; N],
+            next:    0,
+            len:     0,
+        }
+    }
+
+    /// Add a sample, dropping the oldest one if the window is full. A window of 0 samples
+    /// stays empty.
+    pub fn push(&mut self, value: f32) {
+        if N == 0 {
+            return;
+        }
+        self.samples[self.next] = value;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Number of samples in the window
+    #[inline]
+    pub fn len(&self) -> usize { self.len }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// The samples, from the oldest to the newest
+    pub fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        let start = (self.next + N - self.len).checked_rem(N).unwrap_or(0);
+        (0..self.len).map(move |i| self.samples[(start + i) % N])
+    }
+
+    /// The newest sample, or 0 if there is none
+    pub fn last(&self) -> f32 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        self.samples[(self.next + N - 1) % N]
+    }
+
+    /// The largest sample, or 0 if there is none
+    pub fn max(&self) -> f32 { self.iter().fold(0.0, f32::max) }
+
+    /// The average of the samples, or 0 if there is none
+    pub fn average(&self) -> f32 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        self.iter().sum::<f32>() / self.len as f32
+    }
+
+    pub fn clear(&mut self) {
+        self.next = 0;
+        self.len = 0;
+    }
+}
+
+/// Time spent by the RSP in a profiler slot
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotUsage {
+    /// Name of the slot, usually the name of an overlay
+    pub name:    String,
+    /// Average time per frame, in microseconds
+    pub time_us: f32,
+    /// Fraction of the elapsed time, from 0 to 1
+    pub busy:    f32,
+    /// One of the first [PROFILE_CSLOT_COUNT](crate::rspq::consts::PROFILE_CSLOT_COUNT) slots, which
+    /// account for rspq itself (waiting for the CPU or the RDP, switching overlays) rather than
+    /// for an overlay. Not counted in [ProfileSummary::rsp_busy].
+    pub control: bool,
+}
+
+/// Per-frame averages of the profiler data recorded over a few frames
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProfileSummary {
+    /// Number of frames the data was recorded over
+    pub frames:        u64,
+    /// Average frame time measured by the RCP, in microseconds
+    pub frame_time_us: f32,
+    /// Fraction of the time the RSP was running overlays, from 0 to 1
+    pub rsp_busy:      f32,
+    /// Fraction of the time the RDP was busy, from 0 to 1
+    pub rdp_busy:      f32,
+    /// The used slots, in the order of the profiler
+    pub slots:         Vec<SlotUsage>,
+}
+
+impl ProfileSummary {
+    /// Summarize `slots` (name and ticks, the first `control_slots` being the control slots)
+    /// recorded over `frame_count` frames and `total_ticks` RCP ticks, with the RDP busy for
+    /// `rdp_busy_ticks`
+    pub fn from_ticks<'a, I>(
+        slots: I,
+        control_slots: usize,
+        total_ticks: u64,
+        rdp_busy_ticks: u64,
+        frame_count: u64,
+    ) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, u64)>,
+    {
+        if total_ticks == 0 || frame_count == 0 {
+            return Self::default();
+        }
+        let per_frame_us = |ticks: u64| {
+            (ticks as f64 * 1_000_000.0 / RCP_FREQUENCY as f64 / frame_count as f64) as f32
+        };
+        let fraction = |ticks: u64| (ticks as f64 / total_ticks as f64).min(1.0) as f32;
+
+        let mut rsp_ticks = 0;
+        let slots = slots
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, ticks))| {
+                let control = i < control_slots;
+                if !control {
+                    rsp_ticks += ticks;
+                }
+                SlotUsage {
+                    name: name.to_owned(),
+                    time_us: per_frame_us(ticks),
+                    busy: fraction(ticks),
+                    control,
+                }
+            })
+            .collect();
+        Self {
+            frames: frame_count,
+            frame_time_us: per_frame_us(total_ticks),
+            rsp_busy: fraction(rsp_ticks),
+            rdp_busy: fraction(rdp_busy_ticks),
+            slots,
+        }
+    }
+}
+
+/// Returns true when all the buttons of the `combo` mask are `held` and at least one of them was
+/// just `pressed`, so that the combo triggers once. An empty combo never triggers.
+pub fn combo_triggered(combo: u16, held: u16, pressed: u16) -> bool {
+    combo != 0 && held & combo == combo && pressed & combo != 0
+}
+
+/// Length of a bar showing `value` out of `max`, for a bar of `width` pixels when full
+pub fn bar_length(value: f32, max: f32, width: u32) -> u32 {
+    if max <= 0.0 || value <= 0.0 {
+        return 0;
+    }
+    ((value / max).min(1.0) * width as f32) as u32
+}
+
+/// A short label formatted without allocating, truncated to `N` bytes
+#[derive(Debug, Copy, Clone)]
+pub struct Label<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Label<N> {
+    /// Format `args`, e.g. with [format_args]
+    pub fn new(args: core::fmt::Arguments) -> Self {
+        let mut label = Self {
+            buf: [0; N],
+            len: 0,
+        };
+        // truncating is not an error
+        let _ = core::fmt::Write::write_fmt(&mut label, args);
+        label
+    }
+
+    /// The text of the label
+    pub fn as_str(&self) -> &str {
+        // only whole characters are written
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> core::fmt::Write for Label<N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            let end = self.len + c.len_utf8();
+            if end > N {
+                return Err(core::fmt::Error);
+            }
+            c.encode_utf8(&mut self.buf[self.len..end]);
+            self.len = end;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use core::fmt::Write;
+
+    #[test]
+    fn history() {
+        let mut history = History::<3>::new();
+        assert!(history.is_empty());
+        assert_eq!(
+            (history.last(), history.max(), history.average()),
+            (0.0, 0.0, 0.0)
+        );
+
+        history.push(1.0);
+        history.push(4.0);
+        assert_eq!(history.iter().collect::<Vec<_>>(), vec![1.0, 4.0]);
+        history.push(2.0);
+        history.push(3.0);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.iter().collect::<Vec<_>>(), vec![4.0, 2.0, 3.0]);
+        assert_eq!(
+            (history.last(), history.max(), history.average()),
+            (3.0, 4.0, 3.0)
+        );
+
+        history.clear();
+        assert!(history.is_empty());
+        assert_eq!(history.iter().count(), 0);
+    }
+
+    #[test]
+    fn empty_history() {
+        let mut history = History::<0>::new();
+        history.push(1.0);
+        assert!(history.is_empty());
+        assert_eq!(history.iter().count(), 0);
+        assert_eq!(
+            (history.last(), history.max(), history.average()),
+            (0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn profile_summary() {
+        let second = RCP_FREQUENCY as u64;
+        let slots = [
+            ("wait", second / 2),
+            ("rdpq", second / 4),
+            ("t3d", second / 4),
+        ];
+        let summary = ProfileSummary::from_ticks(slots, 1, second, second / 8, 50);
+        assert_eq!(summary.frames, 50);
+        assert_eq!(summary.frame_time_us, 20_000.0);
+        assert_eq!(summary.rsp_busy, 0.5);
+        assert_eq!(summary.rdp_busy, 0.125);
+        assert_eq!(summary.slots.len(), 3);
+        assert_eq!(summary.slots[0].name, "wait");
+        assert!(summary.slots[0].control);
+        assert!(!summary.slots[1].control);
+        assert_eq!(summary.slots[1].time_us, 5_000.0);
+        assert_eq!(summary.slots[1].busy, 0.25);
+
+        // the fractions are clamped, and no data gives an empty summary
+        let summary = ProfileSummary::from_ticks([("rdpq", 2 * second)], 0, second, 0, 1);
+        assert_eq!((summary.rsp_busy, summary.slots[0].busy), (1.0, 1.0));
+        assert_eq!(
+            ProfileSummary::from_ticks([("rdpq", 1)], 0, 0, 0, 1),
+            ProfileSummary::default()
+        );
+        assert_eq!(
+            ProfileSummary::from_ticks([("rdpq", 1)], 0, 1, 0, 0),
+            ProfileSummary::default()
+        );
+    }
+
+    #[test]
+    fn combo() {
+        const L: u16 = 1 << 10;
+        const R: u16 = 1 << 11;
+        const Z: u16 = 1 << 2;
+        let combo = L | R | Z;
+        assert!(combo_triggered(combo, combo, Z));
+        assert!(combo_triggered(combo, combo | 1, combo));
+        // still held on the next frame
+        assert!(!combo_triggered(combo, combo, 0));
+        // a button missing, or only another button pressed
+        assert!(!combo_triggered(combo, L | R, L | R));
+        assert!(!combo_triggered(combo, combo | 1, 1));
+        assert!(!combo_triggered(0, 0xFFFF, 0xFFFF));
+    }
+
+    #[test]
+    fn bars() {
+        assert_eq!(bar_length(0.5, 1.0, 64), 32);
+        assert_eq!(bar_length(2.0, 1.0, 64), 64);
+        assert_eq!(bar_length(-1.0, 1.0, 64), 0);
+        assert_eq!(bar_length(1.0, 0.0, 64), 0);
+    }
+
+    #[test]
+    fn label() {
+        let label = Label::<16>::new(format_args!("CPU {:5.1}ms", 16.25));
+        assert_eq!(label.as_str(), "CPU  16.2ms");
+
+        let label = Label::<4>::new(format_args!("{}", "abcdef"));
+        assert_eq!(label.as_str(), "abcd");
+        // truncated at a character boundary
+        let mut label = Label::<4>::new(format_args!("ab"));
+        assert!(label.write_str("cé").is_err());
+        assert_eq!(label.as_str(), "abc");
+        assert_eq!(Label::<0>::new(format_args!("x")).as_str(), "");
+    }
+}
@@ -0,0 +1,141 @@
+//! Image file encoding for `debug::screenshot`.
+//!
+//! This module only depends on `core` and `alloc`. It is shared with `libdragon-build`, whose
+//! `screenshot` module decodes the files produced here.
+
+use alloc::{vec, vec::Vec};
+
+/// USB data type used by `debug::screenshot` to send image files.
+///
+/// UNFLoader's `DATATYPE_SCREENSHOT` expects a raw framebuffer, so encoded files use their own
+/// type, outside the range used by UNFLoader.
+pub const DATATYPE_SCREENSHOT_FILE: u32 = 0x20;
+
+/// CRC-32 (ISO-HDLC), as used by PNG chunks
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Adler-32, as used by the zlib stream in PNG IDAT chunks
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &v in chunk {
+            a += v as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encode 8-bit RGBA pixels (row-major, no padding) into a PNG file.
+///
+/// The image data is stored with uncompressed deflate blocks: it is fast to produce on the
+/// N64 and still readable by any PNG decoder.
+pub fn encode_png(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    assert!(
+        rgba.len() == (width * height * 4) as usize,
+        "invalid RGBA buffer size"
+    );
+
+    // raw scanlines, each prefixed by filter type 0 (None)
+    let row_len = (width * 4) as usize;
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+    for row in rgba.chunks(row_len.max(1)).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // zlib stream made of stored deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(last as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8-bit depth, color type 6 (RGBA), deflate, adaptive filtering, no interlace
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut out = Vec::with_capacity(zlib.len() + 64);
+    out.extend_from_slice(b"\x89PNG\r\n\x1a\n");
+    png_chunk(&mut out, b"IHDR", &ihdr);
+    png_chunk(&mut out, b"IDAT", &zlib);
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Encode 8-bit RGBA pixels (row-major, no padding) into a 24-bit BMP file.
+pub fn encode_bmp(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    assert!(
+        rgba.len() == (width * height * 4) as usize,
+        "invalid RGBA buffer size"
+    );
+
+    // rows are padded to a multiple of 4 bytes
+    let row_len = ((width * 3 + 3) & !3) as usize;
+    let image_size = (row_len * height as usize) as u32;
+    let offset = 14 + 40;
+
+    let mut out = Vec::with_capacity(offset + image_size as usize);
+    // BITMAPFILEHEADER
+    out.extend_from_slice(b"BM");
+    out.extend_from_slice(&(offset as u32 + image_size).to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&(offset as u32).to_le_bytes());
+    // BITMAPINFOHEADER
+    out.extend_from_slice(&40u32.to_le_bytes());
+    out.extend_from_slice(&(width as i32).to_le_bytes());
+    out.extend_from_slice(&(height as i32).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&24u16.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes()); // BI_RGB
+    out.extend_from_slice(&image_size.to_le_bytes());
+    out.extend_from_slice(&2835i32.to_le_bytes()); // 72 DPI
+    out.extend_from_slice(&2835i32.to_le_bytes());
+    out.extend_from_slice(&[0; 8]);
+
+    // pixel rows are stored bottom-up
+    for y in (0..height as usize).rev() {
+        let start = out.len();
+        for x in 0..width as usize {
+            let p = &rgba[(y * width as usize + x) * 4..][..4];
+            out.extend_from_slice(&[p[2], p[1], p[0]]);
+        }
+        out.resize(start + row_len, 0);
+    }
+    out
+}
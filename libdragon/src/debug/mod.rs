@@ -2,6 +2,12 @@
 
 use crate::*;
 
+/// On-screen profiler overlay
+pub mod hud;
+mod image;
+
+pub use image::{encode_bmp, encode_png, DATATYPE_SCREENSHOT_FILE};
+
 /// Flag to activate the USB logging channel.
 ///
 /// See [libdragon_sys::DEBUG_FEATURE_LOG_USB] for details.
@@ -110,3 +116,74 @@ pub fn debug_assert_func_f(file: &str, line: u32, func: &str, failedexpr: &str,
         )
     }
 }
+
+/// Image file format used by [screenshot]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScreenshotFormat {
+    /// PNG, 8-bit RGBA (uncompressed deflate blocks)
+    Png,
+    /// BMP, 24-bit BGR (alpha is dropped)
+    Bmp,
+}
+
+/// Where [screenshot] sends the encoded image
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScreenshotTarget<'a> {
+    /// Send via [usb::write_bytes] with [usb::DataType::ScreenshotFile]
+    Usb,
+    /// Write to a file on the SD card (e.g. `"sd:/shot.png"`). Requires [init_sdfs].
+    Sd(&'a str),
+}
+
+/// Capture a surface (e.g. from [display::get] or [rdpq::get_attached]) and send it to the
+/// host or to the SD card.
+///
+/// The encoding is done in software on the CPU, so this is slow and meant for debugging
+/// only. Make sure the RDP has finished drawing (e.g. with [rspq::wait]) before calling it.
+///
+/// Rust: the USB payload is the encoded image file, sent with its own data type
+/// ([DATATYPE_SCREENSHOT_FILE]) rather than UNFLoader's raw framebuffer screenshots. Use
+/// `libdragon_build::screenshot` on the host to decode it, or simply save it to disk as-is.
+pub fn screenshot(
+    surface: &surface::Surface,
+    format: ScreenshotFormat,
+    target: ScreenshotTarget,
+) -> Result<()> {
+    let data = encode_screenshot(surface, format);
+    match target {
+        ScreenshotTarget::Usb => usb::write_bytes(usb::DataType::ScreenshotFile, &data),
+        ScreenshotTarget::Sd(path) => {
+            let mut file = dfs::File::open(path, "wb")?;
+            embedded_io::Write::write_all(&mut file, &data)
+                .map_err(|error| LibDragonError::DfsError { error })?;
+            embedded_io::Write::flush(&mut file)
+                .map_err(|error| LibDragonError::DfsError { error })?;
+        }
+    }
+    Ok(())
+}
+
+/// Encode a [TexFormat::Rgba16](surface::TexFormat::Rgba16) or
+/// [TexFormat::Rgba32](surface::TexFormat::Rgba32) surface into an image file.
+///
+/// Panics if the surface is in any other format.
+pub fn encode_screenshot(surface: &surface::Surface, format: ScreenshotFormat) -> Vec<u8> {
+    let fmt = surface.get_format();
+    assert!(
+        fmt == surface::TexFormat::Rgba16 || fmt == surface::TexFormat::Rgba32,
+        "screenshot only supports RGBA16 and RGBA32 surfaces (got {:?})",
+        fmt
+    );
+    let (width, height) = (surface.width() as u32, surface.height() as u32);
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let c = surface.get_pixel(x, y, None).c;
+            rgba.extend_from_slice(&[c.r, c.g, c.b, c.a]);
+        }
+    }
+    match format {
+        ScreenshotFormat::Png => encode_png(&rgba, width, height),
+        ScreenshotFormat::Bmp => encode_bmp(&rgba, width, height),
+    }
+}
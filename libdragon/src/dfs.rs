@@ -471,6 +471,44 @@ impl embedded_io::Read for File {
     }
 }
 
+impl embedded_io::Write for File {
+    /// Write data to a [FILE](libdragon_sys::FILE) using [fwrite](libdragon_sys::fwrite).
+    ///
+    /// Only files opened on a writable filesystem (e.g. the SD card, see
+    /// [debug::init_sdfs]) can be written. On success, the amount of data written is returned.
+    fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+        if let Some(ref fp) = self.fp {
+            let size = buf.len() as u32;
+            let write_size = unsafe {
+                let buf_ptr = buf.as_ptr() as *const ::core::ffi::c_void;
+                libdragon_sys::fwrite(buf_ptr, 1, size, *fp)
+            };
+            let error = unsafe { libdragon_sys::ferror(*fp) };
+            if error != 0 {
+                Err(DfsError::BadHandle)
+            } else {
+                Ok(write_size as usize)
+            }
+        } else {
+            Err(DfsError::InvalidInput)
+        }
+    }
+
+    /// Flush buffered data using [fflush](libdragon_sys::fflush).
+    fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+        if let Some(ref fp) = self.fp {
+            let r = unsafe { libdragon_sys::fflush(*fp) };
+            if r != 0 {
+                Err(DfsError::BadHandle)
+            } else {
+                Ok(())
+            }
+        } else {
+            Err(DfsError::InvalidInput)
+        }
+    }
+}
+
 impl embedded_io::Seek for File {
     /// Seek to a position within a [FILE](libdragon_sys::FILE) using
     /// [fseek](libdragon_sys::fseek).
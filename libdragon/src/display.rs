@@ -1,8 +1,14 @@
 #![allow(non_upper_case_globals)]
 
+use core::sync::atomic::{AtomicU32, Ordering};
+
 use crate::*;
 
-use surface::Surface;
+use surface::{Surface, TexFormat};
+
+// Settings of the last call to init, for set_resolution
+static mut CONFIG: Option<DisplayConfig> = None;
+static GENERATION: AtomicU32 = AtomicU32::new(0);
 
 /// Valid interlace modes
 ///
@@ -44,7 +50,7 @@ static RESOLUTION_640x480: &libdragon_sys::resolution_t =
 /// Video resolutions
 ///
 /// Rust-specific: instead of structs on the C side, enumerations are used in Rust.
-/// If a custom resolution is desired, use [Resolution::Custom].
+/// If a custom resolution is desired, use [Resolution::custom].
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Resolution {
     /// 256x240 mode
@@ -83,6 +89,39 @@ impl From<Resolution> for libdragon_sys::resolution_t {
     }
 }
 
+impl Resolution {
+    /// A custom resolution. Panics if a dimension is 0 or larger than the 800x600 the video
+    /// interface can output.
+    pub fn custom(width: u32, height: u32, interlace: InterlaceMode) -> Self {
+        assert!(
+            (1..=800).contains(&width) && (1..=600).contains(&height),
+            "resolution out of range: {}x{}",
+            width,
+            height
+        );
+        Resolution::Custom(width, height, interlace)
+    }
+
+    /// Width of the framebuffer, in pixels
+    pub fn width(&self) -> u32 { libdragon_sys::resolution_t::from(*self).width as u32 }
+
+    /// Height of the framebuffer, in pixels
+    pub fn height(&self) -> u32 { libdragon_sys::resolution_t::from(*self).height as u32 }
+
+    /// Interlace mode of the resolution
+    pub fn interlace(&self) -> InterlaceMode {
+        match *self {
+            Resolution::_512x480 | Resolution::_640x480 => InterlaceMode::Half,
+            Resolution::Custom(_, _, interlace) => interlace,
+            _ => InterlaceMode::Off,
+        }
+    }
+
+    /// Width of a pixel relative to its height once displayed: the framebuffer is always
+    /// stretched to a 4:3 picture, so e.g. 640x240 pixels are twice as high as wide.
+    pub fn pixel_aspect(&self) -> f32 { (4.0 / 3.0) * self.height() as f32 / self.width() as f32 }
+}
+
 /// Valid bit depths
 ///
 /// See [`bitdepth_t`](libdragon_sys::bitdepth_t)
@@ -209,6 +248,16 @@ pub fn init(
             filter.into(),
         )
     }
+    unsafe {
+        CONFIG = Some(DisplayConfig {
+            resolution,
+            depth,
+            num_buffers,
+            gamma,
+            filter,
+        });
+    }
+    GENERATION.fetch_add(1, Ordering::Relaxed);
 }
 
 /// Close the display
@@ -217,6 +266,7 @@ pub fn init(
 pub fn close() {
     unsafe {
         libdragon_sys::display_close();
+        CONFIG = None;
     }
 }
 
@@ -273,3 +323,241 @@ pub fn get_num_buffers() -> u32 { unsafe { libdragon_sys::display_get_num_buffer
 ///
 /// See [`display_get_fps`](libdragon_sys::display_get_fps) for details.
 pub fn get_fps() -> f32 { unsafe { libdragon_sys::display_get_fps() } }
+
+/// Settings of the display, as passed to [init]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayConfig {
+    pub resolution:  Resolution,
+    pub depth:       BitDepth,
+    pub num_buffers: u32,
+    pub gamma:       Gamma,
+    pub filter:      FilterOptions,
+}
+
+impl DisplayConfig {
+    /// 16-bit, triple buffered, without gamma correction, resampled
+    pub fn new(resolution: Resolution) -> Self {
+        Self {
+            resolution,
+            depth: BitDepth::Bpp16,
+            num_buffers: 3,
+            gamma: Gamma::None,
+            filter: FilterOptions::Resample,
+        }
+    }
+}
+
+/// Initialize the display with `config`. Same as [init].
+pub fn init_with(config: DisplayConfig) {
+    init(
+        config.resolution,
+        config.depth,
+        config.num_buffers,
+        config.gamma,
+        config.filter,
+    );
+}
+
+/// Settings the display was initialized with, or `None` if it is not initialized
+///
+/// Rust: only tracks initializations made through this module.
+pub fn config() -> Option<DisplayConfig> { unsafe { CONFIG } }
+
+/// Counter incremented every time the display is initialized, so that code keeping surfaces
+/// sized after the display knows when to reallocate them (see [DisplaySurface]).
+pub fn generation() -> u32 { GENERATION.load(Ordering::Relaxed) }
+
+/// Change the resolution at runtime, keeping the other settings. Waits for the RSP and RDP to
+/// be done with the current framebuffers, then closes and initializes the display again. The
+/// z-buffer of [get_zbuf] is reallocated by libdragon, and [DisplaySurface]s on their next use.
+///
+/// Panics if the display is not initialized.
+pub fn set_resolution(resolution: Resolution) {
+    let config = config().expect("display not initialized");
+    reconfigure(DisplayConfig {
+        resolution,
+        ..config
+    });
+}
+
+/// Change all the display settings at runtime, see [set_resolution]
+pub fn reconfigure(config: DisplayConfig) {
+    rspq::wait();
+    close();
+    init_with(config);
+}
+
+/// A surface sized after the display (e.g. an offscreen render target or an extra z-buffer),
+/// reallocated when the resolution changes.
+///
+/// ```rust
+/// let mut target = display::DisplaySurface::new(surface::TexFormat::Rgba16);
+/// loop {
+///     rdpq::attach_clear(target.get(), None);
+///     // ...
+/// }
+/// ```
+pub struct DisplaySurface {
+    format:     TexFormat,
+    scale:      f32,
+    surface:    Option<Surface<'static>>,
+    generation: u32,
+}
+
+impl DisplaySurface {
+    /// A surface of the same size as the display
+    pub fn new(format: TexFormat) -> Self { Self::scaled(format, 1.0) }
+
+    /// A z-buffer of the same size as the display
+    pub fn zbuf() -> Self { Self::new(TexFormat::Rgba16) }
+
+    /// A surface of the size of the display multiplied by `scale`, e.g. 0.5 for half resolution
+    pub fn scaled(format: TexFormat, scale: f32) -> Self {
+        Self {
+            format,
+            scale,
+            surface: None,
+            generation: 0,
+        }
+    }
+
+    /// The surface, (re)allocated if the display was initialized since the last call
+    pub fn get(&mut self) -> &Surface<'static> {
+        let generation = generation();
+        if self.generation != generation {
+            self.surface = None;
+            self.generation = generation;
+        }
+        let (width, height) = (
+            ((get_width() as f32 * self.scale) as u32).max(1),
+            ((get_height() as f32 * self.scale) as u32).max(1),
+        );
+        self.surface
+            .get_or_insert_with(|| Surface::alloc(self.format, width, height))
+    }
+
+    /// Free the surface until the next call to [DisplaySurface::get]
+    pub fn release(&mut self) { self.surface = None; }
+}
+
+/// A rectangle of the framebuffer, in pixels from the top-left corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub x:      i32,
+    pub y:      i32,
+    pub width:  u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    /// The whole framebuffer of the current resolution
+    pub fn full() -> Self {
+        Self {
+            x:      0,
+            y:      0,
+            width:  get_width(),
+            height: get_height(),
+        }
+    }
+
+    /// The largest rectangle of this viewport that looks `aspect` wide (width / height) on the
+    /// TV, centered, leaving black bars on the sides or at the top and bottom. `resolution`
+    /// gives the shape of the pixels (see [Resolution::pixel_aspect]).
+    pub fn fit_aspect(&self, resolution: Resolution, aspect: f32) -> Self {
+        let displayed = self.width as f32 * resolution.pixel_aspect() / self.height as f32;
+        let (width, height) = if displayed > aspect {
+            ((self.width as f32 * aspect / displayed) as u32, self.height)
+        } else {
+            (self.width, (self.height as f32 * displayed / aspect) as u32)
+        };
+        Self {
+            x: self.x + (self.width - width) as i32 / 2,
+            y: self.y + (self.height - height) as i32 / 2,
+            width,
+            height,
+        }
+    }
+
+    /// Shrink the viewport by a fraction of its size on each side
+    pub fn inset(&self, horizontal: f32, vertical: f32) -> Self {
+        let dx = (self.width as f32 * horizontal) as u32;
+        let dy = (self.height as f32 * vertical) as u32;
+        Self {
+            x:      self.x + dx as i32,
+            y:      self.y + dy as i32,
+            width:  self.width.saturating_sub(2 * dx),
+            height: self.height.saturating_sub(2 * dy),
+        }
+    }
+
+    /// Aspect ratio of the viewport on the TV, given the shape of the pixels of `resolution`
+    pub fn aspect(&self, resolution: Resolution) -> f32 {
+        self.width as f32 * resolution.pixel_aspect() / self.height as f32
+    }
+
+    /// The viewport as `[x, y, width, height]`, as used by [rdpq::mesh::MeshParms::viewport]
+    pub fn to_array(self) -> [f32; 4] {
+        [
+            self.x as f32,
+            self.y as f32,
+            self.width as f32,
+            self.height as f32,
+        ]
+    }
+
+    /// Restrict rdpq drawing to the viewport with [rdpq::set_scissor]
+    pub fn scissor(&self) {
+        rdpq::set_scissor(
+            self.x,
+            self.y,
+            self.x + self.width as i32,
+            self.y + self.height as i32,
+        );
+    }
+
+    /// Set the GL viewport. GL counts rows from the bottom of the framebuffer, which is
+    /// `framebuffer_height` pixels high.
+    pub fn gl_viewport(&self, framebuffer_height: u32) {
+        let y = framebuffer_height as i32 - self.y - self.height as i32;
+        gl::Viewport(self.x, y, self.width, self.height);
+    }
+}
+
+/// Part of the picture that TVs may hide behind their bezel, as a fraction of the size on each
+/// side
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overscan {
+    pub horizontal: f32,
+    pub vertical:   f32,
+}
+
+impl Overscan {
+    /// Typical overscan of `tv`: NTSC sets usually hide more lines than PAL ones
+    pub fn of(tv: TvType) -> Self {
+        match tv {
+            TvType::Ntsc | TvType::Mpal => Self {
+                horizontal: 0.05,
+                vertical:   0.05,
+            },
+            TvType::Pal => Self {
+                horizontal: 0.05,
+                vertical:   0.035,
+            },
+        }
+    }
+
+    /// Overscan of the current TV (see [get_tv_type])
+    pub fn current() -> Self { Self::of(get_tv_type()) }
+
+    /// Area of `viewport` visible on any TV, where important content should be drawn
+    /// ("action safe")
+    pub fn action_safe(&self, viewport: &Viewport) -> Viewport {
+        viewport.inset(self.horizontal, self.vertical)
+    }
+
+    /// Area of `viewport` where text is readable without distortion near the edges
+    /// ("title safe"), twice the overscan away from them
+    pub fn title_safe(&self, viewport: &Viewport) -> Viewport {
+        viewport.inset(self.horizontal * 2.0, self.vertical * 2.0)
+    }
+}
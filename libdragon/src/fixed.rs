@@ -0,0 +1,317 @@
+//! Fixed-point number formats used by the RDP.
+//!
+//! RDP commands take coordinates in several fixed-point formats, and passing a raw integer in
+//! the wrong scale (e.g. pixels where 10.2 is expected) silently draws garbage. Each format here
+//! is a newtype over its raw bits, with conversions from integers and `f32` that round to the
+//! nearest representable value and report values out of range instead of truncating them.
+//!
+//! This module only depends on `core`. It is shared with `libdragon-build`, so the conversions
+//! can be tested on a development host.
+//!
+//! | Type       | Format          | Range                 | Used for                             |
+//! |------------|-----------------|-----------------------|--------------------------------------|
+//! | [Fx10_2]   | unsigned 10.2   | 0 to 1023.75          | tile coordinates, screen coordinates |
+//! | [Fx1_11]   | unsigned 1.11   | 0 to 1.9995           | LOAD_BLOCK `dxt`                     |
+//! | [S10_5]    | signed 10.5     | -1024 to 1023.97      | texture coordinates (S, T)           |
+//! | [S5_10]    | signed 5.10     | -32 to 31.999         | texture coordinate slopes            |
+//! | [S16_16]   | signed 16.16    | -32768 to 32767.99998 | blit scale factors                   |
+
+/// Error returned when a value does not fit in a fixed-point format
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OutOfRange;
+
+impl core::fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "value out of range for the fixed-point format")
+    }
+}
+
+macro_rules! fixed {
+    (
+        $(#[$doc:meta])*
+        $name:ident, $repr:ty, signed: $signed:literal, int: $int:literal, frac: $frac:literal
+    ) => {
+        $(#[$doc])*
+        #[repr(transparent)]
+        #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name($repr);
+
+        impl $name {
+            /// Number of fractional bits
+            pub const FRAC_BITS: u32 = $frac;
+            const MIN_BITS: i64 = if $signed { -(1i64 << ($int + $frac)) } else { 0 };
+            const MAX_BITS: i64 = (1i64 << ($int + $frac)) - 1;
+            const SCALE: f32 = (1i64 << $frac) as f32;
+
+            /// Smallest representable value
+            pub const MIN: Self = Self(Self::MIN_BITS as $repr);
+            /// Largest representable value
+            pub const MAX: Self = Self(Self::MAX_BITS as $repr);
+            /// 0
+            pub const ZERO: Self = Self(0);
+            /// 1
+            pub const ONE: Self = Self(1 << $frac);
+
+            /// Create a value from its raw bits, as sent to the RDP
+            #[inline]
+            pub const fn from_bits(bits: $repr) -> Self { Self(bits) }
+
+            /// The raw bits, as sent to the RDP
+            #[inline]
+            pub const fn to_bits(self) -> $repr { self.0 }
+
+            #[inline]
+            fn checked_from_raw(raw: i64) -> Option<Self> {
+                if (Self::MIN_BITS..=Self::MAX_BITS).contains(&raw) {
+                    Some(Self(raw as $repr))
+                } else {
+                    None
+                }
+            }
+
+            /// Convert an integer, or `None` if it is out of range
+            #[inline]
+            pub fn checked_from_int(v: i32) -> Option<Self> {
+                Self::checked_from_raw((v as i64) << $frac)
+            }
+
+            /// Convert an integer.
+            ///
+            /// Rust: panics if the value is out of range.
+            #[inline]
+            pub fn from_int(v: i32) -> Self {
+                Self::checked_from_int(v).unwrap_or_else(|| {
+                    panic!(concat!("{} out of range for ", stringify!($name)), v)
+                })
+            }
+
+            /// Convert a float, rounding to the nearest representable value, or `None` if it is
+            /// out of range or NaN
+            #[inline]
+            pub fn checked_from_f32(v: f32) -> Option<Self> {
+                if v.is_nan() {
+                    return None;
+                }
+                let scaled = v * Self::SCALE;
+                // float to integer casts saturate, so infinities end up out of range too
+                let raw = (if scaled >= 0.0 { scaled + 0.5 } else { scaled - 0.5 }) as i64;
+                Self::checked_from_raw(raw)
+            }
+
+            /// Convert a float, rounding to the nearest representable value.
+            ///
+            /// Rust: panics if the value is out of range or NaN.
+            #[inline]
+            pub fn from_f32(v: f32) -> Self {
+                Self::checked_from_f32(v).unwrap_or_else(|| {
+                    panic!(concat!("{} out of range for ", stringify!($name)), v)
+                })
+            }
+
+            /// Convert a float, clamping it to the representable range (NaN converts to 0)
+            #[inline]
+            pub fn saturating_from_f32(v: f32) -> Self {
+                if v.is_nan() {
+                    return Self::ZERO;
+                }
+                let scaled = v * Self::SCALE;
+                let raw = (if scaled >= 0.0 { scaled + 0.5 } else { scaled - 0.5 }) as i64;
+                Self(raw.clamp(Self::MIN_BITS, Self::MAX_BITS) as $repr)
+            }
+
+            /// The value as a float
+            #[inline]
+            pub fn to_f32(self) -> f32 { self.0 as f32 / Self::SCALE }
+
+            /// The integer part, rounded towards negative infinity
+            #[inline]
+            pub fn to_int(self) -> i32 { (self.0 as i64 >> $frac) as i32 }
+
+            /// Sum, or `None` if it is out of range
+            #[inline]
+            pub fn checked_add(self, rhs: Self) -> Option<Self> {
+                Self::checked_from_raw(self.0 as i64 + rhs.0 as i64)
+            }
+
+            /// Difference, or `None` if it is out of range
+            #[inline]
+            pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+                Self::checked_from_raw(self.0 as i64 - rhs.0 as i64)
+            }
+        }
+
+        impl TryFrom<f32> for $name {
+            type Error = OutOfRange;
+            #[inline]
+            fn try_from(v: f32) -> Result<Self, OutOfRange> {
+                Self::checked_from_f32(v).ok_or(OutOfRange)
+            }
+        }
+
+        impl TryFrom<i32> for $name {
+            type Error = OutOfRange;
+            #[inline]
+            fn try_from(v: i32) -> Result<Self, OutOfRange> {
+                Self::checked_from_int(v).ok_or(OutOfRange)
+            }
+        }
+
+        impl From<$name> for f32 {
+            #[inline]
+            fn from(v: $name) -> f32 { v.to_f32() }
+        }
+
+        impl core::fmt::Display for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                core::fmt::Display::fmt(&self.to_f32(), f)
+            }
+        }
+    };
+}
+
+fixed!(
+    /// Unsigned 10.2 fixed point (0 to 1023.75), used for tile and screen coordinates
+    Fx10_2, u16, signed: false, int: 10, frac: 2
+);
+fixed!(
+    /// Unsigned 1.11 fixed point (0 to 1.9995), used for the `dxt` increment of LOAD_BLOCK
+    Fx1_11, u16, signed: false, int: 1, frac: 11
+);
+fixed!(
+    /// Signed 10.5 fixed point (-1024 to 1023.97), used for texture coordinates
+    S10_5, i16, signed: true, int: 10, frac: 5
+);
+fixed!(
+    /// Signed 5.10 fixed point (-32 to 31.999), used for texture coordinate slopes
+    /// (texels per pixel)
+    S5_10, i16, signed: true, int: 5, frac: 10
+);
+fixed!(
+    /// Signed 16.16 fixed point (-32768 to 32767.99998), the RDP general purpose format
+    S16_16, i32, signed: true, int: 15, frac: 16
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(Fx10_2::MIN.to_f32(), 0.0);
+        assert_eq!(Fx10_2::MAX.to_f32(), 1023.75);
+        assert_eq!(Fx10_2::MAX.to_bits(), 0xFFF);
+        assert_eq!(Fx1_11::MAX.to_f32(), 2.0 - 1.0 / 2048.0);
+        assert_eq!(S10_5::MIN.to_f32(), -1024.0);
+        assert_eq!(S10_5::MAX.to_f32(), 1024.0 - 1.0 / 32.0);
+        assert_eq!(S5_10::MIN.to_f32(), -32.0);
+        assert_eq!(S5_10::MAX.to_f32(), 32.0 - 1.0 / 1024.0);
+        assert_eq!(S16_16::MIN.to_bits(), i32::MIN);
+        assert_eq!(S16_16::MAX.to_bits(), i32::MAX);
+        assert_eq!(S16_16::ONE.to_bits(), 0x10000);
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(Fx10_2::from_int(1023).to_bits(), 4092);
+        assert_eq!(Fx10_2::checked_from_int(1024), None);
+        assert_eq!(Fx10_2::checked_from_int(-1), None);
+        assert_eq!(Fx1_11::from_int(1), Fx1_11::ONE);
+        assert_eq!(Fx1_11::checked_from_int(2), None);
+        assert_eq!(S10_5::from_int(-1024), S10_5::MIN);
+        assert_eq!(S10_5::checked_from_int(1024), None);
+        assert_eq!(S5_10::from_int(-3).to_bits(), -3 << 10);
+        assert_eq!(S5_10::checked_from_int(32), None);
+        assert_eq!(S16_16::from_int(-32768), S16_16::MIN);
+        assert_eq!(S16_16::checked_from_int(32768), None);
+        assert_eq!(S16_16::checked_from_int(i32::MIN), None);
+        assert_eq!(S10_5::try_from(5), Ok(S10_5::from_bits(160)));
+        assert_eq!(Fx10_2::try_from(-5), Err(OutOfRange));
+    }
+
+    #[test]
+    #[should_panic(expected = "1024 out of range for Fx10_2")]
+    fn integer_out_of_range() { Fx10_2::from_int(1024); }
+
+    #[test]
+    fn rounding() {
+        // to the nearest value, ties away from zero
+        assert_eq!(Fx10_2::from_f32(1.1).to_bits(), 4);
+        assert_eq!(Fx10_2::from_f32(1.125).to_bits(), 5);
+        assert_eq!(Fx10_2::from_f32(1023.8).to_bits(), 0xFFF);
+        assert_eq!(Fx10_2::checked_from_f32(1023.9), None);
+        assert_eq!(Fx10_2::checked_from_f32(-0.1), Some(Fx10_2::ZERO));
+        assert_eq!(Fx10_2::checked_from_f32(-0.2), None);
+        assert_eq!(Fx1_11::from_f32(1.0 / 3.0).to_bits(), 683);
+        assert_eq!(S10_5::from_f32(-0.5 / 32.0).to_bits(), -1);
+        assert_eq!(S10_5::from_f32(-1.24).to_bits(), -40);
+        assert_eq!(S10_5::checked_from_f32(-1024.01), Some(S10_5::MIN));
+        assert_eq!(S10_5::checked_from_f32(-1024.02), None);
+        assert_eq!(S5_10::from_f32(0.5).to_bits(), 512);
+        assert_eq!(S5_10::checked_from_f32(31.9996), None);
+        assert_eq!(S16_16::from_f32(-1.5).to_bits(), -0x18000);
+        assert_eq!(S16_16::checked_from_f32(32768.0), None);
+        assert_eq!(S16_16::checked_from_f32(-32768.0), Some(S16_16::MIN));
+        assert_eq!(S16_16::try_from(1e10), Err(OutOfRange));
+
+        for v in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(S10_5::checked_from_f32(v), None);
+            assert_eq!(S16_16::checked_from_f32(v), None);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range for S5_10")]
+    fn float_out_of_range() { S5_10::from_f32(-40.0); }
+
+    #[test]
+    fn saturation() {
+        assert_eq!(Fx10_2::saturating_from_f32(2000.0), Fx10_2::MAX);
+        assert_eq!(Fx10_2::saturating_from_f32(-3.0), Fx10_2::MIN);
+        assert_eq!(S10_5::saturating_from_f32(f32::NEG_INFINITY), S10_5::MIN);
+        assert_eq!(S16_16::saturating_from_f32(f32::INFINITY), S16_16::MAX);
+        assert_eq!(S5_10::saturating_from_f32(f32::NAN), S5_10::ZERO);
+        assert_eq!(S5_10::saturating_from_f32(1.25).to_f32(), 1.25);
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(S10_5::from_f32(-0.25).to_int(), -1);
+        assert_eq!(S10_5::from_f32(2.75).to_int(), 2);
+        assert_eq!(S16_16::MIN.to_int(), -32768);
+        assert_eq!(Fx10_2::MAX.to_int(), 1023);
+        let a = Fx10_2::from_f32(1000.0);
+        assert_eq!(
+            a.checked_add(Fx10_2::from_int(23)),
+            Some(Fx10_2::from_int(1023))
+        );
+        assert_eq!(a.checked_add(Fx10_2::from_int(24)), None);
+        assert_eq!(Fx10_2::ZERO.checked_sub(Fx10_2::from_f32(0.25)), None);
+        assert_eq!(S16_16::MAX.checked_add(S16_16::from_bits(1)), None);
+        assert_eq!(S16_16::MIN.checked_sub(S16_16::from_bits(1)), None);
+        assert_eq!(
+            S5_10::from_f32(-1.5).checked_sub(S5_10::ONE),
+            Some(S5_10::from_f32(-2.5))
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        for bits in [0, 1, 0x7FF, 0xFFF] {
+            let v = Fx10_2::from_bits(bits);
+            assert_eq!(Fx10_2::from_f32(v.to_f32()), v);
+        }
+        for bits in [i16::MIN, -1, 0, 1, i16::MAX] {
+            let v = S10_5::from_bits(bits);
+            assert_eq!(S10_5::from_f32(f32::from(v)), v);
+            let v = S5_10::from_bits(bits);
+            assert_eq!(S5_10::from_f32(f32::from(v)), v);
+        }
+        assert_eq!(S10_5::from_f32(-2.5).to_string(), "-2.5");
+        assert_eq!(Fx1_11::from_f32(0.75).to_string(), "0.75");
+        assert_eq!(
+            OutOfRange.to_string(),
+            "value out of range for the fixed-point format"
+        );
+    }
+}
@@ -0,0 +1,612 @@
+//! Parser for the `.font64` files produced by LibDragon's `mkfont` tool.
+//!
+//! This module only depends on `core` and the [escape code parser](crate::rdpq::escape), and
+//! never copies the file contents: a [FontData] borrows the raw bytes and decodes records on
+//! demand. It is shared with `libdragon-build`, so the same code can check glyph coverage on the
+//! host (e.g. for localization tables) and query glyph metrics on the N64.
+//!
+//! The file is the big-endian image of `rdpq_font_t` as written by `mkfont`, where pointers are
+//! stored as offsets from the start of the file:
+//!
+//! | Offset | Field                                                                |
+//! |--------|----------------------------------------------------------------------|
+//! | 0      | magic (`"FNT"`) and version                                          |
+//! | 4      | point size, ascent, descent, line gap, space width (5 x 32-bit)      |
+//! | 24     | ellipsis width, glyph, repetitions and advance (4 x 16-bit)          |
+//! | 32     | number of ranges, glyphs, atlases, kerning pairs, styles (5 x 32-bit)|
+//! | 52     | offsets of ranges, glyphs, atlases, kerning pairs, styles (5 x 32-bit)|
+
+use crate::rdpq::escape::{EscapeParser, Token};
+
+/// Magic bytes at the start of a font file
+pub const MAGIC: &[u8; 3] = b"FNT";
+
+const HEADER_SIZE: usize = 72;
+const RANGE_SIZE: usize = 12;
+const GLYPH_SIZE: usize = 16;
+const ATLAS_SIZE: usize = 12;
+const KERNING_SIZE: usize = 4;
+const STYLE_SIZE: usize = 8;
+
+/// Errors that can happen while parsing a font file
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FontError {
+    /// The file does not start with [MAGIC]
+    BadMagic,
+    /// The file is shorter than what its header describes
+    Truncated,
+    /// A record references a glyph or range that does not exist
+    InvalidIndex,
+}
+
+#[inline]
+fn be16(data: &[u8], offset: usize) -> u16 { u16::from_be_bytes([data[offset], data[offset + 1]]) }
+
+#[inline]
+fn be32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// A contiguous range of Unicode codepoints, mapped to consecutive glyphs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Range {
+    /// First codepoint of the range
+    pub first_codepoint: u32,
+    /// Number of codepoints in the range
+    pub num_codepoints:  u32,
+    /// Index of the glyph of the first codepoint
+    pub first_glyph:     u32,
+}
+
+impl Range {
+    /// Returns true if `codepoint` belongs to this range
+    #[inline]
+    pub fn contains(&self, codepoint: u32) -> bool {
+        codepoint >= self.first_codepoint && codepoint - self.first_codepoint < self.num_codepoints
+    }
+}
+
+/// Metrics and atlas location of a single glyph
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Glyph {
+    /// Number of pixels to advance the cursor after drawing the glyph
+    pub xadvance:   f32,
+    /// Offset of the top-left corner of the glyph from the cursor
+    pub xoff:       i8,
+    /// Offset of the top-left corner of the glyph from the cursor
+    pub yoff:       i8,
+    /// Offset of the bottom-right corner of the glyph from the cursor
+    pub xoff2:      i8,
+    /// Offset of the bottom-right corner of the glyph from the cursor
+    pub yoff2:      i8,
+    /// S coordinate of the glyph in its atlas
+    pub s:          u8,
+    /// T coordinate of the glyph in its atlas
+    pub t:          u8,
+    /// Index of the atlas containing the glyph
+    pub natlas:     u8,
+    /// Index of the tile used to draw the glyph
+    pub ntile:      u8,
+    /// First kerning pair of this glyph
+    pub kerning_lo: u16,
+    /// Last kerning pair of this glyph
+    pub kerning_hi: u16,
+}
+
+impl Glyph {
+    /// Width of the glyph bitmap, in pixels
+    #[inline]
+    pub fn width(&self) -> i32 { self.xoff2 as i32 - self.xoff as i32 }
+    /// Height of the glyph bitmap, in pixels
+    #[inline]
+    pub fn height(&self) -> i32 { self.yoff2 as i32 - self.yoff as i32 }
+    /// Returns true if the glyph has kerning pairs
+    #[inline]
+    pub fn has_kerning(&self) -> bool { self.kerning_lo != 0 || self.kerning_hi != 0 }
+}
+
+/// Location of an atlas (a sprite containing glyph bitmaps) within the font file
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Atlas {
+    /// Offset of the `.sprite` data from the start of the file
+    pub offset: u32,
+    /// Size of the `.sprite` data, in bytes
+    pub size:   u32,
+}
+
+impl Atlas {
+    // Offset of the end of the `.sprite` data, unless it does not fit in the address space
+    fn end(&self) -> Option<usize> { (self.offset as usize).checked_add(self.size as usize) }
+}
+
+/// A zero-copy view over the contents of a `.font64` file
+#[derive(Debug, Copy, Clone)]
+pub struct FontData<'a> {
+    data:        &'a [u8],
+    num_ranges:  usize,
+    num_glyphs:  usize,
+    num_atlases: usize,
+    num_kerning: usize,
+    num_styles:  usize,
+    ranges:      usize,
+    glyphs:      usize,
+    atlases:     usize,
+    kerning:     usize,
+    styles:      usize,
+}
+
+impl<'a> FontData<'a> {
+    /// Validate the header of a font file and create a view over it.
+    pub fn parse(data: &'a [u8]) -> Result<Self, FontError> {
+        if data.len() < HEADER_SIZE {
+            return Err(FontError::Truncated);
+        }
+        if &data[0..3] != MAGIC {
+            return Err(FontError::BadMagic);
+        }
+        let count = |i: usize| be32(data, 32 + i * 4) as usize;
+        let offset = |i: usize| be32(data, 52 + i * 4) as usize;
+        let font = Self {
+            data,
+            num_ranges: count(0),
+            num_glyphs: count(1),
+            num_atlases: count(2),
+            num_kerning: count(3),
+            num_styles: count(4),
+            ranges: offset(0),
+            glyphs: offset(1),
+            atlases: offset(2),
+            kerning: offset(3),
+            styles: offset(4),
+        };
+
+        // every table must fit in the file
+        let tables = [
+            (font.ranges, font.num_ranges, RANGE_SIZE),
+            (font.glyphs, font.num_glyphs, GLYPH_SIZE),
+            (font.atlases, font.num_atlases, ATLAS_SIZE),
+            (font.kerning, font.num_kerning, KERNING_SIZE),
+            (font.styles, font.num_styles, STYLE_SIZE),
+        ];
+        for (offset, num, size) in tables {
+            let end = num
+                .checked_mul(size)
+                .and_then(|len| len.checked_add(offset));
+            if num > 0 && end.map_or(true, |end| end > data.len()) {
+                return Err(FontError::Truncated);
+            }
+        }
+        for range in font.ranges() {
+            let end = (range.first_glyph as usize).checked_add(range.num_codepoints as usize);
+            if end.map_or(true, |end| end > font.num_glyphs) {
+                return Err(FontError::InvalidIndex);
+            }
+        }
+        for atlas in font.atlases() {
+            if atlas.end().map_or(true, |end| end > data.len()) {
+                return Err(FontError::Truncated);
+            }
+        }
+        Ok(font)
+    }
+
+    /// Format version written by `mkfont`
+    #[inline]
+    pub fn version(&self) -> u8 { self.data[3] }
+    /// Point size of the font
+    #[inline]
+    pub fn point_size(&self) -> u32 { be32(self.data, 4) }
+    /// Number of pixels above the baseline
+    #[inline]
+    pub fn ascent(&self) -> i32 { be32(self.data, 8) as i32 }
+    /// Number of pixels below the baseline (usually negative)
+    #[inline]
+    pub fn descent(&self) -> i32 { be32(self.data, 12) as i32 }
+    /// Spacing between the descent of a line and the ascent of the next one
+    #[inline]
+    pub fn line_gap(&self) -> i32 { be32(self.data, 16) as i32 }
+    /// Width of the space character, in pixels
+    #[inline]
+    pub fn space_width(&self) -> i32 { be32(self.data, 20) as i32 }
+    /// Height of a line of text (ascent - descent + line gap)
+    #[inline]
+    pub fn line_height(&self) -> i32 { self.ascent() - self.descent() + self.line_gap() }
+
+    /// Number of glyphs in the font
+    #[inline]
+    pub fn num_glyphs(&self) -> usize { self.num_glyphs }
+    /// Number of styles defined in the font
+    #[inline]
+    pub fn num_styles(&self) -> usize { self.num_styles }
+
+    /// Iterate over the codepoint ranges covered by the font
+    pub fn ranges(&self) -> impl Iterator<Item = Range> + 'a {
+        let (data, base) = (self.data, self.ranges);
+        (0..self.num_ranges).map(move |i| {
+            let o = base + i * RANGE_SIZE;
+            Range {
+                first_codepoint: be32(data, o),
+                num_codepoints:  be32(data, o + 4),
+                first_glyph:     be32(data, o + 8),
+            }
+        })
+    }
+
+    /// Iterate over the atlases of the font
+    pub fn atlases(&self) -> impl Iterator<Item = Atlas> + 'a {
+        let (data, base) = (self.data, self.atlases);
+        (0..self.num_atlases).map(move |i| {
+            let o = base + i * ATLAS_SIZE;
+            Atlas {
+                offset: be32(data, o),
+                size:   be32(data, o + 4),
+            }
+        })
+    }
+
+    /// Raw `.sprite` data of an atlas. Empty if the atlas does not lie within the file, which
+    /// never happens for the atlases of the font.
+    pub fn atlas_data(&self, atlas: Atlas) -> &'a [u8] {
+        atlas
+            .end()
+            .and_then(|end| self.data.get(atlas.offset as usize..end))
+            .unwrap_or_default()
+    }
+
+    /// Default color of a style, as packed RGBA32
+    pub fn style_color(&self, index: usize) -> Option<u32> {
+        (index < self.num_styles).then(|| be32(self.data, self.styles + index * STYLE_SIZE))
+    }
+
+    /// Index of the glyph used to draw `codepoint`, if the font covers it
+    pub fn glyph_index(&self, codepoint: u32) -> Option<usize> {
+        self.ranges()
+            .find(|r| r.contains(codepoint))
+            .and_then(|r| r.first_glyph.checked_add(codepoint - r.first_codepoint))
+            .map(|index| index as usize)
+    }
+
+    /// Returns true if `c` can be rendered. Whitespace handled by the layout engine (space
+    /// and newline) is always considered covered.
+    pub fn has_char(&self, c: char) -> bool {
+        c == ' ' || c == '\n' || self.glyph_index(c as u32).is_some()
+    }
+
+    /// Iterate over the characters of `text` that the font cannot render.
+    ///
+    /// Escape codes for fonts and styles (`$xx`, `^xx`) are skipped, as done by the text
+    /// engine: the text is split with the same [EscapeParser] as `rdpq::TextWriter`.
+    pub fn missing_chars<'t>(&self, text: &'t str) -> impl Iterator<Item = char> + 't
+    where
+        'a: 't,
+    {
+        let font = *self;
+        let mut chars = text.chars();
+        let mut parser = EscapeParser::new();
+        let mut finished = false;
+        // text produced by the last char fed to the parser: at most a pending `$x` and the char
+        let mut queue = ['\0'; 3];
+        let (mut len, mut pos) = (0, 0);
+        core::iter::from_fn(move || loop {
+            while pos < len {
+                let c = queue[pos];
+                pos += 1;
+                if !font.has_char(c) {
+                    return Some(c);
+                }
+            }
+            if finished {
+                return None;
+            }
+            (len, pos) = (0, 0);
+            let mut push = |token: Token| {
+                if let Token::Text(text) = token {
+                    // escape sequences are ASCII, so the text is split on char boundaries
+                    for c in core::str::from_utf8(text).unwrap_or_default().chars() {
+                        queue[len] = c;
+                        len += 1;
+                    }
+                }
+            };
+            match chars.next() {
+                Some(c) => parser.feed(c.encode_utf8(&mut [0; 4]).as_bytes(), &mut push),
+                None => {
+                    parser.finish(&mut push);
+                    finished = true;
+                }
+            }
+        })
+    }
+
+    /// Metrics of glyph `index`
+    pub fn glyph(&self, index: usize) -> Option<Glyph> {
+        if index >= self.num_glyphs {
+            return None;
+        }
+        let d = self.data;
+        let o = self.glyphs + index * GLYPH_SIZE;
+        Some(Glyph {
+            xadvance:   be16(d, o) as i16 as f32 / 64.0,
+            xoff:       d[o + 2] as i8,
+            yoff:       d[o + 3] as i8,
+            xoff2:      d[o + 4] as i8,
+            yoff2:      d[o + 5] as i8,
+            s:          d[o + 6],
+            t:          d[o + 7],
+            natlas:     d[o + 8],
+            ntile:      d[o + 9],
+            kerning_lo: be16(d, o + 10),
+            kerning_hi: be16(d, o + 12),
+        })
+    }
+
+    /// Metrics of the glyph used to draw `c`
+    #[inline]
+    pub fn glyph_for(&self, c: char) -> Option<Glyph> { self.glyph(self.glyph_index(c as u32)?) }
+
+    /// Horizontal advance of `c`, in pixels
+    pub fn advance(&self, c: char) -> Option<f32> {
+        if c == ' ' {
+            return Some(self.space_width() as f32);
+        }
+        self.glyph_for(c).map(|g| g.xadvance)
+    }
+
+    /// Extra horizontal advance (in pixels) to apply between glyphs `glyph1` and `glyph2`
+    pub fn kerning(&self, glyph1: usize, glyph2: usize) -> f32 {
+        let Some(g) = self.glyph(glyph1) else {
+            return 0.0;
+        };
+        if !g.has_kerning() {
+            return 0.0;
+        }
+        let d = self.data;
+        let lo = g.kerning_lo as usize;
+        let hi = (g.kerning_hi as usize).min(self.num_kerning.saturating_sub(1));
+        // pairs of a glyph are sorted by second glyph
+        let pairs: &[u8] = match d.get(self.kerning + lo * KERNING_SIZE..) {
+            Some(p) if lo <= hi => &p[..(hi - lo + 1) * KERNING_SIZE],
+            _ => return 0.0,
+        };
+        let n = pairs.len() / KERNING_SIZE;
+        let (mut l, mut r) = (0, n);
+        while l < r {
+            let m = (l + r) / 2;
+            let other = be16(pairs, m * KERNING_SIZE) as i16 as usize;
+            match other.cmp(&glyph2) {
+                core::cmp::Ordering::Equal => {
+                    let k = pairs[m * KERNING_SIZE + 2] as i8;
+                    return k as f32 * self.point_size() as f32 / 127.0;
+                }
+                core::cmp::Ordering::Less => l = m + 1,
+                core::cmp::Ordering::Greater => r = m,
+            }
+        }
+        0.0
+    }
+
+    /// Width in pixels of a single line of text, including kerning and `char_spacing` extra
+    /// pixels between characters. Unsupported characters are skipped.
+    pub fn line_width(&self, text: &str, char_spacing: f32) -> f32 {
+        let mut width = 0.0;
+        let mut prev: Option<usize> = None;
+        for c in text.chars() {
+            if c == ' ' {
+                width += self.space_width() as f32 + char_spacing;
+                prev = None;
+                continue;
+            }
+            let Some(index) = self.glyph_index(c as u32) else {
+                continue;
+            };
+            if let Some(prev) = prev {
+                width += self.kerning(prev, index);
+            }
+            width += self.glyph(index).map_or(0.0, |g| g.xadvance) + char_spacing;
+            prev = Some(index);
+        }
+        width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const POINT_SIZE: u32 = 12;
+
+    fn put16(out: &mut Vec<u8>, v: u16) { out.extend_from_slice(&v.to_be_bytes()); }
+    fn put32(out: &mut Vec<u8>, v: u32) { out.extend_from_slice(&v.to_be_bytes()); }
+
+    // A font laid out like mkfont does: header, ranges, glyphs, atlases, kerning, styles, then
+    // the atlas sprites. Covers `A`-`C` and `a`-`b`; `A` kerns with `B` and `a`.
+    fn font() -> Vec<u8> {
+        let ranges = HEADER_SIZE;
+        let glyphs = ranges + 2 * RANGE_SIZE;
+        let atlases = glyphs + 5 * GLYPH_SIZE;
+        let kerning = atlases + ATLAS_SIZE;
+        let styles = kerning + 3 * KERNING_SIZE;
+        let sprite = styles + STYLE_SIZE;
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"FNT\x04");
+        for v in [POINT_SIZE, 10, -3i32 as u32, 2, 4] {
+            put32(&mut out, v);
+        }
+        for v in [12, 5, 3, 4] {
+            put16(&mut out, v);
+        }
+        for v in [2, 5, 1, 3, 1] {
+            put32(&mut out, v);
+        }
+        for v in [ranges, glyphs, atlases, kerning, styles] {
+            put32(&mut out, v as u32);
+        }
+        assert_eq!(out.len(), HEADER_SIZE);
+
+        for (first, num, glyph) in [(b'A', 3, 0), (b'a', 2, 3)] {
+            for v in [first as u32, num, glyph] {
+                put32(&mut out, v);
+            }
+        }
+        // xadvance (x64), xoff, yoff, xoff2, yoff2, s, t, natlas, ntile, kerning lo/hi
+        for (i, (advance, kerning)) in [
+            (480, (1, 2)),
+            (384, (0, 0)),
+            (320, (0, 0)),
+            (320, (0, 0)),
+            (288, (0, 0)),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            put16(&mut out, advance);
+            out.extend_from_slice(&[0, -9i8 as u8, 6, 1, i as u8 * 8, 0, 0, 0]);
+            put16(&mut out, kerning.0);
+            put16(&mut out, kerning.1);
+            put16(&mut out, 0);
+        }
+        put32(&mut out, sprite as u32);
+        put32(&mut out, 8);
+        put32(&mut out, 0);
+        // the first kerning pair is unused, so that 0 means "no kerning"
+        for (glyph2, k) in [(0, 0), (1, -32i8), (3, 16)] {
+            put16(&mut out, glyph2);
+            out.extend_from_slice(&[k as u8, 0]);
+        }
+        put32(&mut out, 0xFF00_00FF);
+        put32(&mut out, 0);
+        out.extend_from_slice(b"SPRITE!!");
+        out
+    }
+
+    fn kern(k: i8) -> f32 { k as f32 * POINT_SIZE as f32 / 127.0 }
+
+    #[test]
+    fn header() {
+        let data = font();
+        let font = FontData::parse(&data).unwrap();
+        assert_eq!(font.version(), 4);
+        assert_eq!(font.point_size(), POINT_SIZE);
+        assert_eq!(
+            (font.ascent(), font.descent(), font.line_gap()),
+            (10, -3, 2)
+        );
+        assert_eq!(font.line_height(), 15);
+        assert_eq!(font.space_width(), 4);
+        assert_eq!(font.num_glyphs(), 5);
+        assert_eq!(font.num_styles(), 1);
+        assert_eq!(font.style_color(0), Some(0xFF00_00FF));
+        assert_eq!(font.style_color(1), None);
+        let atlas = font.atlases().next().unwrap();
+        assert_eq!(font.atlas_data(atlas), b"SPRITE!!");
+    }
+
+    #[test]
+    fn glyphs() {
+        let data = font();
+        let font = FontData::parse(&data).unwrap();
+        let indices: Vec<_> = "ABCabc"
+            .chars()
+            .map(|c| font.glyph_index(c as u32))
+            .collect();
+        assert_eq!(indices, [Some(0), Some(1), Some(2), Some(3), Some(4), None]);
+        assert!(font.has_char(' ') && font.has_char('\n') && !font.has_char('D'));
+
+        let a = font.glyph_for('A').unwrap();
+        assert_eq!(a.xadvance, 7.5);
+        assert_eq!((a.xoff, a.yoff, a.xoff2, a.yoff2), (0, -9, 6, 1));
+        assert_eq!((a.width(), a.height()), (6, 10));
+        assert!(a.has_kerning());
+        assert_eq!(font.glyph(1).unwrap().s, 8);
+        assert!(!font.glyph(1).unwrap().has_kerning());
+        assert_eq!(font.glyph(5), None);
+        assert_eq!(font.advance(' '), Some(4.0));
+        assert_eq!(font.advance('b'), Some(4.5));
+        assert_eq!(font.advance('z'), None);
+    }
+
+    #[test]
+    fn kerning_and_width() {
+        let data = font();
+        let font = FontData::parse(&data).unwrap();
+        assert_eq!(font.kerning(0, 1), kern(-32));
+        assert_eq!(font.kerning(0, 3), kern(16));
+        assert_eq!(font.kerning(0, 2), 0.0);
+        assert_eq!(font.kerning(1, 0), 0.0);
+        assert_eq!(font.kerning(9, 0), 0.0);
+
+        // `D` is skipped, the space breaks the kerning between `B` and `a`
+        let expected = (7.5 + 1.0) + kern(-32) + (6.0 + 1.0) + (4.0 + 1.0) + (5.0 + 1.0);
+        assert_eq!(font.line_width("ABD a", 1.0), expected);
+        assert_eq!(font.line_width("Aa", 0.0), 7.5 + kern(16) + 5.0);
+    }
+
+    #[test]
+    fn missing_chars_skip_escapes() {
+        let data = font();
+        let font = FontData::parse(&data).unwrap();
+        let missing: Vec<char> = font.missing_chars("Ab$01c^0FZ ^^a$$").collect();
+        assert_eq!(missing, ['c', 'Z', '^', '$']);
+        assert_eq!(font.missing_chars("ABC abab").count(), 0);
+
+        // incomplete escape codes are text, like for EscapeParser
+        let missing: Vec<char> = font.missing_chars("$1Z$gA^").collect();
+        assert_eq!(missing, ['$', '1', 'Z', '$', 'g', '^']);
+        assert_eq!(font.missing_chars("Aé$").collect::<Vec<_>>(), ['é', '$']);
+    }
+
+    #[test]
+    fn invalid_files() {
+        let data = font();
+        let mut bad = data.clone();
+        bad[0] = b'X';
+        assert_eq!(FontData::parse(&bad).err(), Some(FontError::BadMagic));
+        assert_eq!(
+            FontData::parse(&data[..HEADER_SIZE - 1]).err(),
+            Some(FontError::Truncated)
+        );
+
+        // glyph count beyond the end of the file
+        let mut bad = data.clone();
+        bad[36..40].copy_from_slice(&0x1000_0000u32.to_be_bytes());
+        assert_eq!(FontData::parse(&bad).err(), Some(FontError::Truncated));
+
+        // second range pointing past the last glyph
+        let mut bad = data.clone();
+        let o = HEADER_SIZE + RANGE_SIZE + 8;
+        bad[o..o + 4].copy_from_slice(&4u32.to_be_bytes());
+        assert_eq!(FontData::parse(&bad).err(), Some(FontError::InvalidIndex));
+
+        // ranges and atlases ending past u32::MAX
+        let mut bad = data.clone();
+        bad[o..o + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(FontData::parse(&bad).err(), Some(FontError::InvalidIndex));
+        let mut bad = data.clone();
+        let o = HEADER_SIZE + 2 * RANGE_SIZE + 5 * GLYPH_SIZE;
+        bad[o..o + 4].copy_from_slice(&0xFFFF_FFF0u32.to_be_bytes());
+        assert_eq!(FontData::parse(&bad).err(), Some(FontError::Truncated));
+        let font = FontData::parse(&data).unwrap();
+        let atlas = Atlas {
+            offset: 0xFFFF_FFF0,
+            size:   0x20,
+        };
+        assert_eq!(font.atlas_data(atlas), &[] as &[u8]);
+
+        // every truncation is either rejected or still safe to query
+        for len in 0..data.len() {
+            if let Ok(font) = FontData::parse(&data[..len]) {
+                for c in "ABCabz".chars() {
+                    let _ = font.advance(c);
+                }
+                let _ = font.line_width("ABab", 0.0);
+            }
+        }
+    }
+}
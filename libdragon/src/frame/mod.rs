@@ -0,0 +1,192 @@
+//! A main loop handling the per-frame boilerplate: input polling, acquiring the display surface
+//! and z-buffer, attaching rdpq, fixed-timestep updates, showing the frame and throttling.
+//!
+//! ```rust
+//! display::init(
+//!     Resolution::_320x240,
+//!     BitDepth::Bpp16,
+//!     3,
+//!     Gamma::None,
+//!     FilterOptions::Resample,
+//! );
+//! rdpq::init();
+//! joypad::init();
+//!
+//! let mut world = World::new();
+//! frame::FrameLoop::new(frame::FrameConfig::default()).run(|frame| {
+//!     for _ in 0..frame.updates() {
+//!         world.update(frame.step());
+//!     }
+//!     world.draw(frame.alpha());
+//!     if joypad::Port::get_port_1().get_buttons_pressed().start {
+//!         frame.quit();
+//!     }
+//! });
+//! ```
+//!
+//! The simulation runs at a fixed rate ([FrameConfig::step]) whatever the display rate, and
+//! [Frame::alpha] tells how far the displayed frame is between the last two simulation steps,
+//! to interpolate positions. This logic lives in [Timestep], which can be driven and tested on
+//! the host.
+
+use crate::*;
+
+use graphics::Color;
+use surface::Surface;
+
+mod timestep;
+pub use timestep::{FrameStats, Timestep};
+
+/// Configuration of a [FrameLoop]
+#[derive(Copy, Clone)]
+pub struct FrameConfig {
+    /// Color the frame is cleared to, or `None` to keep the previous contents (default: black)
+    pub clear:       Option<Color>,
+    /// Attach the display z-buffer (see [display::get_zbuf]), cleared with the frame (default:
+    /// false)
+    pub zbuf:        bool,
+    /// Call [joypad::poll] at the start of every frame (default: true)
+    pub poll_joypad: bool,
+    /// Length of a simulation step, in seconds (default: 1/60)
+    pub step:        f32,
+    /// Maximum number of simulation steps per frame (default: 4)
+    pub max_steps:   u32,
+    /// Throttle the loop to this number of frames per second with [throttle::wait], or `None`
+    /// to run as fast as the display allows (default: `None`)
+    pub throttle:    Option<f32>,
+}
+
+impl Default for FrameConfig {
+    fn default() -> Self {
+        Self {
+            clear:       Some(graphics::rgba32(0, 0, 0, 0xFF)),
+            zbuf:        false,
+            poll_joypad: true,
+            step:        1.0 / 60.0,
+            max_steps:   4,
+            throttle:    None,
+        }
+    }
+}
+
+/// The frame being drawn, passed to the [FrameLoop::run] callback with rdpq attached to it
+pub struct Frame<'a> {
+    surface: &'a Surface<'a>,
+    zbuf:    Option<&'a Surface<'a>>,
+    updates: u32,
+    step:    f32,
+    alpha:   f32,
+    stats:   &'a FrameStats,
+    quit:    bool,
+}
+
+impl<'a> Frame<'a> {
+    /// The display surface rdpq is attached to
+    #[inline]
+    pub fn surface(&self) -> &Surface<'a> { self.surface }
+
+    /// The z-buffer, if enabled with [FrameConfig::zbuf]
+    #[inline]
+    pub fn zbuf(&self) -> Option<&Surface<'a>> { self.zbuf }
+
+    /// Number of fixed simulation steps to run this frame
+    #[inline]
+    pub fn updates(&self) -> u32 { self.updates }
+
+    /// Length of a simulation step, in seconds
+    #[inline]
+    pub fn step(&self) -> f32 { self.step }
+
+    /// How far the frame is between the last simulation step and the next one, from 0 to 1
+    #[inline]
+    pub fn alpha(&self) -> f32 { self.alpha }
+
+    /// Frame time statistics
+    #[inline]
+    pub fn stats(&self) -> &FrameStats { self.stats }
+
+    /// Stop the loop after this frame is shown
+    #[inline]
+    pub fn quit(&mut self) { self.quit = true; }
+}
+
+/// The main loop. See the [module documentation](self).
+pub struct FrameLoop {
+    config:   FrameConfig,
+    timestep: Timestep,
+    stats:    FrameStats,
+}
+
+impl FrameLoop {
+    /// Create a loop. The display and rdpq must be initialized, and the joypad too if
+    /// [FrameConfig::poll_joypad] is set.
+    pub fn new(config: FrameConfig) -> Self {
+        Self {
+            config,
+            timestep: Timestep::new(config.step, config.max_steps),
+            stats: FrameStats::default(),
+        }
+    }
+
+    /// Frame time statistics
+    #[inline]
+    pub fn stats(&self) -> &FrameStats { &self.stats }
+
+    /// Run `f` every frame until it calls [Frame::quit]. Every frame is shown with
+    /// [rdpq::detach_show]; when the loop stops, this waits for the RSP and RDP to be done.
+    ///
+    /// Rust: frame times are measured with [ticks::get].
+    pub fn run<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Frame),
+    {
+        if let Some(fps) = self.config.throttle {
+            throttle::init(fps, 0, 8);
+        }
+        let mut last = ticks::get();
+        loop {
+            let now = ticks::get();
+            let delta = (now - last) as f32 / ticks::per_second() as f32;
+            last = now;
+            self.stats.record(delta);
+            self.stats.fps = display::get_fps();
+            let updates = self.timestep.advance(delta);
+
+            if self.config.poll_joypad {
+                joypad::poll();
+            }
+
+            let surface = display::get();
+            let zbuf = self.config.zbuf.then(display::get_zbuf);
+            rdpq::attach(&surface, zbuf.as_ref());
+            if let Some(color) = self.config.clear {
+                rdpq::clear(color);
+                if zbuf.is_some() {
+                    rdpq::clear_z(rdpq::ZBUF_MAX);
+                }
+            }
+
+            let mut frame = Frame {
+                surface: &surface,
+                zbuf: zbuf.as_ref(),
+                updates,
+                step: self.timestep.step(),
+                alpha: self.timestep.alpha(),
+                stats: &self.stats,
+                quit: false,
+            };
+            f(&mut frame);
+            let quit = frame.quit;
+
+            rdpq::detach_show();
+            if self.config.throttle.is_some() {
+                self.stats.time_left = throttle::frame_time_left();
+                throttle::wait();
+            }
+            if quit {
+                break;
+            }
+        }
+        rspq::wait();
+    }
+}
@@ -0,0 +1,145 @@
+//! Frame timing, independent of the display.
+//!
+//! This module only depends on `core`. It is shared with `libdragon-build`, so the fixed-timestep
+//! logic can be tested on a development host.
+
+/// Fixed-timestep accumulator: turns variable frame times into a whole number of fixed
+/// simulation steps, carrying the remainder over to the next frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Timestep {
+    step:        f32,
+    max_steps:   u32,
+    accumulator: f32,
+}
+
+impl Timestep {
+    /// Steps of `step` seconds, at most `max_steps` per frame: when a frame takes longer than
+    /// that, the extra time is dropped and the simulation slows down instead of trying to catch
+    /// up forever.
+    pub fn new(step: f32, max_steps: u32) -> Self {
+        assert!(step > 0.0, "step must be positive");
+        Self {
+            step,
+            max_steps,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Length of a step, in seconds
+    #[inline]
+    pub fn step(&self) -> f32 { self.step }
+
+    /// Add `dt` seconds of frame time and return the number of steps to run
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt.max(0.0);
+        let steps = (self.accumulator / self.step) as u32;
+        if steps > self.max_steps {
+            self.accumulator = 0.0;
+            return self.max_steps;
+        }
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    /// How far the time is between the last step and the next one, from 0 to 1
+    #[inline]
+    pub fn alpha(&self) -> f32 { (self.accumulator / self.step).clamp(0.0, 1.0) }
+
+    /// Drop the accumulated time, e.g. after loading or pausing
+    pub fn reset(&mut self) { self.accumulator = 0.0; }
+}
+
+/// Frame time statistics
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    /// Number of frames since the loop started
+    pub frames:        u64,
+    /// Time since the previous frame, in seconds
+    pub delta:         f32,
+    /// Average frame time, smoothed over about the last 16 frames, in seconds
+    pub average_delta: f32,
+    /// Longest frame time since the loop started, in seconds
+    pub max_delta:     f32,
+    /// Frames per second measured by the display (see
+    /// [display::get_fps](crate::display::get_fps))
+    pub fps:           f32,
+    /// Time that was left before the end of the previous frame when throttling, in ticks (see
+    /// [throttle::frame_time_left](crate::throttle::frame_time_left)). Negative if the frame was
+    /// late, 0 without throttling.
+    pub time_left:     i32,
+}
+
+impl FrameStats {
+    /// Account for a frame that took `delta` seconds
+    pub fn record(&mut self, delta: f32) {
+        self.average_delta = if self.frames == 0 {
+            delta
+        } else {
+            self.average_delta + (delta - self.average_delta) / 16.0
+        };
+        self.frames += 1;
+        self.delta = delta;
+        self.max_delta = self.max_delta.max(delta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps() {
+        let mut timestep = Timestep::new(0.25, 4);
+        assert_eq!(timestep.step(), 0.25);
+        assert_eq!(timestep.advance(0.125), 0);
+        assert_eq!(timestep.alpha(), 0.5);
+        assert_eq!(timestep.advance(0.125), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(0.625), 2);
+        assert_eq!(timestep.alpha(), 0.5);
+        // negative frame times are ignored
+        assert_eq!(timestep.advance(-1.0), 0);
+        assert_eq!(timestep.alpha(), 0.5);
+        timestep.reset();
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn too_slow() {
+        let mut timestep = Timestep::new(0.25, 4);
+        assert_eq!(timestep.advance(1.0), 4);
+        assert_eq!(timestep.alpha(), 0.0);
+        // the extra time is dropped instead of being caught up later
+        assert_eq!(timestep.advance(3.125), 4);
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(0.125), 0);
+    }
+
+    #[test]
+    fn fixed_rate() {
+        // a 60 Hz simulation on a display running at 50 Hz
+        let mut timestep = Timestep::new(1.0 / 60.0, 4);
+        let steps: u32 = (0..50).map(|_| timestep.advance(1.0 / 50.0)).sum();
+        assert!((59..=60).contains(&steps), "{}", steps);
+        assert!((0.0..=1.0).contains(&timestep.alpha()));
+    }
+
+    #[test]
+    #[should_panic(expected = "step must be positive")]
+    fn zero_step() { Timestep::new(0.0, 4); }
+
+    #[test]
+    fn stats() {
+        let mut stats = FrameStats::default();
+        stats.record(0.5);
+        assert_eq!(
+            (stats.frames, stats.delta, stats.average_delta),
+            (1, 0.5, 0.5)
+        );
+        stats.record(2.1);
+        assert_eq!((stats.frames, stats.delta), (2, 2.1));
+        assert_eq!(stats.average_delta, 0.6);
+        stats.record(1.0);
+        assert_eq!(stats.max_delta, 2.1);
+    }
+}
@@ -0,0 +1,1013 @@
+//! OpenGL 1.1 (plus libdragon's N64 extensions) on top of rdpq, with typed enums and owning
+//! object wrappers.
+//!
+//! The functions taking enum parameters accept typed enums ([Capability], [PrimitiveMode],
+//! [BlendFactor], ...) so that a value from the wrong category can't be passed. Everything else,
+//! including the GL constants, is re-exported from [raw], which keeps the C API with plain `i32`
+//! enums for code that needs it.
+//!
+//! GL objects can be managed with the owning types [Texture], [Buffer], [VertexArray] and
+//! [DisplayList], which delete the object when dropped and take typed slices instead of raw
+//! pointers.
+//!
+//! In debug builds, the typed functions check [GetError] after the call and panic with the
+//! decoded error at the call site. Use [check] to get the pending error as a [Result] instead.
+
+#![allow(non_snake_case)]
+
+use crate::*;
+
+use bitflags::bitflags;
+
+mod object;
+pub mod raw;
+
+pub use object::*;
+pub use raw::*;
+
+/// An error reported by GL
+///
+/// See [`glGetError`](libdragon_sys::glGetError) for details.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GlError {
+    /// An enum parameter is not valid for the function (`GL_INVALID_ENUM`)
+    InvalidEnum,
+    /// A numeric parameter is out of range (`GL_INVALID_VALUE`)
+    InvalidValue,
+    /// The function is not allowed in the current state (`GL_INVALID_OPERATION`)
+    InvalidOperation,
+    /// A push would overflow a stack (`GL_STACK_OVERFLOW`)
+    StackOverflow,
+    /// A pop would underflow a stack (`GL_STACK_UNDERFLOW`)
+    StackUnderflow,
+    /// Not enough memory to execute the function (`GL_OUT_OF_MEMORY`)
+    OutOfMemory,
+    /// An error code not known to this crate
+    Other { code: u32 },
+}
+
+impl GlError {
+    /// Decode a value returned by `glGetError`, or `None` for `GL_NO_ERROR`
+    pub fn from_code(code: u32) -> Option<Self> {
+        #[rustfmt::skip]
+        let error = match code {
+            libdragon_sys::GL_NO_ERROR          => return None,
+            libdragon_sys::GL_INVALID_ENUM      => Self::InvalidEnum,
+            libdragon_sys::GL_INVALID_VALUE     => Self::InvalidValue,
+            libdragon_sys::GL_INVALID_OPERATION => Self::InvalidOperation,
+            libdragon_sys::GL_STACK_OVERFLOW    => Self::StackOverflow,
+            libdragon_sys::GL_STACK_UNDERFLOW   => Self::StackUnderflow,
+            libdragon_sys::GL_OUT_OF_MEMORY     => Self::OutOfMemory,
+            code => Self::Other { code },
+        };
+        Some(error)
+    }
+
+    // The panic handler only prints static messages, so each error gets its own panic!().
+    #[cfg(debug_assertions)]
+    #[track_caller]
+    fn panic(self) -> ! {
+        match self {
+            Self::InvalidEnum => panic!("GL error: GL_INVALID_ENUM"),
+            Self::InvalidValue => panic!("GL error: GL_INVALID_VALUE"),
+            Self::InvalidOperation => panic!("GL error: GL_INVALID_OPERATION"),
+            Self::StackOverflow => panic!("GL error: GL_STACK_OVERFLOW"),
+            Self::StackUnderflow => panic!("GL error: GL_STACK_UNDERFLOW"),
+            Self::OutOfMemory => panic!("GL error: GL_OUT_OF_MEMORY"),
+            Self::Other { .. } => panic!("GL error: unknown error code"),
+        }
+    }
+}
+
+impl core::fmt::Display for GlError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidEnum => write!(f, "GL_INVALID_ENUM"),
+            Self::InvalidValue => write!(f, "GL_INVALID_VALUE"),
+            Self::InvalidOperation => write!(f, "GL_INVALID_OPERATION"),
+            Self::StackOverflow => write!(f, "GL_STACK_OVERFLOW"),
+            Self::StackUnderflow => write!(f, "GL_STACK_UNDERFLOW"),
+            Self::OutOfMemory => write!(f, "GL_OUT_OF_MEMORY"),
+            Self::Other { code } => write!(f, "unknown GL error {:#x}", code),
+        }
+    }
+}
+
+/// Return and clear the error recorded by GL, if any
+///
+/// See [`glGetError`](libdragon_sys::glGetError) for details.
+#[inline(always)]
+pub fn GetError() -> Option<GlError> { GlError::from_code(unsafe { libdragon_sys::glGetError() }) }
+
+/// Return the error recorded by GL since the last check as a [LibDragonError::GlError]
+#[inline]
+pub fn check() -> Result<()> {
+    match GetError() {
+        Some(error) => Err(LibDragonError::GlError { error }),
+        None => Ok(()),
+    }
+}
+
+// Called after each typed function. Does nothing in release builds.
+#[track_caller]
+#[inline(always)]
+fn debug_check() {
+    #[cfg(debug_assertions)]
+    if let Some(error) = GetError() {
+        error.panic();
+    }
+}
+
+macro_rules! gl_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident = $value:ident,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        #[repr(u32)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant = libdragon_sys::$value as u32,)*
+        }
+
+        impl From<$name> for u32 {
+            #[inline(always)]
+            fn from(v: $name) -> u32 { v as u32 }
+        }
+
+        impl From<$name> for i32 {
+            #[inline(always)]
+            fn from(v: $name) -> i32 { v as i32 }
+        }
+
+        impl TryFrom<u32> for $name {
+            type Error = u32;
+            /// Convert a raw GL value, returning it back if it's not part of the enum
+            fn try_from(v: u32) -> core::result::Result<Self, u32> {
+                $(if v == libdragon_sys::$value as u32 { return Ok(Self::$variant); })*
+                Err(v)
+            }
+        }
+    };
+}
+
+gl_enum!(
+    /// Server-side capabilities, for [Enable], [Disable] and [IsEnabled]
+    Capability {
+        AlphaTest = GL_ALPHA_TEST,
+        Blend = GL_BLEND,
+        ColorMaterial = GL_COLOR_MATERIAL,
+        CullFace = GL_CULL_FACE,
+        DepthTest = GL_DEPTH_TEST,
+        Dither = GL_DITHER,
+        Fog = GL_FOG,
+        Light0 = GL_LIGHT0,
+        Light1 = GL_LIGHT1,
+        Light2 = GL_LIGHT2,
+        Light3 = GL_LIGHT3,
+        Light4 = GL_LIGHT4,
+        Light5 = GL_LIGHT5,
+        Light6 = GL_LIGHT6,
+        Light7 = GL_LIGHT7,
+        Lighting = GL_LIGHTING,
+        MatrixPalette = GL_MATRIX_PALETTE_ARB,
+        Multisample = GL_MULTISAMPLE_ARB,
+        Normalize = GL_NORMALIZE,
+        /// Let rdpq configure the render mode (N64 extension)
+        RdpqMaterial = GL_RDPQ_MATERIAL_N64,
+        /// Let rdpq configure texturing (N64 extension)
+        RdpqTexturing = GL_RDPQ_TEXTURING_N64,
+        ScissorTest = GL_SCISSOR_TEST,
+        Texture1D = GL_TEXTURE_1D,
+        Texture2D = GL_TEXTURE_2D,
+        /// Flip the T texture coordinate (N64 extension)
+        TextureFlipT = GL_TEXTURE_FLIP_T_N64,
+        TextureGenS = GL_TEXTURE_GEN_S,
+        TextureGenT = GL_TEXTURE_GEN_T,
+        TextureGenR = GL_TEXTURE_GEN_R,
+        TextureGenQ = GL_TEXTURE_GEN_Q,
+    }
+);
+
+gl_enum!(
+    /// Client-side vertex arrays, for [EnableClientState] and [DisableClientState]
+    ClientArray {
+        Color = GL_COLOR_ARRAY,
+        MatrixIndex = GL_MATRIX_INDEX_ARRAY_ARB,
+        Normal = GL_NORMAL_ARRAY,
+        TextureCoord = GL_TEXTURE_COORD_ARRAY,
+        Vertex = GL_VERTEX_ARRAY,
+    }
+);
+
+gl_enum!(
+    /// Primitives drawn by [Begin], [DrawArrays] and [DrawElements]
+    PrimitiveMode {
+        Points = GL_POINTS,
+        Lines = GL_LINES,
+        LineLoop = GL_LINE_LOOP,
+        LineStrip = GL_LINE_STRIP,
+        Triangles = GL_TRIANGLES,
+        TriangleStrip = GL_TRIANGLE_STRIP,
+        TriangleFan = GL_TRIANGLE_FAN,
+        Quads = GL_QUADS,
+        QuadStrip = GL_QUAD_STRIP,
+        Polygon = GL_POLYGON,
+    }
+);
+
+gl_enum!(
+    /// Component types of vertex arrays
+    DataType {
+        Byte = GL_BYTE,
+        UnsignedByte = GL_UNSIGNED_BYTE,
+        Short = GL_SHORT,
+        UnsignedShort = GL_UNSIGNED_SHORT,
+        Int = GL_INT,
+        UnsignedInt = GL_UNSIGNED_INT,
+        Float = GL_FLOAT,
+        Double = GL_DOUBLE,
+        /// Signed 16-bit fixed point, see [VertexHalfFixedPrecisionN64] (N64 extension)
+        HalfFixed = GL_HALF_FIXED_N64,
+    }
+);
+
+gl_enum!(
+    /// Index types for [DrawElements]
+    IndexType {
+        UnsignedByte = GL_UNSIGNED_BYTE,
+        UnsignedShort = GL_UNSIGNED_SHORT,
+        UnsignedInt = GL_UNSIGNED_INT,
+    }
+);
+
+gl_enum!(
+    /// Buffer object binding points, for [BindBufferARB]
+    BufferTarget {
+        Array = GL_ARRAY_BUFFER_ARB,
+        ElementArray = GL_ELEMENT_ARRAY_BUFFER_ARB,
+    }
+);
+
+gl_enum!(
+    /// Expected usage of a buffer object, for [BufferDataARB]
+    BufferUsage {
+        StreamDraw = GL_STREAM_DRAW_ARB,
+        StreamRead = GL_STREAM_READ_ARB,
+        StreamCopy = GL_STREAM_COPY_ARB,
+        StaticDraw = GL_STATIC_DRAW_ARB,
+        StaticRead = GL_STATIC_READ_ARB,
+        StaticCopy = GL_STATIC_COPY_ARB,
+        DynamicDraw = GL_DYNAMIC_DRAW_ARB,
+        DynamicRead = GL_DYNAMIC_READ_ARB,
+        DynamicCopy = GL_DYNAMIC_COPY_ARB,
+    }
+);
+
+gl_enum!(
+    /// Access to a mapped buffer object, for [MapBufferARB]
+    BufferAccess {
+        ReadOnly = GL_READ_ONLY_ARB,
+        WriteOnly = GL_WRITE_ONLY_ARB,
+        ReadWrite = GL_READ_WRITE_ARB,
+    }
+);
+
+gl_enum!(
+    /// Matrix stacks, for [MatrixMode] and [CopyMatrixN64]
+    MatrixMode {
+        Modelview = GL_MODELVIEW,
+        Projection = GL_PROJECTION,
+        Texture = GL_TEXTURE,
+        MatrixPalette = GL_MATRIX_PALETTE_ARB,
+    }
+);
+
+gl_enum!(
+    /// Polygon faces, for [CullFace], [Materialfv] and [ColorMaterial]
+    Face {
+        Front = GL_FRONT,
+        Back = GL_BACK,
+        FrontAndBack = GL_FRONT_AND_BACK,
+    }
+);
+
+gl_enum!(
+    /// Winding of front-facing polygons, for [FrontFace]
+    Winding {
+        Clockwise = GL_CW,
+        CounterClockwise = GL_CCW,
+    }
+);
+
+gl_enum!(
+    /// Shading of primitives, for [ShadeModel]
+    Shading {
+        Flat = GL_FLAT,
+        Smooth = GL_SMOOTH,
+    }
+);
+
+gl_enum!(
+    /// Light sources, for [Lightfv] and friends
+    Light {
+        Light0 = GL_LIGHT0,
+        Light1 = GL_LIGHT1,
+        Light2 = GL_LIGHT2,
+        Light3 = GL_LIGHT3,
+        Light4 = GL_LIGHT4,
+        Light5 = GL_LIGHT5,
+        Light6 = GL_LIGHT6,
+        Light7 = GL_LIGHT7,
+    }
+);
+
+impl Light {
+    /// All the light sources, in order
+    pub const ALL: [Light; 8] = [
+        Light::Light0,
+        Light::Light1,
+        Light::Light2,
+        Light::Light3,
+        Light::Light4,
+        Light::Light5,
+        Light::Light6,
+        Light::Light7,
+    ];
+}
+
+impl From<Light> for Capability {
+    fn from(v: Light) -> Self {
+        match v {
+            Light::Light0 => Capability::Light0,
+            Light::Light1 => Capability::Light1,
+            Light::Light2 => Capability::Light2,
+            Light::Light3 => Capability::Light3,
+            Light::Light4 => Capability::Light4,
+            Light::Light5 => Capability::Light5,
+            Light::Light6 => Capability::Light6,
+            Light::Light7 => Capability::Light7,
+        }
+    }
+}
+
+gl_enum!(
+    /// Parameters of a light source, for [Lightfv] and friends
+    LightParameter {
+        Ambient = GL_AMBIENT,
+        Diffuse = GL_DIFFUSE,
+        Specular = GL_SPECULAR,
+        Position = GL_POSITION,
+        SpotDirection = GL_SPOT_DIRECTION,
+        SpotExponent = GL_SPOT_EXPONENT,
+        SpotCutoff = GL_SPOT_CUTOFF,
+        ConstantAttenuation = GL_CONSTANT_ATTENUATION,
+        LinearAttenuation = GL_LINEAR_ATTENUATION,
+        QuadraticAttenuation = GL_QUADRATIC_ATTENUATION,
+    }
+);
+
+gl_enum!(
+    /// Parameters of the lighting model, for [LightModelfv] and friends
+    LightModelParameter {
+        Ambient = GL_LIGHT_MODEL_AMBIENT,
+        LocalViewer = GL_LIGHT_MODEL_LOCAL_VIEWER,
+        TwoSide = GL_LIGHT_MODEL_TWO_SIDE,
+    }
+);
+
+gl_enum!(
+    /// Material parameters, for [Materialfv] and friends, and [ColorMaterial]
+    MaterialParameter {
+        Ambient = GL_AMBIENT,
+        Diffuse = GL_DIFFUSE,
+        Specular = GL_SPECULAR,
+        Emission = GL_EMISSION,
+        Shininess = GL_SHININESS,
+        AmbientAndDiffuse = GL_AMBIENT_AND_DIFFUSE,
+    }
+);
+
+gl_enum!(
+    /// Fog parameters, for [Fogfv] and friends
+    FogParameter {
+        Mode = GL_FOG_MODE,
+        Density = GL_FOG_DENSITY,
+        Start = GL_FOG_START,
+        End = GL_FOG_END,
+        Color = GL_FOG_COLOR,
+    }
+);
+
+gl_enum!(
+    /// Texture targets, for [BindTexture] and [TexParameteri] and friends
+    TextureTarget {
+        Texture1D = GL_TEXTURE_1D,
+        Texture2D = GL_TEXTURE_2D,
+    }
+);
+
+gl_enum!(
+    /// Texture parameters, for [TexParameteri] and friends
+    TextureParameter {
+        MagFilter = GL_TEXTURE_MAG_FILTER,
+        MinFilter = GL_TEXTURE_MIN_FILTER,
+        WrapS = GL_TEXTURE_WRAP_S,
+        WrapT = GL_TEXTURE_WRAP_T,
+        BorderColor = GL_TEXTURE_BORDER_COLOR,
+        Priority = GL_TEXTURE_PRIORITY,
+    }
+);
+
+gl_enum!(
+    /// Comparison functions, for [DepthFunc] and [AlphaFunc]
+    CompareFunc {
+        Never = GL_NEVER,
+        Less = GL_LESS,
+        Equal = GL_EQUAL,
+        LessOrEqual = GL_LEQUAL,
+        Greater = GL_GREATER,
+        NotEqual = GL_NOTEQUAL,
+        GreaterOrEqual = GL_GEQUAL,
+        Always = GL_ALWAYS,
+        /// Like [CompareFunc::Less], with the RDP interpenetrating mode (N64 extension)
+        LessInterpenetrating = GL_LESS_INTERPENETRATING_N64,
+    }
+);
+
+gl_enum!(
+    /// Blending factors, for [BlendFunc]
+    BlendFactor {
+        Zero = GL_ZERO,
+        One = GL_ONE,
+        SrcColor = GL_SRC_COLOR,
+        OneMinusSrcColor = GL_ONE_MINUS_SRC_COLOR,
+        SrcAlpha = GL_SRC_ALPHA,
+        OneMinusSrcAlpha = GL_ONE_MINUS_SRC_ALPHA,
+        DstColor = GL_DST_COLOR,
+        OneMinusDstColor = GL_ONE_MINUS_DST_COLOR,
+        DstAlpha = GL_DST_ALPHA,
+        OneMinusDstAlpha = GL_ONE_MINUS_DST_ALPHA,
+        SrcAlphaSaturate = GL_SRC_ALPHA_SATURATE,
+    }
+);
+
+gl_enum!(
+    /// Behaviors that can be tuned with [Hint]
+    HintTarget {
+        Fog = GL_FOG_HINT,
+        LineSmooth = GL_LINE_SMOOTH_HINT,
+        /// Reduced aliasing instead of full antialiasing (N64 extension)
+        Multisample = GL_MULTISAMPLE_HINT_N64,
+        PerspectiveCorrection = GL_PERSPECTIVE_CORRECTION_HINT,
+        PointSmooth = GL_POINT_SMOOTH_HINT,
+        PolygonSmooth = GL_POLYGON_SMOOTH_HINT,
+    }
+);
+
+gl_enum!(
+    /// Hint values, for [Hint]
+    HintMode {
+        DontCare = GL_DONT_CARE,
+        Fastest = GL_FASTEST,
+        Nicest = GL_NICEST,
+    }
+);
+
+gl_enum!(
+    /// Display list compilation modes, for [NewList] and [record_list]
+    ListMode {
+        Compile = GL_COMPILE,
+        CompileAndExecute = GL_COMPILE_AND_EXECUTE,
+    }
+);
+
+bitflags! {
+    /// Buffers cleared by [Clear]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct ClearMask: u32 {
+        /// The color buffer
+        const COLOR = libdragon_sys::GL_COLOR_BUFFER_BIT as u32;
+        /// The depth buffer
+        const DEPTH = libdragon_sys::GL_DEPTH_BUFFER_BIT as u32;
+    }
+}
+
+/// See [`glEnable`](libdragon_sys::glEnable) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Enable(cap: Capability) {
+    unsafe { libdragon_sys::glEnable(cap.into()) }
+    debug_check();
+}
+/// See [`glDisable`](libdragon_sys::glDisable) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Disable(cap: Capability) {
+    unsafe { libdragon_sys::glDisable(cap.into()) }
+    debug_check();
+}
+/// See [`glIsEnabled`](libdragon_sys::glIsEnabled) for details.
+#[track_caller]
+#[inline(always)]
+pub fn IsEnabled(cap: Capability) -> bool {
+    let enabled = unsafe { libdragon_sys::glIsEnabled(cap.into()) != 0 };
+    debug_check();
+    enabled
+}
+
+/// See [`glBegin`](libdragon_sys::glBegin) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Begin(mode: PrimitiveMode) {
+    unsafe { libdragon_sys::glBegin(mode.into()) }
+    debug_check();
+}
+/// See [`glEnd`](libdragon_sys::glEnd) for details.
+///
+/// Rust: errors raised between [Begin] and [End] are reported here, as the vertex functions are
+/// not checked.
+#[track_caller]
+#[inline(always)]
+pub fn End() {
+    unsafe { libdragon_sys::glEnd() }
+    debug_check();
+}
+
+/// See [`glVertexPointer`](libdragon_sys::glVertexPointer) for details.
+#[track_caller]
+#[inline(always)]
+pub fn VertexPointer<T>(size: i32, type_: DataType, stride: usize, pointer: *const T) {
+    raw::VertexPointer(size, type_.into(), stride, pointer);
+    debug_check();
+}
+/// See [`glTexCoordPointer`](libdragon_sys::glTexCoordPointer) for details.
+#[track_caller]
+#[inline(always)]
+pub fn TexCoordPointer<T>(size: i32, type_: DataType, stride: usize, pointer: *const T) {
+    raw::TexCoordPointer(size, type_.into(), stride, pointer);
+    debug_check();
+}
+/// See [`glNormalPointer`](libdragon_sys::glNormalPointer) for details.
+#[track_caller]
+#[inline(always)]
+pub fn NormalPointer<T>(type_: DataType, stride: usize, pointer: *const T) {
+    raw::NormalPointer(type_.into(), stride, pointer);
+    debug_check();
+}
+/// See [`glColorPointer`](libdragon_sys::glColorPointer) for details.
+#[track_caller]
+#[inline(always)]
+pub fn ColorPointer<T>(size: i32, type_: DataType, stride: usize, pointer: *const T) {
+    raw::ColorPointer(size, type_.into(), stride, pointer);
+    debug_check();
+}
+/// See [`glMatrixIndexPointerARB`](libdragon_sys::glMatrixIndexPointerARB) for details.
+#[track_caller]
+#[inline(always)]
+pub fn MatrixIndexPointerARB<T>(size: i32, type_: DataType, stride: usize, pointer: *const T) {
+    raw::MatrixIndexPointerARB(size, type_.into(), stride, pointer);
+    debug_check();
+}
+
+/// See [`glEnableClientState`](libdragon_sys::glEnableClientState) for details.
+#[track_caller]
+#[inline(always)]
+pub fn EnableClientState(array: ClientArray) {
+    unsafe { libdragon_sys::glEnableClientState(array.into()) }
+    debug_check();
+}
+/// See [`glDisableClientState`](libdragon_sys::glDisableClientState) for details.
+#[track_caller]
+#[inline(always)]
+pub fn DisableClientState(array: ClientArray) {
+    unsafe { libdragon_sys::glDisableClientState(array.into()) }
+    debug_check();
+}
+
+/// See [`glDrawArrays`](libdragon_sys::glDrawArrays) for details.
+#[track_caller]
+#[inline(always)]
+pub fn DrawArrays(mode: PrimitiveMode, first: i32, count: usize) {
+    unsafe { libdragon_sys::glDrawArrays(mode.into(), first, count as u32) }
+    debug_check();
+}
+/// See [`glDrawElements`](libdragon_sys::glDrawElements) for details.
+#[track_caller]
+#[inline(always)]
+pub fn DrawElements<T>(mode: PrimitiveMode, count: usize, type_: IndexType, pointer: *const T) {
+    raw::DrawElements(mode.into(), count, type_.into(), pointer);
+    debug_check();
+}
+
+/// See [`glBindBufferARB`](libdragon_sys::glBindBufferARB) for details.
+#[track_caller]
+#[inline(always)]
+pub fn BindBufferARB(target: BufferTarget, buffer: u32) {
+    unsafe { libdragon_sys::glBindBufferARB(target.into(), buffer) }
+    debug_check();
+}
+/// See [`glBufferDataARB`](libdragon_sys::glBufferDataARB) for details.
+#[track_caller]
+#[inline(always)]
+pub fn BufferDataARB<T>(target: BufferTarget, size: usize, pointer: *const T, usage: BufferUsage) {
+    raw::BufferDataARB(target.into(), size, pointer, usage.into());
+    debug_check();
+}
+/// See [`glBufferSubDataARB`](libdragon_sys::glBufferSubDataARB) for details.
+#[track_caller]
+#[inline(always)]
+pub fn BufferSubDataARB<T>(target: BufferTarget, offset: isize, size: usize, data: *const T) {
+    raw::BufferSubDataARB(target.into(), offset, size, data);
+    debug_check();
+}
+/// See [`glMapBufferARB`](libdragon_sys::glMapBufferARB) for details.
+#[track_caller]
+#[inline(always)]
+pub fn MapBufferARB<T>(target: BufferTarget, access: BufferAccess) -> *mut T {
+    let ptr = raw::MapBufferARB(target.into(), access.into());
+    debug_check();
+    ptr
+}
+/// See [`glUnmapBufferARB`](libdragon_sys::glUnmapBufferARB) for details.
+#[track_caller]
+#[inline(always)]
+pub fn UnmapBufferARB(target: BufferTarget) -> bool {
+    let ok = raw::UnmapBufferARB(target.into());
+    debug_check();
+    ok
+}
+
+/// See [`glMatrixMode`](libdragon_sys::glMatrixMode) for details.
+#[track_caller]
+#[inline(always)]
+pub fn MatrixMode(mode: MatrixMode) {
+    unsafe { libdragon_sys::glMatrixMode(mode.into()) }
+    debug_check();
+}
+/// See [`glCopyMatrixN64`](libdragon_sys::glCopyMatrixN64) for details.
+#[track_caller]
+#[inline(always)]
+pub fn CopyMatrixN64(source: MatrixMode) {
+    unsafe { libdragon_sys::glCopyMatrixN64(source.into()) }
+    debug_check();
+}
+/// See [`glPushMatrix`](libdragon_sys::glPushMatrix) for details.
+#[track_caller]
+#[inline(always)]
+pub fn PushMatrix() {
+    unsafe { libdragon_sys::glPushMatrix() }
+    debug_check();
+}
+/// See [`glPopMatrix`](libdragon_sys::glPopMatrix) for details.
+#[track_caller]
+#[inline(always)]
+pub fn PopMatrix() {
+    unsafe { libdragon_sys::glPopMatrix() }
+    debug_check();
+}
+
+/// See [`glLightf`](libdragon_sys::glLightf) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Lightf(light: Light, pname: LightParameter, param: f32) {
+    unsafe { libdragon_sys::glLightf(light.into(), pname.into(), param) }
+    debug_check();
+}
+/// See [`glLighti`](libdragon_sys::glLighti) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Lighti(light: Light, pname: LightParameter, param: i32) {
+    unsafe { libdragon_sys::glLighti(light.into(), pname.into(), param) }
+    debug_check();
+}
+/// See [`glLightfv`](libdragon_sys::glLightfv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Lightfv(light: Light, pname: LightParameter, param: &[f32]) {
+    unsafe { libdragon_sys::glLightfv(light.into(), pname.into(), param.as_ptr()) }
+    debug_check();
+}
+/// See [`glLightiv`](libdragon_sys::glLightiv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Lightiv(light: Light, pname: LightParameter, param: &[i32]) {
+    unsafe { libdragon_sys::glLightiv(light.into(), pname.into(), param.as_ptr()) }
+    debug_check();
+}
+/// See [`glLightModelf`](libdragon_sys::glLightModelf) for details.
+#[track_caller]
+#[inline(always)]
+pub fn LightModelf(pname: LightModelParameter, param: f32) {
+    unsafe { libdragon_sys::glLightModelf(pname.into(), param) }
+    debug_check();
+}
+/// See [`glLightModeli`](libdragon_sys::glLightModeli) for details.
+#[track_caller]
+#[inline(always)]
+pub fn LightModeli(pname: LightModelParameter, param: i32) {
+    unsafe { libdragon_sys::glLightModeli(pname.into(), param) }
+    debug_check();
+}
+/// See [`glLightModelfv`](libdragon_sys::glLightModelfv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn LightModelfv(pname: LightModelParameter, param: &[f32]) {
+    unsafe { libdragon_sys::glLightModelfv(pname.into(), param.as_ptr()) }
+    debug_check();
+}
+/// See [`glLightModeliv`](libdragon_sys::glLightModeliv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn LightModeliv(pname: LightModelParameter, param: &[i32]) {
+    unsafe { libdragon_sys::glLightModeliv(pname.into(), param.as_ptr()) }
+    debug_check();
+}
+/// See [`glMaterialf`](libdragon_sys::glMaterialf) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Materialf(face: Face, pname: MaterialParameter, param: f32) {
+    unsafe { libdragon_sys::glMaterialf(face.into(), pname.into(), param) }
+    debug_check();
+}
+/// See [`glMateriali`](libdragon_sys::glMateriali) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Materiali(face: Face, pname: MaterialParameter, param: i32) {
+    unsafe { libdragon_sys::glMateriali(face.into(), pname.into(), param) }
+    debug_check();
+}
+/// See [`glMaterialfv`](libdragon_sys::glMaterialfv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Materialfv(face: Face, pname: MaterialParameter, param: &[f32]) {
+    unsafe { libdragon_sys::glMaterialfv(face.into(), pname.into(), param.as_ptr()) }
+    debug_check();
+}
+/// See [`glMaterialiv`](libdragon_sys::glMaterialiv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Materialiv(face: Face, pname: MaterialParameter, param: &[i32]) {
+    unsafe { libdragon_sys::glMaterialiv(face.into(), pname.into(), param.as_ptr()) }
+    debug_check();
+}
+/// See [`glColorMaterial`](libdragon_sys::glColorMaterial) for details.
+#[track_caller]
+#[inline(always)]
+pub fn ColorMaterial(face: Face, mode: MaterialParameter) {
+    unsafe { libdragon_sys::glColorMaterial(face.into(), mode.into()) }
+    debug_check();
+}
+/// See [`glShadeModel`](libdragon_sys::glShadeModel) for details.
+#[track_caller]
+#[inline(always)]
+pub fn ShadeModel(mode: Shading) {
+    unsafe { libdragon_sys::glShadeModel(mode.into()) }
+    debug_check();
+}
+
+/// See [`glCullFace`](libdragon_sys::glCullFace) for details.
+#[track_caller]
+#[inline(always)]
+pub fn CullFace(mode: Face) {
+    unsafe { libdragon_sys::glCullFace(mode.into()) }
+    debug_check();
+}
+/// See [`glFrontFace`](libdragon_sys::glFrontFace) for details.
+#[track_caller]
+#[inline(always)]
+pub fn FrontFace(dir: Winding) {
+    unsafe { libdragon_sys::glFrontFace(dir.into()) }
+    debug_check();
+}
+
+/// See [`glFogf`](libdragon_sys::glFogf) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Fogf(pname: FogParameter, param: f32) {
+    unsafe { libdragon_sys::glFogf(pname.into(), param) }
+    debug_check();
+}
+/// See [`glFogi`](libdragon_sys::glFogi) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Fogi(pname: FogParameter, param: i32) {
+    unsafe { libdragon_sys::glFogi(pname.into(), param) }
+    debug_check();
+}
+/// See [`glFogfv`](libdragon_sys::glFogfv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Fogfv(pname: FogParameter, param: &[f32]) {
+    unsafe { libdragon_sys::glFogfv(pname.into(), param.as_ptr()) }
+    debug_check();
+}
+/// See [`glFogiv`](libdragon_sys::glFogiv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Fogiv(pname: FogParameter, param: &[i32]) {
+    unsafe { libdragon_sys::glFogiv(pname.into(), param.as_ptr()) }
+    debug_check();
+}
+
+/// See [`glBindTexture`](libdragon_sys::glBindTexture) for details.
+#[track_caller]
+#[inline(always)]
+pub fn BindTexture(target: TextureTarget, texture: u32) {
+    unsafe { libdragon_sys::glBindTexture(target.into(), texture) }
+    debug_check();
+}
+/// See [`glTexParameteri`](libdragon_sys::glTexParameteri) for details.
+#[track_caller]
+#[inline(always)]
+pub fn TexParameteri(target: TextureTarget, pname: TextureParameter, param: i32) {
+    unsafe { libdragon_sys::glTexParameteri(target.into(), pname.into(), param) }
+    debug_check();
+}
+/// See [`glTexParameterf`](libdragon_sys::glTexParameterf) for details.
+#[track_caller]
+#[inline(always)]
+pub fn TexParameterf(target: TextureTarget, pname: TextureParameter, param: f32) {
+    unsafe { libdragon_sys::glTexParameterf(target.into(), pname.into(), param) }
+    debug_check();
+}
+/// See [`glTexParameteriv`](libdragon_sys::glTexParameteriv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn TexParameteriv(target: TextureTarget, pname: TextureParameter, param: &[i32]) {
+    unsafe { libdragon_sys::glTexParameteriv(target.into(), pname.into(), param.as_ptr()) }
+    debug_check();
+}
+/// See [`glTexParameterfv`](libdragon_sys::glTexParameterfv) for details.
+#[track_caller]
+#[inline(always)]
+pub fn TexParameterfv(target: TextureTarget, pname: TextureParameter, param: &[f32]) {
+    unsafe { libdragon_sys::glTexParameterfv(target.into(), pname.into(), param.as_ptr()) }
+    debug_check();
+}
+/// See [`glSurfaceTexImageN64`](libdragon_sys::glSurfaceTexImageN64) for details.
+#[track_caller]
+#[inline(always)]
+pub fn SurfaceTexImageN64(
+    target: TextureTarget,
+    level: i32,
+    surface: &surface::Surface,
+    texparms: rdpq::TexParms,
+) {
+    raw::SurfaceTexImageN64(target.into(), level, surface, texparms);
+    debug_check();
+}
+/// See [`glSpriteTextureN64`](libdragon_sys::glSpriteTextureN64) for details.
+#[track_caller]
+#[inline(always)]
+pub fn SpriteTextureN64(target: TextureTarget, sprite: &sprite::Sprite, texparms: rdpq::TexParms) {
+    raw::SpriteTextureN64(target.into(), sprite, texparms);
+    debug_check();
+}
+
+/// See [`glAlphaFunc`](libdragon_sys::glAlphaFunc) for details.
+#[track_caller]
+#[inline(always)]
+pub fn AlphaFunc(func: CompareFunc, ref_: f32) {
+    unsafe { libdragon_sys::glAlphaFunc(func.into(), ref_) }
+    debug_check();
+}
+/// See [`glDepthFunc`](libdragon_sys::glDepthFunc) for details.
+#[track_caller]
+#[inline(always)]
+pub fn DepthFunc(func: CompareFunc) {
+    unsafe { libdragon_sys::glDepthFunc(func.into()) }
+    debug_check();
+}
+/// See [`glDepthMask`](libdragon_sys::glDepthMask) for details.
+#[track_caller]
+#[inline(always)]
+pub fn DepthMask(flag: bool) {
+    unsafe { libdragon_sys::glDepthMask(flag as u8) }
+    debug_check();
+}
+/// See [`glBlendFunc`](libdragon_sys::glBlendFunc) for details.
+#[track_caller]
+#[inline(always)]
+pub fn BlendFunc(src: BlendFactor, dst: BlendFactor) {
+    unsafe { libdragon_sys::glBlendFunc(src.into(), dst.into()) }
+    debug_check();
+}
+/// See [`glClear`](libdragon_sys::glClear) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Clear(mask: ClearMask) {
+    unsafe { libdragon_sys::glClear(mask.bits()) }
+    debug_check();
+}
+/// See [`glHint`](libdragon_sys::glHint) for details.
+#[track_caller]
+#[inline(always)]
+pub fn Hint(target: HintTarget, mode: HintMode) {
+    unsafe { libdragon_sys::glHint(target.into(), mode.into()) }
+    debug_check();
+}
+
+/// Rust: shares its state with [rspq::BlockRecorder], so it panics if an rspq block (or another
+/// list) is already being recorded. Prefer [record_list], which also keeps alive the blocks
+/// called from within the list.
+///
+/// See [`glNewList`](libdragon_sys::glNewList) for details.
+#[track_caller]
+#[inline(always)]
+pub fn NewList(n: u32, mode: ListMode) {
+    rspq::begin_raw_gl_list(n);
+    unsafe { libdragon_sys::glNewList(n, mode.into()) }
+    debug_check();
+}
+/// Rust: panics if no list was opened with [NewList].
+///
+/// See [`glEndList`](libdragon_sys::glEndList) for details.
+#[track_caller]
+#[inline(always)]
+pub fn EndList() {
+    rspq::end_raw_gl_list();
+    unsafe { libdragon_sys::glEndList() }
+    debug_check();
+}
+
+/// A display list recorded with [record_list].
+///
+/// Rust: keeps alive the [rspq::Block]s called while recording. The GL list itself is not
+/// deleted when this object is dropped.
+pub struct RecordedList {
+    list:          u32,
+    command_words: usize,
+    _children:     Vec<rspq::Block>,
+}
+
+impl RecordedList {
+    /// The GL name of the list
+    #[inline]
+    pub fn list(&self) -> u32 { self.list }
+
+    /// Size of the commands recorded in the list, in 32-bit words (see
+    /// [rspq::Block::command_words])
+    #[inline]
+    pub fn command_words(&self) -> usize { self.command_words }
+
+    /// Execute the list. See [CallList].
+    #[inline]
+    pub fn call(&self) { CallList(self.list) }
+}
+
+/// Record a display list, the Rust way. Usage:
+///
+/// ```rust
+/// let list = gl::record_list(gl::GenLists(1), gl::ListMode::Compile, |rec| {
+///     gl::Begin(gl::PrimitiveMode::Triangles);
+///     ...
+///     gl::End();
+/// });
+/// list.call();
+/// ```
+///
+/// The recorder shares its state with [rspq::BlockRecorder], so it panics if an rspq block
+/// (or another list) is already being recorded.
+///
+/// See [`glNewList`](libdragon_sys::glNewList) and [`glEndList`](libdragon_sys::glEndList).
+pub fn record_list<F>(list: u32, mode: ListMode, f: F) -> RecordedList
+where
+    F: FnOnce(&mut rspq::BlockRecorder),
+{
+    let mut rec = rspq::BlockRecorder::begin_gl_list(list, mode);
+    f(&mut rec);
+    let (command_words, children) = rec.end_gl_list();
+    RecordedList {
+        list,
+        command_words,
+        _children: children,
+    }
+}
+
+// gl_integration.h
+pub fn init() {
+    unsafe {
+        libdragon_sys::gl_init();
+    }
+}
+
+pub fn close() {
+    unsafe {
+        libdragon_sys::gl_close();
+    }
+}
+
+pub fn context_begin() {
+    unsafe {
+        libdragon_sys::gl_context_begin();
+    }
+}
+
+pub fn context_end() {
+    unsafe {
+        libdragon_sys::gl_context_end();
+    }
+}
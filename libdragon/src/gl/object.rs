@@ -0,0 +1,701 @@
+use crate::*;
+
+use super::{
+    BufferAccess, BufferTarget, BufferUsage, ClientArray, DataType, IndexType, ListMode,
+    PrimitiveMode, RecordedList, TextureParameter, TextureTarget,
+};
+
+// Name of the object currently bound, queried with `binding` (e.g. `TEXTURE_BINDING_2D`), so
+// that guards can bind it again when dropped
+fn current_binding(binding: i32) -> u32 {
+    let mut name = [0];
+    super::GetIntegerv(binding, &mut name);
+    name[0] as u32
+}
+
+fn texture_binding(target: TextureTarget) -> i32 {
+    match target {
+        TextureTarget::Texture1D => super::TEXTURE_BINDING_1D,
+        TextureTarget::Texture2D => super::TEXTURE_BINDING_2D,
+    }
+}
+
+fn buffer_binding(target: BufferTarget) -> i32 {
+    match target {
+        BufferTarget::Array => super::ARRAY_BUFFER_BINDING_ARB,
+        BufferTarget::ElementArray => super::ELEMENT_ARRAY_BUFFER_BINDING_ARB,
+    }
+}
+
+/// Plain data that can be copied to and from GL memory as bytes.
+///
+/// # Safety
+///
+/// The type must be `repr(C)` (or `repr(transparent)`) without padding, and every bit pattern
+/// must be a valid value, as GL may hand back any bytes through [Buffer::map].
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => { $(unsafe impl Pod for $ty {})* };
+}
+
+impl_pod!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+impl_pod!(math::Vec2, math::Vec3, math::Vec4);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// A type that can be used as a vertex attribute: a scalar or a small array
+pub trait AttributeData: Pod {
+    /// Number of components
+    const SIZE: i32;
+    /// Type of the components
+    const TYPE: DataType;
+}
+
+macro_rules! impl_attribute_data {
+    ($($ty:ty => $data_type:ident),*) => {
+        $(
+            impl AttributeData for $ty {
+                const SIZE: i32 = 1;
+                const TYPE: DataType = DataType::$data_type;
+            }
+
+            impl<const N: usize> AttributeData for [$ty; N] {
+                const SIZE: i32 = N as i32;
+                const TYPE: DataType = DataType::$data_type;
+            }
+        )*
+    };
+}
+
+#[rustfmt::skip]
+impl_attribute_data!(
+    i8  => Byte,
+    u8  => UnsignedByte,
+    i16 => Short,
+    u16 => UnsignedShort,
+    i32 => Int,
+    u32 => UnsignedInt,
+    f32 => Float,
+    f64 => Double
+);
+
+impl AttributeData for math::Vec2 {
+    const SIZE: i32 = 2;
+    const TYPE: DataType = DataType::Float;
+}
+
+impl AttributeData for math::Vec3 {
+    const SIZE: i32 = 3;
+    const TYPE: DataType = DataType::Float;
+}
+
+impl AttributeData for math::Vec4 {
+    const SIZE: i32 = 4;
+    const TYPE: DataType = DataType::Float;
+}
+
+/// Location of a vertex attribute within a vertex
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    /// Number of components
+    pub size:   i32,
+    /// Type of the components
+    pub type_:  DataType,
+    /// Offset of the attribute from the start of the vertex, in bytes
+    pub offset: usize,
+}
+
+impl Attribute {
+    /// The attribute stored in `field` of the vertex at `base`. Used by [gl_vertex!].
+    #[doc(hidden)]
+    pub fn of_field<V, A: AttributeData>(base: *const V, field: *const A) -> Self {
+        Self {
+            size:   A::SIZE,
+            type_:  A::TYPE,
+            offset: field as usize - base as usize,
+        }
+    }
+}
+
+/// Vertex attributes read from a vertex array, as set by [VertexPointer](super::VertexPointer)
+/// and friends
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    /// Size of a vertex, in bytes
+    pub stride:       usize,
+    pub position:     Option<Attribute>,
+    pub tex_coord:    Option<Attribute>,
+    pub normal:       Option<Attribute>,
+    pub color:        Option<Attribute>,
+    pub matrix_index: Option<Attribute>,
+}
+
+impl VertexLayout {
+    /// A layout without attributes for vertices of `stride` bytes
+    pub fn new(stride: usize) -> Self {
+        Self {
+            stride,
+            position: None,
+            tex_coord: None,
+            normal: None,
+            color: None,
+            matrix_index: None,
+        }
+    }
+
+    // Point the client arrays at the bound array buffer, enabling the ones that are part of the
+    // layout and disabling the others.
+    fn apply(&self) {
+        let arrays = [
+            (ClientArray::Vertex, self.position),
+            (ClientArray::TextureCoord, self.tex_coord),
+            (ClientArray::Normal, self.normal),
+            (ClientArray::Color, self.color),
+            (ClientArray::MatrixIndex, self.matrix_index),
+        ];
+        for (array, attribute) in arrays {
+            let Some(a) = attribute else {
+                super::DisableClientState(array);
+                continue;
+            };
+            let pointer = a.offset as *const u8;
+            match array {
+                ClientArray::Vertex => super::VertexPointer(a.size, a.type_, self.stride, pointer),
+                ClientArray::TextureCoord => {
+                    super::TexCoordPointer(a.size, a.type_, self.stride, pointer)
+                }
+                ClientArray::Normal => super::NormalPointer(a.type_, self.stride, pointer),
+                ClientArray::Color => super::ColorPointer(a.size, a.type_, self.stride, pointer),
+                ClientArray::MatrixIndex => {
+                    super::MatrixIndexPointerARB(a.size, a.type_, self.stride, pointer)
+                }
+            }
+            super::EnableClientState(array);
+        }
+    }
+}
+
+/// A vertex type that can be stored in a [Buffer] and drawn through a [VertexArray].
+///
+/// Implement it with [gl_vertex!].
+///
+/// # Safety
+///
+/// The attributes returned by [Vertex::layout] must lie within the type.
+pub unsafe trait Vertex: Pod {
+    /// Attributes of the vertex
+    fn layout() -> VertexLayout;
+}
+
+/// Implement [gl::Vertex](crate::gl::Vertex) for a `repr(C)` struct, mapping its fields to
+/// vertex attributes (`position`, `tex_coord`, `normal`, `color`, `matrix_index`). The size and
+/// type of each attribute are derived from the field type (see
+/// [gl::AttributeData](crate::gl::AttributeData)).
+///
+/// ```rust
+/// #[repr(C)]
+/// #[derive(Copy, Clone)]
+/// struct MyVertex {
+///     pos: [f32; 3],
+///     uv:  [f32; 2],
+///     rgb: [u8; 4],
+/// }
+/// unsafe impl gl::Pod for MyVertex {}
+/// gl_vertex!(MyVertex { pos => position, uv => tex_coord, rgb => color });
+/// ```
+#[macro_export]
+macro_rules! gl_vertex {
+    ($ty:ty { $($field:ident => $attribute:ident),* $(,)? }) => {
+        unsafe impl $crate::gl::Vertex for $ty {
+            fn layout() -> $crate::gl::VertexLayout {
+                let v = ::core::mem::MaybeUninit::<$ty>::uninit();
+                let base = v.as_ptr();
+                let mut layout = $crate::gl::VertexLayout::new(::core::mem::size_of::<$ty>());
+                $(
+                    layout.$attribute = Some($crate::gl::Attribute::of_field(base, unsafe {
+                        ::core::ptr::addr_of!((*base).$field)
+                    }));
+                )*
+                layout
+            }
+        }
+    };
+}
+
+/// An index type for the index buffer of a [VertexArray]
+pub trait Index: Pod {
+    /// The GL type of the index
+    const TYPE: IndexType;
+}
+
+impl Index for u8 {
+    const TYPE: IndexType = IndexType::UnsignedByte;
+}
+
+impl Index for u16 {
+    const TYPE: IndexType = IndexType::UnsignedShort;
+}
+
+impl Index for u32 {
+    const TYPE: IndexType = IndexType::UnsignedInt;
+}
+
+/// A texture object, deleted when dropped.
+///
+/// ```rust
+/// let texture = gl::Texture::new();
+/// {
+///     let bound = texture.bind(gl::TextureTarget::Texture2D);
+///     bound.sprite(&sprite, rdpq::TexParms::default());
+///     bound.parameter_i(gl::TextureParameter::MagFilter, gl::LINEAR);
+/// }
+/// ```
+///
+/// See [`glGenTextures`](libdragon_sys::glGenTextures) for details.
+#[derive(Debug)]
+pub struct Texture {
+    name: u32,
+}
+
+impl Texture {
+    /// Create a texture object
+    pub fn new() -> Self {
+        let mut names = [0u32];
+        super::GenTextures(&mut names);
+        Self { name: names[0] }
+    }
+
+    /// The GL name of the texture
+    #[inline]
+    pub fn name(&self) -> u32 { self.name }
+
+    /// Bind the texture to `target` until the returned guard is dropped
+    #[track_caller]
+    pub fn bind(&self, target: TextureTarget) -> TextureBinding<'_> {
+        let previous = current_binding(texture_binding(target));
+        super::BindTexture(target, self.name);
+        TextureBinding {
+            target,
+            previous,
+            _texture: self,
+        }
+    }
+}
+
+impl Default for Texture {
+    fn default() -> Self { Self::new() }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) { super::DeleteTextures(&[self.name]); }
+}
+
+/// A bound [Texture]. The texture bound before is bound again when dropped.
+#[must_use = "the texture is unbound when the guard is dropped"]
+pub struct TextureBinding<'a> {
+    target:   TextureTarget,
+    previous: u32,
+    _texture: &'a Texture,
+}
+
+impl TextureBinding<'_> {
+    /// Set an integer parameter. See [TexParameteri](super::TexParameteri).
+    #[track_caller]
+    pub fn parameter_i(&self, pname: TextureParameter, param: i32) {
+        super::TexParameteri(self.target, pname, param);
+    }
+
+    /// Set a float parameter. See [TexParameterf](super::TexParameterf).
+    #[track_caller]
+    pub fn parameter_f(&self, pname: TextureParameter, param: f32) {
+        super::TexParameterf(self.target, pname, param);
+    }
+
+    /// Use a sprite as the texture image. See [SpriteTextureN64](super::SpriteTextureN64).
+    #[track_caller]
+    pub fn sprite(&self, sprite: &sprite::Sprite, texparms: rdpq::TexParms) {
+        super::SpriteTextureN64(self.target, sprite, texparms);
+    }
+
+    /// Use a surface as the image of mipmap `level`. See
+    /// [SurfaceTexImageN64](super::SurfaceTexImageN64).
+    #[track_caller]
+    pub fn surface(&self, level: i32, surface: &surface::Surface, texparms: rdpq::TexParms) {
+        super::SurfaceTexImageN64(self.target, level, surface, texparms);
+    }
+}
+
+impl Drop for TextureBinding<'_> {
+    fn drop(&mut self) { super::BindTexture(self.target, self.previous); }
+}
+
+/// A buffer object holding elements of type `T`, deleted when dropped.
+///
+/// The data is copied into memory owned by GL, so the source slice does not need to outlive the
+/// buffer.
+///
+/// ```rust
+/// let vertices = gl::Buffer::from_slice(
+///     gl::BufferTarget::Array,
+///     &VERTICES,
+///     gl::BufferUsage::StaticDraw,
+/// );
+/// ```
+///
+/// See [`glGenBuffersARB`](libdragon_sys::glGenBuffersARB) for details.
+#[derive(Debug)]
+pub struct Buffer<T: Pod> {
+    name:    u32,
+    target:  BufferTarget,
+    len:     usize,
+    phantom: core::marker::PhantomData<T>,
+}
+
+impl<T: Pod> Buffer<T> {
+    /// Create a buffer of `len` zeroed elements
+    pub fn new(target: BufferTarget, len: usize, usage: BufferUsage) -> Self {
+        let mut buffer = Self::empty(target);
+        buffer.len = len;
+        let zeros = vec![0u8; len * core::mem::size_of::<T>()];
+        {
+            let _bound = buffer.bind();
+            super::BufferDataARB(target, zeros.len(), zeros.as_ptr(), usage);
+        }
+        buffer
+    }
+
+    /// Create a buffer holding a copy of `data`
+    pub fn from_slice(target: BufferTarget, data: &[T], usage: BufferUsage) -> Self {
+        let mut buffer = Self::empty(target);
+        buffer.set_data(data, usage);
+        buffer
+    }
+
+    fn empty(target: BufferTarget) -> Self {
+        let mut names = [0u32];
+        super::GenBuffersARB(&mut names);
+        Self {
+            name: names[0],
+            target,
+            len: 0,
+            phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// The GL name of the buffer
+    #[inline]
+    pub fn name(&self) -> u32 { self.name }
+    /// The target the buffer is bound to
+    #[inline]
+    pub fn target(&self) -> BufferTarget { self.target }
+    /// Number of elements in the buffer
+    #[inline]
+    pub fn len(&self) -> usize { self.len }
+    /// Returns true if the buffer has no elements
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Replace the content of the buffer with a copy of `data`, resizing it if needed.
+    ///
+    /// See [`glBufferDataARB`](libdragon_sys::glBufferDataARB) for details.
+    pub fn set_data(&mut self, data: &[T], usage: BufferUsage) {
+        self.len = data.len();
+        let _bound = self.bind();
+        super::BufferDataARB(
+            self.target,
+            core::mem::size_of_val(data),
+            data.as_ptr(),
+            usage,
+        );
+    }
+
+    /// Overwrite the elements starting at `offset` with a copy of `data`.
+    ///
+    /// Rust: panics if `data` does not fit in the buffer.
+    ///
+    /// See [`glBufferSubDataARB`](libdragon_sys::glBufferSubDataARB) for details.
+    pub fn set_sub_data(&mut self, offset: usize, data: &[T]) {
+        assert!(
+            offset + data.len() <= self.len,
+            "data does not fit in the buffer"
+        );
+        let _bound = self.bind();
+        super::BufferSubDataARB(
+            self.target,
+            (offset * core::mem::size_of::<T>()) as isize,
+            core::mem::size_of_val(data),
+            data.as_ptr(),
+        );
+    }
+
+    /// Map the buffer into memory until the returned guard is dropped.
+    ///
+    /// See [`glMapBufferARB`](libdragon_sys::glMapBufferARB) for details.
+    pub fn map(&mut self, access: BufferAccess) -> BufferMap<'_, T> {
+        let ptr = {
+            let _bound = self.bind();
+            super::MapBufferARB::<T>(self.target, access)
+        };
+        assert!(!ptr.is_null() || self.len == 0, "failed to map the buffer");
+        BufferMap { buffer: self, ptr }
+    }
+
+    /// Bind the buffer to its target until the returned guard is dropped
+    #[track_caller]
+    pub fn bind(&self) -> BufferBinding<'_, T> {
+        let previous = current_binding(buffer_binding(self.target));
+        super::BindBufferARB(self.target, self.name);
+        BufferBinding {
+            buffer: self,
+            previous,
+        }
+    }
+}
+
+impl<T: Pod> Drop for Buffer<T> {
+    fn drop(&mut self) { super::DeletBuffersARB(&[self.name]); }
+}
+
+/// A bound [Buffer]. The buffer bound to the target before is bound again when dropped.
+#[must_use = "the buffer is unbound when the guard is dropped"]
+pub struct BufferBinding<'a, T: Pod> {
+    buffer:   &'a Buffer<T>,
+    previous: u32,
+}
+
+impl<T: Pod> Drop for BufferBinding<'_, T> {
+    fn drop(&mut self) { super::BindBufferARB(self.buffer.target, self.previous); }
+}
+
+/// A mapped [Buffer], accessible as a slice. The buffer is unmapped when dropped.
+pub struct BufferMap<'a, T: Pod> {
+    buffer: &'a mut Buffer<T>,
+    ptr:    *mut T,
+}
+
+impl<T: Pod> core::ops::Deref for BufferMap<'_, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        if self.ptr.is_null() {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(self.ptr, self.buffer.len) }
+    }
+}
+
+impl<T: Pod> core::ops::DerefMut for BufferMap<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        if self.ptr.is_null() {
+            return &mut [];
+        }
+        unsafe { core::slice::from_raw_parts_mut(self.ptr, self.buffer.len) }
+    }
+}
+
+impl<T: Pod> Drop for BufferMap<'_, T> {
+    fn drop(&mut self) {
+        let _bound = self.buffer.bind();
+        super::UnmapBufferARB(self.buffer.target);
+    }
+}
+
+/// A vertex array object drawing from a vertex buffer and an optional index buffer, deleted when
+/// dropped.
+///
+/// Rust: the array owns its buffers, so they can't be deleted while the array points at them.
+/// They can still be updated through [VertexArray::vertices_mut] and
+/// [VertexArray::indices_mut].
+///
+/// ```rust
+/// let vertices = gl::Buffer::from_slice(
+///     gl::BufferTarget::Array,
+///     &VERTICES,
+///     gl::BufferUsage::StaticDraw,
+/// );
+/// let indices = gl::Buffer::from_slice(
+///     gl::BufferTarget::ElementArray,
+///     &INDICES,
+///     gl::BufferUsage::StaticDraw,
+/// );
+/// let array = gl::VertexArray::with_indices(vertices, indices);
+///
+/// array.bind().draw_elements(gl::PrimitiveMode::Triangles);
+/// ```
+///
+/// See [`glGenVertexArrays`](libdragon_sys::glGenVertexArrays) for details.
+#[derive(Debug)]
+pub struct VertexArray<V: Vertex, I: Index = u16> {
+    name:     u32,
+    vertices: Buffer<V>,
+    indices:  Option<Buffer<I>>,
+}
+
+impl<V: Vertex, I: Index> VertexArray<V, I> {
+    /// Create a vertex array reading the vertices from `vertices`, with the attributes of
+    /// [Vertex::layout]. The client arrays not used by `V` are disabled.
+    pub fn new(vertices: Buffer<V>) -> Self {
+        let mut names = [0u32];
+        super::GenVertexArrays(&mut names);
+        let previous = current_binding(super::VERTEX_ARRAY_BINDING);
+        super::BindVertexArray(names[0]);
+        {
+            let _bound = vertices.bind();
+            V::layout().apply();
+        }
+        super::BindVertexArray(previous);
+        Self {
+            name: names[0],
+            vertices,
+            indices: None,
+        }
+    }
+
+    /// Create a vertex array like [VertexArray::new], drawing elements from `indices`
+    pub fn with_indices(vertices: Buffer<V>, indices: Buffer<I>) -> Self {
+        let mut array = Self::new(vertices);
+        array.indices = Some(indices);
+        array
+    }
+
+    /// The GL name of the vertex array
+    #[inline]
+    pub fn name(&self) -> u32 { self.name }
+    /// The vertex buffer
+    #[inline]
+    pub fn vertices(&self) -> &Buffer<V> { &self.vertices }
+    /// The vertex buffer, e.g. to update its content
+    #[inline]
+    pub fn vertices_mut(&mut self) -> &mut Buffer<V> { &mut self.vertices }
+    /// The index buffer, if any
+    #[inline]
+    pub fn indices(&self) -> Option<&Buffer<I>> { self.indices.as_ref() }
+    /// The index buffer if any, e.g. to update its content
+    #[inline]
+    pub fn indices_mut(&mut self) -> Option<&mut Buffer<I>> { self.indices.as_mut() }
+
+    /// Bind the vertex array until the returned guard is dropped
+    #[track_caller]
+    pub fn bind(&self) -> VertexArrayBinding<'_, V, I> {
+        let previous = current_binding(super::VERTEX_ARRAY_BINDING);
+        super::BindVertexArray(self.name);
+        VertexArrayBinding {
+            array: self,
+            previous,
+        }
+    }
+}
+
+impl<V: Vertex, I: Index> Drop for VertexArray<V, I> {
+    fn drop(&mut self) { super::DeleteVertexArrays(&[self.name]); }
+}
+
+/// A bound [VertexArray]. The vertex array bound before is bound again when dropped.
+#[must_use = "the vertex array is unbound when the guard is dropped"]
+pub struct VertexArrayBinding<'a, V: Vertex, I: Index> {
+    array:    &'a VertexArray<V, I>,
+    previous: u32,
+}
+
+impl<V: Vertex, I: Index> VertexArrayBinding<'_, V, I> {
+    /// Draw all the vertices. See [DrawArrays](super::DrawArrays).
+    #[track_caller]
+    pub fn draw_arrays(&self, mode: PrimitiveMode) {
+        super::DrawArrays(mode, 0, self.array.vertices.len());
+    }
+
+    /// Draw `count` vertices starting at `first`. See [DrawArrays](super::DrawArrays).
+    ///
+    /// Rust: panics if the range is outside of the vertex buffer.
+    #[track_caller]
+    pub fn draw_arrays_range(&self, mode: PrimitiveMode, first: usize, count: usize) {
+        assert!(
+            first + count <= self.array.vertices.len(),
+            "range outside of the vertex buffer"
+        );
+        super::DrawArrays(mode, first as i32, count);
+    }
+
+    /// Draw the vertices listed in the index buffer. See [DrawElements](super::DrawElements).
+    ///
+    /// Rust: panics if the array has no index buffer.
+    #[track_caller]
+    pub fn draw_elements(&self, mode: PrimitiveMode) {
+        let len = self.index_buffer().len();
+        self.draw_elements_range(mode, 0, len);
+    }
+
+    /// Draw the vertices listed in `count` elements of the index buffer, starting at `first`.
+    /// See [DrawElements](super::DrawElements).
+    ///
+    /// Rust: panics if the array has no index buffer, or if the range is outside of it.
+    #[track_caller]
+    pub fn draw_elements_range(&self, mode: PrimitiveMode, first: usize, count: usize) {
+        let indices = self.index_buffer();
+        assert!(
+            first + count <= indices.len(),
+            "range outside of the index buffer"
+        );
+        let _bound = indices.bind();
+        let offset = first * core::mem::size_of::<I>();
+        super::DrawElements(mode, count, I::TYPE, offset as *const u8);
+    }
+
+    #[track_caller]
+    fn index_buffer(&self) -> &Buffer<I> {
+        self.array
+            .indices
+            .as_ref()
+            .expect("the vertex array has no index buffer")
+    }
+}
+
+impl<V: Vertex, I: Index> Drop for VertexArrayBinding<'_, V, I> {
+    fn drop(&mut self) { super::BindVertexArray(self.previous); }
+}
+
+/// A display list, deleted when dropped.
+///
+/// ```rust
+/// let list = gl::DisplayList::record(gl::ListMode::Compile, |_| {
+///     gl::Begin(gl::PrimitiveMode::Triangles);
+///     ...
+///     gl::End();
+/// });
+/// list.call();
+/// ```
+///
+/// See [record_list](super::record_list) for details.
+pub struct DisplayList {
+    list: RecordedList,
+}
+
+impl DisplayList {
+    /// Allocate a list and record it
+    pub fn record<F>(mode: ListMode, f: F) -> Self
+    where
+        F: FnOnce(&mut rspq::BlockRecorder),
+    {
+        let name = super::GenLists(1);
+        Self {
+            list: super::record_list(name, mode, f),
+        }
+    }
+
+    /// The GL name of the list
+    #[inline]
+    pub fn name(&self) -> u32 { self.list.list() }
+
+    /// Size of the commands recorded in the list, in 32-bit words (see
+    /// [rspq::Block::command_words])
+    #[inline]
+    pub fn command_words(&self) -> usize { self.list.command_words() }
+
+    /// Execute the list. See [CallList](super::CallList).
+    #[inline]
+    pub fn call(&self) { self.list.call() }
+}
+
+impl Drop for DisplayList {
+    fn drop(&mut self) { super::DeleteLists(self.list.list(), 1); }
+}
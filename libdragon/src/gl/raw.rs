@@ -1,3 +1,8 @@
+//! The GL API as in C, with all enums as plain `i32` constants.
+//!
+//! The functions in [gl](super) that take typed enums shadow the ones with the same name here;
+//! the others are re-exported as is.
+
 #![allow(non_snake_case)]
 #![allow(non_upper_case_globals)]
 
@@ -340,6 +345,8 @@ pub const UNSIGNED_INT_10_10_10_2_EXT: i32 = libdragon_sys::GL_UNSIGNED_INT_10_1
 pub const TEXTURE_1D: i32 = libdragon_sys::GL_TEXTURE_1D as i32;
 pub const TEXTURE_2D: i32 = libdragon_sys::GL_TEXTURE_2D as i32;
 pub const PROXY_TEXTURE_1D: i32 = libdragon_sys::GL_PROXY_TEXTURE_1D as i32;
+pub const TEXTURE_BINDING_1D: i32 = libdragon_sys::GL_TEXTURE_BINDING_1D as i32;
+pub const TEXTURE_BINDING_2D: i32 = libdragon_sys::GL_TEXTURE_BINDING_2D as i32;
 pub const PROXY_TEXTURE_2D: i32 = libdragon_sys::GL_PROXY_TEXTURE_2D as i32;
 pub const TEXTURE_MAG_FILTER: i32 = libdragon_sys::GL_TEXTURE_MAG_FILTER as i32;
 pub const TEXTURE_MIN_FILTER: i32 = libdragon_sys::GL_TEXTURE_MIN_FILTER as i32;
@@ -954,7 +961,7 @@ pub fn MatrixIndexPointerARB<T>(size: i32, type_: i32, stride: usize, pointer: *
 pub fn EnableClientState(array: i32) { unsafe { libdragon_sys::glEnableClientState(array as u32) } }
 #[inline(always)]
 pub fn DisableClientState(array: i32) {
-    unsafe { libdragon_sys::glEnableClientState(array as u32) }
+    unsafe { libdragon_sys::glDisableClientState(array as u32) }
 }
 
 #[inline(always)]
@@ -1483,10 +1490,18 @@ pub fn Clear(buf: i32) { unsafe { libdragon_sys::glClear(buf as u32) } }
 #[inline(always)]
 pub fn ClearDepth(d: f64) { unsafe { libdragon_sys::glClearDepth(d) } }
 
+/// Rust: panics if a block or another list is already being recorded, like [gl::NewList].
 #[inline(always)]
-pub fn NewList(n: u32, mode: i32) { unsafe { libdragon_sys::glNewList(n, mode as u32) } }
+pub fn NewList(n: u32, mode: i32) {
+    rspq::begin_raw_gl_list(n);
+    unsafe { libdragon_sys::glNewList(n, mode as u32) }
+}
+/// Rust: panics if no list was opened with [NewList].
 #[inline(always)]
-pub fn EndList() { unsafe { libdragon_sys::glEndList() } }
+pub fn EndList() {
+    rspq::end_raw_gl_list();
+    unsafe { libdragon_sys::glEndList() }
+}
 #[inline(always)]
 pub fn CallList(n: u32) { unsafe { libdragon_sys::glCallList(n) } }
 #[inline(always)]
@@ -1553,28 +1568,3 @@ pub fn GetString(name: i32) -> String {
     let c_str = unsafe { CStr::from_ptr(libdragon_sys::glGetString(name as u32) as *const i8) };
     String::from_utf8_lossy(c_str.to_bytes()).to_string()
 }
-
-// gl_integration.h
-pub fn init() {
-    unsafe {
-        libdragon_sys::gl_init();
-    }
-}
-
-pub fn close() {
-    unsafe {
-        libdragon_sys::gl_close();
-    }
-}
-
-pub fn context_begin() {
-    unsafe {
-        libdragon_sys::gl_context_begin();
-    }
-}
-
-pub fn context_end() {
-    unsafe {
-        libdragon_sys::gl_context_end();
-    }
-}
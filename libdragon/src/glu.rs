@@ -1,5 +1,9 @@
 #![allow(non_snake_case)]
 
+use crate::*;
+
+use math::{Mat4, Vec3};
+
 // GLU
 #[inline(always)]
 #[allow(clippy::too_many_arguments)]
@@ -25,3 +29,68 @@ pub fn Perspective(fovy: f32, aspect: f32, z_near: f32, z_far: f32) {
         libdragon_sys::gluPerspective(fovy, aspect, z_near, z_far);
     }
 }
+
+/// Define a 2D orthographic projection, i.e. [gl::Ortho](crate::gl::Ortho) with near and far
+/// planes at -1 and 1.
+///
+/// Rust: implemented in Rust, libdragon doesn't provide `gluOrtho2D`.
+#[inline(always)]
+pub fn Ortho2D(left: f32, right: f32, bottom: f32, top: f32) {
+    gl::Ortho(
+        left as f64,
+        right as f64,
+        bottom as f64,
+        top as f64,
+        -1.0,
+        1.0,
+    );
+}
+
+/// Map object coordinates to window coordinates with the current modelview and projection
+/// matrices and viewport. See [math::project] for details.
+///
+/// Rust: implemented in Rust on top of [gl::GetFloatv](crate::gl::GetFloatv) and
+/// [gl::GetIntegerv](crate::gl::GetIntegerv), the matrices and viewport are not parameters.
+pub fn Project(obj: Vec3) -> Option<Vec3> {
+    let (model, proj, viewport) = current_transform();
+    math::project(obj, &model, &proj, viewport)
+}
+
+/// Map window coordinates to object coordinates with the current modelview and projection
+/// matrices and viewport, e.g. to turn a mouse position into a ray for picking. See
+/// [math::unproject] for details.
+///
+/// Rust: implemented in Rust on top of [gl::GetFloatv](crate::gl::GetFloatv) and
+/// [gl::GetIntegerv](crate::gl::GetIntegerv), the matrices and viewport are not parameters.
+pub fn UnProject(win: Vec3) -> Option<Vec3> {
+    let (model, proj, viewport) = current_transform();
+    math::unproject(win, &model, &proj, viewport)
+}
+
+/// Restrict drawing to a `width` x `height` region centered on `(x, y)` in window coordinates,
+/// by multiplying the current matrix with [Mat4::pick]. Call it on the projection matrix before
+/// setting up the projection. Does nothing if the region is empty.
+///
+/// Rust: implemented in Rust, the viewport is read with
+/// [gl::GetIntegerv](crate::gl::GetIntegerv) instead of being a parameter.
+pub fn PickMatrix(x: f32, y: f32, width: f32, height: f32) {
+    let mut viewport = [0i32; 4];
+    gl::GetIntegerv(gl::VIEWPORT, &mut viewport);
+    if let Some(m) = Mat4::pick(x, y, width, height, viewport) {
+        gl::MultMatrixf(m.as_slice());
+    }
+}
+
+fn current_transform() -> (Mat4, Mat4, [i32; 4]) {
+    let mut model = [0f32; 16];
+    let mut proj = [0f32; 16];
+    let mut viewport = [0i32; 4];
+    gl::GetFloatv(gl::MODELVIEW_MATRIX, &mut model);
+    gl::GetFloatv(gl::PROJECTION_MATRIX, &mut proj);
+    gl::GetIntegerv(gl::VIEWPORT, &mut viewport);
+    (
+        Mat4::from_cols_array(&model),
+        Mat4::from_cols_array(&proj),
+        viewport,
+    )
+}
@@ -26,7 +26,7 @@ impl Color {
     /// See [`color_to_packed16`](libdragon_sys::color_to_packed16) for details.
     #[inline]
     pub fn to_packed16(&self) -> u16 {
-        ((self.c.r as u16) << 11)
+        (((self.c.r as u16) >> 3) << 11)
             | (((self.c.g as u16) >> 3) << 6)
             | (((self.c.b as u16) >> 3) << 1)
             | ((self.c.a >> 7) as u16)
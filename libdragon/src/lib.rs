@@ -30,6 +30,8 @@ pub use paste::paste;
 mod allocator;
 mod panic;
 
+/// Sprite sheet animation
+pub mod anim;
 /// Asset subsystem
 pub mod asset;
 /// Audio - mixer, wav64, etc.
@@ -42,6 +44,8 @@ pub mod console;
 pub mod cop0;
 /// COP1 interface
 pub mod cop1;
+/// View frustum culling
+pub mod cull;
 /// Debugging Support
 pub mod debug;
 /// Dragon Filesystem
@@ -58,6 +62,12 @@ pub mod eeprom;
 pub mod eepromfs;
 /// Exception handling
 pub mod exception;
+/// Fixed-point number formats used by the RDP
+pub mod fixed;
+/// Parser for .font64 files (glyph metrics and coverage)
+pub mod font64;
+/// Main loop with fixed-timestep updates
+pub mod frame;
 /// OpenGL support
 pub mod gl;
 /// GLU helper functions
@@ -68,12 +78,20 @@ pub mod graphics;
 pub mod joybus;
 /// Input support
 pub mod joypad;
+/// Vectors, matrices and quaternions
+pub mod math;
 /// Controller Pak Filesystem Routines
 pub mod mempak;
 /// Model64 support
 pub mod model64;
 /// MPEG2 support
 pub mod mpeg2;
+/// Palettes (TLUT) for color-indexed textures
+pub mod palette;
+/// 2D particle effects
+pub mod particles;
+/// Typed pixel access and software format conversion
+pub mod pixel;
 /// Direct RDP commands
 pub mod rdp;
 /// RDPQ module
@@ -90,8 +108,12 @@ pub mod sprite;
 pub mod surface;
 /// Throttling engine
 pub mod throttle;
+/// Tile maps with scrolling and parallax layers
+pub mod tilemap;
 /// System timer support
 pub mod timer;
+/// Timeline tracing in Chrome trace-event format
+pub mod trace;
 /// USB support
 pub mod usb;
 /// YUV conversion library
@@ -111,8 +133,12 @@ pub enum LibDragonError {
     DfsError { error: dfs::DfsError },
     DlError { error: dlfcn::Error },
     EepfsError { error: eepromfs::EepfsError },
+    FontError { error: font64::FontError },
+    GlError { error: gl::GlError },
     ErrnoError { errno: u32 },
     MemPakError { code: i32 },
+    Mpeg2Error { error: mpeg2::Mpeg2Error },
+    TilemapError { error: tilemap::TilemapError },
     UsbError { code: i8 },
     Utf8Error { error: Option<core::str::Utf8Error> },
 }
@@ -121,6 +147,22 @@ impl From<core::str::Utf8Error> for LibDragonError {
     fn from(error: core::str::Utf8Error) -> Self { Self::Utf8Error { error: Some(error) } }
 }
 
+impl From<font64::FontError> for LibDragonError {
+    fn from(error: font64::FontError) -> Self { Self::FontError { error } }
+}
+
+impl From<gl::GlError> for LibDragonError {
+    fn from(error: gl::GlError) -> Self { Self::GlError { error } }
+}
+
+impl From<mpeg2::Mpeg2Error> for LibDragonError {
+    fn from(error: mpeg2::Mpeg2Error) -> Self { Self::Mpeg2Error { error } }
+}
+
+impl From<tilemap::TilemapError> for LibDragonError {
+    fn from(error: tilemap::TilemapError) -> Self { Self::TilemapError { error } }
+}
+
 impl core::fmt::Display for LibDragonError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         // TODO what is the right way to format errors?
@@ -568,6 +610,19 @@ pub fn data_cache_hit_invalidate<T>(v: *const T, size: usize, write_back: bool)
     }
 }
 
+/// Write back the data cache lines holding `data`, so that the RCP or a DMA can read it from
+/// RDRAM. Unlike [data_cache_hit_invalidate], the slice doesn't need to be aligned: the range is
+/// rounded out to whole cache lines.
+///
+/// See [`data_cache_hit_writeback`](libdragon_sys::data_cache_hit_writeback) for details.
+#[inline]
+pub fn data_cache_hit_writeback<T>(data: &[T]) {
+    let size = core::mem::size_of_val(data);
+    unsafe {
+        libdragon_sys::data_cache_hit_writeback(data.as_ptr() as *const _, size as _);
+    }
+}
+
 /// See
 /// [`data_cache_index_writeback_invalidate`](libdragon_sys::data_cache_index_writeback_invalidate)
 /// for details.
@@ -639,6 +694,30 @@ pub fn inst_cache_invalidate_all() {
 #[inline]
 pub fn get_memory_size() -> usize { unsafe { libdragon_sys::get_memory_size() as usize } }
 
+/// Heap memory statistics, see [get_heap_stats]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Size of the heap, in bytes
+    pub total: usize,
+    /// Memory currently allocated, in bytes
+    pub used:  usize,
+}
+
+/// Get the heap memory statistics.
+///
+/// See [`sys_get_heap_stats`](libdragon_sys::sys_get_heap_stats) for details.
+#[inline]
+pub fn get_heap_stats() -> HeapStats {
+    let mut stats = libdragon_sys::heap_stats_t { total: 0, used: 0 };
+    unsafe {
+        libdragon_sys::sys_get_heap_stats(&mut stats as *mut _);
+    }
+    HeapStats {
+        total: stats.total as usize,
+        used:  stats.used as usize,
+    }
+}
+
 /// Is expansion pak in use
 ///
 /// See [`is_memory_expanded`](libdragon_sys::is_memory_expanded)for details.
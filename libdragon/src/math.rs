@@ -0,0 +1,1388 @@
+//! Vectors, matrices and quaternions for CPU-side 3D math.
+//!
+//! Matrices are column-major like in OpenGL: [Mat4::as_slice] can be passed directly to
+//! [gl::LoadMatrixf](crate::gl::LoadMatrixf) or [gl::MultMatrixf](crate::gl::MultMatrixf), and
+//! the builders ([Mat4::perspective], [Mat4::look_at], ...) produce the same matrices as their
+//! GL and GLU counterparts, so that culling or physics code running on the CPU agrees with what
+//! is drawn.
+//!
+//! Rust: `core` has no floating point functions, so this module provides its own [sqrt], [sin],
+//! [cos], [tan], [atan2] and [acos]. They are written in plain Rust and behave the same on the
+//! N64 and on the host.
+//!
+//! This module only depends on `core`. It is shared with `libdragon-build`, so the builders and
+//! conversions can be checked against reference values on a development host.
+
+use core::ops::{
+    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
+};
+
+pub use core::f32::consts::{FRAC_PI_2, PI, TAU};
+
+const FRAC_PI_6: f32 = core::f32::consts::FRAC_PI_6;
+const SQRT_3: f32 = 1.732_050_8;
+const TAN_PI_12: f32 = 0.267_949_2;
+
+#[inline]
+fn abs(x: f32) -> f32 { f32::from_bits(x.to_bits() & 0x7FFF_FFFF) }
+
+/// Square root of `x` (NaN if `x` is negative)
+pub fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return if x == 0.0 { x } else { f32::NAN };
+    }
+    if x.is_nan() || x.is_infinite() {
+        return x;
+    }
+    // estimate from the halved exponent, then refine with Newton's method
+    let mut y = f32::from_bits((x.to_bits() >> 1) + 0x1FC0_0000);
+    for _ in 0..3 {
+        y = 0.5 * (y + x / y);
+    }
+    y
+}
+
+// Taylor polynomials, accurate to f32 precision on [-PI/4, PI/4]
+#[inline]
+fn sin_kernel(r: f32) -> f32 {
+    let r2 = r * r;
+    r + r * r2 * (-1.0 / 6.0 + r2 * (1.0 / 120.0 + r2 * (-1.0 / 5040.0 + r2 * (1.0 / 362880.0))))
+}
+
+#[inline]
+fn cos_kernel(r: f32) -> f32 {
+    let r2 = r * r;
+    1.0 + r2
+        * (-0.5
+            + r2 * (1.0 / 24.0
+                + r2 * (-1.0 / 720.0 + r2 * (1.0 / 40320.0 + r2 * (-1.0 / 3628800.0)))))
+}
+
+/// Sine and cosine of `x` (in radians).
+///
+/// Accurate to a few ULPs for angles up to about a million radians.
+pub fn sin_cos(x: f32) -> (f32, f32) {
+    if x.is_nan() || x.is_infinite() {
+        return (f32::NAN, f32::NAN);
+    }
+    // reduce to [-PI/4, PI/4] around the nearest multiple of PI/2; the subtraction is done in
+    // double precision to keep the low bits of the angle
+    let q = x * core::f32::consts::FRAC_2_PI;
+    let k = (if q >= 0.0 { q + 0.5 } else { q - 0.5 }) as i32;
+    let r = (x as f64 - k as f64 * core::f64::consts::FRAC_PI_2) as f32;
+    let (s, c) = (sin_kernel(r), cos_kernel(r));
+    #[rustfmt::skip]
+    let result = match k & 3 {
+        0 => ( s,  c),
+        1 => ( c, -s),
+        2 => (-s, -c),
+        _ => (-c,  s),
+    };
+    result
+}
+
+/// Sine of `x` (in radians)
+#[inline]
+pub fn sin(x: f32) -> f32 { sin_cos(x).0 }
+
+/// Cosine of `x` (in radians)
+#[inline]
+pub fn cos(x: f32) -> f32 { sin_cos(x).1 }
+
+/// Tangent of `x` (in radians)
+#[inline]
+pub fn tan(x: f32) -> f32 {
+    let (s, c) = sin_cos(x);
+    s / c
+}
+
+/// Arctangent of `x`, in radians in the range [-PI/2, PI/2]
+pub fn atan(x: f32) -> f32 {
+    let negative = x < 0.0;
+    let mut x = abs(x);
+    let inverted = x > 1.0;
+    if inverted {
+        x = 1.0 / x;
+    }
+    // atan(x) = PI/6 + atan((x*sqrt(3) - 1) / (sqrt(3) + x)), bringing x within tan(PI/12)
+    let mut base = 0.0;
+    if x > TAN_PI_12 {
+        base = FRAC_PI_6;
+        x = (x * SQRT_3 - 1.0) / (SQRT_3 + x);
+    }
+    let x2 = x * x;
+    let mut r = base
+        + x * (1.0
+            + x2 * (-1.0 / 3.0
+                + x2 * (1.0 / 5.0 + x2 * (-1.0 / 7.0 + x2 * (1.0 / 9.0 + x2 * (-1.0 / 11.0))))));
+    if inverted {
+        r = FRAC_PI_2 - r;
+    }
+    if negative {
+        -r
+    } else {
+        r
+    }
+}
+
+/// Angle of the point (`x`, `y`) from the X axis, in radians in the range [-PI, PI]
+pub fn atan2(y: f32, x: f32) -> f32 {
+    if x > 0.0 {
+        atan(y / x)
+    } else if x < 0.0 {
+        if y >= 0.0 {
+            atan(y / x) + PI
+        } else {
+            atan(y / x) - PI
+        }
+    } else if y > 0.0 {
+        FRAC_PI_2
+    } else if y < 0.0 {
+        -FRAC_PI_2
+    } else {
+        0.0
+    }
+}
+
+/// Arccosine of `x`, in radians in the range [0, PI] (NaN outside of [-1, 1])
+#[inline]
+pub fn acos(x: f32) -> f32 { atan2(sqrt((1.0 - x) * (1.0 + x)), x) }
+
+macro_rules! impl_vector {
+    ($name:ident, $n:literal, $($field:ident),+) => {
+        impl $name {
+            /// All components set to 0
+            pub const ZERO: Self = Self { $($field: 0.0),+ };
+            /// All components set to 1
+            pub const ONE: Self = Self { $($field: 1.0),+ };
+
+            /// Create a vector from its components
+            #[inline]
+            pub const fn new($($field: f32),+) -> Self { Self { $($field),+ } }
+
+            /// Create a vector with all components set to `v`
+            #[inline]
+            pub const fn splat(v: f32) -> Self { Self { $($field: v),+ } }
+
+            /// Dot product
+            #[inline]
+            pub fn dot(self, rhs: Self) -> f32 { 0.0 $(+ self.$field * rhs.$field)+ }
+
+            /// Squared length, cheaper than [Self::length] for comparisons
+            #[inline]
+            pub fn length_squared(self) -> f32 { self.dot(self) }
+
+            /// Length
+            #[inline]
+            pub fn length(self) -> f32 { sqrt(self.length_squared()) }
+
+            /// Distance to `rhs`
+            #[inline]
+            pub fn distance(self, rhs: Self) -> f32 { (self - rhs).length() }
+
+            /// Vector with the same direction and a length of 1, or [Self::ZERO] if the length
+            /// is 0
+            #[inline]
+            pub fn normalize(self) -> Self {
+                let len = self.length();
+                if len > 0.0 {
+                    self / len
+                } else {
+                    Self::ZERO
+                }
+            }
+
+            /// Linear interpolation between `self` (`t` = 0) and `rhs` (`t` = 1)
+            #[inline]
+            pub fn lerp(self, rhs: Self, t: f32) -> Self { self + (rhs - self) * t }
+
+            /// Component-wise minimum
+            #[inline]
+            pub fn min(self, rhs: Self) -> Self {
+                Self { $($field: self.$field.min(rhs.$field)),+ }
+            }
+
+            /// Component-wise maximum
+            #[inline]
+            pub fn max(self, rhs: Self) -> Self {
+                Self { $($field: self.$field.max(rhs.$field)),+ }
+            }
+
+            /// Component-wise absolute value
+            #[inline]
+            pub fn abs(self) -> Self { Self { $($field: abs(self.$field)),+ } }
+
+            /// The components as an array
+            #[inline]
+            pub fn to_array(self) -> [f32; $n] { [$(self.$field),+] }
+
+            /// The components as an array reference, e.g. for
+            /// [gl::Vertex3fv](crate::gl::Vertex3fv)
+            #[inline]
+            pub fn as_array(&self) -> &[f32; $n] {
+                // repr(C) struct of f32 fields, same layout as the array
+                unsafe { &*(self as *const Self as *const [f32; $n]) }
+            }
+
+            /// Mutable access to the components as an array
+            #[inline]
+            pub fn as_array_mut(&mut self) -> &mut [f32; $n] {
+                unsafe { &mut *(self as *mut Self as *mut [f32; $n]) }
+            }
+        }
+
+        impl From<[f32; $n]> for $name {
+            #[inline]
+            fn from(v: [f32; $n]) -> Self {
+                let [$($field),+] = v;
+                Self { $($field),+ }
+            }
+        }
+
+        impl From<$name> for [f32; $n] {
+            #[inline]
+            fn from(v: $name) -> Self { v.to_array() }
+        }
+
+        impl AsRef<[f32]> for $name {
+            #[inline]
+            fn as_ref(&self) -> &[f32] { self.as_array() }
+        }
+
+        impl Index<usize> for $name {
+            type Output = f32;
+            #[inline]
+            fn index(&self, i: usize) -> &f32 { &self.as_array()[i] }
+        }
+
+        impl IndexMut<usize> for $name {
+            #[inline]
+            fn index_mut(&mut self, i: usize) -> &mut f32 { &mut self.as_array_mut()[i] }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+            #[inline]
+            fn add(self, rhs: Self) -> Self { Self { $($field: self.$field + rhs.$field),+ } }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+            #[inline]
+            fn sub(self, rhs: Self) -> Self { Self { $($field: self.$field - rhs.$field),+ } }
+        }
+
+        /// Component-wise product
+        impl Mul for $name {
+            type Output = Self;
+            #[inline]
+            fn mul(self, rhs: Self) -> Self { Self { $($field: self.$field * rhs.$field),+ } }
+        }
+
+        impl Mul<f32> for $name {
+            type Output = Self;
+            #[inline]
+            fn mul(self, rhs: f32) -> Self { Self { $($field: self.$field * rhs),+ } }
+        }
+
+        impl Mul<$name> for f32 {
+            type Output = $name;
+            #[inline]
+            fn mul(self, rhs: $name) -> $name { rhs * self }
+        }
+
+        impl Div<f32> for $name {
+            type Output = Self;
+            #[inline]
+            fn div(self, rhs: f32) -> Self { Self { $($field: self.$field / rhs),+ } }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+            #[inline]
+            fn neg(self) -> Self { Self { $($field: -self.$field),+ } }
+        }
+
+        impl AddAssign for $name {
+            #[inline]
+            fn add_assign(&mut self, rhs: Self) { *self = *self + rhs; }
+        }
+
+        impl SubAssign for $name {
+            #[inline]
+            fn sub_assign(&mut self, rhs: Self) { *self = *self - rhs; }
+        }
+
+        impl MulAssign<f32> for $name {
+            #[inline]
+            fn mul_assign(&mut self, rhs: f32) { *self = *self * rhs; }
+        }
+
+        impl DivAssign<f32> for $name {
+            #[inline]
+            fn div_assign(&mut self, rhs: f32) { *self = *self / rhs; }
+        }
+    };
+}
+
+/// A 2D vector
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl_vector!(Vec2, 2, x, y);
+
+impl Vec2 {
+    /// Create a [Vec3] from this vector and `z`
+    #[inline]
+    pub fn extend(self, z: f32) -> Vec3 { Vec3::new(self.x, self.y, z) }
+
+    /// The vector rotated by 90 degrees counter-clockwise
+    #[inline]
+    pub fn perp(self) -> Self { Self::new(-self.y, self.x) }
+}
+
+/// A 3D vector
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl_vector!(Vec3, 3, x, y, z);
+
+impl Vec3 {
+    /// Unit vector along the X axis
+    pub const X: Self = Self::new(1.0, 0.0, 0.0);
+    /// Unit vector along the Y axis
+    pub const Y: Self = Self::new(0.0, 1.0, 0.0);
+    /// Unit vector along the Z axis
+    pub const Z: Self = Self::new(0.0, 0.0, 1.0);
+
+    /// Cross product
+    #[inline]
+    pub fn cross(self, rhs: Self) -> Self {
+        Self::new(
+            self.y * rhs.z - self.z * rhs.y,
+            self.z * rhs.x - self.x * rhs.z,
+            self.x * rhs.y - self.y * rhs.x,
+        )
+    }
+
+    /// Create a [Vec4] from this vector and `w`
+    #[inline]
+    pub fn extend(self, w: f32) -> Vec4 { Vec4::new(self.x, self.y, self.z, w) }
+
+    /// The X and Y components
+    #[inline]
+    pub fn truncate(self) -> Vec2 { Vec2::new(self.x, self.y) }
+}
+
+/// A 4D vector, or a 3D point in homogeneous coordinates
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl_vector!(Vec4, 4, x, y, z, w);
+
+impl Vec4 {
+    /// The X, Y and Z components
+    #[inline]
+    pub fn truncate(self) -> Vec3 { Vec3::new(self.x, self.y, self.z) }
+
+    /// The X, Y and Z components divided by W (perspective divide)
+    #[inline]
+    pub fn project(self) -> Vec3 { self.truncate() / self.w }
+}
+
+/// A 4x4 matrix, stored column-major like OpenGL matrices
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat4 {
+    /// The columns of the matrix
+    pub cols: [Vec4; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self { Self::IDENTITY }
+}
+
+impl Mat4 {
+    /// The identity matrix
+    pub const IDENTITY: Self = Self::from_cols(
+        Vec4::new(1.0, 0.0, 0.0, 0.0),
+        Vec4::new(0.0, 1.0, 0.0, 0.0),
+        Vec4::new(0.0, 0.0, 1.0, 0.0),
+        Vec4::new(0.0, 0.0, 0.0, 1.0),
+    );
+    /// All elements set to 0
+    pub const ZERO: Self = Self::from_cols(Vec4::ZERO, Vec4::ZERO, Vec4::ZERO, Vec4::ZERO);
+
+    /// Create a matrix from its columns
+    #[inline]
+    pub const fn from_cols(x: Vec4, y: Vec4, z: Vec4, w: Vec4) -> Self {
+        Self { cols: [x, y, z, w] }
+    }
+
+    /// Create a matrix from 16 elements in column-major order
+    #[inline]
+    pub fn from_cols_array(m: &[f32; 16]) -> Self {
+        let col = |i: usize| Vec4::new(m[i], m[i + 1], m[i + 2], m[i + 3]);
+        Self::from_cols(col(0), col(4), col(8), col(12))
+    }
+
+    /// Create a matrix from the first 16 elements of a slice in column-major order, as returned
+    /// by [gl::GetFloatv](crate::gl::GetFloatv) or
+    /// [Model64Node::get_node_world_mtx](crate::model64::Model64Node::get_node_world_mtx)
+    ///
+    /// Rust: panics if the slice holds less than 16 elements.
+    #[inline]
+    pub fn from_slice(m: &[f32]) -> Self {
+        assert!(m.len() >= 16, "a matrix needs 16 elements");
+        Self::from_cols_array(m[..16].try_into().unwrap())
+    }
+
+    /// The elements in column-major order
+    #[inline]
+    pub fn to_cols_array(self) -> [f32; 16] { *self.as_array() }
+
+    /// The elements in column-major order, as a reference
+    #[inline]
+    pub fn as_array(&self) -> &[f32; 16] {
+        // repr(C) array of four repr(C) Vec4, same layout as 16 floats
+        unsafe { &*(self as *const Self as *const [f32; 16]) }
+    }
+
+    /// The elements in column-major order, for [gl::LoadMatrixf](crate::gl::LoadMatrixf) and
+    /// [gl::MultMatrixf](crate::gl::MultMatrixf)
+    #[inline]
+    pub fn as_slice(&self) -> &[f32] { self.as_array() }
+
+    /// Row `i` of the matrix
+    #[inline]
+    pub fn row(&self, i: usize) -> Vec4 {
+        Vec4::new(
+            self.cols[0][i],
+            self.cols[1][i],
+            self.cols[2][i],
+            self.cols[3][i],
+        )
+    }
+
+    /// Transposed matrix
+    #[inline]
+    pub fn transpose(&self) -> Self {
+        Self::from_cols(self.row(0), self.row(1), self.row(2), self.row(3))
+    }
+
+    fn cofactors(&self) -> [f32; 16] {
+        let m = self.as_array();
+        let mut inv = [0.0; 16];
+        inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+            + m[9] * m[7] * m[14]
+            + m[13] * m[6] * m[11]
+            - m[13] * m[7] * m[10];
+        inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+            - m[8] * m[7] * m[14]
+            - m[12] * m[6] * m[11]
+            + m[12] * m[7] * m[10];
+        inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+            + m[8] * m[7] * m[13]
+            + m[12] * m[5] * m[11]
+            - m[12] * m[7] * m[9];
+        inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+            - m[8] * m[6] * m[13]
+            - m[12] * m[5] * m[10]
+            + m[12] * m[6] * m[9];
+        inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+            - m[9] * m[3] * m[14]
+            - m[13] * m[2] * m[11]
+            + m[13] * m[3] * m[10];
+        inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+            + m[8] * m[3] * m[14]
+            + m[12] * m[2] * m[11]
+            - m[12] * m[3] * m[10];
+        inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+            - m[8] * m[3] * m[13]
+            - m[12] * m[1] * m[11]
+            + m[12] * m[3] * m[9];
+        inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+            + m[8] * m[2] * m[13]
+            + m[12] * m[1] * m[10]
+            - m[12] * m[2] * m[9];
+        inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+            + m[5] * m[3] * m[14]
+            + m[13] * m[2] * m[7]
+            - m[13] * m[3] * m[6];
+        inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+            - m[4] * m[3] * m[14]
+            - m[12] * m[2] * m[7]
+            + m[12] * m[3] * m[6];
+        inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+            + m[4] * m[3] * m[13]
+            + m[12] * m[1] * m[7]
+            - m[12] * m[3] * m[5];
+        inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+            - m[4] * m[2] * m[13]
+            - m[12] * m[1] * m[6]
+            + m[12] * m[2] * m[5];
+        inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+            - m[5] * m[3] * m[10]
+            - m[9] * m[2] * m[7]
+            + m[9] * m[3] * m[6];
+        inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+            + m[4] * m[3] * m[10]
+            + m[8] * m[2] * m[7]
+            - m[8] * m[3] * m[6];
+        inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+            - m[4] * m[3] * m[9]
+            - m[8] * m[1] * m[7]
+            + m[8] * m[3] * m[5];
+        inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+            + m[4] * m[2] * m[9]
+            + m[8] * m[1] * m[6]
+            - m[8] * m[2] * m[5];
+        inv
+    }
+
+    /// Determinant
+    pub fn determinant(&self) -> f32 {
+        let m = self.as_array();
+        let c = self.cofactors();
+        m[0] * c[0] + m[1] * c[4] + m[2] * c[8] + m[3] * c[12]
+    }
+
+    /// Inverse matrix, or `None` if the matrix is not invertible
+    pub fn inverse(&self) -> Option<Self> {
+        let m = self.as_array();
+        let mut c = self.cofactors();
+        let det = m[0] * c[0] + m[1] * c[4] + m[2] * c[8] + m[3] * c[12];
+        if det == 0.0 || det.is_nan() {
+            return None;
+        }
+        for v in c.iter_mut() {
+            *v /= det;
+        }
+        Some(Self::from_cols_array(&c))
+    }
+
+    /// Transform a point (W = 1), applying the perspective divide
+    #[inline]
+    pub fn transform_point3(&self, p: Vec3) -> Vec3 {
+        let v = *self * p.extend(1.0);
+        if v.w == 1.0 {
+            v.truncate()
+        } else {
+            v.project()
+        }
+    }
+
+    /// Transform a direction (W = 0), ignoring the translation
+    #[inline]
+    pub fn transform_vector3(&self, v: Vec3) -> Vec3 { (*self * v.extend(0.0)).truncate() }
+
+    /// Translation matrix, see [gl::Translatef](crate::gl::Translatef)
+    #[inline]
+    pub fn translation(t: Vec3) -> Self {
+        let mut m = Self::IDENTITY;
+        m.cols[3] = t.extend(1.0);
+        m
+    }
+
+    /// Scale matrix, see [gl::Scalef](crate::gl::Scalef)
+    #[inline]
+    pub fn scale(s: Vec3) -> Self {
+        Self::from_cols(
+            Vec4::new(s.x, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, s.y, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, s.z, 0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+        )
+    }
+
+    /// Rotation of `angle` radians around the X axis
+    pub fn rotation_x(angle: f32) -> Self {
+        let (s, c) = sin_cos(angle);
+        let mut m = Self::IDENTITY;
+        m.cols[1] = Vec4::new(0.0, c, s, 0.0);
+        m.cols[2] = Vec4::new(0.0, -s, c, 0.0);
+        m
+    }
+
+    /// Rotation of `angle` radians around the Y axis
+    pub fn rotation_y(angle: f32) -> Self {
+        let (s, c) = sin_cos(angle);
+        let mut m = Self::IDENTITY;
+        m.cols[0] = Vec4::new(c, 0.0, -s, 0.0);
+        m.cols[2] = Vec4::new(s, 0.0, c, 0.0);
+        m
+    }
+
+    /// Rotation of `angle` radians around the Z axis
+    pub fn rotation_z(angle: f32) -> Self {
+        let (s, c) = sin_cos(angle);
+        let mut m = Self::IDENTITY;
+        m.cols[0] = Vec4::new(c, s, 0.0, 0.0);
+        m.cols[1] = Vec4::new(-s, c, 0.0, 0.0);
+        m
+    }
+
+    /// Rotation of `angle` radians around `axis`, like [gl::Rotatef](crate::gl::Rotatef) (which
+    /// takes degrees)
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let Vec3 { x, y, z } = axis.normalize();
+        let (s, c) = sin_cos(angle);
+        let t = 1.0 - c;
+        Self::from_cols(
+            Vec4::new(x * x * t + c, y * x * t + z * s, x * z * t - y * s, 0.0),
+            Vec4::new(x * y * t - z * s, y * y * t + c, y * z * t + x * s, 0.0),
+            Vec4::new(x * z * t + y * s, y * z * t - x * s, z * z * t + c, 0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+        )
+    }
+
+    /// Rotation matrix of a unit quaternion
+    pub fn from_quat(q: Quat) -> Self {
+        let Quat { x, y, z, w } = q;
+        Self::from_cols(
+            Vec4::new(
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y + w * z),
+                2.0 * (x * z - w * y),
+                0.0,
+            ),
+            Vec4::new(
+                2.0 * (x * y - w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z + w * x),
+                0.0,
+            ),
+            Vec4::new(
+                2.0 * (x * z + w * y),
+                2.0 * (y * z - w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+        )
+    }
+
+    /// Matrix that scales, then rotates, then translates (the usual node transform)
+    pub fn from_scale_rotation_translation(scale: Vec3, rotation: Quat, translation: Vec3) -> Self {
+        let mut m = Self::from_quat(rotation);
+        m.cols[0] *= scale.x;
+        m.cols[1] *= scale.y;
+        m.cols[2] *= scale.z;
+        m.cols[3] = translation.extend(1.0);
+        m
+    }
+
+    /// Perspective projection matrix, same as [gl::Frustum](crate::gl::Frustum)
+    pub fn frustum(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        Self::from_cols(
+            Vec4::new(2.0 * near / (right - left), 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 * near / (top - bottom), 0.0, 0.0),
+            Vec4::new(
+                (right + left) / (right - left),
+                (top + bottom) / (top - bottom),
+                -(far + near) / (far - near),
+                -1.0,
+            ),
+            Vec4::new(0.0, 0.0, -2.0 * far * near / (far - near), 0.0),
+        )
+    }
+
+    /// Perspective projection matrix, same as [glu::Perspective](crate::glu::Perspective).
+    ///
+    /// Rust: `fovy` is in radians, while `gluPerspective` takes degrees.
+    pub fn perspective(fovy: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let f = 1.0 / tan(fovy / 2.0);
+        Self::from_cols(
+            Vec4::new(f / aspect, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, f, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, (far + near) / (near - far), -1.0),
+            Vec4::new(0.0, 0.0, 2.0 * far * near / (near - far), 0.0),
+        )
+    }
+
+    /// Orthographic projection matrix, same as [gl::Ortho](crate::gl::Ortho)
+    pub fn ortho(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        Self::from_cols(
+            Vec4::new(2.0 / (right - left), 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 / (top - bottom), 0.0, 0.0),
+            Vec4::new(0.0, 0.0, -2.0 / (far - near), 0.0),
+            Vec4::new(
+                -(right + left) / (right - left),
+                -(top + bottom) / (top - bottom),
+                -(far + near) / (far - near),
+                1.0,
+            ),
+        )
+    }
+
+    /// View matrix of a camera at `eye` looking at `center`, same as
+    /// [glu::LookAt](crate::glu::LookAt)
+    pub fn look_at(eye: Vec3, center: Vec3, up: Vec3) -> Self {
+        let f = (center - eye).normalize();
+        let s = f.cross(up).normalize();
+        let u = s.cross(f);
+        Self::from_cols(
+            Vec4::new(s.x, u.x, -f.x, 0.0),
+            Vec4::new(s.y, u.y, -f.y, 0.0),
+            Vec4::new(s.z, u.z, -f.z, 0.0),
+            Vec4::new(-s.dot(eye), -u.dot(eye), f.dot(eye), 1.0),
+        )
+    }
+
+    /// Picking matrix restricting drawing to a `width` x `height` region centered on `(x, y)` in
+    /// window coordinates, same as [glu::PickMatrix](crate::glu::PickMatrix). Multiply it before
+    /// the projection matrix. `viewport` is `[x, y, width, height]`, as returned by
+    /// [gl::GetIntegerv](crate::gl::GetIntegerv) for `gl::VIEWPORT`.
+    ///
+    /// Returns `None` if the region is empty.
+    pub fn pick(x: f32, y: f32, width: f32, height: f32, viewport: [i32; 4]) -> Option<Self> {
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        let [vx, vy, vw, vh] = viewport.map(|v| v as f32);
+        let translate = Vec3::new(
+            (vw - 2.0 * (x - vx)) / width,
+            (vh - 2.0 * (y - vy)) / height,
+            0.0,
+        );
+        Some(Self::translation(translate) * Self::scale(Vec3::new(vw / width, vh / height, 1.0)))
+    }
+}
+
+impl From<[f32; 16]> for Mat4 {
+    #[inline]
+    fn from(m: [f32; 16]) -> Self { Self::from_cols_array(&m) }
+}
+
+impl From<Mat4> for [f32; 16] {
+    #[inline]
+    fn from(m: Mat4) -> Self { m.to_cols_array() }
+}
+
+impl AsRef<[f32]> for Mat4 {
+    #[inline]
+    fn as_ref(&self) -> &[f32] { self.as_slice() }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self::from_cols(
+            self * rhs.cols[0],
+            self * rhs.cols[1],
+            self * rhs.cols[2],
+            self * rhs.cols[3],
+        )
+    }
+}
+
+impl MulAssign for Mat4 {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) { *self = *self * rhs; }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+    #[inline]
+    fn mul(self, v: Vec4) -> Vec4 {
+        self.cols[0] * v.x + self.cols[1] * v.y + self.cols[2] * v.z + self.cols[3] * v.w
+    }
+}
+
+/// Map object coordinates to window coordinates, same as [glu::Project](crate::glu::Project).
+/// `viewport` is `[x, y, width, height]`, as returned by [gl::GetIntegerv](crate::gl::GetIntegerv)
+/// for `gl::VIEWPORT`. The window Z is the depth between 0 (near plane) and 1 (far plane).
+///
+/// Returns `None` if the point projects to infinity (W = 0 in clip space).
+pub fn project(obj: Vec3, model: &Mat4, proj: &Mat4, viewport: [i32; 4]) -> Option<Vec3> {
+    let clip = *proj * (*model * obj.extend(1.0));
+    if clip.w == 0.0 {
+        return None;
+    }
+    let ndc = clip.project();
+    let [vx, vy, vw, vh] = viewport.map(|v| v as f32);
+    Some(Vec3::new(
+        vx + vw * (ndc.x + 1.0) / 2.0,
+        vy + vh * (ndc.y + 1.0) / 2.0,
+        (ndc.z + 1.0) / 2.0,
+    ))
+}
+
+/// Map window coordinates back to object coordinates, the inverse of [project]. Same as
+/// [glu::UnProject](crate::glu::UnProject).
+///
+/// Rust: window coordinates have their origin at the bottom left of the viewport, so screen
+/// positions (e.g. from the mouse) need their Y flipped first.
+///
+/// Returns `None` if `proj * model` is not invertible or the point is at infinity.
+pub fn unproject(win: Vec3, model: &Mat4, proj: &Mat4, viewport: [i32; 4]) -> Option<Vec3> {
+    let inverse = (*proj * *model).inverse()?;
+    let [vx, vy, vw, vh] = viewport.map(|v| v as f32);
+    let ndc = Vec4::new(
+        (win.x - vx) / vw * 2.0 - 1.0,
+        (win.y - vy) / vh * 2.0 - 1.0,
+        win.z * 2.0 - 1.0,
+        1.0,
+    );
+    let obj = inverse * ndc;
+    if obj.w == 0.0 {
+        return None;
+    }
+    Some(obj.project())
+}
+
+/// An axis-aligned bounding box
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Create a box from its corners
+    #[inline]
+    pub const fn new(min: Vec3, max: Vec3) -> Self { Self { min, max } }
+
+    /// The smallest box containing all the points, or `None` if there are none
+    pub fn from_points<I: IntoIterator<Item = Vec3>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |b, p| b.extend(p)))
+    }
+
+    /// The smallest box containing this box and `p`
+    #[inline]
+    pub fn extend(self, p: Vec3) -> Self { Self::new(self.min.min(p), self.max.max(p)) }
+
+    /// The smallest box containing both boxes
+    #[inline]
+    pub fn union(self, rhs: Self) -> Self {
+        Self::new(self.min.min(rhs.min), self.max.max(rhs.max))
+    }
+
+    /// Center of the box
+    #[inline]
+    pub fn center(&self) -> Vec3 { (self.min + self.max) * 0.5 }
+
+    /// Half the size of the box along each axis
+    #[inline]
+    pub fn half_extents(&self) -> Vec3 { (self.max - self.min) * 0.5 }
+
+    /// Returns true if `p` is inside the box or on its boundary
+    #[inline]
+    pub fn contains(&self, p: Vec3) -> bool {
+        p.x >= self.min.x
+            && p.x <= self.max.x
+            && p.y >= self.min.y
+            && p.y <= self.max.y
+            && p.z >= self.min.z
+            && p.z <= self.max.z
+    }
+
+    /// The smallest axis-aligned box containing this box transformed by `m`, which must be an
+    /// affine transform (no projection)
+    pub fn transform(&self, m: &Mat4) -> Self {
+        let center = m.transform_point3(self.center());
+        let e = self.half_extents();
+        let col = |i: usize| m.cols[i].truncate().abs();
+        let extents = col(0) * e.x + col(1) * e.y + col(2) * e.z;
+        Self::new(center - extents, center + extents)
+    }
+}
+
+/// A rotation, stored as a unit quaternion
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Default for Quat {
+    fn default() -> Self { Self::IDENTITY }
+}
+
+impl Quat {
+    /// No rotation
+    pub const IDENTITY: Self = Self::new(0.0, 0.0, 0.0, 1.0);
+
+    /// Create a quaternion from its components (not normalized)
+    #[inline]
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self { Self { x, y, z, w } }
+
+    #[inline]
+    fn to_vec4(self) -> Vec4 { Vec4::new(self.x, self.y, self.z, self.w) }
+
+    #[inline]
+    fn from_vec4(v: Vec4) -> Self { Self::new(v.x, v.y, v.z, v.w) }
+
+    /// Rotation of `angle` radians around `axis`
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let (s, c) = sin_cos(angle / 2.0);
+        let v = axis.normalize() * s;
+        Self::new(v.x, v.y, v.z, c)
+    }
+
+    /// Rotation part of a matrix (which must not contain a scale or shear)
+    pub fn from_mat4(m: &Mat4) -> Self {
+        let e = |row: usize, col: usize| m.cols[col][row];
+        let trace = e(0, 0) + e(1, 1) + e(2, 2);
+        let q = if trace > 0.0 {
+            let s = sqrt(trace + 1.0) * 2.0;
+            Self::new(
+                (e(2, 1) - e(1, 2)) / s,
+                (e(0, 2) - e(2, 0)) / s,
+                (e(1, 0) - e(0, 1)) / s,
+                0.25 * s,
+            )
+        } else if e(0, 0) > e(1, 1) && e(0, 0) > e(2, 2) {
+            let s = sqrt(1.0 + e(0, 0) - e(1, 1) - e(2, 2)) * 2.0;
+            Self::new(
+                0.25 * s,
+                (e(0, 1) + e(1, 0)) / s,
+                (e(0, 2) + e(2, 0)) / s,
+                (e(2, 1) - e(1, 2)) / s,
+            )
+        } else if e(1, 1) > e(2, 2) {
+            let s = sqrt(1.0 + e(1, 1) - e(0, 0) - e(2, 2)) * 2.0;
+            Self::new(
+                (e(0, 1) + e(1, 0)) / s,
+                0.25 * s,
+                (e(1, 2) + e(2, 1)) / s,
+                (e(0, 2) - e(2, 0)) / s,
+            )
+        } else {
+            let s = sqrt(1.0 + e(2, 2) - e(0, 0) - e(1, 1)) * 2.0;
+            Self::new(
+                (e(0, 2) + e(2, 0)) / s,
+                (e(1, 2) + e(2, 1)) / s,
+                0.25 * s,
+                (e(1, 0) - e(0, 1)) / s,
+            )
+        };
+        q.normalize()
+    }
+
+    /// The vector part (X, Y, Z)
+    #[inline]
+    pub fn xyz(self) -> Vec3 { Vec3::new(self.x, self.y, self.z) }
+
+    /// Dot product
+    #[inline]
+    pub fn dot(self, rhs: Self) -> f32 { self.to_vec4().dot(rhs.to_vec4()) }
+
+    /// Length (1 for a unit quaternion)
+    #[inline]
+    pub fn length(self) -> f32 { self.to_vec4().length() }
+
+    /// Quaternion scaled to a length of 1
+    #[inline]
+    pub fn normalize(self) -> Self { Self::from_vec4(self.to_vec4().normalize()) }
+
+    /// Conjugate, which is the inverse rotation for a unit quaternion
+    #[inline]
+    pub fn conjugate(self) -> Self { Self::new(-self.x, -self.y, -self.z, self.w) }
+
+    /// Inverse rotation
+    #[inline]
+    pub fn inverse(self) -> Self {
+        Self::from_vec4(self.conjugate().to_vec4() / self.to_vec4().length_squared())
+    }
+
+    /// Normalized linear interpolation: cheaper than [Quat::slerp], with a non-constant speed
+    pub fn nlerp(self, end: Self, t: f32) -> Self {
+        let end = if self.dot(end) < 0.0 { -end } else { end };
+        Self::from_vec4(self.to_vec4().lerp(end.to_vec4(), t)).normalize()
+    }
+
+    /// Spherical linear interpolation between `self` (`t` = 0) and `end` (`t` = 1), along the
+    /// shortest path
+    pub fn slerp(self, end: Self, t: f32) -> Self {
+        let mut d = self.dot(end);
+        let end = if d < 0.0 {
+            d = -d;
+            -end
+        } else {
+            end
+        };
+        // nearly identical rotations: sin(theta) gets too small to divide by
+        if d > 0.9995 {
+            return self.nlerp(end, t);
+        }
+        let theta = acos(d);
+        let sin_theta = sin(theta);
+        let a = sin((1.0 - t) * theta) / sin_theta;
+        let b = sin(t * theta) / sin_theta;
+        Self::from_vec4(self.to_vec4() * a + end.to_vec4() * b)
+    }
+
+    /// Rotation matrix, see [Mat4::from_quat]
+    #[inline]
+    pub fn to_mat4(self) -> Mat4 { Mat4::from_quat(self) }
+}
+
+impl Mul for Quat {
+    type Output = Self;
+    /// Combined rotation: `rhs` first, then `self`
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        let (a, b) = (self.xyz(), rhs.xyz());
+        let v = b * self.w + a * rhs.w + a.cross(b);
+        Self::new(v.x, v.y, v.z, self.w * rhs.w - a.dot(b))
+    }
+}
+
+impl MulAssign for Quat {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) { *self = *self * rhs; }
+}
+
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+    /// Rotate a vector
+    #[inline]
+    fn mul(self, v: Vec3) -> Vec3 {
+        let q = self.xyz();
+        let t = q.cross(v) * 2.0;
+        v + t * self.w + q.cross(t)
+    }
+}
+
+impl Neg for Quat {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self { Self::new(-self.x, -self.y, -self.z, -self.w) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn assert_close(a: &[f32], b: &[f32], eps: f32) {
+        assert_eq!(a.len(), b.len());
+        for (i, (x, y)) in a.iter().zip(b).enumerate() {
+            assert!(abs(x - y) <= eps, "element {i}: {a:?} != {b:?}");
+        }
+    }
+
+    #[track_caller]
+    fn assert_quat(a: Quat, b: Quat) {
+        // q and -q are the same rotation
+        let b = if a.dot(b) < 0.0 { -b } else { b };
+        assert_close(a.to_vec4().as_array(), b.to_vec4().as_array(), 1e-6);
+    }
+
+    #[test]
+    fn functions() {
+        for i in -2000..2000 {
+            let x = i as f32 * 0.01;
+            assert!(abs(sin(x) - (x as f64).sin() as f32) < 1e-6, "sin({x})");
+            assert!(abs(cos(x) - (x as f64).cos() as f32) < 1e-6, "cos({x})");
+            assert!(abs(atan(x) - (x as f64).atan() as f32) < 1e-6, "atan({x})");
+            let y = abs(x);
+            assert!(
+                abs(sqrt(y) - (y as f64).sqrt() as f32) <= 1e-6 * sqrt(y),
+                "sqrt({y})"
+            );
+        }
+        assert!(abs(tan(PI / 3.0) - SQRT_3) < 1e-6);
+        assert!(abs(acos(-1.0) - PI) < 1e-6);
+        assert!(abs(acos(0.5) - PI / 3.0) < 1e-6);
+        assert_eq!(atan2(0.0, 0.0), 0.0);
+        assert!(abs(atan2(-1.0, -1.0) + 3.0 * PI / 4.0) < 1e-6);
+        assert!(abs(atan2(1.0, -1.0) - 3.0 * PI / 4.0) < 1e-6);
+        assert!(sqrt(-1.0).is_nan());
+        assert_eq!(sqrt(0.0), 0.0);
+        assert_eq!(sqrt(f32::INFINITY), f32::INFINITY);
+    }
+
+    #[test]
+    fn perspective() {
+        #[rustfmt::skip]
+        let expected = [
+            0.5, 0.0, 0.0,   0.0,
+            0.0, 1.0, 0.0,   0.0,
+            0.0, 0.0, -1.25, -1.0,
+            0.0, 0.0, -2.25, 0.0,
+        ];
+        let m = Mat4::perspective(FRAC_PI_2, 2.0, 1.0, 9.0);
+        assert_close(m.as_array(), &expected, 1e-6);
+        let frustum = Mat4::frustum(-2.0, 2.0, -1.0, 1.0, 1.0, 9.0);
+        assert_close(m.as_array(), frustum.as_array(), 1e-6);
+
+        // gluPerspective(60, 4/3, 0.1, 100)
+        #[rustfmt::skip]
+        let expected = [
+            1.299_038,   0.0,         0.0,           0.0,
+            0.0,         1.732_050_8, 0.0,           0.0,
+            0.0,         0.0,         -1.002_002,    -1.0,
+            0.0,         0.0,         -0.200_200_2,  0.0,
+        ];
+        let m = Mat4::perspective(PI / 3.0, 4.0 / 3.0, 0.1, 100.0);
+        assert_close(m.as_array(), &expected, 1e-6);
+        // near and far planes map to -1 and 1
+        assert!(abs(m.transform_point3(Vec3::new(0.0, 0.0, -0.1)).z + 1.0) < 1e-5);
+        assert!(abs(m.transform_point3(Vec3::new(0.0, 0.0, -100.0)).z - 1.0) < 1e-5);
+    }
+
+    #[test]
+    fn ortho() {
+        #[rustfmt::skip]
+        let expected = [
+            0.01, 0.0,  0.0,  0.0,
+            0.0,  0.02, 0.0,  0.0,
+            0.0,  0.0,  -1.0, 0.0,
+            -1.0, -1.0, 0.0,  1.0,
+        ];
+        let m = Mat4::ortho(0.0, 200.0, 0.0, 100.0, -1.0, 1.0);
+        assert_close(m.as_array(), &expected, 1e-6);
+    }
+
+    #[test]
+    fn look_at() {
+        let m = Mat4::look_at(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y);
+        assert_close(
+            m.as_array(),
+            Mat4::translation(Vec3::new(0.0, 0.0, -5.0)).as_array(),
+            1e-6,
+        );
+
+        // looking down -X from the +X axis
+        #[rustfmt::skip]
+        let expected = [
+            0.0,  0.0, 1.0,  0.0,
+            0.0,  1.0, 0.0,  0.0,
+            -1.0, 0.0, 0.0,  0.0,
+            0.0,  0.0, -5.0, 1.0,
+        ];
+        let m = Mat4::look_at(Vec3::new(5.0, 0.0, 0.0), Vec3::ZERO, Vec3::Y);
+        assert_close(m.as_array(), &expected, 1e-6);
+        let p = m.transform_point3(Vec3::new(0.0, 1.0, 1.0));
+        assert_close(p.as_array(), &[-1.0, 1.0, -5.0], 1e-6);
+    }
+
+    #[test]
+    fn inverse() {
+        let m = Mat4::from_cols_array(&[
+            2.0, 0.0, 1.0, 0.0, 1.0, 3.0, 0.0, 0.0, 0.0, 1.0, 4.0, 0.0, 5.0, -2.0, 1.0, 1.0,
+        ]);
+        assert_eq!(m.determinant(), 25.0);
+        let expected = [
+            12.0, 1.0, -3.0, 0.0, -4.0, 8.0, 1.0, 0.0, 1.0, -2.0, 6.0, 0.0, -69.0, 13.0, 11.0, 25.0,
+        ]
+        .map(|v| v / 25.0);
+        let inverse = m.inverse().unwrap();
+        assert_close(inverse.as_array(), &expected, 1e-6);
+        assert_close((m * inverse).as_array(), Mat4::IDENTITY.as_array(), 1e-6);
+
+        let scale = Mat4::scale(Vec3::new(2.0, 4.0, 8.0));
+        let m = Mat4::translation(Vec3::new(1.0, 2.0, 3.0)) * scale;
+        let expected = Mat4::scale(Vec3::new(0.5, 0.25, 0.125))
+            * Mat4::translation(Vec3::new(-1.0, -2.0, -3.0));
+        assert_eq!(m.inverse(), Some(expected));
+
+        assert_eq!(Mat4::ZERO.inverse(), None);
+        assert_eq!(Mat4::scale(Vec3::new(1.0, 0.0, 1.0)).inverse(), None);
+    }
+
+    #[test]
+    fn rotations() {
+        let s = core::f32::consts::FRAC_1_SQRT_2;
+        let q = Quat::from_axis_angle(Vec3::Z, FRAC_PI_2);
+        assert_close(q.to_vec4().as_array(), &[0.0, 0.0, s, s], 1e-6);
+        assert_close((q * Vec3::X).as_array(), Vec3::Y.as_array(), 1e-6);
+
+        #[rustfmt::skip]
+        let expected = [
+            0.0,  1.0, 0.0, 0.0,
+            -1.0, 0.0, 0.0, 0.0,
+            0.0,  0.0, 1.0, 0.0,
+            0.0,  0.0, 0.0, 1.0,
+        ];
+        assert_close(q.to_mat4().as_array(), &expected, 1e-6);
+        assert_close(Mat4::rotation_z(FRAC_PI_2).as_array(), &expected, 1e-6);
+        assert_close(
+            Mat4::from_axis_angle(Vec3::new(0.0, 0.0, 3.0), FRAC_PI_2).as_array(),
+            &expected,
+            1e-6,
+        );
+
+        let axis = Vec3::new(1.0, -2.0, 0.5);
+        for angle in [0.3, 1.0, 2.5, PI] {
+            let q = Quat::from_axis_angle(axis, angle);
+            let m = Mat4::from_axis_angle(axis, angle);
+            assert_close(q.to_mat4().as_array(), m.as_array(), 1e-6);
+            assert_quat(Quat::from_mat4(&m), q);
+            let v = Vec3::new(0.2, 0.7, -1.5);
+            assert_close((q * v).as_array(), m.transform_vector3(v).as_array(), 1e-5);
+        }
+    }
+
+    #[test]
+    fn from_mat4() {
+        // half turns exercise each branch of the conversion
+        assert_quat(
+            Quat::from_mat4(&Mat4::rotation_x(PI)),
+            Quat::new(1.0, 0.0, 0.0, 0.0),
+        );
+        assert_quat(
+            Quat::from_mat4(&Mat4::rotation_y(PI)),
+            Quat::new(0.0, 1.0, 0.0, 0.0),
+        );
+        assert_quat(
+            Quat::from_mat4(&Mat4::rotation_z(PI)),
+            Quat::new(0.0, 0.0, 1.0, 0.0),
+        );
+        assert_quat(Quat::from_mat4(&Mat4::IDENTITY), Quat::IDENTITY);
+
+        let q = Quat::new(0.1, 0.2, 0.3, 0.4).normalize();
+        let m = Mat4::from_scale_rotation_translation(Vec3::ONE, q, Vec3::new(4.0, 5.0, 6.0));
+        assert_quat(Quat::from_mat4(&m), q);
+        assert_close(
+            (q * q.inverse()).to_vec4().as_array(),
+            &[0.0, 0.0, 0.0, 1.0],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn slerp() {
+        let a = Quat::IDENTITY;
+        let b = Quat::from_axis_angle(Vec3::Z, FRAC_PI_2);
+        // sin and cos of 22.5 and 15 degrees
+        assert_quat(
+            a.slerp(b, 0.5),
+            Quat::new(0.0, 0.0, 0.382_683_43, 0.923_879_5),
+        );
+        assert_quat(
+            a.slerp(b, 1.0 / 3.0),
+            Quat::new(0.0, 0.0, 0.258_819_04, 0.965_925_8),
+        );
+        assert_quat(a.slerp(b, 0.0), a);
+        assert_quat(a.slerp(b, 1.0), b);
+        // -b is the same rotation: the shortest path is taken
+        assert_quat(a.slerp(-b, 0.5), a.slerp(b, 0.5));
+
+        let c = Quat::from_axis_angle(Vec3::Z, 0.001);
+        assert_quat(a.slerp(c, 0.5), Quat::from_axis_angle(Vec3::Z, 0.0005));
+        assert_quat(a.nlerp(b, 0.5), a.slerp(b, 0.5));
+    }
+
+    // gluLookAt(0, 0, 5, 0, 0, 0, 0, 1, 0) and gluPerspective(90, 4/3, 1, 100)
+    fn camera() -> (Mat4, Mat4) {
+        (
+            Mat4::look_at(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y),
+            Mat4::perspective(FRAC_PI_2, 4.0 / 3.0, 1.0, 100.0),
+        )
+    }
+
+    #[test]
+    fn project() {
+        let (model, proj) = camera();
+        let viewport = [0, 0, 320, 240];
+        let win = super::project(Vec3::new(1.0, 1.0, 0.0), &model, &proj, viewport).unwrap();
+        assert_close(win.as_array(), &[184.0, 144.0, 0.808_080_8], 1e-4);
+        let win = super::project(Vec3::new(-2.0, 0.5, -3.0), &model, &proj, viewport).unwrap();
+        assert_close(win.as_array(), &[130.0, 127.5, 0.883_838_4], 1e-4);
+        let win = super::project(Vec3::ZERO, &model, &proj, [10, 20, 320, 240]).unwrap();
+        assert_close(&win.as_array()[..2], &[170.0, 140.0], 1e-4);
+        let offset = [10, 20, 320, 240];
+        let win = super::project(Vec3::new(1.0, 1.0, 0.0), &model, &proj, offset).unwrap();
+        assert_close(win.as_array(), &[194.0, 164.0, 0.808_080_8], 1e-4);
+
+        // on the plane of the eye
+        assert_eq!(
+            super::project(Vec3::new(1.0, 0.0, 5.0), &model, &proj, viewport),
+            None
+        );
+    }
+
+    #[test]
+    fn unproject() {
+        let (model, proj) = camera();
+        let viewport = [0, 0, 320, 240];
+        let win = Vec3::new(184.0, 144.0, 0.808_080_8);
+        let obj = super::unproject(win, &model, &proj, viewport).unwrap();
+        assert_close(obj.as_array(), &[1.0, 1.0, 0.0], 1e-3);
+
+        // the center of the viewport at the near and far planes
+        let near = super::unproject(Vec3::new(160.0, 120.0, 0.0), &model, &proj, viewport);
+        assert_close(near.unwrap().as_array(), &[0.0, 0.0, 4.0], 1e-4);
+        let far = super::unproject(Vec3::new(160.0, 120.0, 1.0), &model, &proj, viewport);
+        assert_close(far.unwrap().as_array(), &[0.0, 0.0, -95.0], 1e-2);
+
+        for obj in [Vec3::new(0.3, -0.7, 1.5), Vec3::new(-4.0, 2.0, -20.0)] {
+            let win = super::project(obj, &model, &proj, [8, 8, 304, 224]).unwrap();
+            let back = super::unproject(win, &model, &proj, [8, 8, 304, 224]).unwrap();
+            assert_close(back.as_array(), obj.as_array(), 1e-3);
+        }
+        assert_eq!(
+            super::unproject(Vec3::ZERO, &Mat4::ZERO, &proj, viewport),
+            None
+        );
+    }
+
+    #[test]
+    fn pick() {
+        // gluPickMatrix(100, 80, 10, 20, {0, 0, 320, 240})
+        #[rustfmt::skip]
+        let expected = [
+            32.0, 0.0,  0.0, 0.0,
+            0.0,  12.0, 0.0, 0.0,
+            0.0,  0.0,  1.0, 0.0,
+            12.0, 4.0,  0.0, 1.0,
+        ];
+        let m = Mat4::pick(100.0, 80.0, 10.0, 20.0, [0, 0, 320, 240]).unwrap();
+        assert_close(m.as_array(), &expected, 1e-6);
+
+        // the picking region fills the viewport
+        let (model, proj) = camera();
+        let viewport = [0, 0, 320, 240];
+        let obj = super::unproject(Vec3::new(105.0, 90.0, 0.5), &model, &proj, viewport).unwrap();
+        let win = super::project(obj, &model, &(m * proj), viewport).unwrap();
+        assert_close(&win.as_array()[..2], &[320.0, 240.0], 1e-2);
+
+        let m = Mat4::pick(20.0, 30.0, 4.0, 4.0, [10, 20, 100, 50]).unwrap();
+        let center = m.transform_point3(Vec3::new(-0.8, -0.6, 0.0));
+        assert_close(center.as_array(), &[0.0, 0.0, 0.0], 1e-5);
+
+        assert_eq!(Mat4::pick(0.0, 0.0, 0.0, 4.0, viewport), None);
+        assert_eq!(Mat4::pick(0.0, 0.0, 4.0, -1.0, viewport), None);
+    }
+
+    #[test]
+    fn aabb() {
+        let points = [Vec3::new(1.0, -2.0, 3.0), Vec3::new(-1.0, 4.0, 0.0)];
+        let b = Aabb::from_points(points).unwrap();
+        assert_eq!(
+            b,
+            Aabb::new(Vec3::new(-1.0, -2.0, 0.0), Vec3::new(1.0, 4.0, 3.0))
+        );
+        assert!(b.contains(Vec3::new(0.0, 4.0, 1.5)));
+        assert!(!b.contains(Vec3::new(0.0, 4.1, 1.5)));
+        assert_eq!(Aabb::from_points([]), None);
+
+        let t = b.transform(&(Mat4::translation(Vec3::X) * Mat4::rotation_z(FRAC_PI_2)));
+        assert_close(t.min.as_array(), &[-3.0, -1.0, 0.0], 1e-6);
+        assert_close(t.max.as_array(), &[3.0, 1.0, 3.0], 1e-6);
+    }
+}
@@ -0,0 +1,240 @@
+use super::{
+    timeline::{root_motion, EventMarker, SlotStep, Timeline},
+    Model64, Model64AnimSlot, NodeTransform,
+};
+use crate::*;
+
+// A copy of the model sampling the animation of one slot
+struct SlotPose<'a> {
+    model: Model64<'a>,
+    // position of the root motion node at the start and at the end of the animation
+    start: math::Vec3,
+    end:   math::Vec3,
+    // position of the root motion node at the previous update
+    last:  math::Vec3,
+}
+
+/// Plays animations on a [Model64] with blend weights, crossfades, event markers and root motion
+/// extraction.
+///
+/// The animations in each [Model64AnimSlot] are sampled separately on a clone of the model (see
+/// [`model64_clone`](libdragon_sys::model64_clone)), then the node transforms are blended by
+/// weight into the model, which must not play animations itself. The playback state is a
+/// [Timeline].
+///
+/// ```rust
+/// let mut blender = model64::blend::AnimBlender::new(model);
+/// blender.timeline_mut().add_event("Walk", 0.4, "footstep");
+/// blender.play(Model64AnimSlot::Slot0, "Idle", true);
+/// ...
+/// blender.crossfade(Model64AnimSlot::Slot0, Model64AnimSlot::Slot1, "Walk", true, 0.25);
+/// ...
+/// let motion = blender.update(dt, |slot, event| play_sound(&event.name));
+/// blender.model().draw();
+/// ```
+pub struct AnimBlender<'a> {
+    model:       Model64<'a>,
+    poses:       [Option<SlotPose<'a>>; 4],
+    timeline:    Timeline,
+    root_motion: Option<usize>,
+}
+
+impl<'a> AnimBlender<'a> {
+    /// Create a blender drawing into `model`
+    pub fn new(model: Model64<'a>) -> Self {
+        Self {
+            model,
+            poses: [None, None, None, None],
+            timeline: Timeline::new(),
+            root_motion: None,
+        }
+    }
+
+    /// The blended model, e.g. to draw it
+    #[inline]
+    pub fn model(&self) -> &Model64<'a> { &self.model }
+
+    /// The blended model. Node transforms changed here are overwritten by [AnimBlender::update]
+    /// for the nodes that are animated.
+    #[inline]
+    pub fn model_mut(&mut self) -> &mut Model64<'a> { &mut self.model }
+
+    /// The playback state
+    #[inline]
+    pub fn timeline(&self) -> &Timeline { &self.timeline }
+
+    /// The playback state, e.g. to add event markers or change the speed of a slot
+    #[inline]
+    pub fn timeline_mut(&mut self) -> &mut Timeline { &mut self.timeline }
+
+    /// Extract the motion of node `node_index` from the animations: its position is pinned to
+    /// where it is at the start of the animations, and its movement is returned by
+    /// [AnimBlender::update] instead, to be applied to the object. `None` disables it.
+    pub fn set_root_motion_node(&mut self, node_index: Option<usize>) {
+        self.root_motion = node_index;
+        for slot in Model64AnimSlot::ALL {
+            self.sample_root_motion(slot);
+        }
+    }
+
+    /// Start playing `anim` in `slot` from the start, at full weight.
+    ///
+    /// See [`model64_anim_play`](libdragon_sys::model64_anim_play) for details.
+    pub fn play(&mut self, slot: Model64AnimSlot, anim: &str, looping: bool) {
+        let length = self.start_pose(slot, anim);
+        self.timeline.play(slot, anim, length, looping);
+        self.sample_root_motion(slot);
+    }
+
+    /// Stop the animation in `slot`
+    pub fn stop(&mut self, slot: Model64AnimSlot) {
+        self.timeline.stop(slot);
+        self.poses[slot.index()] = None;
+    }
+
+    /// Start playing `anim` in slot `to` and crossfade to it from slot `from` over `duration`
+    /// seconds. See [Timeline::crossfade].
+    pub fn crossfade(
+        &mut self,
+        from: Model64AnimSlot,
+        to: Model64AnimSlot,
+        anim: &str,
+        looping: bool,
+        duration: f32,
+    ) {
+        let length = self.start_pose(to, anim);
+        self.timeline
+            .crossfade(from, to, anim, length, looping, duration);
+        self.sample_root_motion(to);
+    }
+
+    fn start_pose(&mut self, slot: Model64AnimSlot, anim: &str) -> f32 {
+        let mut model = self.model.clone();
+        model.anim_play(anim, Model64AnimSlot::Slot0, true, 0.0);
+        let length = model.get_length(anim);
+        self.poses[slot.index()] = Some(SlotPose {
+            model,
+            start: math::Vec3::ZERO,
+            end: math::Vec3::ZERO,
+            last: math::Vec3::ZERO,
+        });
+        length
+    }
+
+    // sample the root motion node at the start, at the end and at the current time
+    fn sample_root_motion(&mut self, slot: Model64AnimSlot) {
+        let (Some(node), Some(pose)) = (self.root_motion, self.poses[slot.index()].as_mut()) else {
+            return;
+        };
+        let length = self.timeline.slot(slot).map_or(0.0, |s| s.length);
+        let time = self.timeline.slot(slot).map_or(0.0, |s| s.time);
+        let mut position_at = |time: f32| {
+            pose.model.set_time(Model64AnimSlot::Slot0, time);
+            pose.model.update(0.0);
+            pose.model.get_node(node).transform().position
+        };
+        pose.start = position_at(0.0);
+        pose.end = position_at(length);
+        pose.last = position_at(time);
+    }
+
+    /// Advance the animations by `deltatime` seconds and blend them into the model, calling
+    /// `on_event` for each event marker crossed (see [Timeline::advance]).
+    ///
+    /// Returns the movement of the root motion node during the step, or zero if disabled (see
+    /// [AnimBlender::set_root_motion_node]).
+    ///
+    /// See [`model64_update`](libdragon_sys::model64_update) for details.
+    pub fn update<F>(&mut self, deltatime: f32, on_event: F) -> math::Vec3
+    where
+        F: FnMut(Model64AnimSlot, &EventMarker),
+    {
+        let steps = self.timeline.advance(deltatime, on_event);
+
+        // slots stopped by the end of a crossfade
+        let mut weights = [0.0; 4];
+        for slot in Model64AnimSlot::ALL {
+            match self.timeline.slot(slot) {
+                Some(s) => weights[slot.index()] = s.weight,
+                None => self.poses[slot.index()] = None,
+            }
+        }
+
+        for (pose, step) in self.poses.iter_mut().zip(steps) {
+            if let (Some(pose), Some(step)) = (pose, step) {
+                pose.model.set_time(Model64AnimSlot::Slot0, step.time);
+                pose.model.update(0.0);
+            }
+        }
+
+        for node in 0..self.model.get_node_count() {
+            let transforms = self.poses.iter().zip(weights).filter_map(|(pose, weight)| {
+                let pose = pose.as_ref()?;
+                Some((pose.model.get_node(node).transform(), weight))
+            });
+            let Some(mut t) = blend_transforms(transforms) else {
+                continue;
+            };
+            if self.root_motion == Some(node) {
+                let starts = self.poses.iter().zip(weights);
+                let starts = starts.filter_map(|(pose, w)| Some((pose.as_ref()?.start, w)));
+                t.position = weighted_average(starts).unwrap_or(t.position);
+            }
+            let mut n = self.model.get_node(node);
+            n.set_node_pos(t.position.x, t.position.y, t.position.z);
+            n.set_node_rot_quat(t.rotation.x, t.rotation.y, t.rotation.z, t.rotation.w);
+            n.set_node_scale(t.scale.x, t.scale.y, t.scale.z);
+        }
+        self.model.update(0.0);
+
+        let Some(node) = self.root_motion else {
+            return math::Vec3::ZERO;
+        };
+        let mut motion = [None; 4];
+        for ((pose, step), motion) in self.poses.iter_mut().zip(steps).zip(motion.iter_mut()) {
+            let (Some(pose), Some(SlotStep { wraps, .. })) = (pose, step) else {
+                continue;
+            };
+            let current = pose.model.get_node(node).transform().position;
+            *motion = Some(root_motion(pose.last, current, pose.start, pose.end, wraps));
+            pose.last = current;
+        }
+        let motion = motion.into_iter().zip(weights);
+        let motion = motion.filter_map(|(m, w)| Some((m?, w)));
+        weighted_average(motion).unwrap_or(math::Vec3::ZERO)
+    }
+}
+
+/// Weighted average of node transforms. Weights don't need to add up to 1, and transforms
+/// with a weight of 0 or less are ignored.
+///
+/// Returns `None` if no transform has a positive weight.
+pub fn blend_transforms<I>(transforms: I) -> Option<NodeTransform>
+where
+    I: IntoIterator<Item = (NodeTransform, f32)>,
+{
+    let mut transforms = transforms.into_iter().filter(|(_, w)| *w > 0.0);
+    let (mut result, mut total) = transforms.next()?;
+    for (t, w) in transforms {
+        total += w;
+        let f = w / total;
+        result.position = result.position.lerp(t.position, f);
+        result.rotation = result.rotation.nlerp(t.rotation, f);
+        result.scale = result.scale.lerp(t.scale, f);
+    }
+    Some(result)
+}
+
+// Average of the vectors with a positive weight
+fn weighted_average<I>(items: I) -> Option<math::Vec3>
+where
+    I: IntoIterator<Item = (math::Vec3, f32)>,
+{
+    let (sum, total) = items
+        .into_iter()
+        .filter(|(_, w)| *w > 0.0)
+        .fold((math::Vec3::ZERO, 0.0), |(sum, total), (v, w)| {
+            (sum + v * w, total + w)
+        });
+    (total > 0.0).then(|| sum / total)
+}
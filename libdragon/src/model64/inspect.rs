@@ -0,0 +1,427 @@
+//! Read access to the node tree and the vertex data of a [Model64], which libdragon keeps in
+//! private structs, see [`libdragon_sys::internal`].
+//!
+//! Only built with the `internal` feature, since these structs may change between libdragon
+//! versions without notice.
+
+use super::{Mesh, Model64, Model64Node, Primitive};
+use crate::*;
+use libdragon_sys::internal;
+
+/// The private part of a model, see [`libdragon_sys::internal`]
+#[inline]
+fn model_data<'a>(model: *mut libdragon_sys::model64_t) -> &'a internal::model64_data_t {
+    unsafe { &*(*(model as *const internal::model64_s)).data }
+}
+
+impl Model64<'_> {
+    /// The root node of the model
+    #[inline]
+    pub fn root(&self) -> Model64Node<'_> { Model64Node::at(self.ptr, self.data().root_node) }
+
+    /// Iterate over all nodes, in index order
+    pub fn nodes(&self) -> impl ExactSizeIterator<Item = Model64Node<'_>> + '_ {
+        (0..self.data().num_nodes).map(move |i| Model64Node::at(self.ptr, i))
+    }
+
+    #[inline]
+    fn data(&self) -> &internal::model64_data_t { model_data(self.ptr) }
+}
+
+impl Mesh<'_> {
+    /// Bounding box of the vertex positions of all primitives, in the mesh coordinate space, or
+    /// `None` if the mesh has no vertices.
+    ///
+    /// Rust: computed from the vertex data on every call.
+    pub fn bounding_box(&self) -> Option<math::Aabb> {
+        self.primitives()
+            .filter_map(|p| p.bounding_box())
+            .reduce(math::Aabb::union)
+    }
+}
+
+/// Transform of a node relative to its parent, see [Model64Node::transform]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NodeTransform {
+    pub position: math::Vec3,
+    pub rotation: math::Quat,
+    pub scale:    math::Vec3,
+}
+
+impl NodeTransform {
+    /// The transform as a matrix (scale, then rotation, then translation)
+    #[inline]
+    pub fn to_matrix(&self) -> math::Mat4 {
+        math::Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    }
+}
+
+impl<'a> Model64Node<'a> {
+    #[inline]
+    fn at(model_ptr: *mut libdragon_sys::model64_t, index: u32) -> Self {
+        Model64Node {
+            ptr: unsafe { model_data(model_ptr).nodes.add(index as usize).cast() },
+            model_ptr,
+            phantom: core::marker::PhantomData,
+        }
+    }
+
+    #[inline]
+    fn node(&self) -> &'a internal::model64_node_s { unsafe { &*self.ptr.cast() } }
+
+    #[inline]
+    fn data(&self) -> &'a internal::model64_data_t { model_data(self.model_ptr) }
+
+    /// Index of the node in the model, as accepted by [Model64::get_node]
+    #[inline]
+    pub fn index(&self) -> usize {
+        unsafe {
+            self.ptr
+                .cast::<internal::model64_node_s>()
+                .offset_from(self.data().nodes) as usize
+        }
+    }
+
+    /// Name of the node, or `None` if it has no name or it is not valid UTF-8
+    pub fn name(&self) -> Option<&'a str> {
+        let name = self.node().name;
+        if name.is_null() {
+            return None;
+        }
+        unsafe { core::ffi::CStr::from_ptr(name) }.to_str().ok()
+    }
+
+    /// The parent node, or `None` for the root node
+    pub fn parent(&self) -> Option<Model64Node<'a>> {
+        let parent = self.node().parent;
+        if self.index() as u32 == self.data().root_node || parent >= self.data().num_nodes {
+            return None;
+        }
+        Some(Self::at(self.model_ptr, parent))
+    }
+
+    #[inline]
+    fn child_indices(&self) -> &'a [u32] {
+        let node = self.node();
+        if node.num_children == 0 {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(node.children, node.num_children as usize) }
+    }
+
+    /// Iterate over the direct children of the node
+    pub fn children(&self) -> impl ExactSizeIterator<Item = Model64Node<'a>> + 'a {
+        let model_ptr = self.model_ptr;
+        self.child_indices()
+            .iter()
+            .map(move |&i| Self::at(model_ptr, i))
+    }
+
+    /// Iterate over the node and all its descendants, depth first, parents before their children
+    pub fn descendants(&self) -> Descendants<'a> {
+        Descendants {
+            model_ptr: self.model_ptr,
+            root:      self.index() as u32,
+            next:      Some(self.index() as u32),
+            phantom:   core::marker::PhantomData,
+        }
+    }
+
+    /// The mesh attached to the node, if any
+    pub fn mesh(&self) -> Option<Mesh<'a>> {
+        let ptr = self.node().mesh;
+        if ptr.is_null() {
+            return None;
+        }
+        Some(Mesh {
+            ptr:     ptr.cast(),
+            phantom: core::marker::PhantomData,
+        })
+    }
+
+    /// Current transform of the node relative to its parent, as changed by
+    /// [set_node_pos](Self::set_node_pos) and friends or by animations
+    pub fn transform(&self) -> NodeTransform {
+        let model = self.model_ptr as *const internal::model64_s;
+        let t = unsafe { &(*(*model).transforms.add(self.index())).transform };
+        NodeTransform {
+            position: math::Vec3::new(t.pos[0], t.pos[1], t.pos[2]),
+            rotation: math::Quat::new(t.rot[0], t.rot[1], t.rot[2], t.rot[3]),
+            scale:    math::Vec3::new(t.scale[0], t.scale[1], t.scale[2]),
+        }
+    }
+
+    /// Transform of the node relative to its parent as stored in the file, before any change
+    pub fn rest_transform(&self) -> NodeTransform {
+        let t = &self.node().transform;
+        NodeTransform {
+            position: math::Vec3::new(t.pos[0], t.pos[1], t.pos[2]),
+            rotation: math::Quat::new(t.rot[0], t.rot[1], t.rot[2], t.rot[3]),
+            scale:    math::Vec3::new(t.scale[0], t.scale[1], t.scale[2]),
+        }
+    }
+
+    /// Bounding box of the mesh attached to the node, in the node coordinate space. Transform it
+    /// with [get_node_world_matrix](Self::get_node_world_matrix) to get it relative to the model.
+    ///
+    /// Rust: computed from the vertex data on every call.
+    pub fn bounding_box(&self) -> Option<math::Aabb> { self.mesh()?.bounding_box() }
+
+    /// Draw the node and its descendants. `f` is called before drawing each node, e.g. to change
+    /// the material, and the node is skipped if it returns false.
+    ///
+    /// Rust: uses [draw](Self::draw) for each node.
+    pub fn draw_subtree<F>(&self, mut f: F)
+    where
+        F: FnMut(&Model64Node<'a>) -> bool,
+    {
+        for node in self.descendants() {
+            if f(&node) {
+                node.draw();
+            }
+        }
+    }
+}
+
+/// Iterator over a node and its descendants, see [Model64Node::descendants]
+pub struct Descendants<'a> {
+    model_ptr: *mut libdragon_sys::model64_t,
+    root:      u32,
+    next:      Option<u32>,
+    phantom:   core::marker::PhantomData<&'a u8>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = Model64Node<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = Model64Node::at(self.model_ptr, self.next?);
+
+        // first child, or else the next sibling of the node or of its closest ancestor
+        self.next = node.child_indices().first().copied();
+        let mut current = node.index() as u32;
+        while self.next.is_none() && current != self.root {
+            let parent = Model64Node::at(self.model_ptr, current).parent();
+            let Some(parent) = parent else {
+                break;
+            };
+            let siblings = parent.child_indices();
+            let position = siblings.iter().position(|&i| i == current);
+            self.next = position.and_then(|p| siblings.get(p + 1).copied());
+            current = parent.index() as u32;
+        }
+
+        Some(node)
+    }
+}
+
+/// Indices of a [Primitive], see [Primitive::indices]
+#[derive(Debug, Copy, Clone)]
+pub enum PrimitiveIndices<'a> {
+    U8(&'a [u8]),
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+impl PrimitiveIndices<'_> {
+    /// Number of indices
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U8(i) => i.len(),
+            Self::U16(i) => i.len(),
+            Self::U32(i) => i.len(),
+        }
+    }
+
+    /// Returns true if there are no indices
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Index `i`, widened to `u32`
+    pub fn get(&self, i: usize) -> Option<u32> {
+        match self {
+            Self::U8(v) => v.get(i).map(|&x| x as u32),
+            Self::U16(v) => v.get(i).map(|&x| x as u32),
+            Self::U32(v) => v.get(i).copied(),
+        }
+    }
+}
+
+/// A vertex attribute of a [Primitive] (positions, colors, ...)
+#[derive(Copy, Clone)]
+pub struct VertexAttribute<'a> {
+    attribute: &'a internal::attribute_t,
+    count:     usize,
+    precision: u32,
+}
+
+impl<'a> VertexAttribute<'a> {
+    /// Number of components per vertex (1 to 4)
+    #[inline]
+    pub fn size(&self) -> usize { self.attribute.size as usize }
+
+    /// Type of the components, or `None` if it is not a [gl::DataType]
+    #[inline]
+    pub fn data_type(&self) -> Option<gl::DataType> { self.attribute.type_.try_into().ok() }
+
+    /// Distance between the attributes of consecutive vertices, in bytes
+    #[inline]
+    pub fn stride(&self) -> usize {
+        match self.attribute.stride {
+            0 => self.size() * self.component_size(),
+            stride => stride as usize,
+        }
+    }
+
+    /// Number of fractional bits of [gl::DataType::HalfFixed] components
+    #[inline]
+    pub fn precision(&self) -> u32 { self.precision }
+
+    /// Number of vertices
+    #[inline]
+    pub fn len(&self) -> usize { self.count }
+
+    /// Returns true if there are no vertices
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.count == 0 }
+
+    fn component_size(&self) -> usize {
+        match self.data_type() {
+            Some(gl::DataType::Byte | gl::DataType::UnsignedByte) => 1,
+            Some(gl::DataType::Short | gl::DataType::UnsignedShort | gl::DataType::HalfFixed) => 2,
+            Some(gl::DataType::Double) => 8,
+            _ => 4,
+        }
+    }
+
+    /// The raw bytes of the attribute of vertex `i`.
+    ///
+    /// Rust: panics if `i` is out of bounds.
+    pub fn bytes(&self, i: usize) -> &'a [u8] {
+        assert!(i < self.count, "vertex index out of bounds");
+        unsafe {
+            let ptr = (self.attribute.pointer as *const u8).add(i * self.stride());
+            core::slice::from_raw_parts(ptr, self.size() * self.component_size())
+        }
+    }
+
+    /// The attribute of vertex `i` converted to floats, with the missing components set to
+    /// `(0, 0, 0, 1)` like GL does. Integers are not normalized, and
+    /// [HalfFixed](gl::DataType::HalfFixed) components are divided by `2^precision`.
+    ///
+    /// Rust: panics if `i` is out of bounds.
+    pub fn get(&self, i: usize) -> [f32; 4] {
+        let bytes = self.bytes(i);
+        let mut out = [0.0, 0.0, 0.0, 1.0];
+        let scale = 1.0 / (1u32 << self.precision) as f32;
+        for (c, out) in out.iter_mut().enumerate().take(self.size()) {
+            let b = &bytes[c * self.component_size()..];
+            *out = match self.data_type() {
+                Some(gl::DataType::Byte) => b[0] as i8 as f32,
+                Some(gl::DataType::UnsignedByte) => b[0] as f32,
+                Some(gl::DataType::Short) => i16::from_ne_bytes([b[0], b[1]]) as f32,
+                Some(gl::DataType::UnsignedShort) => u16::from_ne_bytes([b[0], b[1]]) as f32,
+                Some(gl::DataType::HalfFixed) => i16::from_ne_bytes([b[0], b[1]]) as f32 * scale,
+                Some(gl::DataType::Int) => i32::from_ne_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                Some(gl::DataType::UnsignedInt) => {
+                    u32::from_ne_bytes([b[0], b[1], b[2], b[3]]) as f32
+                }
+                Some(gl::DataType::Float) => f32::from_ne_bytes([b[0], b[1], b[2], b[3]]),
+                Some(gl::DataType::Double) => f64::from_ne_bytes(b[..8].try_into().unwrap()) as f32,
+                None => 0.0,
+            };
+        }
+        out
+    }
+}
+
+impl<'a> Primitive<'a> {
+    #[inline]
+    fn primitive(&self) -> &'a internal::primitive_s { unsafe { &*self.ptr.cast() } }
+
+    /// How the vertices are assembled, or `None` if it is not a [gl::PrimitiveMode]
+    #[inline]
+    pub fn mode(&self) -> Option<gl::PrimitiveMode> { self.primitive().mode.try_into().ok() }
+
+    /// Number of vertices
+    #[inline]
+    pub fn vertex_count(&self) -> usize { self.primitive().num_vertices as usize }
+
+    /// Number of indices, 0 if the primitive is not indexed
+    #[inline]
+    pub fn index_count(&self) -> usize { self.primitive().num_indices as usize }
+
+    fn attribute(
+        &self,
+        attribute: &'a internal::attribute_t,
+        precision: u32,
+    ) -> Option<VertexAttribute<'a>> {
+        if attribute.size == 0 || attribute.pointer.is_null() {
+            return None;
+        }
+        Some(VertexAttribute {
+            attribute,
+            count: self.vertex_count(),
+            precision,
+        })
+    }
+
+    /// Vertex positions
+    pub fn positions(&self) -> Option<VertexAttribute<'a>> {
+        let p = self.primitive();
+        self.attribute(&p.position, p.vertex_precision)
+    }
+
+    /// Vertex colors
+    pub fn colors(&self) -> Option<VertexAttribute<'a>> {
+        self.attribute(&self.primitive().color, 0)
+    }
+
+    /// Vertex texture coordinates
+    pub fn tex_coords(&self) -> Option<VertexAttribute<'a>> {
+        let p = self.primitive();
+        self.attribute(&p.texcoord, p.texcoord_precision)
+    }
+
+    /// Vertex normals
+    pub fn normals(&self) -> Option<VertexAttribute<'a>> {
+        self.attribute(&self.primitive().normal, 0)
+    }
+
+    /// Vertex matrix indices, for skinned meshes
+    pub fn matrix_indices(&self) -> Option<VertexAttribute<'a>> {
+        self.attribute(&self.primitive().mtx_index, 0)
+    }
+
+    /// The indices, or `None` if the primitive is not indexed
+    pub fn indices(&self) -> Option<PrimitiveIndices<'a>> {
+        let p = self.primitive();
+        if p.num_indices == 0 || p.indices.is_null() {
+            return None;
+        }
+        let len = p.num_indices as usize;
+        unsafe {
+            Some(match p.index_type.try_into().ok()? {
+                gl::IndexType::UnsignedByte => {
+                    PrimitiveIndices::U8(core::slice::from_raw_parts(p.indices as *const u8, len))
+                }
+                gl::IndexType::UnsignedShort => {
+                    PrimitiveIndices::U16(core::slice::from_raw_parts(p.indices as *const u16, len))
+                }
+                gl::IndexType::UnsignedInt => {
+                    PrimitiveIndices::U32(core::slice::from_raw_parts(p.indices as *const u32, len))
+                }
+            })
+        }
+    }
+
+    /// Bounding box of the vertex positions, or `None` if the primitive has no vertices.
+    ///
+    /// Rust: computed from the vertex data on every call.
+    pub fn bounding_box(&self) -> Option<math::Aabb> {
+        let positions = self.positions()?;
+        math::Aabb::from_points((0..positions.len()).map(|i| {
+            let [x, y, z, _] = positions.get(i);
+            math::Vec3::new(x, y, z)
+        }))
+    }
+}
@@ -1,13 +1,17 @@
 use crate::*;
 
-/// Wrapper around [`model64_anim_slot_t`](libdragon_sys::model64_anim_slot_t).
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum Model64AnimSlot {
-    Slot0,
-    Slot1,
-    Slot2,
-    Slot3,
-}
+/// Animation blending, crossfades, events and root motion
+#[cfg(feature = "internal")]
+pub mod blend;
+#[cfg(feature = "internal")]
+mod inspect;
+/// Playback state of blended animations
+pub mod timeline;
+
+pub use timeline::Model64AnimSlot;
+
+#[cfg(feature = "internal")]
+pub use inspect::{Descendants, NodeTransform, PrimitiveIndices, VertexAttribute};
 
 impl From<Model64AnimSlot> for libdragon_sys::model64_anim_slot_t {
     fn from(v: Model64AnimSlot) -> Self {
@@ -144,6 +148,11 @@ impl Model64<'_> {
         })
     }
 
+    /// Iterate over all meshes, in index order
+    pub fn meshes(&self) -> impl ExactSizeIterator<Item = Mesh<'_>> + '_ {
+        (0..self.get_mesh_count()).map(move |i| self.get_mesh(i))
+    }
+
     /// Draw an entire model.
     ///
     /// See [`model64_draw`](libdragon_sys::model64_draw) for details.
@@ -295,6 +304,11 @@ impl Mesh<'_> {
         }
     }
 
+    /// Iterate over the primitives of the mesh
+    pub fn primitives(&self) -> impl ExactSizeIterator<Item = Primitive<'_>> + '_ {
+        (0..self.get_primitive_count()).map(move |i| self.get_primitive(i))
+    }
+
     /// Draw a single mesh
     ///
     /// See [`model64_draw_mesh`](libdragon_sys::model64_draw_mesh) for details.
@@ -306,7 +320,7 @@ impl Mesh<'_> {
     }
 }
 
-impl Model64Node<'_> {
+impl<'a> Model64Node<'a> {
     /// Sets the position of a node in a model relative to its parent
     ///
     /// See [`model64_set_node_pos`](libdragon_sys::model64_set_node_pos) for details.
@@ -350,14 +364,25 @@ impl Model64Node<'_> {
 
     /// Gets the transformation matrix between a model's root node and a node in a model.
     ///
+    /// Rust: allocates, prefer [get_node_world_matrix](Self::get_node_world_matrix).
+    ///
     /// See [`model64_get_node_world_mtx`](libdragon_sys::model64_get_node_world_mtx) for details.
     #[inline]
     pub fn get_node_world_mtx(&self) -> Vec<f32> {
-        let mut mtx = Vec::with_capacity(16);
+        self.get_node_world_matrix().as_slice().to_vec()
+    }
+
+    /// Gets the transformation matrix between a model's root node and a node in a model, as a
+    /// column-major [math::Mat4].
+    ///
+    /// See [`model64_get_node_world_mtx`](libdragon_sys::model64_get_node_world_mtx) for details.
+    #[inline]
+    pub fn get_node_world_matrix(&self) -> math::Mat4 {
+        let mut mtx = [0.0f32; 16];
         unsafe {
             libdragon_sys::model64_get_node_world_mtx(self.model_ptr, self.ptr, mtx.as_mut_ptr());
         }
-        mtx
+        math::Mat4::from_cols_array(&mtx)
     }
 
     /// Draw a single node
@@ -371,7 +396,7 @@ impl Model64Node<'_> {
     }
 }
 
-impl Primitive<'_> {
+impl<'a> Primitive<'a> {
     /// Draw a single primitive
     ///
     /// See [`model64_draw_primitive`](libdragon_sys::model64_draw_primitive) for details.
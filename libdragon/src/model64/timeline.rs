@@ -0,0 +1,468 @@
+//! [Timeline] advances the time of the animations in each [Model64AnimSlot], fades their blend
+//! weights and fires [EventMarker]s, all from the delta time passed to [Timeline::advance]. It
+//! does not sample animations, which is the job of `blend::AnimBlender` (with the `internal`
+//! feature), so it can be driven by any clock.
+//!
+//! This module only depends on `core`, `alloc` and [math](crate::math). It is shared with
+//! `libdragon-build`, so the playback logic can be tested on a development host with a mock clock.
+
+use alloc::{string::String, vec::Vec};
+
+use crate::math;
+
+/// One of the four animation slots of a model, see `model64_anim_slot_t`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Model64AnimSlot {
+    Slot0,
+    Slot1,
+    Slot2,
+    Slot3,
+}
+
+impl Model64AnimSlot {
+    /// All the slots, in order
+    pub const ALL: [Self; 4] = [Self::Slot0, Self::Slot1, Self::Slot2, Self::Slot3];
+
+    /// Index of the slot, from 0 to 3
+    #[inline]
+    pub fn index(self) -> usize {
+        match self {
+            Self::Slot0 => 0,
+            Self::Slot1 => 1,
+            Self::Slot2 => 2,
+            Self::Slot3 => 3,
+        }
+    }
+}
+
+/// A named marker at a fixed time of an animation, e.g. a footstep
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventMarker {
+    /// Name of the animation
+    pub anim: String,
+    /// Time of the marker in seconds from the start of the animation
+    pub time: f32,
+    /// Name of the event
+    pub name: String,
+}
+
+/// State of an animation playing in a slot
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotState {
+    /// Name of the animation
+    pub anim:    String,
+    /// Current time in seconds
+    pub time:    f32,
+    /// Length of the animation in seconds
+    pub length:  f32,
+    /// Playback speed (1 is normal speed, negative values play backwards)
+    pub speed:   f32,
+    /// Whether the animation restarts when it reaches its end
+    pub looping: bool,
+    /// Whether the time is frozen
+    pub paused:  bool,
+    /// Blend weight, relative to the other slots
+    pub weight:  f32,
+    fade:        Option<Fade>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Fade {
+    from:     f32,
+    to:       f32,
+    duration: f32,
+    elapsed:  f32,
+    // stop the slot once the fade is over (end of a crossfade)
+    stop:     bool,
+}
+
+/// How the time of a slot moved during [Timeline::advance], for root motion
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SlotStep {
+    /// Time before the step
+    pub previous_time: f32,
+    /// Time after the step
+    pub time:          f32,
+    /// Number of times the animation looped: positive when playing forward, negative when
+    /// playing backwards
+    pub wraps:         i32,
+}
+
+/// Playback state of the animations in all [Model64AnimSlot]s
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    slots:   [Option<SlotState>; 4],
+    markers: Vec<EventMarker>,
+}
+
+impl Timeline {
+    /// Create a timeline with nothing playing
+    pub fn new() -> Self { Self::default() }
+
+    /// Start playing `anim` of `length` seconds in `slot` from the start, at full weight. Any
+    /// fade of the slot is cancelled.
+    pub fn play(&mut self, slot: Model64AnimSlot, anim: &str, length: f32, looping: bool) {
+        self.slots[slot.index()] = Some(SlotState {
+            anim: anim.into(),
+            time: 0.0,
+            length,
+            speed: 1.0,
+            looping,
+            paused: false,
+            weight: 1.0,
+            fade: None,
+        });
+    }
+
+    /// Stop the animation in `slot`
+    pub fn stop(&mut self, slot: Model64AnimSlot) { self.slots[slot.index()] = None; }
+
+    /// State of `slot`, or `None` if nothing is playing in it
+    #[inline]
+    pub fn slot(&self, slot: Model64AnimSlot) -> Option<&SlotState> {
+        self.slots[slot.index()].as_ref()
+    }
+
+    /// Mutable state of `slot`, e.g. to change its speed
+    #[inline]
+    pub fn slot_mut(&mut self, slot: Model64AnimSlot) -> Option<&mut SlotState> {
+        self.slots[slot.index()].as_mut()
+    }
+
+    /// Set the blend weight of `slot` immediately, cancelling any fade
+    pub fn set_weight(&mut self, slot: Model64AnimSlot, weight: f32) {
+        if let Some(s) = self.slot_mut(slot) {
+            s.weight = weight;
+            s.fade = None;
+        }
+    }
+
+    /// Change the blend weight of `slot` to `weight` linearly over `duration` seconds
+    pub fn fade_to(&mut self, slot: Model64AnimSlot, weight: f32, duration: f32) {
+        self.fade(slot, weight, duration, false);
+    }
+
+    fn fade(&mut self, slot: Model64AnimSlot, weight: f32, duration: f32, stop: bool) {
+        let Some(s) = self.slot_mut(slot) else {
+            return;
+        };
+        if duration <= 0.0 {
+            s.weight = weight;
+            s.fade = None;
+            if stop {
+                self.stop(slot);
+            }
+            return;
+        }
+        s.fade = Some(Fade {
+            from: s.weight,
+            to: weight,
+            duration,
+            elapsed: 0.0,
+            stop,
+        });
+    }
+
+    /// Start playing `anim` in slot `to` and crossfade to it from the animation in slot `from`
+    /// over `duration` seconds. The animation in `from` is stopped at the end of the fade.
+    ///
+    /// When `from` and `to` are the same slot, `anim` restarts in it immediately at the weight of
+    /// the previous animation, since a slot can't fade into itself.
+    #[allow(clippy::too_many_arguments)]
+    pub fn crossfade(
+        &mut self,
+        from: Model64AnimSlot,
+        to: Model64AnimSlot,
+        anim: &str,
+        length: f32,
+        looping: bool,
+        duration: f32,
+    ) {
+        let target = self.slot(from).map_or(1.0, |s| s.weight);
+        self.play(to, anim, length, looping);
+        if from == to {
+            self.set_weight(to, target);
+            return;
+        }
+        self.set_weight(to, 0.0);
+        self.fade(to, target, duration, false);
+        self.fade(from, 0.0, duration, true);
+    }
+
+    /// Add an event marker named `name` at `time` seconds of `anim`
+    pub fn add_event(&mut self, anim: &str, time: f32, name: &str) {
+        // kept sorted by time, so that markers fire in order
+        let index = self.markers.partition_point(|m| m.time <= time);
+        self.markers.insert(
+            index,
+            EventMarker {
+                anim: anim.into(),
+                time,
+                name: name.into(),
+            },
+        );
+    }
+
+    /// Remove all the event markers of `anim`
+    pub fn clear_events(&mut self, anim: &str) { self.markers.retain(|m| m.anim != anim); }
+
+    /// Advance all the slots by `dt` seconds, calling `on_event` for each marker crossed by a
+    /// playing animation, in order. A marker is fired when the time reaches it, so a marker at
+    /// time 0 fires at every loop but not when the animation starts.
+    ///
+    /// Returns how the time of each slot moved, or `None` for the empty slots.
+    pub fn advance<F>(&mut self, dt: f32, mut on_event: F) -> [Option<SlotStep>; 4]
+    where
+        F: FnMut(Model64AnimSlot, &EventMarker),
+    {
+        let mut steps = [None; 4];
+        for slot in Model64AnimSlot::ALL {
+            // taken out of the slot while it advances, so that the markers can be borrowed
+            let Some(mut s) = self.slots[slot.index()].take() else {
+                continue;
+            };
+
+            let markers = self.markers.iter().filter(|m| m.anim == s.anim);
+            let mut fire_forward = |lo: f32, hi: f32| {
+                for m in markers.clone().filter(|m| m.time > lo && m.time <= hi) {
+                    on_event(slot, m);
+                }
+            };
+            let previous_time = s.time;
+            let mut wraps = 0;
+            let mut remaining = if s.paused || s.length <= 0.0 {
+                0.0
+            } else {
+                dt * s.speed
+            };
+            // a paused slot stays where it is, even at the end of a looping animation
+            if remaining > 0.0 {
+                loop {
+                    let end = s.time + remaining;
+                    if end < s.length || !s.looping {
+                        let end = end.min(s.length);
+                        fire_forward(s.time, end);
+                        s.time = end;
+                        break;
+                    }
+                    fire_forward(s.time, s.length);
+                    remaining = end - s.length;
+                    s.time = 0.0;
+                    wraps += 1;
+                    // markers at time 0 are reached again when looping
+                    fire_forward(f32::NEG_INFINITY, 0.0);
+                }
+            } else if remaining < 0.0 {
+                let mut fire_backward = |lo: f32, hi: f32| {
+                    for m in markers
+                        .clone()
+                        .rev()
+                        .filter(|m| m.time >= lo && m.time < hi)
+                    {
+                        on_event(slot, m);
+                    }
+                };
+                loop {
+                    let end = s.time + remaining;
+                    if end > 0.0 || !s.looping {
+                        let end = end.max(0.0);
+                        fire_backward(end, s.time);
+                        s.time = end;
+                        break;
+                    }
+                    fire_backward(0.0, s.time);
+                    remaining = end;
+                    s.time = s.length;
+                    wraps -= 1;
+                    // markers at the end are reached again when looping backwards
+                    fire_backward(s.length, f32::INFINITY);
+                }
+            }
+            steps[slot.index()] = Some(SlotStep {
+                previous_time,
+                time: s.time,
+                wraps,
+            });
+
+            let mut stopped = false;
+            if let Some(fade) = s.fade.as_mut() {
+                fade.elapsed += dt;
+                let t = (fade.elapsed / fade.duration).min(1.0);
+                s.weight = fade.from + (fade.to - fade.from) * t;
+                if t >= 1.0 {
+                    stopped = fade.stop;
+                    s.fade = None;
+                }
+            }
+            if !stopped {
+                self.slots[slot.index()] = Some(s);
+            }
+        }
+        steps
+    }
+}
+
+/// Movement of a root node between two steps of an animation, given its position before and
+/// after the step and at the start and end of the animation. Each loop adds the movement of a
+/// whole cycle, see [SlotStep::wraps].
+#[inline]
+pub fn root_motion(
+    previous: math::Vec3,
+    current: math::Vec3,
+    start: math::Vec3,
+    end: math::Vec3,
+    wraps: i32,
+) -> math::Vec3 {
+    current - previous + (end - start) * wraps as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{borrow::ToOwned, vec};
+    use Model64AnimSlot::*;
+
+    // a mock clock: advance the timeline `steps` times by `dt`, collecting the fired events
+    fn run(timeline: &mut Timeline, dt: f32, steps: usize) -> (Vec<String>, Vec<i32>) {
+        let mut events = Vec::new();
+        let mut wraps = Vec::new();
+        for _ in 0..steps {
+            let step = timeline.advance(dt, |_, m| events.push(m.name.to_owned()));
+            wraps.push(step[0].map_or(0, |s| s.wraps));
+        }
+        (events, wraps)
+    }
+
+    fn walk(looping: bool) -> Timeline {
+        let mut timeline = Timeline::new();
+        timeline.add_event("walk", 1.0, "end");
+        timeline.add_event("walk", 0.0, "start");
+        timeline.add_event("walk", 0.5, "half");
+        timeline.add_event("run", 0.5, "other");
+        timeline.play(Slot0, "walk", 1.0, looping);
+        timeline
+    }
+
+    #[test]
+    fn markers() {
+        let mut timeline = walk(true);
+        let (events, wraps) = run(&mut timeline, 0.25, 8);
+        assert_eq!(events, ["half", "end", "start", "half", "end", "start"]);
+        assert_eq!(wraps, [0, 0, 0, 1, 0, 0, 0, 1]);
+
+        timeline.clear_events("walk");
+        assert_eq!(run(&mut timeline, 0.25, 8).0, Vec::<String>::new());
+    }
+
+    #[test]
+    fn wraps() {
+        let mut timeline = walk(true);
+        let step = timeline.advance(2.5, |_, _| {})[0].unwrap();
+        assert_eq!(
+            step,
+            SlotStep {
+                previous_time: 0.0,
+                time:          0.5,
+                wraps:         2,
+            }
+        );
+        assert_eq!(timeline.advance(1.0, |_, _| {})[1], None);
+
+        // backwards
+        let mut timeline = walk(true);
+        timeline.slot_mut(Slot0).unwrap().speed = -1.0;
+        let (events, wraps) = run(&mut timeline, 0.25, 4);
+        assert_eq!(events, ["end", "half", "start", "end"]);
+        assert_eq!(wraps, [-1, 0, 0, -1]);
+
+        // paused
+        timeline.slot_mut(Slot0).unwrap().paused = true;
+        assert_eq!(run(&mut timeline, 0.25, 4), (vec![], vec![0; 4]));
+        assert_eq!(timeline.slot(Slot0).unwrap().time, 1.0);
+    }
+
+    #[test]
+    fn not_looping() {
+        let mut timeline = walk(false);
+        let (events, wraps) = run(&mut timeline, 0.75, 4);
+        assert_eq!(events, ["half", "end"]);
+        assert_eq!(wraps, [0; 4]);
+        assert_eq!(timeline.slot(Slot0).unwrap().time, 1.0);
+    }
+
+    #[test]
+    fn fade_end() {
+        let mut timeline = walk(true);
+        timeline.fade_to(Slot0, 0.5, 1.0);
+        run(&mut timeline, 0.25, 1);
+        assert_eq!(timeline.slot(Slot0).unwrap().weight, 0.875);
+        run(&mut timeline, 0.5, 2);
+        assert_eq!(timeline.slot(Slot0).unwrap().weight, 0.5);
+        run(&mut timeline, 0.5, 2);
+        assert_eq!(timeline.slot(Slot0).unwrap().weight, 0.5);
+
+        timeline.fade_to(Slot0, 1.0, 0.0);
+        assert_eq!(timeline.slot(Slot0).unwrap().weight, 1.0);
+        timeline.fade_to(Slot0, 0.0, 1.0);
+        timeline.set_weight(Slot0, 0.25);
+        run(&mut timeline, 0.5, 1);
+        assert_eq!(timeline.slot(Slot0).unwrap().weight, 0.25);
+    }
+
+    #[test]
+    fn crossfade() {
+        let mut timeline = walk(true);
+        timeline.crossfade(Slot0, Slot1, "run", 2.0, true, 0.5);
+        let events = run(&mut timeline, 0.25, 1).0;
+        assert_eq!(timeline.slot(Slot0).unwrap().weight, 0.5);
+        assert_eq!(timeline.slot(Slot1).unwrap().weight, 0.5);
+        // both animations play during the fade
+        let events = [events, run(&mut timeline, 0.25, 1).0].concat();
+        assert_eq!(events, ["half", "other"]);
+        assert_eq!(timeline.slot(Slot0), None);
+        assert_eq!(timeline.slot(Slot1).unwrap().weight, 1.0);
+
+        // immediately
+        timeline.crossfade(Slot1, Slot2, "walk", 1.0, true, 0.0);
+        assert_eq!(timeline.slot(Slot1), None);
+        assert_eq!(timeline.slot(Slot2).unwrap().weight, 1.0);
+    }
+
+    #[test]
+    fn crossfade_same_slot() {
+        let mut timeline = walk(true);
+        timeline.set_weight(Slot0, 0.5);
+        run(&mut timeline, 0.25, 1);
+        timeline.crossfade(Slot0, Slot0, "run", 2.0, false, 0.5);
+        let slot = timeline.slot(Slot0).unwrap();
+        assert_eq!(
+            (slot.anim.as_str(), slot.time, slot.weight),
+            ("run", 0.0, 0.5)
+        );
+        assert_eq!(run(&mut timeline, 0.25, 4).0, ["other"]);
+        let slot = timeline.slot(Slot0).unwrap();
+        assert_eq!(
+            (slot.anim.as_str(), slot.time, slot.weight),
+            ("run", 1.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn motion() {
+        let v = math::Vec3::new;
+        let start = v(0.0, 0.0, 0.0);
+        let end = v(4.0, 0.0, 0.0);
+        assert_eq!(
+            root_motion(v(1.0, 0.0, 0.0), v(2.0, 0.0, 0.0), start, end, 0),
+            v(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            root_motion(v(3.0, 0.0, 0.0), v(1.0, 0.0, 0.0), start, end, 1),
+            v(2.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            root_motion(v(1.0, 0.0, 0.0), v(3.0, 0.0, 0.0), start, end, -1),
+            v(-2.0, 0.0, 0.0)
+        );
+    }
+}
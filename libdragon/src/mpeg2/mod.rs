@@ -0,0 +1,412 @@
+use crate::*;
+
+use display::Viewport;
+use surface::Surface;
+
+mod stream;
+pub use stream::{FramePacer, Mpeg2Error, SequenceHeader, StreamInfo};
+use stream::{StartCodes, HEADER_SEARCH};
+
+/// Wrapper around [`mpeg2_t`](libdragon_sys::mpeg2).
+pub struct Mpeg2 {
+    m: libdragon_sys::mpeg2_t,
+}
+
+impl Mpeg2 {
+    /// Create a new [Mpeg2] object.
+    ///
+    /// Rust: libdragon asserts if the file is missing or is not a video. Use [Mpeg2::open] to
+    /// get an error instead.
+    ///
+    /// See [`mpeg2_open`](libdragon_sys::mpeg2_open) for details.
+    pub fn new<T: AsRef<dfs::Path>>(path: T, output_width: usize, output_height: usize) -> Self {
+        let path_bytes: &[u8] = path.as_ref().as_bytes();
+        let cpath = CString::new(path_bytes).unwrap();
+
+        let mut tmp: core::mem::MaybeUninit<libdragon_sys::mpeg2_t> =
+            core::mem::MaybeUninit::uninit();
+        unsafe {
+            libdragon_sys::mpeg2_open(
+                tmp.as_mut_ptr(),
+                cpath.as_ptr() as *const _,
+                output_width as i32,
+                output_height as i32,
+            );
+        }
+
+        Self {
+            m: unsafe { tmp.assume_init() },
+        }
+    }
+
+    /// Check that `path` is a video the decoder supports with [StreamInfo::read], then open it
+    pub fn open<T: AsRef<dfs::Path>>(
+        path: T,
+        output_width: usize,
+        output_height: usize,
+    ) -> Result<Self> {
+        StreamInfo::read(path.as_ref())?;
+        Ok(Self::new(path, output_width, output_height))
+    }
+
+    /// Replace the blitter drawing the decoded frames to the output, e.g. to change their zoom
+    /// and alignment (by default they are centered with [yuv::Zoom::KeepAspect]). It must be
+    /// created for the size of the video and the output size given to [Mpeg2::new].
+    ///
+    /// Rust: the blitter created by [`mpeg2_open`](libdragon_sys::mpeg2_open) is freed.
+    pub fn set_blitter(&mut self, blitter: yuv::Blitter<'static>) {
+        unsafe {
+            libdragon_sys::yuv_blitter_free(&mut self.m.yuv_blitter as *mut _);
+        }
+        self.m.yuv_blitter = blitter.into_raw();
+    }
+
+    /// See [`mpeg2_get_framerate`](libdragon_sys::mpeg2_get_framerate) for details.
+    #[inline]
+    pub fn get_framerate(&mut self) -> f32 {
+        unsafe { libdragon_sys::mpeg2_get_framerate(&mut self.m as *mut _) }
+    }
+    /// See [`mpeg2_next_frame`](libdragon_sys::mpeg2_next_frame) for details.
+    #[inline]
+    pub fn next_frame(&mut self) -> bool {
+        unsafe { libdragon_sys::mpeg2_next_frame(&mut self.m as *mut _) }
+    }
+    /// See [`mpeg2_draw_frame`](libdragon_sys::mpeg2_draw_frame) for details.
+    #[inline]
+    pub fn draw_frame(&mut self, surface: &mut Surface) {
+        unsafe { libdragon_sys::mpeg2_draw_frame(&mut self.m as *mut _, surface.ptr) }
+    }
+    /// See [`mpeg2_rewind`](libdragon_sys::mpeg2_rewind) for details.
+    #[inline]
+    pub fn rewind(&mut self) { unsafe { libdragon_sys::mpeg2_rewind(&mut self.m as *mut _) } }
+}
+
+impl Drop for Mpeg2 {
+    /// See [`mpeg2_close`](libdragon_sys::mpeg2_close) for details.
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            libdragon_sys::mpeg2_close(&mut self.m as *mut _);
+        }
+    }
+}
+
+impl SequenceHeader {
+    /// Read the sequence header of the video file at `path`
+    pub fn read<T: AsRef<dfs::Path>>(path: T) -> Result<Self> {
+        use embedded_io::Read;
+
+        let mut file = dfs::File::open(path, "r")?;
+        let mut data = vec![0u8; HEADER_SEARCH];
+        let mut len = 0;
+        while len < data.len() {
+            match file.read(&mut data[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(error) => return Err(LibDragonError::DfsError { error }),
+            }
+        }
+        Ok(Self::parse(&data[..len])?)
+    }
+}
+
+impl StreamInfo {
+    /// Scan the video file at `path`, see [StreamInfo::parse].
+    ///
+    /// Rust: the whole file is read once, in chunks of 4 KiB.
+    pub fn read<T: AsRef<dfs::Path>>(path: T) -> Result<Self> {
+        use embedded_io::Read;
+
+        let mut file = dfs::File::open(path, "r")?;
+        let mut data = vec![0u8; HEADER_SEARCH];
+        let mut header = None;
+        let mut codes = StartCodes::default();
+        loop {
+            let mut len = 0;
+            while len < data.len() {
+                match file.read(&mut data[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(error) => return Err(LibDragonError::DfsError { error }),
+                }
+            }
+            if header.is_none() {
+                header = Some(SequenceHeader::parse(&data[..len])?);
+            }
+            codes.feed(&data[..len]);
+            if len < data.len() {
+                break;
+            }
+        }
+        // the first chunk was parsed above
+        Ok(Self::check(header.unwrap(), &codes)?)
+    }
+}
+
+/// Where a video of `video_width` x `video_height` pixels is drawn on a screen of
+/// `screen_width` x `screen_height`, following the zoom and alignment of `parms`.
+///
+/// Rust: see [yuv::frame_rect].
+pub fn fit(
+    video_width: u32,
+    video_height: u32,
+    screen_width: u32,
+    screen_height: u32,
+    parms: &VideoParms,
+) -> Viewport {
+    let [x, y, width, height] = yuv::frame_rect(
+        video_width,
+        video_height,
+        screen_width,
+        screen_height,
+        parms.zoom,
+        parms.halign,
+        parms.valign,
+    );
+    Viewport {
+        x: x as i32,
+        y: y as i32,
+        width,
+        height,
+    }
+}
+
+/// Configuration of a [VideoPlayer]
+pub struct VideoParms {
+    /// Size of the surfaces the video is drawn to (default: 320x240)
+    pub screen_width:  u32,
+    pub screen_height: u32,
+    /// How the video is scaled to the screen (default: [yuv::Zoom::KeepAspect])
+    pub zoom:          yuv::Zoom,
+    /// Horizontal position of the video on the screen (default: [yuv::Align::Center])
+    pub halign:        yuv::Align,
+    /// Vertical position of the video on the screen (default: [yuv::Align::Center])
+    pub valign:        yuv::Align,
+    /// Color of the borders around the video (default: black)
+    pub bkg_color:     graphics::Color,
+    /// Mixer channel the audio track is played on (default: 0)
+    pub audio_channel: i32,
+    /// Maximum number of frames dropped in a row when decoding falls behind (default: 4)
+    pub max_skip:      u32,
+    /// Restart from the beginning at the end of the video (default: false)
+    pub looping:       bool,
+}
+
+impl Default for VideoParms {
+    fn default() -> Self {
+        Self {
+            screen_width:  320,
+            screen_height: 240,
+            zoom:          yuv::Zoom::KeepAspect,
+            halign:        yuv::Align::Center,
+            valign:        yuv::Align::Center,
+            bkg_color:     graphics::rgba32(0, 0, 0, 0xFF),
+            audio_channel: 0,
+            max_skip:      4,
+            looping:       false,
+        }
+    }
+}
+
+/// State of a [VideoPlayer] after [VideoPlayer::update]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PlayerState {
+    /// Playing, `new_frame` tells if a new frame was decoded and should be drawn
+    Playing { new_frame: bool },
+    /// The end of the video was reached
+    Finished,
+}
+
+/// Plays an MPEG video from DFS with an optional wav64 audio track, pacing the frames against
+/// the audio (or the system clock when there is no audio).
+///
+/// ```rust
+/// let mut player = mpeg2::VideoPlayer::open(
+///     "rom:/intro.m1v",
+///     Some("rom:/intro.wav64"),
+///     mpeg2::VideoParms::default(),
+/// )?;
+/// while player.update()? != mpeg2::PlayerState::Finished {
+///     let mut disp = display::get();
+///     rdpq::attach(&disp, None);
+///     player.draw(&mut disp);
+///     rdpq::detach_show();
+/// }
+/// ```
+///
+/// The audio mixer must be initialized when there is an audio track.
+pub struct VideoPlayer {
+    video:     Mpeg2,
+    info:      StreamInfo,
+    // pictures decoded since the start of the current loop
+    pictures:  u64,
+    audio:     Option<audio::wav64::Wav64>,
+    frequency: f32,
+    pacer:     FramePacer,
+    viewport:  Viewport,
+    parms:     VideoParms,
+    start:     u64,
+    has_frame: bool,
+    finished:  bool,
+}
+
+impl VideoPlayer {
+    /// Open the video at `video_path` and the audio track at `audio_path`, and start playing.
+    /// The video file is scanned first with [StreamInfo::read], so that a missing, invalid or
+    /// unsupported file is reported as an error instead of triggering an assertion in
+    /// libdragon. Corrupted pictures in a valid stream are not detected.
+    ///
+    /// Without an audio track, pass `None::<&str>` as `audio_path`.
+    pub fn open<V: AsRef<dfs::Path>, A: AsRef<dfs::Path>>(
+        video_path: V,
+        audio_path: Option<A>,
+        parms: VideoParms,
+    ) -> Result<Self> {
+        let info = StreamInfo::read(&video_path)?;
+        let header = info.header;
+        let viewport = fit(
+            header.width,
+            header.height,
+            parms.screen_width,
+            parms.screen_height,
+            &parms,
+        );
+        let (screen_width, screen_height) = (parms.screen_width, parms.screen_height);
+        let mut video = Mpeg2::new(video_path, screen_width as usize, screen_height as usize);
+        video.set_blitter(yuv::blitter_new_fmv(
+            header.width,
+            header.height,
+            screen_width,
+            screen_height,
+            yuv::FmvParms {
+                zoom: parms.zoom,
+                halign: parms.halign,
+                valign: parms.valign,
+                bkg_color: parms.bkg_color,
+                ..Default::default()
+            },
+        ));
+        let frame_rate = video.get_framerate();
+        let frame_rate = if frame_rate > 0.0 {
+            frame_rate
+        } else {
+            header.frame_rate
+        };
+
+        let mut audio = audio_path.map(audio::wav64::Wav64::open).transpose()?;
+        let frequency = audio.as_mut().map_or(0.0, |wav| wav.wave().frequency());
+
+        let mut player = Self {
+            video,
+            info,
+            pictures: 0,
+            audio,
+            frequency,
+            pacer: FramePacer::new(frame_rate, parms.max_skip),
+            viewport,
+            parms,
+            start: 0,
+            has_frame: false,
+            finished: false,
+        };
+        player.start();
+        Ok(player)
+    }
+
+    fn start(&mut self) {
+        if let Some(audio) = &mut self.audio {
+            audio.play(self.parms.audio_channel);
+        }
+        self.start = ticks::get();
+    }
+
+    /// The sequence header of the video
+    #[inline]
+    pub fn header(&self) -> &SequenceHeader { &self.info.header }
+
+    /// Number of pictures (frames) in the video
+    #[inline]
+    pub fn pictures(&self) -> u64 { self.info.pictures }
+
+    /// Where the video is drawn on the screen, see [fit]
+    #[inline]
+    pub fn viewport(&self) -> Viewport { self.viewport }
+
+    /// Frame pacing statistics: frames decoded and dropped
+    #[inline]
+    pub fn pacer(&self) -> &FramePacer { &self.pacer }
+
+    /// Returns true once the end of the video was reached (never when looping)
+    #[inline]
+    pub fn is_finished(&self) -> bool { self.finished }
+
+    /// Playback position, in seconds: the position of the audio track while it plays, the time
+    /// since the start otherwise
+    pub fn time(&self) -> f32 {
+        let channel = self.parms.audio_channel;
+        if self.audio.is_some() && self.frequency > 0.0 && audio::mixer::ch_playing(channel) {
+            return audio::mixer::ch_get_pos(channel) / self.frequency;
+        }
+        (ticks::get() - self.start) as f32 / ticks::per_second() as f32
+    }
+
+    /// Restart from the first frame
+    pub fn rewind(&mut self) {
+        if self.audio.is_some() {
+            audio::mixer::ch_stop(self.parms.audio_channel);
+        }
+        self.video.rewind();
+        self.pacer.reset();
+        self.pictures = 0;
+        self.has_frame = false;
+        self.finished = false;
+        self.start();
+    }
+
+    /// Decode the frames due at the current playback time, dropping frames if decoding is
+    /// behind. Also feeds the audio mixer with [audio::mixer::try_play].
+    ///
+    /// Fails with [Mpeg2Error::Decode] if the decoder stops before the last picture found by
+    /// [StreamInfo::read]. Playback is then finished.
+    pub fn update(&mut self) -> Result<PlayerState> {
+        if self.finished {
+            return Ok(PlayerState::Finished);
+        }
+        if self.audio.is_some() {
+            audio::mixer::try_play();
+        }
+
+        let count = self.pacer.advance(self.time());
+        for _ in 0..count {
+            if !self.video.next_frame() {
+                let complete = self.pictures >= self.info.pictures;
+                if complete && self.parms.looping {
+                    self.rewind();
+                    return Ok(PlayerState::Playing { new_frame: false });
+                }
+                self.finished = true;
+                if self.audio.is_some() {
+                    audio::mixer::ch_stop(self.parms.audio_channel);
+                }
+                if !complete {
+                    return Err(Mpeg2Error::Decode.into());
+                }
+                return Ok(PlayerState::Finished);
+            }
+            self.pictures += 1;
+            self.has_frame = true;
+        }
+        Ok(PlayerState::Playing {
+            new_frame: count > 0,
+        })
+    }
+
+    /// Draw the current frame to `surface`, which rdpq must be attached to, scaled and aligned
+    /// following [VideoParms] and with the borders filled with [VideoParms::bkg_color]. Does
+    /// nothing before the first frame is decoded.
+    pub fn draw(&mut self, surface: &mut Surface) {
+        if self.has_frame {
+            self.video.draw_frame(surface);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+//! Checks and pacing of MPEG video streams: the sequence header and start codes are parsed
+//! before opening a video, so that unsupported files are reported as errors, and [FramePacer]
+//! decides which frames to decode for a playback time.
+//!
+//! This module only depends on `core`. It is shared with `libdragon-build`, so the parsing and
+//! pacing can be tested on a development host.
+
+/// Errors found while checking an MPEG video before opening it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mpeg2Error {
+    /// No sequence header at the start of the file: it is not an MPEG-1/2 video stream
+    NoSequenceHeader,
+    /// The file ends in the middle of the sequence header
+    Truncated,
+    /// The sequence header has a zero width or height
+    BadSize,
+    /// The sequence header has a reserved frame rate code
+    BadFrameRate,
+    /// The stream has MPEG-2 extensions, while the decoder only supports MPEG-1 video
+    Unsupported,
+    /// The stream has no picture
+    NoPictures,
+    /// A picture could not be decoded: the stream ended before all its pictures were shown
+    Decode,
+}
+
+/// The sequence header at the start of an MPEG video stream
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SequenceHeader {
+    /// Width of the video, in pixels
+    pub width:      u32,
+    /// Height of the video, in pixels
+    pub height:     u32,
+    /// Frames per second
+    pub frame_rate: f32,
+}
+
+// Start code of the sequence header
+const SEQUENCE_HEADER: [u8; 4] = [0x00, 0x00, 0x01, 0xB3];
+
+// How much of the file is searched for the sequence header
+pub(crate) const HEADER_SEARCH: usize = 4096;
+
+impl SequenceHeader {
+    /// Find and parse the first sequence header in `data`, the start of a video stream
+    pub fn parse(data: &[u8]) -> core::result::Result<Self, Mpeg2Error> {
+        let start = data
+            .windows(4)
+            .position(|w| w == SEQUENCE_HEADER)
+            .ok_or(Mpeg2Error::NoSequenceHeader)?;
+        let h = data
+            .get(start + 4..start + 8)
+            .ok_or(Mpeg2Error::Truncated)?;
+        let width = ((h[0] as u32) << 4) | (h[1] as u32 >> 4);
+        let height = ((h[1] as u32 & 0x0F) << 8) | h[2] as u32;
+        if width == 0 || height == 0 {
+            return Err(Mpeg2Error::BadSize);
+        }
+        let frame_rate = match h[3] & 0x0F {
+            1 => 24000.0 / 1001.0,
+            2 => 24.0,
+            3 => 25.0,
+            4 => 30000.0 / 1001.0,
+            5 => 30.0,
+            6 => 50.0,
+            7 => 60000.0 / 1001.0,
+            8 => 60.0,
+            _ => return Err(Mpeg2Error::BadFrameRate),
+        };
+        Ok(Self {
+            width,
+            height,
+            frame_rate,
+        })
+    }
+}
+
+// Start code of a picture
+const PICTURE_START: u8 = 0x00;
+// Start code of an extension, only found in MPEG-2 streams
+const EXTENSION_START: u8 = 0xB5;
+
+// Counts the start codes of a stream fed in chunks of any size
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct StartCodes {
+    // consecutive zero bytes, up to 2
+    zeros:      u8,
+    // the next byte is a start code
+    code_next:  bool,
+    pictures:   u64,
+    extensions: u64,
+}
+
+impl StartCodes {
+    pub(crate) fn feed(&mut self, data: &[u8]) {
+        for &b in data {
+            if self.code_next {
+                match b {
+                    PICTURE_START => self.pictures += 1,
+                    EXTENSION_START => self.extensions += 1,
+                    _ => {}
+                }
+                self.code_next = false;
+                self.zeros = 0;
+            } else if b == 0 {
+                self.zeros = (self.zeros + 1).min(2);
+            } else {
+                self.code_next = b == 1 && self.zeros == 2;
+                self.zeros = 0;
+            }
+        }
+    }
+}
+
+/// The result of scanning a whole video stream before playing it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StreamInfo {
+    /// The sequence header at the start of the stream
+    pub header:   SequenceHeader,
+    /// Number of pictures (frames) in the stream
+    pub pictures: u64,
+}
+
+impl StreamInfo {
+    /// Parse the sequence header and count the pictures of a whole stream. Fails with
+    /// [Mpeg2Error::Unsupported] for MPEG-2 streams and [Mpeg2Error::NoPictures] for streams
+    /// without pictures, which the decoder would assert on.
+    pub fn parse(data: &[u8]) -> core::result::Result<Self, Mpeg2Error> {
+        let header = SequenceHeader::parse(&data[..data.len().min(HEADER_SEARCH)])?;
+        let mut codes = StartCodes::default();
+        codes.feed(data);
+        Self::check(header, &codes)
+    }
+
+    pub(crate) fn check(
+        header: SequenceHeader,
+        codes: &StartCodes,
+    ) -> core::result::Result<Self, Mpeg2Error> {
+        if codes.extensions > 0 {
+            return Err(Mpeg2Error::Unsupported);
+        }
+        if codes.pictures == 0 {
+            return Err(Mpeg2Error::NoPictures);
+        }
+        Ok(Self {
+            header,
+            pictures: codes.pictures,
+        })
+    }
+}
+
+/// Decides which frames of a video to decode and show for a playback time, dropping frames
+/// when the decoding falls behind
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FramePacer {
+    frame_rate: f32,
+    max_skip:   u32,
+    decoded:    u64,
+    dropped:    u64,
+}
+
+impl FramePacer {
+    /// Pace frames at `frame_rate` per second, dropping at most `max_skip` frames in a row
+    pub fn new(frame_rate: f32, max_skip: u32) -> Self {
+        Self {
+            frame_rate,
+            max_skip,
+            decoded: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Number of frames to decode now that playback reached `time` seconds. Only the last one
+    /// needs to be drawn: the others are dropped. Returns 0 if the current frame is still the
+    /// right one.
+    pub fn advance(&mut self, time: f32) -> u32 {
+        let due = (time.max(0.0) * self.frame_rate) as u64 + 1;
+        if due <= self.decoded {
+            return 0;
+        }
+        let count = (due - self.decoded).min(self.max_skip as u64 + 1);
+        self.decoded += count;
+        self.dropped += count - 1;
+        count as u32
+    }
+
+    /// Number of frames decoded so far
+    #[inline]
+    pub fn decoded(&self) -> u64 { self.decoded }
+
+    /// Number of frames decoded but not shown so far
+    #[inline]
+    pub fn dropped(&self) -> u64 { self.dropped }
+
+    /// Start again from the first frame
+    pub fn reset(&mut self) {
+        self.decoded = 0;
+        self.dropped = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    // 320x240 at 25 fps
+    const HEADER: [u8; 8] = [0x00, 0x00, 0x01, 0xB3, 0x14, 0x00, 0xF0, 0x13];
+    const PICTURE: [u8; 4] = [0x00, 0x00, 0x01, PICTURE_START];
+    const EXTENSION: [u8; 4] = [0x00, 0x00, 0x01, EXTENSION_START];
+
+    fn stream(parts: &[&[u8]]) -> Vec<u8> { parts.concat() }
+
+    #[test]
+    fn sequence_header() {
+        let header = SequenceHeader::parse(&stream(&[&[0xFF, 0x00], &HEADER, &[0xFF]])).unwrap();
+        assert_eq!(header.width, 320);
+        assert_eq!(header.height, 240);
+        assert_eq!(header.frame_rate, 25.0);
+
+        let mut ntsc = HEADER;
+        ntsc[7] = 0x14;
+        let frame_rate = SequenceHeader::parse(&ntsc).unwrap().frame_rate;
+        assert!((frame_rate - 29.97).abs() < 0.001, "{}", frame_rate);
+    }
+
+    #[test]
+    fn bad_sequence_header() {
+        assert_eq!(
+            SequenceHeader::parse(&[]),
+            Err(Mpeg2Error::NoSequenceHeader)
+        );
+        assert_eq!(
+            SequenceHeader::parse(&stream(&[&PICTURE, &[0x14, 0x00, 0xF0, 0x13]])),
+            Err(Mpeg2Error::NoSequenceHeader)
+        );
+        assert_eq!(
+            SequenceHeader::parse(&HEADER[..7]),
+            Err(Mpeg2Error::Truncated)
+        );
+        assert_eq!(
+            SequenceHeader::parse(&HEADER[..4]),
+            Err(Mpeg2Error::Truncated)
+        );
+
+        let mut empty = HEADER;
+        empty[4..7].fill(0);
+        assert_eq!(SequenceHeader::parse(&empty), Err(Mpeg2Error::BadSize));
+
+        for code in [0x0, 0x9, 0xF] {
+            let mut reserved = HEADER;
+            reserved[7] = 0x10 | code;
+            assert_eq!(
+                SequenceHeader::parse(&reserved),
+                Err(Mpeg2Error::BadFrameRate)
+            );
+        }
+    }
+
+    #[test]
+    fn start_codes() {
+        let data = stream(&[
+            &HEADER,
+            &PICTURE,
+            &[0x00, 0x00, 0x00],
+            &PICTURE,
+            &[0x01, 0x00],
+        ]);
+        let mut codes = StartCodes::default();
+        codes.feed(&data);
+        assert_eq!(codes.pictures, 2);
+        assert_eq!(codes.extensions, 0);
+        assert_eq!(StreamInfo::parse(&data).map(|info| info.pictures), Ok(2));
+    }
+
+    #[test]
+    fn split_start_codes() {
+        let data = stream(&[&HEADER, &PICTURE, &EXTENSION]);
+        // the extension start code is cut at every possible position
+        for split in HEADER.len() + PICTURE.len()..data.len() {
+            let mut codes = StartCodes::default();
+            codes.feed(&data[..split]);
+            codes.feed(&data[split..]);
+            assert_eq!(codes.pictures, 1, "split at {}", split);
+            assert_eq!(codes.extensions, 1, "split at {}", split);
+            let header = SequenceHeader::parse(&data).unwrap();
+            assert_eq!(
+                StreamInfo::check(header, &codes),
+                Err(Mpeg2Error::Unsupported)
+            );
+        }
+        // byte by byte
+        let mut codes = StartCodes::default();
+        data.chunks(1).for_each(|b| codes.feed(b));
+        assert_eq!((codes.pictures, codes.extensions), (1, 1));
+    }
+
+    #[test]
+    fn no_pictures() {
+        assert_eq!(StreamInfo::parse(&HEADER), Err(Mpeg2Error::NoPictures));
+        assert_eq!(
+            StreamInfo::parse(&PICTURE),
+            Err(Mpeg2Error::NoSequenceHeader)
+        );
+    }
+
+    #[test]
+    fn pacer() {
+        let mut pacer = FramePacer::new(4.0, 2);
+        assert_eq!(pacer.advance(0.0), 1);
+        assert_eq!(pacer.advance(0.125), 0);
+        assert_eq!(pacer.advance(0.25), 1);
+        assert_eq!((pacer.decoded(), pacer.dropped()), (2, 0));
+        // 7 frames late: catching up is capped at max_skip + 1 frames per call
+        assert_eq!(pacer.advance(2.0), 3);
+        assert_eq!(pacer.advance(2.0), 3);
+        assert_eq!(pacer.advance(2.0), 1);
+        assert_eq!(pacer.advance(2.0), 0);
+        assert_eq!((pacer.decoded(), pacer.dropped()), (9, 4));
+        // going back in time does not decode anything
+        assert_eq!(pacer.advance(-1.0), 0);
+        pacer.reset();
+        assert_eq!((pacer.decoded(), pacer.dropped()), (0, 0));
+        assert_eq!(pacer.advance(-1.0), 1);
+    }
+
+    #[test]
+    fn pacer_no_skip() {
+        let mut pacer = FramePacer::new(4.0, 0);
+        assert_eq!(pacer.advance(10.0), 1);
+        assert_eq!(pacer.advance(10.0), 1);
+        assert_eq!(pacer.dropped(), 0);
+    }
+}
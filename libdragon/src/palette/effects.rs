@@ -0,0 +1,86 @@
+//! Palette cycling and fading on packed entries and RGBA 8888 colors.
+//!
+//! This module only depends on `core`. It is shared with `libdragon-build`, so the effects
+//! behind `Palette` can be tested on a development host.
+
+/// Rotate `entries` by `steps` positions. A positive `steps` moves each entry towards higher
+/// indices, wrapping around at the end of the slice.
+#[inline]
+pub fn cycle(entries: &mut [u16], steps: isize) {
+    if entries.is_empty() {
+        return;
+    }
+    let n = steps.rem_euclid(entries.len() as isize) as usize;
+    entries.rotate_right(n);
+}
+
+/// Linear interpolation between two RGBA colors, with `factor` going from 0 (`a`) to 255 (`b`).
+/// Each channel is rounded to the nearest value.
+#[inline]
+pub fn lerp(a: [u8; 4], b: [u8; 4], factor: u8) -> [u8; 4] {
+    let f = factor as u32;
+    let mix = |x: u8, y: u8| ((x as u32 * (255 - f) + y as u32 * f + 127) / 255) as u8;
+    [
+        mix(a[0], b[0]),
+        mix(a[1], b[1]),
+        mix(a[2], b[2]),
+        mix(a[3], b[3]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle_wraps_both_ways() {
+        let mut entries = [0, 1, 2, 3, 4];
+        cycle(&mut entries, 1);
+        assert_eq!(entries, [4, 0, 1, 2, 3]);
+        cycle(&mut entries, -1);
+        assert_eq!(entries, [0, 1, 2, 3, 4]);
+        cycle(&mut entries, -2);
+        assert_eq!(entries, [2, 3, 4, 0, 1]);
+        cycle(&mut entries, 12);
+        assert_eq!(entries, [0, 1, 2, 3, 4]);
+        cycle(&mut entries, isize::MIN);
+        assert_eq!(entries, [3, 4, 0, 1, 2]);
+        cycle(&mut [], 3);
+    }
+
+    #[test]
+    fn cycle_is_periodic() {
+        let original: [u16; 16] = core::array::from_fn(|i| i as u16 * 3);
+        let mut entries = original;
+        for step in 1..=16 {
+            cycle(&mut entries, 1);
+            assert_eq!(entries[step % 16], original[0]);
+        }
+        assert_eq!(entries, original);
+    }
+
+    #[test]
+    fn lerp_endpoints_and_rounding() {
+        let a = [0, 100, 255, 10];
+        let b = [255, 200, 0, 11];
+        assert_eq!(lerp(a, b, 0), a);
+        assert_eq!(lerp(a, b, 255), b);
+        assert_eq!(lerp(a, b, 128), [128, 150, 127, 11]);
+        assert_eq!(lerp(a, b, 1), [1, 100, 254, 10]);
+        assert_eq!(lerp(b, b, 77), b);
+    }
+
+    #[test]
+    fn fade_is_monotonic() {
+        let from = [255, 0, 128, 255];
+        let to = [0, 255, 128, 0];
+        let mut previous = from;
+        for factor in 0..=255 {
+            let c = lerp(from, to, factor);
+            assert!(c[0] <= previous[0] && c[1] >= previous[1] && c[3] <= previous[3]);
+            assert_eq!(c[2], 128);
+            previous = c;
+        }
+        assert_eq!(previous, to);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::*;
+
+use graphics::Color;
+use pixel::Ia16;
+use rdpq::Tlut;
+
+mod effects;
+
+// TMEM loads require the source to be 8-byte aligned in RDRAM
+#[repr(C, align(8))]
+#[derive(Clone)]
+struct Entries([u16; 256]);
+
+/// A palette (TLUT) for [TexFormat::Ci4](surface::TexFormat::Ci4) or
+/// [TexFormat::Ci8](surface::TexFormat::Ci8) textures.
+///
+/// Holds 16 or 256 entries, packed in the format selected by its [Tlut] mode
+/// ([Tlut::Rgba16] or [Tlut::Ia16]), exactly as they will be uploaded to TMEM. Entries can be
+/// read and written as [Color], and the palette can be cycled or faded every frame without
+/// touching the texture itself.
+#[derive(Clone)]
+pub struct Palette {
+    entries: Box<Entries>,
+    len:     usize,
+    tlut:    Tlut,
+}
+
+impl Palette {
+    /// Create a palette of `num_colors` (16 or 256) entries, all set to transparent black.
+    pub fn new(num_colors: usize, tlut: Tlut) -> Self {
+        assert!(
+            num_colors == 16 || num_colors == 256,
+            "a palette has 16 or 256 entries"
+        );
+        assert!(tlut != Tlut::None, "a palette requires a TLUT mode");
+        Self {
+            entries: Box::new(Entries([0; 256])),
+            len: num_colors,
+            tlut,
+        }
+    }
+
+    /// Create a palette from a list of colors.
+    ///
+    /// The palette has 16 entries if at most 16 colors are given, 256 otherwise; unused entries
+    /// are set to transparent black.
+    pub fn from_colors(colors: &[Color], tlut: Tlut) -> Self {
+        assert!(colors.len() <= 256, "too many colors for a palette");
+        let mut palette = Self::new(if colors.len() <= 16 { 16 } else { 256 }, tlut);
+        for (i, color) in colors.iter().enumerate() {
+            palette.set(i, *color);
+        }
+        palette
+    }
+
+    /// Create a palette from entries already packed in the `tlut` format (e.g. the palette
+    /// returned by [pixel::quantize] or [sprite::Sprite::get_palette]).
+    pub fn from_raw(raw: &[u16], tlut: Tlut) -> Self {
+        assert!(raw.len() <= 256, "too many colors for a palette");
+        let mut palette = Self::new(if raw.len() <= 16 { 16 } else { 256 }, tlut);
+        palette.entries.0[..raw.len()].copy_from_slice(raw);
+        palette
+    }
+
+    /// Copy the palette of a sprite, if it has one.
+    pub fn from_sprite(sprite: &sprite::Sprite) -> Option<Self> {
+        let raw = sprite.get_palette()?;
+        Some(Self::from_raw(raw, sprite.get_format().into()))
+    }
+
+    /// Number of entries (16 or 256)
+    #[inline]
+    pub fn len(&self) -> usize { self.len }
+    /// Always false: a palette has at least 16 entries
+    #[inline]
+    pub fn is_empty(&self) -> bool { false }
+    /// The [Tlut] mode the entries are packed for
+    #[inline]
+    pub fn tlut(&self) -> Tlut { self.tlut }
+
+    /// The packed entries, as uploaded to TMEM
+    #[inline]
+    pub fn as_raw(&self) -> &[u16] { &self.entries.0[..self.len] }
+    /// Mutable access to the packed entries
+    #[inline]
+    pub fn as_raw_mut(&mut self) -> &mut [u16] { &mut self.entries.0[..self.len] }
+
+    /// Pack a [Color] in the format of this palette
+    #[inline]
+    pub fn pack(&self, color: Color) -> u16 {
+        match self.tlut {
+            Tlut::Ia16 => pixel::from_color::<Ia16>(color),
+            _ => color.to_packed16(),
+        }
+    }
+
+    /// Unpack an entry of this palette to a [Color]
+    #[inline]
+    pub fn unpack(&self, raw: u16) -> Color {
+        match self.tlut {
+            Tlut::Ia16 => pixel::to_color::<Ia16>(raw),
+            _ => Color::from_packed16(raw),
+        }
+    }
+
+    /// Read entry `index`
+    #[inline]
+    pub fn get(&self, index: usize) -> Color { self.unpack(self.as_raw()[index]) }
+
+    /// Write entry `index`
+    #[inline]
+    pub fn set(&mut self, index: usize, color: Color) {
+        let raw = self.pack(color);
+        self.as_raw_mut()[index] = raw;
+    }
+
+    /// Set all entries in `range` to `color`
+    pub fn fill(&mut self, range: core::ops::Range<usize>, color: Color) {
+        let raw = self.pack(color);
+        self.as_raw_mut()[range].fill(raw);
+    }
+
+    /// Rotate the entries in `range` by `steps` positions (palette cycling).
+    ///
+    /// A positive `steps` moves each color towards higher indices, wrapping around at the end of
+    /// the range.
+    pub fn cycle(&mut self, range: core::ops::Range<usize>, steps: isize) {
+        effects::cycle(&mut self.as_raw_mut()[range], steps);
+    }
+
+    /// Set this palette to a blend between `from` and `to`, where `factor` goes from 0 (all
+    /// `from`) to 255 (all `to`).
+    ///
+    /// Called once per frame with an increasing factor, this fades between two palettes. The
+    /// three palettes must have the same size; `self` keeps its own [Tlut] mode.
+    pub fn blend(&mut self, from: &Palette, to: &Palette, factor: u8) {
+        assert!(
+            from.len() == self.len && to.len() == self.len,
+            "palettes must have the same size"
+        );
+        for i in 0..self.len {
+            let color = lerp_color(from.get(i), to.get(i), factor);
+            self.set(i, color);
+        }
+    }
+
+    /// Set this palette to a blend between `from` and a single color (e.g. fade to black or
+    /// white). See [Palette::blend].
+    pub fn blend_to_color(&mut self, from: &Palette, color: Color, factor: u8) {
+        assert!(from.len() == self.len, "palettes must have the same size");
+        for i in 0..self.len {
+            let blended = lerp_color(from.get(i), color, factor);
+            self.set(i, blended);
+        }
+    }
+
+    /// Save the render mode, switch it to the [Tlut] of this palette and load the palette into
+    /// TMEM, starting at palette entry `color_idx`.
+    ///
+    /// For CI4 textures, `color_idx` is usually a multiple of 16 that selects one of the 16
+    /// palettes available in TMEM (see [rdpq::TexParms::palette]).
+    ///
+    /// Rust: draw the color-indexed textures while the returned [TlutMode] is alive; the render
+    /// mode that was active before the upload is restored when it is dropped.
+    ///
+    /// See [`rdpq_tex_upload_tlut`](libdragon_sys::rdpq_tex_upload_tlut) for details.
+    pub fn upload(&self, color_idx: i32) -> TlutMode {
+        // the RDP reads the palette from RDRAM
+        data_cache_hit_writeback(self.as_raw());
+        rdpq::mode_push();
+        rdpq::mode_tlut(self.tlut);
+        rdpq::tex_upload_tlut(self.as_raw(), color_idx, self.len as i32);
+        TlutMode { _private: () }
+    }
+}
+
+/// Guard returned by [Palette::upload]. Restores the previous render mode with
+/// [rdpq::mode_pop] when dropped.
+#[must_use = "the previous render mode is restored when the guard is dropped"]
+pub struct TlutMode {
+    _private: (),
+}
+
+impl Drop for TlutMode {
+    fn drop(&mut self) { rdpq::mode_pop(); }
+}
+
+/// Linear interpolation between two colors, with `factor` going from 0 (`a`) to 255 (`b`)
+#[inline]
+pub fn lerp_color(a: Color, b: Color, factor: u8) -> Color {
+    let (a, b) = (a.c, b.c);
+    let c = effects::lerp([a.r, a.g, a.b, a.a], [b.r, b.g, b.b, b.a], factor);
+    graphics::rgba32(c[0], c[1], c[2], c[3])
+}
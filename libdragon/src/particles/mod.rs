@@ -0,0 +1,172 @@
+//! 2D particle effects (sparks, smoke, rain...) drawn with rdpq.
+//!
+//! A [ParticleSystem] owns a fixed-capacity pool of particles and a list of [Emitter]s that
+//! spawn them, either continuously ([Emitter::rate]) or all at once ([ParticleSystem::burst]).
+//! Particles move with their velocity, gravity and drag, and their size and color follow
+//! [Curve]s over their life. The simulation is plain math with its own seeded random generator:
+//! the same seed and the same sequence of time steps always give the same particles, on the
+//! console and on the host.
+//!
+//! ```rust
+//! let mut sparks = particles::Emitter::new(math::Vec2::new(160.0, 120.0));
+//! sparks.rate = 0.0;
+//! sparks.speed = particles::Range::new(40.0, 80.0);
+//! sparks.gravity = math::Vec2::new(0.0, 60.0);
+//! let orange = math::Vec4::new(1.0, 0.8, 0.2, 1.0);
+//! sparks.color = particles::Curve::linear(orange, math::Vec4::ZERO);
+//!
+//! let mut system = particles::ParticleSystem::new(256, 0x1234);
+//! let id = system.add_emitter(sparks);
+//! system.burst(id, 32);
+//! loop {
+//!     system.update(1.0 / 60.0);
+//!     system.draw(&particles::DrawParms::default());
+//! }
+//! ```
+
+use crate::*;
+use math::Vec4;
+use rdpq::{Blender, Tile};
+
+mod simulation;
+pub use simulation::{Curve, Emitter, EmitterId, Lerp, Particle, ParticleSystem, Range, Rng};
+
+/// How [ParticleSystem::draw] draws the particles
+#[derive(Debug, Copy, Clone, Default)]
+pub enum Shape {
+    /// Flat-colored squares, drawn as rectangles
+    #[default]
+    Rect,
+    /// Flat-colored squares, drawn as two shaded triangles each in batches of 16384 particles.
+    /// Unlike [Shape::Rect], they are positioned with subpixel precision.
+    Quad,
+    /// A texture already loaded in `tile` (e.g. with [rdpq::sprite_upload]), `width` x
+    /// `height` texels, scaled to the particle size and tinted with its color
+    Sprite {
+        tile:   Tile,
+        width:  i32,
+        height: i32,
+    },
+}
+
+/// Parameters of [ParticleSystem::draw]
+#[derive(Copy, Clone)]
+pub struct DrawParms {
+    /// Shape of the particles (default: [Shape::Rect])
+    pub shape:   Shape,
+    /// How particles are blended with the framebuffer (default: alpha blending with
+    /// [BLENDER_MULTIPLY](rdpq::consts::BLENDER_MULTIPLY), use
+    /// [BLENDER_ADDTIVE](rdpq::consts::BLENDER_ADDTIVE) for glowing effects)
+    pub blender: Blender,
+}
+
+impl Default for DrawParms {
+    fn default() -> Self {
+        Self {
+            shape:   Shape::Rect,
+            blender: rdpq::consts::BLENDER_MULTIPLY,
+        }
+    }
+}
+
+impl ParticleSystem {
+    /// Draw the particles, centered on their position. The render mode is set up here and
+    /// restored afterwards with [rdpq::mode_push]/[rdpq::mode_pop].
+    ///
+    /// Rust: [Shape::Quad] allocates a vertex buffer for the batch.
+    pub fn draw(&self, parms: &DrawParms) {
+        rdpq::mode_push();
+        rdpq::set_mode_standard();
+        rdpq::mode_blender(parms.blender);
+        match parms.shape {
+            Shape::Rect => {
+                rdpq::mode_combiner(rdpq::consts::COMBINER_FLAT);
+                for particle in self.particles() {
+                    let (size, color) = self.appearance(particle);
+                    let half = size * 0.5;
+                    let p = particle.position;
+                    rdpq::set_prim_color(to_color(color));
+                    rdpq::draw_rects(&[rdpq::Rect::new(
+                        p.x - half,
+                        p.y - half,
+                        p.x + half,
+                        p.y + half,
+                    )]);
+                }
+            }
+            Shape::Quad => {
+                rdpq::mode_combiner(rdpq::consts::COMBINER_SHADE);
+                let batch = self.len().min(QUAD_BATCH);
+                let mut vertices = Vec::with_capacity(batch * 4);
+                let mut indices = Vec::with_capacity(batch * 6);
+                // split so that the 16-bit indices can address every vertex of a batch
+                for particles in self.particles().chunks(QUAD_BATCH) {
+                    vertices.clear();
+                    indices.clear();
+                    for particle in particles {
+                        let (size, color) = self.appearance(particle);
+                        let half = size * 0.5;
+                        let p = particle.position;
+                        let first = vertices.len() as u16;
+                        for (dx, dy) in [(-half, -half), (half, -half), (-half, half), (half, half)]
+                        {
+                            vertices.push(QuadVertex {
+                                position: [p.x + dx, p.y + dy],
+                                shade:    color.to_array(),
+                            });
+                        }
+                        indices.extend([0, 1, 2, 2, 1, 3].map(|i| first + i));
+                    }
+                    rdpq::draw_triangles(
+                        &rdpq::TriFmt::for_vertex::<QuadVertex>(),
+                        &vertices,
+                        &indices,
+                    );
+                }
+            }
+            Shape::Sprite {
+                tile,
+                width,
+                height,
+            } => {
+                rdpq::mode_combiner(rdpq::consts::COMBINER_TEX_FLAT);
+                for particle in self.particles() {
+                    let (size, color) = self.appearance(particle);
+                    let half = size * 0.5;
+                    let p = particle.position;
+                    rdpq::set_prim_color(to_color(color));
+                    rdpq::texture_rectangle_scaled(
+                        tile,
+                        (p.x - half) as i32,
+                        (p.y - half) as i32,
+                        (p.x + half) as i32,
+                        (p.y + half) as i32,
+                        0,
+                        0,
+                        width,
+                        height,
+                    );
+                }
+            }
+        }
+        rdpq::mode_pop();
+    }
+}
+
+// Particles per batch of Shape::Quad, so that their 4 vertices each fit 16-bit indices
+const QUAD_BATCH: usize = 0x10000 / 4;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct QuadVertex {
+    position: [f32; 2],
+    shade:    [f32; 4],
+}
+
+// SAFETY: repr(C) and only f32s
+crate::rdpq_vertex!(unsafe QuadVertex { position => position, shade => shade });
+
+fn to_color(color: Vec4) -> graphics::Color {
+    let [r, g, b, a] = color.to_array().map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+    graphics::rgba32(r, g, b, a)
+}
@@ -0,0 +1,437 @@
+//! The particle simulation, independent of rendering.
+//!
+//! This module only depends on `core`, `alloc` and [math](crate::math). It is shared with
+//! `libdragon-build`, so the determinism of the simulation can be tested on a development host.
+
+use alloc::{vec, vec::Vec};
+
+use crate::math::{self, Vec2, Vec4};
+
+/// A small deterministic pseudo-random generator (xorshift32)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    /// Create a generator from a seed. A seed of 0 is replaced by a fixed non-zero value.
+    pub const fn new(seed: u32) -> Self {
+        Self {
+            state: if seed == 0 { 0x9E3779B9 } else { seed },
+        }
+    }
+
+    /// Next 32-bit value
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    /// Next value in `0.0..1.0`
+    pub fn next_f32(&mut self) -> f32 { (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32 }
+
+    /// Next value in `min..max`
+    pub fn range(&mut self, min: f32, max: f32) -> f32 { min + (max - min) * self.next_f32() }
+}
+
+/// A range of values an emitter picks from randomly for every particle
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Range {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Range {
+    /// Values between `min` and `max`
+    #[inline]
+    pub const fn new(min: f32, max: f32) -> Self { Self { min, max } }
+
+    /// Always `value`
+    #[inline]
+    pub const fn constant(value: f32) -> Self { Self::new(value, value) }
+
+    /// A random value in the range
+    #[inline]
+    pub fn sample(&self, rng: &mut Rng) -> f32 { rng.range(self.min, self.max) }
+}
+
+/// A value that can be interpolated by a [Curve]
+pub trait Lerp: Copy {
+    /// Linear interpolation between `self` (`t` = 0) and `rhs` (`t` = 1)
+    fn lerp(self, rhs: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    #[inline]
+    fn lerp(self, rhs: Self, t: f32) -> Self { self + (rhs - self) * t }
+}
+
+impl Lerp for Vec2 {
+    #[inline]
+    fn lerp(self, rhs: Self, t: f32) -> Self { Vec2::lerp(self, rhs, t) }
+}
+
+impl Lerp for Vec4 {
+    #[inline]
+    fn lerp(self, rhs: Self, t: f32) -> Self { Vec4::lerp(self, rhs, t) }
+}
+
+/// A value over the life of a particle: keys at times from 0 (birth) to 1 (death), linearly
+/// interpolated, and held before the first key and after the last one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: Lerp> Curve<T> {
+    /// The same value during the whole life
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    /// From `start` at birth to `end` at death
+    pub fn linear(start: T, end: T) -> Self {
+        Self {
+            keys: vec![(0.0, start), (1.0, end)],
+        }
+    }
+
+    /// Add a key at time `t` (0 to 1), keeping the keys sorted
+    pub fn key(mut self, t: f32, value: T) -> Self {
+        let i = self.keys.partition_point(|&(time, _)| time <= t);
+        self.keys.insert(i, (t, value));
+        self
+    }
+
+    /// The keys, sorted by time
+    #[inline]
+    pub fn keys(&self) -> &[(f32, T)] { &self.keys }
+
+    /// The value at time `t` (0 to 1). Panics if the curve has no key.
+    pub fn sample(&self, t: f32) -> T {
+        let i = self.keys.partition_point(|&(time, _)| time <= t);
+        if i == 0 {
+            return self.keys[0].1;
+        }
+        if i == self.keys.len() {
+            return self.keys[i - 1].1;
+        }
+        let (t0, v0) = self.keys[i - 1];
+        let (t1, v1) = self.keys[i];
+        v0.lerp(v1, (t - t0) / (t1 - t0))
+    }
+}
+
+/// Identifies an [Emitter] within its [ParticleSystem]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EmitterId(usize);
+
+/// Spawns particles and describes how they behave
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emitter {
+    /// Where particles are spawned, in pixels
+    pub position: Vec2,
+    /// Particles are spawned at a random distance up to this radius from [Emitter::position]
+    pub radius:   f32,
+    /// Particles spawned per second while [Emitter::active] (default: 10)
+    pub rate:     f32,
+    /// Whether the emitter spawns particles at [Emitter::rate] (default: true)
+    pub active:   bool,
+    /// Life of the particles, in seconds (default: 1)
+    pub lifetime: Range,
+    /// Initial speed of the particles, in pixels per second (default: 0 to 50)
+    pub speed:    Range,
+    /// Initial direction of the particles, in radians: 0 is right, `PI / 2` is down (default:
+    /// all directions)
+    pub angle:    Range,
+    /// Acceleration applied to the particles, in pixels per second squared (default: none)
+    pub gravity:  Vec2,
+    /// Fraction of the velocity lost every second (default: 0)
+    pub drag:     f32,
+    /// Size of the particles over their life, in pixels (default: 2)
+    pub size:     Curve<f32>,
+    /// RGBA color of the particles over their life, each component from 0 to 1 (default: opaque
+    /// white fading out)
+    pub color:    Curve<Vec4>,
+    accumulator:  f32,
+}
+
+impl Emitter {
+    /// An emitter at `position` with the default settings
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position,
+            radius: 0.0,
+            rate: 10.0,
+            active: true,
+            lifetime: Range::constant(1.0),
+            speed: Range::new(0.0, 50.0),
+            angle: Range::new(0.0, math::TAU),
+            gravity: Vec2::ZERO,
+            drag: 0.0,
+            size: Curve::constant(2.0),
+            color: Curve::linear(Vec4::ONE, Vec4::new(1.0, 1.0, 1.0, 0.0)),
+            accumulator: 0.0,
+        }
+    }
+
+    fn spawn(&self, emitter: EmitterId, rng: &mut Rng) -> Particle {
+        let (sin, cos) = math::sin_cos(rng.range(0.0, math::TAU));
+        let offset = Vec2::new(cos, sin) * (self.radius * math::sqrt(rng.next_f32()));
+        let (sin, cos) = math::sin_cos(self.angle.sample(rng));
+        Particle {
+            position: self.position + offset,
+            velocity: Vec2::new(cos, sin) * self.speed.sample(rng),
+            age: 0.0,
+            lifetime: self.lifetime.sample(rng).max(f32::MIN_POSITIVE),
+            emitter,
+        }
+    }
+}
+
+/// A live particle
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Particle {
+    /// Position in pixels
+    pub position: Vec2,
+    /// Velocity in pixels per second
+    pub velocity: Vec2,
+    /// Time since the particle was spawned, in seconds
+    pub age:      f32,
+    /// Time after which the particle dies, in seconds
+    pub lifetime: f32,
+    /// The emitter that spawned the particle
+    pub emitter:  EmitterId,
+}
+
+impl Particle {
+    /// How far the particle is in its life, from 0 (birth) to 1 (death)
+    #[inline]
+    pub fn life(&self) -> f32 { (self.age / self.lifetime).min(1.0) }
+}
+
+/// A pool of particles and the emitters spawning them
+#[derive(Debug, Clone)]
+pub struct ParticleSystem {
+    emitters:  Vec<Emitter>,
+    particles: Vec<Particle>,
+    capacity:  usize,
+    rng:       Rng,
+}
+
+impl ParticleSystem {
+    /// Create a system holding at most `capacity` particles, with a random generator seeded
+    /// with `seed`. New particles are dropped while the pool is full.
+    ///
+    /// Rust: the pool is allocated once here.
+    pub fn new(capacity: usize, seed: u32) -> Self {
+        Self {
+            emitters: Vec::new(),
+            particles: Vec::with_capacity(capacity),
+            capacity,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Add an emitter to the system
+    pub fn add_emitter(&mut self, emitter: Emitter) -> EmitterId {
+        self.emitters.push(emitter);
+        EmitterId(self.emitters.len() - 1)
+    }
+
+    /// Get an emitter
+    #[inline]
+    pub fn emitter(&self, id: EmitterId) -> &Emitter { &self.emitters[id.0] }
+
+    /// Get an emitter mutably, e.g. to move it or stop it
+    #[inline]
+    pub fn emitter_mut(&mut self, id: EmitterId) -> &mut Emitter { &mut self.emitters[id.0] }
+
+    /// The live particles, in no particular order
+    #[inline]
+    pub fn particles(&self) -> &[Particle] { &self.particles }
+
+    /// Number of live particles
+    #[inline]
+    pub fn len(&self) -> usize { self.particles.len() }
+
+    /// Returns true if there are no live particles
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.particles.is_empty() }
+
+    /// Maximum number of particles
+    #[inline]
+    pub fn capacity(&self) -> usize { self.capacity }
+
+    /// Kill all particles
+    pub fn clear(&mut self) { self.particles.clear(); }
+
+    /// Spawn `count` particles from an emitter at once, even if it is not active. Returns the
+    /// number of particles actually spawned, fewer if the pool is full.
+    pub fn burst(&mut self, id: EmitterId, count: usize) -> usize {
+        let count = count.min(self.capacity - self.particles.len());
+        for _ in 0..count {
+            let particle = self.emitters[id.0].spawn(id, &mut self.rng);
+            self.particles.push(particle);
+        }
+        count
+    }
+
+    /// Advance the simulation by `dt` seconds: move the particles, kill the ones at the end of
+    /// their life, then spawn new ones from the active emitters
+    pub fn update(&mut self, dt: f32) {
+        let mut i = 0;
+        while i < self.particles.len() {
+            let particle = &mut self.particles[i];
+            particle.age += dt;
+            if particle.age >= particle.lifetime {
+                self.particles.swap_remove(i);
+                continue;
+            }
+            let emitter = &self.emitters[particle.emitter.0];
+            particle.velocity += emitter.gravity * dt;
+            particle.velocity *= (1.0 - emitter.drag * dt).max(0.0);
+            particle.position += particle.velocity * dt;
+            i += 1;
+        }
+
+        for index in 0..self.emitters.len() {
+            let emitter = &mut self.emitters[index];
+            if !emitter.active {
+                emitter.accumulator = 0.0;
+                continue;
+            }
+            emitter.accumulator += emitter.rate * dt;
+            let count = emitter.accumulator as usize;
+            emitter.accumulator -= count as f32;
+            self.burst(EmitterId(index), count);
+        }
+    }
+
+    /// Size and RGBA color of `particle` at its current age
+    pub fn appearance(&self, particle: &Particle) -> (f32, Vec4) {
+        let emitter = &self.emitters[particle.emitter.0];
+        let life = particle.life();
+        (emitter.size.sample(life), emitter.color.sample(life))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(seed: u32) -> ParticleSystem {
+        let mut system = ParticleSystem::new(64, seed);
+        let mut sparks = Emitter::new(Vec2::new(160.0, 120.0));
+        sparks.radius = 4.0;
+        sparks.rate = 30.0;
+        sparks.lifetime = Range::new(0.5, 1.5);
+        sparks.gravity = Vec2::new(0.0, 60.0);
+        sparks.drag = 0.5;
+        let id = system.add_emitter(sparks);
+        system.burst(id, 16);
+        system
+    }
+
+    fn run(system: &mut ParticleSystem, steps: usize) {
+        for _ in 0..steps {
+            system.update(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn rng() {
+        let mut rng = Rng::new(1);
+        assert_eq!(rng.next_u32(), 0x42021);
+        assert_eq!(Rng::new(0), Rng::new(0x9E3779B9));
+        let mut rng = Rng::new(0x1234);
+        for _ in 0..1000 {
+            let x = rng.next_f32();
+            assert!((0.0..1.0).contains(&x));
+            let r = rng.range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&r));
+        }
+        assert_eq!(Range::constant(4.0).sample(&mut rng), 4.0);
+    }
+
+    #[test]
+    fn deterministic() {
+        let (mut a, mut b) = (system(0x1234), system(0x1234));
+        assert_eq!(a.particles(), b.particles());
+        run(&mut a, 90);
+        run(&mut b, 90);
+        assert!(!a.is_empty());
+        assert_eq!(a.particles(), b.particles());
+
+        let mut other = system(0x4321);
+        run(&mut other, 90);
+        assert_ne!(a.particles(), other.particles());
+    }
+
+    #[test]
+    fn capacity_and_rate() {
+        let mut system = ParticleSystem::new(8, 1);
+        let mut emitter = Emitter::new(Vec2::ZERO);
+        emitter.rate = 10.0;
+        let id = system.add_emitter(emitter);
+        system.update(0.05);
+        assert_eq!(system.len(), 0);
+        system.update(0.05);
+        assert_eq!(system.len(), 1);
+
+        assert_eq!(system.burst(id, 20), 7);
+        assert_eq!(system.len(), system.capacity());
+        system.emitter_mut(id).active = false;
+        system.update(0.5);
+        assert_eq!(system.len(), 8);
+        // every particle lives 1 second
+        system.update(0.6);
+        assert!(system.is_empty());
+        system.burst(id, 3);
+        system.clear();
+        assert!(system.is_empty());
+    }
+
+    #[test]
+    fn movement() {
+        let mut system = ParticleSystem::new(8, 1);
+        let mut emitter = Emitter::new(Vec2::new(10.0, 20.0));
+        emitter.active = false;
+        emitter.speed = Range::constant(0.0);
+        emitter.gravity = Vec2::new(0.0, 10.0);
+        emitter.lifetime = Range::constant(2.0);
+        let id = system.add_emitter(emitter);
+        system.burst(id, 1);
+        system.update(0.5);
+        let particle = system.particles()[0];
+        assert_eq!(particle.velocity, Vec2::new(0.0, 5.0));
+        assert_eq!(particle.position, Vec2::new(10.0, 22.5));
+        assert_eq!(particle.life(), 0.25);
+        assert_eq!(particle.emitter, id);
+
+        system.emitter_mut(id).drag = 1.0;
+        system.update(0.5);
+        // gravity then drag: (5 + 5) * 0.5
+        assert_eq!(system.particles()[0].velocity, Vec2::new(0.0, 5.0));
+    }
+
+    #[test]
+    fn curves() {
+        let curve = Curve::linear(0.0, 10.0).key(0.5, 2.0);
+        assert_eq!(curve.keys(), [(0.0, 0.0), (0.5, 2.0), (1.0, 10.0)]);
+        assert_eq!(curve.sample(-1.0), 0.0);
+        assert_eq!(curve.sample(0.25), 1.0);
+        assert_eq!(curve.sample(0.75), 6.0);
+        assert_eq!(curve.sample(2.0), 10.0);
+        assert_eq!(Curve::constant(3.0).sample(0.5), 3.0);
+
+        let color = Curve::linear(Vec4::ONE, Vec4::ZERO);
+        assert_eq!(color.sample(0.5), Vec4::new(0.5, 0.5, 0.5, 0.5));
+    }
+}
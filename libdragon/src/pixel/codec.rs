@@ -0,0 +1,354 @@
+//! Pixel encodings and color quantization on plain RGBA 8888 quadruplets.
+//!
+//! This module only depends on `core` and `alloc`. It is shared with `libdragon-build`, so the
+//! conversions behind [pixel](crate::pixel) can be tested on a development host.
+
+use alloc::{vec, vec::Vec};
+
+/// A color as 8-bit red, green, blue and alpha channels
+pub type Rgba = [u8; 4];
+
+/// Read nibble-sized pixel `x`: the first pixel of each byte is stored in the high nibble
+#[inline]
+pub fn read_nibble(row: &[u8], x: usize) -> u8 {
+    let b = row[x / 2];
+    if x & 1 == 0 {
+        b >> 4
+    } else {
+        b & 0x0F
+    }
+}
+
+/// Write nibble-sized pixel `x`, leaving the other nibble of the byte untouched
+#[inline]
+pub fn write_nibble(row: &mut [u8], x: usize, value: u8) {
+    let b = &mut row[x / 2];
+    if x & 1 == 0 {
+        *b = (*b & 0x0F) | ((value & 0x0F) << 4);
+    } else {
+        *b = (*b & 0xF0) | (value & 0x0F);
+    }
+}
+
+// Expand a 4-bit value to 8 bits
+#[inline]
+fn expand4(v: u8) -> u8 { (v << 4) | v }
+
+// Expand a 3-bit value to 8 bits
+#[inline]
+fn expand3(v: u8) -> u8 { (v << 5) | (v << 2) | (v >> 1) }
+
+/// Intensity of a color (ITU BT.601 luma)
+#[inline]
+pub fn luminance(c: Rgba) -> u8 {
+    ((c[0] as u32 * 77 + c[1] as u32 * 150 + c[2] as u32 * 29) >> 8) as u8
+}
+
+/// Decode an RGBA 5551 pixel, like `color_from_packed16`
+#[inline]
+pub fn from_rgba16(raw: u16) -> Rgba {
+    [
+        (((raw >> 11) & 0x1F) << 3) as u8,
+        (((raw >> 6) & 0x1F) << 3) as u8,
+        (((raw >> 1) & 0x1F) << 3) as u8,
+        if raw & 1 != 0 { 0xFF } else { 0 },
+    ]
+}
+
+/// Encode an RGBA 5551 pixel, like `color_to_packed16`
+#[inline]
+pub fn to_rgba16(c: Rgba) -> u16 {
+    ((c[0] as u16 >> 3) << 11)
+        | ((c[1] as u16 >> 3) << 6)
+        | ((c[2] as u16 >> 3) << 1)
+        | (c[3] >> 7) as u16
+}
+
+/// Decode an IA 88 pixel: 8-bit intensity (high byte) and 8-bit alpha (low byte)
+#[inline]
+pub fn from_ia16(raw: u16) -> Rgba {
+    let i = (raw >> 8) as u8;
+    [i, i, i, raw as u8]
+}
+
+/// Encode an IA 88 pixel
+#[inline]
+pub fn to_ia16(c: Rgba) -> u16 { ((luminance(c) as u16) << 8) | c[3] as u16 }
+
+/// Decode an IA 44 pixel: 4-bit intensity (high nibble) and 4-bit alpha (low nibble)
+#[inline]
+pub fn from_ia8(raw: u8) -> Rgba {
+    let i = expand4(raw >> 4);
+    [i, i, i, expand4(raw & 0x0F)]
+}
+
+/// Encode an IA 44 pixel (truncating precision)
+#[inline]
+pub fn to_ia8(c: Rgba) -> u8 { (luminance(c) & 0xF0) | (c[3] >> 4) }
+
+/// Decode an IA 31 pixel: 3-bit intensity and 1-bit alpha
+#[inline]
+pub fn from_ia4(raw: u8) -> Rgba {
+    let i = expand3((raw >> 1) & 0x07);
+    [i, i, i, if raw & 1 != 0 { 0xFF } else { 0 }]
+}
+
+/// Encode an IA 31 pixel (truncating precision)
+#[inline]
+pub fn to_ia4(c: Rgba) -> u8 { ((luminance(c) >> 5) << 1) | (c[3] >> 7) }
+
+/// Decode an 8-bit intensity pixel. The RDP replicates the intensity on all four channels,
+/// alpha included.
+#[inline]
+pub fn from_i8(raw: u8) -> Rgba { [raw; 4] }
+
+/// Encode an 8-bit intensity pixel
+#[inline]
+pub fn to_i8(c: Rgba) -> u8 { luminance(c) }
+
+/// Decode a 4-bit intensity pixel, replicated on all four channels like [from_i8]
+#[inline]
+pub fn from_i4(raw: u8) -> Rgba { [expand4(raw & 0x0F); 4] }
+
+/// Encode a 4-bit intensity pixel (truncating precision)
+#[inline]
+pub fn to_i4(c: Rgba) -> u8 { luminance(c) >> 4 }
+
+/// 4x4 Bayer matrix used for ordered dithering
+const BAYER4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Apply ordered dithering to a color that is about to be truncated to 5 bits per channel.
+/// `x` and `y` select the position in the dithering pattern.
+#[inline]
+pub fn dither_rgba16(c: Rgba, x: usize, y: usize) -> Rgba {
+    // spread the 3 truncated bits over the pattern: offsets go from 0 to 7
+    let d = BAYER4[y & 3][x & 3] >> 1;
+    [
+        c[0].saturating_add(d),
+        c[1].saturating_add(d),
+        c[2].saturating_add(d),
+        c[3],
+    ]
+}
+
+// Squared distance between two colors, alpha included
+#[inline]
+fn color_distance(a: Rgba, b: Rgba) -> u32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(&x, &y)| {
+            let v = x as i32 - y as i32;
+            (v * v) as u32
+        })
+        .sum()
+}
+
+/// Index of the palette entry closest to `color`
+pub fn nearest_color(palette: impl IntoIterator<Item = Rgba>, color: Rgba) -> usize {
+    let mut best = 0;
+    let mut best_distance = u32::MAX;
+    for (i, entry) in palette.into_iter().enumerate() {
+        let distance = color_distance(entry, color);
+        if distance < best_distance {
+            best = i;
+            best_distance = distance;
+            if distance == 0 {
+                break;
+            }
+        }
+    }
+    best
+}
+
+/// Compute a palette of at most `max_colors` entries with the median cut algorithm. Colors are
+/// first truncated to RGBA 5551 precision, since that is the format of palettes in TMEM.
+pub fn median_cut(colors: impl IntoIterator<Item = Rgba>, max_colors: usize) -> Vec<Rgba> {
+    let mut pixels: Vec<Rgba> = colors
+        .into_iter()
+        .map(|c| from_rgba16(to_rgba16(c)))
+        .collect();
+    pixels.sort_unstable();
+    pixels.dedup();
+    if pixels.is_empty() || max_colors == 0 {
+        return Vec::new();
+    }
+
+    // each box is a range of `pixels`
+    let mut boxes: Vec<(usize, usize)> = vec![(0, pixels.len())];
+    while boxes.len() < max_colors {
+        // split the box with the widest channel range
+        let mut best: Option<(usize, usize, u8)> = None; // (box, channel, range)
+        for (i, &(start, end)) in boxes.iter().enumerate() {
+            if end - start < 2 {
+                continue;
+            }
+            for ch in 0..4 {
+                let (lo, hi) = pixels[start..end]
+                    .iter()
+                    .fold((255u8, 0u8), |(lo, hi), p| (lo.min(p[ch]), hi.max(p[ch])));
+                let range = hi - lo;
+                if best.map_or(true, |b| range > b.2) {
+                    best = Some((i, ch, range));
+                }
+            }
+        }
+        let Some((i, ch, _)) = best else {
+            break;
+        };
+        let (start, end) = boxes[i];
+        pixels[start..end].sort_unstable_by_key(|p| p[ch]);
+        let mid = start + (end - start) / 2;
+        boxes[i] = (start, mid);
+        boxes.push((mid, end));
+    }
+
+    boxes
+        .iter()
+        .map(|&(start, end)| {
+            let n = (end - start) as u32;
+            let mut sum = [0u32; 4];
+            for p in &pixels[start..end] {
+                for (s, &v) in sum.iter_mut().zip(p.iter()) {
+                    *s += v as u32;
+                }
+            }
+            sum.map(|s| (s / n) as u8)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nibbles_are_high_first() {
+        let mut row = [0u8; 2];
+        write_nibble(&mut row, 0, 0x1);
+        write_nibble(&mut row, 1, 0x2);
+        write_nibble(&mut row, 3, 0xF4);
+        assert_eq!(row, [0x12, 0x04]);
+        assert_eq!(read_nibble(&row, 0), 0x1);
+        assert_eq!(read_nibble(&row, 1), 0x2);
+        assert_eq!(read_nibble(&row, 2), 0x0);
+        assert_eq!(read_nibble(&row, 3), 0x4);
+    }
+
+    #[test]
+    fn rgba16_matches_libdragon() {
+        assert_eq!(from_rgba16(0xF801), [0xF8, 0, 0, 0xFF]);
+        assert_eq!(from_rgba16(0x07C0), [0, 0xF8, 0, 0]);
+        assert_eq!(from_rgba16(0x003E), [0, 0, 0xF8, 0]);
+        assert_eq!(to_rgba16([0xFF, 0xFF, 0xFF, 0x80]), 0xFFFF);
+        assert_eq!(to_rgba16([0x07, 0x08, 0x10, 0x7F]), 0x0044);
+        for raw in 0..=u16::MAX {
+            assert_eq!(to_rgba16(from_rgba16(raw)), raw);
+        }
+    }
+
+    #[test]
+    fn intensity_formats_round_trip() {
+        for raw in 0..=u16::MAX {
+            assert_eq!(to_ia16(from_ia16(raw)), raw);
+        }
+        for raw in 0..=u8::MAX {
+            assert_eq!(to_ia8(from_ia8(raw)), raw);
+            assert_eq!(to_i8(from_i8(raw)), raw);
+        }
+        for raw in 0..16 {
+            assert_eq!(to_ia4(from_ia4(raw)), raw);
+            assert_eq!(to_i4(from_i4(raw)), raw);
+        }
+    }
+
+    #[test]
+    fn intensity_formats_expand() {
+        assert_eq!(from_ia8(0xF0), [0xFF, 0xFF, 0xFF, 0]);
+        assert_eq!(from_ia8(0x5A), [0x55, 0x55, 0x55, 0xAA]);
+        assert_eq!(from_ia4(0x0F), [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(from_ia4(0x08), [0x92, 0x92, 0x92, 0]);
+        assert_eq!(from_i4(0x7), [0x77; 4]);
+        assert_eq!(from_i8(0x42), [0x42; 4]);
+    }
+
+    #[test]
+    fn luminance_weights() {
+        assert_eq!(luminance([0, 0, 0, 0]), 0);
+        assert_eq!(luminance([255, 255, 255, 0]), 255);
+        assert_eq!(luminance([255, 0, 0, 0]), 76);
+        assert_eq!(luminance([0, 255, 0, 0]), 149);
+        assert_eq!(luminance([0, 0, 255, 0]), 28);
+        assert_eq!(to_i8([255, 0, 0, 255]), 76);
+        assert_eq!(to_ia16([0, 255, 0, 0x12]), 0x9512);
+    }
+
+    #[test]
+    fn dither_offsets() {
+        let mut seen = [false; 8];
+        for y in 0..4 {
+            for x in 0..4 {
+                let c = dither_rgba16([100, 100, 100, 7], x, y);
+                let d = c[0] - 100;
+                assert!(d < 8);
+                assert_eq!(c, [100 + d, 100 + d, 100 + d, 7]);
+                assert_eq!(dither_rgba16([100, 100, 100, 7], x + 4, y + 8), c);
+                seen[d as usize] = true;
+            }
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!(dither_rgba16([250, 255, 0, 0], 1, 0), [254, 255, 4, 0]);
+    }
+
+    #[test]
+    fn nearest_color_picks_closest() {
+        let palette = [[0, 0, 0, 255], [255, 255, 255, 255], [255, 0, 0, 255]];
+        assert_eq!(nearest_color(palette, [10, 10, 10, 255]), 0);
+        assert_eq!(nearest_color(palette, [200, 180, 190, 255]), 1);
+        assert_eq!(nearest_color(palette, [255, 0, 0, 255]), 2);
+        assert_eq!(nearest_color(palette, [200, 40, 40, 200]), 2);
+        assert_eq!(nearest_color([], [1, 2, 3, 4]), 0);
+    }
+
+    #[test]
+    fn median_cut_degenerate() {
+        assert!(median_cut([], 16).is_empty());
+        assert!(median_cut([[1, 2, 3, 4]], 0).is_empty());
+        // duplicates and colors that only differ below RGBA 5551 precision collapse
+        let colors = [
+            [0x80, 0x40, 0x20, 0xFF],
+            [0x87, 0x47, 0x27, 0xFF],
+            [0x80, 0x40, 0x20, 0xFF],
+        ];
+        assert_eq!(median_cut(colors, 16), vec![[0x80, 0x40, 0x20, 0xFF]]);
+    }
+
+    #[test]
+    fn median_cut_keeps_few_colors() {
+        let colors = [
+            [0, 0, 0, 0xFF],
+            [0xF8, 0, 0, 0xFF],
+            [0, 0xF8, 0, 0xFF],
+            [0, 0, 0xF8, 0],
+        ];
+        let mut palette = median_cut(colors, 16);
+        palette.sort_unstable();
+        let mut expected = colors.to_vec();
+        expected.sort_unstable();
+        assert_eq!(palette, expected);
+    }
+
+    #[test]
+    fn median_cut_averages_clusters() {
+        let dark = (0..8u8).map(|i| [i * 8, i * 8, 0, 0xFF]);
+        let light = (0..8u8).map(|i| [0xC0 + i * 8, 0xC0 + i * 8, 0xF8, 0xFF]);
+        let mut palette = median_cut(dark.chain(light), 2);
+        palette.sort_unstable();
+        assert_eq!(palette, vec![[28, 28, 0, 0xFF], [220, 220, 0xF8, 0xFF]]);
+
+        let colors: Vec<Rgba> = (0..=255u8).map(|i| [i, 255 - i, i / 2, 0xFF]).collect();
+        for max in [1, 2, 3, 16, 256] {
+            let palette = median_cut(colors.iter().copied(), max);
+            assert!(!palette.is_empty() && palette.len() <= max);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use crate::*;
+
+use graphics::Color;
+use surface::TexFormat;
+
+mod codec;
+mod view;
+pub use view::{
+    blit, convert, quantize, row_len, Ci4, Ci8, ColorFormat, Ia16, Ia4, Ia8, IndexFormat,
+    PixelLayout, Pixels, PixelsMut, Rgba16, Rgba32, Row, RowMut, I4, I8,
+};
+
+/// A [PixelLayout] that matches a [TexFormat], so that the pixels of a surface of that format
+/// can be accessed with [Surface::pixels](surface::Surface::pixels).
+pub trait PixelFormat: PixelLayout {
+    /// The texture format described by this type
+    const FORMAT: TexFormat;
+}
+
+macro_rules! pixel_formats {
+    ($($ty:ident),*) => {
+        $(
+            impl PixelFormat for $ty {
+                const FORMAT: TexFormat = TexFormat::$ty;
+            }
+        )*
+    };
+}
+
+pixel_formats!(Rgba16, Rgba32, Ia16, Ia8, Ia4, I8, I4, Ci8, Ci4);
+
+#[inline]
+fn to_rgba(color: Color) -> codec::Rgba {
+    let c = color.c;
+    [c.r, c.g, c.b, c.a]
+}
+
+#[inline]
+fn from_rgba(c: codec::Rgba) -> Color { graphics::rgba32(c[0], c[1], c[2], c[3]) }
+
+/// Intensity of a color (ITU BT.601 luma), used when converting to the I and IA formats
+#[inline]
+pub fn luminance(color: Color) -> u8 { codec::luminance(to_rgba(color)) }
+
+/// Convert a raw pixel value of format `F` to a [Color]
+#[inline]
+pub fn to_color<F: ColorFormat>(raw: F::Raw) -> Color { from_rgba(F::to_rgba(raw)) }
+
+/// Convert a [Color] to a raw pixel value of format `F` (truncating precision)
+#[inline]
+pub fn from_color<F: ColorFormat>(color: Color) -> F::Raw { F::from_rgba(to_rgba(color)) }
+
+impl<'s, F: ColorFormat> Pixels<'s, F> {
+    /// Read the pixel at (`x`, `y`) as a [Color]
+    #[inline]
+    pub fn get_color(&self, x: usize, y: usize) -> Color { to_color::<F>(self.get(x, y)) }
+}
+
+impl<'s, F: ColorFormat> PixelsMut<'s, F> {
+    /// Read the pixel at (`x`, `y`) as a [Color]
+    #[inline]
+    pub fn get_color(&self, x: usize, y: usize) -> Color { to_color::<F>(self.get(x, y)) }
+
+    /// Write a [Color] at (`x`, `y`)
+    #[inline]
+    pub fn set_color(&mut self, x: usize, y: usize, color: Color) {
+        self.set(x, y, from_color::<F>(color));
+    }
+}
+
+/// Apply ordered dithering to a color that is about to be truncated to 5 bits per channel
+/// (as in [TexFormat::Rgba16]). `x` and `y` select the position in the dithering pattern.
+#[inline]
+pub fn dither_rgba16(color: Color, x: usize, y: usize) -> Color {
+    from_rgba(codec::dither_rgba16(to_rgba(color), x, y))
+}
+
+/// Index of the palette entry closest to `color`
+pub fn nearest_color(palette: &[Color], color: Color) -> usize {
+    codec::nearest_color(palette.iter().map(|c| to_rgba(*c)), to_rgba(color))
+}
+
+/// Compute a palette of at most `max_colors` entries for a list of colors, using the median cut
+/// algorithm. Colors are first truncated to [TexFormat::Rgba16] precision, since that is the
+/// format used by palettes in TMEM.
+pub fn median_cut(colors: &[Color], max_colors: usize) -> Vec<Color> {
+    codec::median_cut(colors.iter().map(|c| to_rgba(*c)), max_colors)
+        .into_iter()
+        .map(from_rgba)
+        .collect()
+}
+
+/// Read the pixel `x` of a row of any [TexFormat] as a [Color].
+///
+/// Color-indexed formats are resolved through `palette` (packed [TexFormat::Rgba16] entries);
+/// if no palette is given, the index is returned as a gray level.
+pub fn read_color(format: TexFormat, row: &[u8], x: usize, palette: Option<&[u16]>) -> Color {
+    let lookup = |index: u8| match palette {
+        Some(p) => Color::from_packed16(p[index as usize]),
+        None => graphics::rgba32(index, index, index, 0xFF),
+    };
+    match format {
+        TexFormat::Rgba16 => to_color::<Rgba16>(Rgba16::read(row, x)),
+        TexFormat::Rgba32 => to_color::<Rgba32>(Rgba32::read(row, x)),
+        TexFormat::Ia16 => to_color::<Ia16>(Ia16::read(row, x)),
+        TexFormat::Ia8 => to_color::<Ia8>(Ia8::read(row, x)),
+        TexFormat::Ia4 => to_color::<Ia4>(Ia4::read(row, x)),
+        TexFormat::I8 => to_color::<I8>(I8::read(row, x)),
+        TexFormat::I4 => to_color::<I4>(I4::read(row, x)),
+        TexFormat::Ci8 => lookup(Ci8::read(row, x)),
+        TexFormat::Ci4 => lookup(Ci4::read(row, x)),
+        _ => panic!("unsupported format {:?}", format),
+    }
+}
+
+/// Write a [Color] into pixel `x` of a row of any non color-indexed [TexFormat].
+pub fn write_color(format: TexFormat, row: &mut [u8], x: usize, color: Color) {
+    match format {
+        TexFormat::Rgba16 => Rgba16::write(row, x, from_color::<Rgba16>(color)),
+        TexFormat::Rgba32 => Rgba32::write(row, x, from_color::<Rgba32>(color)),
+        TexFormat::Ia16 => Ia16::write(row, x, from_color::<Ia16>(color)),
+        TexFormat::Ia8 => Ia8::write(row, x, from_color::<Ia8>(color)),
+        TexFormat::Ia4 => Ia4::write(row, x, from_color::<Ia4>(color)),
+        TexFormat::I8 => I8::write(row, x, from_color::<I8>(color)),
+        TexFormat::I4 => I4::write(row, x, from_color::<I4>(color)),
+        _ => panic!("unsupported format {:?}", format),
+    }
+}
@@ -0,0 +1,658 @@
+//! Typed views over 2D arrays of pixels stored in plain byte slices with a row stride, and the
+//! software blit, conversion and quantization built on them.
+//!
+//! This module only depends on `core`, `alloc` and [codec](super::codec). It is shared with
+//! `libdragon-build`, so the row and stride handling can be tested on a development host.
+
+use alloc::vec::Vec;
+
+use super::codec::{self, Rgba};
+
+/// Describes how the pixels of a format are laid out in memory.
+///
+/// Pixels are always accessed as big-endian bytes, like the RDP sees them, so that the
+/// conversion code behaves identically on the N64 and on a development host.
+pub trait PixelLayout {
+    /// Number of bits of a pixel
+    const BITS: usize;
+    /// The raw value of a single pixel
+    type Raw: Copy + Default;
+
+    /// Read pixel `x` from a row of pixels
+    fn read(row: &[u8], x: usize) -> Self::Raw;
+    /// Write pixel `x` into a row of pixels
+    fn write(row: &mut [u8], x: usize, value: Self::Raw);
+}
+
+/// A [PixelLayout] that stores colors directly (that is, anything but the color-indexed
+/// formats).
+pub trait ColorFormat: PixelLayout {
+    /// Convert a raw pixel value to RGBA 8888
+    fn to_rgba(raw: Self::Raw) -> Rgba;
+    /// Convert an RGBA 8888 color to a raw pixel value (truncating precision)
+    fn from_rgba(color: Rgba) -> Self::Raw;
+    /// Apply ordered dithering to a color about to be converted with
+    /// [from_rgba](Self::from_rgba) at (`x`, `y`). Only formats with 5 bits per channel dither.
+    #[inline]
+    fn dither(color: Rgba, _x: usize, _y: usize) -> Rgba { color }
+}
+
+/// A color-indexed [PixelLayout] ([Ci4] or [Ci8]).
+pub trait IndexFormat: PixelLayout<Raw = u8> {
+    /// Number of palette entries that can be addressed
+    const MAX_COLORS: usize;
+}
+
+/// `TexFormat::Rgba16` pixels: RGBA 5551 stored in a `u16`
+pub struct Rgba16;
+/// `TexFormat::Rgba32` pixels: RGBA 8888 stored in a `u32`
+pub struct Rgba32;
+/// `TexFormat::Ia16` pixels: 8-bit intensity (high byte) and 8-bit alpha (low byte)
+pub struct Ia16;
+/// `TexFormat::Ia8` pixels: 4-bit intensity (high nibble) and 4-bit alpha (low nibble)
+pub struct Ia8;
+/// `TexFormat::Ia4` pixels: 3-bit intensity and 1-bit alpha, stored in a nibble
+pub struct Ia4;
+/// `TexFormat::I8` pixels: 8-bit intensity
+pub struct I8;
+/// `TexFormat::I4` pixels: 4-bit intensity, stored in a nibble
+pub struct I4;
+/// `TexFormat::Ci8` pixels: 8-bit palette index
+pub struct Ci8;
+/// `TexFormat::Ci4` pixels: 4-bit palette index, stored in a nibble
+pub struct Ci4;
+
+impl PixelLayout for Rgba16 {
+    const BITS: usize = 16;
+    type Raw = u16;
+
+    #[inline]
+    fn read(row: &[u8], x: usize) -> u16 { u16::from_be_bytes([row[x * 2], row[x * 2 + 1]]) }
+
+    #[inline]
+    fn write(row: &mut [u8], x: usize, value: u16) {
+        row[x * 2..x * 2 + 2].copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+impl ColorFormat for Rgba16 {
+    #[inline]
+    fn to_rgba(raw: u16) -> Rgba { codec::from_rgba16(raw) }
+    #[inline]
+    fn from_rgba(color: Rgba) -> u16 { codec::to_rgba16(color) }
+    #[inline]
+    fn dither(color: Rgba, x: usize, y: usize) -> Rgba { codec::dither_rgba16(color, x, y) }
+}
+
+impl PixelLayout for Rgba32 {
+    const BITS: usize = 32;
+    type Raw = u32;
+
+    #[inline]
+    fn read(row: &[u8], x: usize) -> u32 {
+        u32::from_be_bytes([row[x * 4], row[x * 4 + 1], row[x * 4 + 2], row[x * 4 + 3]])
+    }
+
+    #[inline]
+    fn write(row: &mut [u8], x: usize, value: u32) {
+        row[x * 4..x * 4 + 4].copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+impl ColorFormat for Rgba32 {
+    #[inline]
+    fn to_rgba(raw: u32) -> Rgba { raw.to_be_bytes() }
+    #[inline]
+    fn from_rgba(color: Rgba) -> u32 { u32::from_be_bytes(color) }
+}
+
+impl PixelLayout for Ia16 {
+    const BITS: usize = 16;
+    type Raw = u16;
+
+    #[inline]
+    fn read(row: &[u8], x: usize) -> u16 { Rgba16::read(row, x) }
+    #[inline]
+    fn write(row: &mut [u8], x: usize, value: u16) { Rgba16::write(row, x, value) }
+}
+
+impl ColorFormat for Ia16 {
+    #[inline]
+    fn to_rgba(raw: u16) -> Rgba { codec::from_ia16(raw) }
+    #[inline]
+    fn from_rgba(color: Rgba) -> u16 { codec::to_ia16(color) }
+}
+
+impl PixelLayout for Ia8 {
+    const BITS: usize = 8;
+    type Raw = u8;
+
+    #[inline]
+    fn read(row: &[u8], x: usize) -> u8 { row[x] }
+    #[inline]
+    fn write(row: &mut [u8], x: usize, value: u8) { row[x] = value; }
+}
+
+impl ColorFormat for Ia8 {
+    #[inline]
+    fn to_rgba(raw: u8) -> Rgba { codec::from_ia8(raw) }
+    #[inline]
+    fn from_rgba(color: Rgba) -> u8 { codec::to_ia8(color) }
+}
+
+impl PixelLayout for Ia4 {
+    const BITS: usize = 4;
+    type Raw = u8;
+
+    #[inline]
+    fn read(row: &[u8], x: usize) -> u8 { codec::read_nibble(row, x) }
+    #[inline]
+    fn write(row: &mut [u8], x: usize, value: u8) { codec::write_nibble(row, x, value) }
+}
+
+impl ColorFormat for Ia4 {
+    #[inline]
+    fn to_rgba(raw: u8) -> Rgba { codec::from_ia4(raw) }
+    #[inline]
+    fn from_rgba(color: Rgba) -> u8 { codec::to_ia4(color) }
+}
+
+impl PixelLayout for I8 {
+    const BITS: usize = 8;
+    type Raw = u8;
+
+    #[inline]
+    fn read(row: &[u8], x: usize) -> u8 { row[x] }
+    #[inline]
+    fn write(row: &mut [u8], x: usize, value: u8) { row[x] = value; }
+}
+
+impl ColorFormat for I8 {
+    /// The RDP replicates the intensity on all four channels, alpha included
+    #[inline]
+    fn to_rgba(raw: u8) -> Rgba { codec::from_i8(raw) }
+    #[inline]
+    fn from_rgba(color: Rgba) -> u8 { codec::to_i8(color) }
+}
+
+impl PixelLayout for I4 {
+    const BITS: usize = 4;
+    type Raw = u8;
+
+    #[inline]
+    fn read(row: &[u8], x: usize) -> u8 { codec::read_nibble(row, x) }
+    #[inline]
+    fn write(row: &mut [u8], x: usize, value: u8) { codec::write_nibble(row, x, value) }
+}
+
+impl ColorFormat for I4 {
+    /// The RDP replicates the intensity on all four channels, alpha included
+    #[inline]
+    fn to_rgba(raw: u8) -> Rgba { codec::from_i4(raw) }
+    #[inline]
+    fn from_rgba(color: Rgba) -> u8 { codec::to_i4(color) }
+}
+
+impl PixelLayout for Ci8 {
+    const BITS: usize = 8;
+    type Raw = u8;
+
+    #[inline]
+    fn read(row: &[u8], x: usize) -> u8 { row[x] }
+    #[inline]
+    fn write(row: &mut [u8], x: usize, value: u8) { row[x] = value; }
+}
+
+impl IndexFormat for Ci8 {
+    const MAX_COLORS: usize = 256;
+}
+
+impl PixelLayout for Ci4 {
+    const BITS: usize = 4;
+    type Raw = u8;
+
+    #[inline]
+    fn read(row: &[u8], x: usize) -> u8 { codec::read_nibble(row, x) }
+    #[inline]
+    fn write(row: &mut [u8], x: usize, value: u8) { codec::write_nibble(row, x, value) }
+}
+
+impl IndexFormat for Ci4 {
+    const MAX_COLORS: usize = 16;
+}
+
+/// Number of bytes of a row of `width` pixels of format `F`
+#[inline]
+pub fn row_len<F: PixelLayout>(width: usize) -> usize { (width * F::BITS).div_ceil(8) }
+
+// Number of bytes covered by a `width` x `height` image with the given stride
+#[inline]
+fn image_len<F: PixelLayout>(width: usize, height: usize, stride: usize) -> usize {
+    if width == 0 || height == 0 {
+        0
+    } else {
+        stride * (height - 1) + row_len::<F>(width)
+    }
+}
+
+/// Read-only typed view over a 2D array of pixels, honoring the row stride.
+pub struct Pixels<'s, F: PixelLayout> {
+    data:    &'s [u8],
+    width:   usize,
+    height:  usize,
+    stride:  usize,
+    phantom: core::marker::PhantomData<F>,
+}
+
+impl<'s, F: PixelLayout> Pixels<'s, F> {
+    /// Create a view over a buffer of pixels. `stride` is the distance in bytes between rows.
+    ///
+    /// Panics if the buffer is too small to contain the image, or if the stride is smaller than
+    /// a row.
+    pub fn new(data: &'s [u8], width: usize, height: usize, stride: usize) -> Self {
+        assert!(
+            height < 2 || stride >= row_len::<F>(width),
+            "stride too small"
+        );
+        assert!(
+            data.len() >= image_len::<F>(width, height, stride),
+            "pixel buffer too small"
+        );
+        Self {
+            data,
+            width,
+            height,
+            stride,
+            phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// Width of the image in pixels
+    #[inline]
+    pub fn width(&self) -> usize { self.width }
+    /// Height of the image in pixels
+    #[inline]
+    pub fn height(&self) -> usize { self.height }
+    /// Distance in bytes between two rows
+    #[inline]
+    pub fn stride(&self) -> usize { self.stride }
+
+    /// Access row `y`
+    #[inline]
+    pub fn row(&self, y: usize) -> Row<'s, F> {
+        assert!(y < self.height, "row out of bounds");
+        let start = y * self.stride;
+        Row {
+            data:    &self.data[start..start + row_len::<F>(self.width)],
+            width:   self.width,
+            phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// Iterate over the rows of the image, from top to bottom
+    pub fn rows(&self) -> impl Iterator<Item = Row<'s, F>> + '_ {
+        (0..self.height).map(|y| self.row(y))
+    }
+
+    /// Read the pixel at (`x`, `y`)
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> F::Raw {
+        assert!(x < self.width, "column out of bounds");
+        self.row(y).get(x)
+    }
+}
+
+/// A single row of a [Pixels] view
+pub struct Row<'s, F: PixelLayout> {
+    data:    &'s [u8],
+    width:   usize,
+    phantom: core::marker::PhantomData<F>,
+}
+
+impl<'s, F: PixelLayout> Row<'s, F> {
+    /// Number of pixels in the row
+    #[inline]
+    pub fn len(&self) -> usize { self.width }
+    /// Returns true if the row contains no pixels
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.width == 0 }
+    /// Read pixel `x`
+    #[inline]
+    pub fn get(&self, x: usize) -> F::Raw { F::read(self.data, x) }
+    /// Iterate over the pixels of the row
+    pub fn iter(&self) -> impl Iterator<Item = F::Raw> + '_ { (0..self.width).map(|x| self.get(x)) }
+    /// The raw bytes of the row, without the padding up to the stride
+    #[inline]
+    pub fn as_bytes(&self) -> &'s [u8] { self.data }
+}
+
+/// Mutable typed view over a 2D array of pixels, honoring the row stride.
+pub struct PixelsMut<'s, F: PixelLayout> {
+    data:    &'s mut [u8],
+    width:   usize,
+    height:  usize,
+    stride:  usize,
+    phantom: core::marker::PhantomData<F>,
+}
+
+impl<'s, F: PixelLayout> PixelsMut<'s, F> {
+    /// Create a mutable view over a buffer of pixels. `stride` is the distance in bytes between
+    /// rows.
+    ///
+    /// Panics if the buffer is too small to contain the image, or if the stride is smaller than
+    /// a row.
+    pub fn new(data: &'s mut [u8], width: usize, height: usize, stride: usize) -> Self {
+        assert!(
+            height < 2 || stride >= row_len::<F>(width),
+            "stride too small"
+        );
+        assert!(
+            data.len() >= image_len::<F>(width, height, stride),
+            "pixel buffer too small"
+        );
+        Self {
+            data,
+            width,
+            height,
+            stride,
+            phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// Width of the image in pixels
+    #[inline]
+    pub fn width(&self) -> usize { self.width }
+    /// Height of the image in pixels
+    #[inline]
+    pub fn height(&self) -> usize { self.height }
+    /// Distance in bytes between two rows
+    #[inline]
+    pub fn stride(&self) -> usize { self.stride }
+
+    /// Reborrow as a read-only view
+    pub fn as_pixels(&self) -> Pixels<'_, F> {
+        Pixels::new(self.data, self.width, self.height, self.stride)
+    }
+
+    /// Access row `y` mutably
+    #[inline]
+    pub fn row_mut(&mut self, y: usize) -> RowMut<'_, F> {
+        assert!(y < self.height, "row out of bounds");
+        let start = y * self.stride;
+        RowMut {
+            data:    &mut self.data[start..start + row_len::<F>(self.width)],
+            width:   self.width,
+            phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// Iterate mutably over the rows of the image, from top to bottom
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = RowMut<'_, F>> + '_ {
+        let len = row_len::<F>(self.width);
+        let width = self.width;
+        self.data
+            .chunks_mut(core::cmp::max(self.stride, 1))
+            .take(self.height)
+            .map(move |row| RowMut {
+                data: &mut row[..len],
+                width,
+                phantom: core::marker::PhantomData,
+            })
+    }
+
+    /// Read the pixel at (`x`, `y`)
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> F::Raw { self.as_pixels().get(x, y) }
+
+    /// Write the pixel at (`x`, `y`)
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, value: F::Raw) {
+        assert!(x < self.width, "column out of bounds");
+        self.row_mut(y).set(x, value);
+    }
+
+    /// Set all pixels to `value`, leaving the padding between rows untouched
+    pub fn fill(&mut self, value: F::Raw) {
+        for mut row in self.rows_mut() {
+            for x in 0..row.len() {
+                row.set(x, value);
+            }
+        }
+    }
+}
+
+/// A single mutable row of a [PixelsMut] view
+pub struct RowMut<'s, F: PixelLayout> {
+    data:    &'s mut [u8],
+    width:   usize,
+    phantom: core::marker::PhantomData<F>,
+}
+
+impl<'s, F: PixelLayout> RowMut<'s, F> {
+    /// Number of pixels in the row
+    #[inline]
+    pub fn len(&self) -> usize { self.width }
+    /// Returns true if the row contains no pixels
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.width == 0 }
+    /// Read pixel `x`
+    #[inline]
+    pub fn get(&self, x: usize) -> F::Raw { F::read(self.data, x) }
+    /// Write pixel `x`
+    #[inline]
+    pub fn set(&mut self, x: usize, value: F::Raw) { F::write(self.data, x, value) }
+    /// The raw bytes of the row, without the padding up to the stride
+    #[inline]
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] { self.data }
+}
+
+/// Software blit with format conversion.
+///
+/// Copies `src` into `dst` with its top-left corner at (`x0`, `y0`), clipping against the
+/// destination bounds. If `dither` is true, ordered dithering is applied when the destination
+/// format has 5 bits per channel (see [ColorFormat::dither]) to reduce banding.
+pub fn blit<S: ColorFormat, D: ColorFormat>(
+    src: &Pixels<S>,
+    dst: &mut PixelsMut<D>,
+    x0: i32,
+    y0: i32,
+    dither: bool,
+) {
+    for sy in 0..src.height() {
+        let dy = y0 + sy as i32;
+        if dy < 0 || dy as usize >= dst.height() {
+            continue;
+        }
+        let srow = src.row(sy);
+        let mut drow = dst.row_mut(dy as usize);
+        for sx in 0..srow.len() {
+            let dx = x0 + sx as i32;
+            if dx < 0 || dx as usize >= drow.len() {
+                continue;
+            }
+            let mut color = S::to_rgba(srow.get(sx));
+            if dither {
+                color = D::dither(color, dx as usize, dy as usize);
+            }
+            drow.set(dx as usize, D::from_rgba(color));
+        }
+    }
+}
+
+/// Software conversion between two images of the same size. See [blit].
+pub fn convert<S: ColorFormat, D: ColorFormat>(
+    src: &Pixels<S>,
+    dst: &mut PixelsMut<D>,
+    dither: bool,
+) {
+    assert!(
+        src.width() == dst.width() && src.height() == dst.height(),
+        "source and destination must have the same size"
+    );
+    blit(src, dst, 0, 0, dither);
+}
+
+/// Quantize an image to a color-indexed format.
+///
+/// A palette of at most [IndexFormat::MAX_COLORS] entries is computed with the median cut
+/// algorithm, and each pixel of `src` is mapped to its nearest palette entry in `dst`. The
+/// palette is returned in packed RGBA 5551 format, ready to be uploaded to TMEM.
+pub fn quantize<S: ColorFormat, D: IndexFormat>(
+    src: &Pixels<S>,
+    dst: &mut PixelsMut<D>,
+) -> Vec<u16> {
+    assert!(
+        src.width() == dst.width() && src.height() == dst.height(),
+        "source and destination must have the same size"
+    );
+    let width = src.width();
+    let mut colors: Vec<Rgba> = Vec::with_capacity(width * src.height());
+    for row in src.rows() {
+        colors.extend(row.iter().map(S::to_rgba));
+    }
+    let palette = codec::median_cut(colors.iter().copied(), D::MAX_COLORS);
+    for (y, mut row) in dst.rows_mut().enumerate() {
+        for x in 0..width {
+            let index = codec::nearest_color(palette.iter().copied(), colors[y * width + x]);
+            row.set(x, index as u8);
+        }
+    }
+    palette.iter().map(|&c| codec::to_rgba16(c)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const PAD: u8 = 0xAA;
+
+    #[test]
+    fn padded_stride() {
+        // 3 RGBA16 pixels (6 bytes) per row, padded to 8 bytes
+        let mut data = vec![PAD; 8 * 2 + 6];
+        let mut pixels = PixelsMut::<Rgba16>::new(&mut data, 3, 3, 8);
+        pixels.fill(0);
+        pixels.set(2, 1, 0x1234);
+        pixels.set(0, 2, 0xABCD);
+        assert_eq!(pixels.get(2, 1), 0x1234);
+        assert_eq!(pixels.row_mut(1).as_bytes_mut().len(), 6);
+
+        let view = pixels.as_pixels();
+        let rows: Vec<Vec<u16>> = view.rows().map(|row| row.iter().collect()).collect();
+        assert_eq!(rows, [[0, 0, 0], [0, 0, 0x1234], [0xABCD, 0, 0]]);
+        assert_eq!(&data[6..8], [PAD, PAD]);
+        assert_eq!(&data[14..16], [PAD, PAD]);
+        assert_eq!(&data[12..14], [0x12, 0x34]);
+        assert_eq!(&data[16..18], [0xAB, 0xCD]);
+    }
+
+    #[test]
+    fn padded_stride_nibbles() {
+        // 3 I4 pixels use 2 bytes, the second nibble of the last byte is never written
+        let mut data = vec![PAD; 4 * 2];
+        let mut pixels = PixelsMut::<I4>::new(&mut data, 3, 2, 4);
+        pixels.fill(0x3);
+        pixels.set(1, 1, 0xF);
+        assert_eq!(data, [0x33, 0x3A, PAD, PAD, 0x3F, 0x3A, PAD, PAD]);
+        let view = Pixels::<I4>::new(&data, 3, 2, 4);
+        assert_eq!(view.row(1).iter().collect::<Vec<_>>(), [0x3, 0xF, 0x3]);
+        assert_eq!(view.row(0).as_bytes(), [0x33, 0x3A]);
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel buffer too small")]
+    fn buffer_too_small() {
+        // the last row needs 6 bytes, not the full stride
+        Pixels::<Rgba16>::new(&[0; 8 + 6], 3, 2, 8);
+        Pixels::<Rgba16>::new(&[0; 8 + 5], 3, 2, 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "stride too small")]
+    fn stride_too_small() { Pixels::<Rgba16>::new(&[0; 16], 3, 2, 4); }
+
+    #[test]
+    fn clipped_blit() {
+        let red = 0xFF0000FF;
+        let green = 0x00FF00FF;
+        let src_data = [red, green, green, red].map(u32::to_be_bytes).concat();
+        let src = Pixels::<Rgba32>::new(&src_data, 2, 2, 8);
+
+        let mut dst_data = vec![PAD; 4 * 3];
+        let mut dst = PixelsMut::<Ia8>::new(&mut dst_data, 3, 3, 4);
+        dst.fill(0);
+        // only the top-right pixel of the source lands in the bottom-left corner
+        blit(&src, &mut dst, -1, 2, false);
+        blit(&src, &mut dst, 3, 0, false);
+        blit(&src, &mut dst, 0, -2, false);
+        let (ia8_red, ia8_green) = (
+            Ia8::from_rgba(red.to_be_bytes()),
+            Ia8::from_rgba(green.to_be_bytes()),
+        );
+        assert_ne!(ia8_red, ia8_green);
+        assert_eq!(dst_data, [0, 0, 0, PAD, 0, 0, 0, PAD, ia8_green, 0, 0, PAD]);
+
+        // the bottom-right corner of the destination
+        let mut dst = PixelsMut::<Ia8>::new(&mut dst_data, 3, 3, 4);
+        blit(&src, &mut dst, 2, 2, false);
+        assert_eq!(dst_data[10], ia8_red);
+        assert_eq!(dst_data[11], PAD);
+    }
+
+    #[test]
+    fn convert_dither() {
+        let gray = [0x41, 0x41, 0x41, 0xFF];
+        let src_data = [gray; 16].concat();
+        let src = Pixels::<Rgba32>::new(&src_data, 4, 4, 16);
+
+        let mut dst_data = vec![0; 4 * 4 * 2];
+        let mut dst = PixelsMut::<Rgba16>::new(&mut dst_data, 4, 4, 8);
+        convert(&src, &mut dst, false);
+        let dst = dst.as_pixels();
+        assert!(dst
+            .rows()
+            .all(|row| row.iter().all(|raw| raw == Rgba16::from_rgba(gray))));
+
+        let mut dithered_data = vec![0; 4 * 4 * 2];
+        let mut dithered = PixelsMut::<Rgba16>::new(&mut dithered_data, 4, 4, 8);
+        convert(&src, &mut dithered, true);
+        assert!(dithered_data != dst_data);
+
+        // formats with 8 bits per channel are never dithered
+        let mut ia16_data = vec![0; 4 * 4 * 2];
+        let mut ia16 = PixelsMut::<Ia16>::new(&mut ia16_data, 4, 4, 8);
+        convert(&src, &mut ia16, true);
+        assert!(ia16_data.chunks(2).all(|p| p == [0x41, 0xFF]));
+    }
+
+    #[test]
+    fn quantize_ci8_round_trip() {
+        // colors that are exact in RGBA 5551, on rows padded to 16 bytes
+        let colors: [Rgba; 3] = [
+            [0xF8, 0, 0, 0xFF],
+            [0, 0x80, 0x08, 0xFF],
+            [0x10, 0x20, 0x30, 0],
+        ];
+        let mut src_data = vec![PAD; 16 * 2 + 12];
+        let mut src = PixelsMut::<Rgba32>::new(&mut src_data, 3, 3, 16);
+        for y in 0..3 {
+            for x in 0..3 {
+                src.set(x, y, Rgba32::from_rgba(colors[(x + y) % 3]));
+            }
+        }
+        let src = Pixels::<Rgba32>::new(&src_data, 3, 3, 16);
+
+        let mut dst_data = vec![PAD; 4 * 3];
+        let mut dst = PixelsMut::<Ci8>::new(&mut dst_data, 3, 3, 4);
+        let palette = quantize(&src, &mut dst);
+        assert_eq!(palette.len(), 3);
+        for y in 0..3 {
+            for x in 0..3 {
+                let index = dst.get(x, y) as usize;
+                assert_eq!(codec::from_rgba16(palette[index]), colors[(x + y) % 3]);
+            }
+        }
+        assert!(dst_data.chunks(4).all(|row| row[3] == PAD));
+    }
+}
@@ -0,0 +1,192 @@
+//! Incremental parser for the escape codes of `rdpq_text_print`.
+//!
+//! This module only depends on `core`. It is shared with `libdragon-build`, so the parser
+//! behind `TextWriter` (and [font64](crate::font64)) can be tested on a development host.
+
+/// A piece of text produced by [EscapeParser]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// Text to lay out as-is
+    Text(&'a [u8]),
+    /// `$xx`: switch to font `xx`
+    Font(u8),
+    /// `^xx`: switch to style `xx`
+    Style(u8),
+}
+
+/// Splits text into [Token]s, even when escape sequences are split across calls to
+/// [EscapeParser::feed].
+///
+/// `$xx` and `^xx` (two hex digits) switch font and style, `$$` and `^^` produce a literal `$`
+/// or `^`. Any other sequence starting with `$` or `^` is emitted as-is, and the character that
+/// interrupted it is parsed again (so that `$g$01` is the text `$g` followed by font 1).
+#[derive(Debug, Default, Clone)]
+pub struct EscapeParser {
+    pending: [u8; 2],
+    len:     usize,
+}
+
+#[inline]
+fn hex(c: u8) -> Option<u8> { (c as char).to_digit(16).map(|d| d as u8) }
+
+impl EscapeParser {
+    /// Create a parser with no pending escape sequence
+    pub const fn new() -> Self {
+        Self {
+            pending: [0; 2],
+            len:     0,
+        }
+    }
+
+    /// Parse `bytes`, calling `emit` for each token. An incomplete escape sequence at the end is
+    /// kept until the next call.
+    pub fn feed(&mut self, bytes: &[u8], mut emit: impl FnMut(Token)) {
+        let mut start = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            let c = bytes[i];
+            if self.len == 0 {
+                if c == b'$' || c == b'^' {
+                    if start < i {
+                        emit(Token::Text(&bytes[start..i]));
+                    }
+                    self.pending[0] = c;
+                    self.len = 1;
+                    start = i + 1;
+                }
+                i += 1;
+                continue;
+            }
+
+            let lead = self.pending[0];
+            if self.len == 1 && c == lead {
+                // `$$` or `^^`
+                self.len = 0;
+                emit(Token::Text(&bytes[i..i + 1]));
+            } else if let Some(digit) = hex(c) {
+                if self.len == 1 {
+                    self.pending[1] = c;
+                    self.len = 2;
+                } else {
+                    self.len = 0;
+                    let id = (hex(self.pending[1]).unwrap_or(0) << 4) | digit;
+                    emit(if lead == b'$' {
+                        Token::Font(id)
+                    } else {
+                        Token::Style(id)
+                    });
+                }
+            } else {
+                // not an escape code: emit it as-is and parse `c` again
+                let pending = self.pending;
+                let n = core::mem::replace(&mut self.len, 0);
+                emit(Token::Text(&pending[..n]));
+                start = i;
+                continue;
+            }
+            i += 1;
+            start = i;
+        }
+        if self.len == 0 && start < bytes.len() {
+            emit(Token::Text(&bytes[start..]));
+        }
+    }
+
+    /// Emit the incomplete escape sequence, if any, as text
+    pub fn finish(&mut self, mut emit: impl FnMut(Token)) {
+        let pending = self.pending;
+        let n = core::mem::replace(&mut self.len, 0);
+        if n > 0 {
+            emit(Token::Text(&pending[..n]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[derive(Debug, PartialEq)]
+    enum Owned {
+        Text(Vec<u8>),
+        Font(u8),
+        Style(u8),
+    }
+
+    // Feed `chunks` one after the other, merging adjacent text tokens
+    fn parse(chunks: &[&str]) -> Vec<Owned> {
+        let mut out = Vec::new();
+        let mut push = |token: Token| match (token, out.last_mut()) {
+            (Token::Text(t), Some(Owned::Text(last))) => last.extend_from_slice(t),
+            (Token::Text(t), _) => out.push(Owned::Text(t.to_vec())),
+            (Token::Font(id), _) => out.push(Owned::Font(id)),
+            (Token::Style(id), _) => out.push(Owned::Style(id)),
+        };
+        let mut parser = EscapeParser::new();
+        for chunk in chunks {
+            parser.feed(chunk.as_bytes(), &mut push);
+        }
+        parser.finish(&mut push);
+        out
+    }
+
+    fn text(s: &str) -> Owned { Owned::Text(s.as_bytes().to_vec()) }
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(parse(&["hello"]), [text("hello")]);
+        assert_eq!(parse(&["", ""]), []);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            parse(&["Score: ^01 12 ^00$0A!"]),
+            [
+                text("Score: "),
+                Owned::Style(1),
+                text(" 12 "),
+                Owned::Style(0),
+                Owned::Font(0x0A),
+                text("!")
+            ]
+        );
+        assert_eq!(parse(&["$fF"]), [Owned::Font(0xFF)]);
+        assert_eq!(parse(&["100$$ ^^"]), [text("100$ ^")]);
+    }
+
+    #[test]
+    fn invalid_escapes_do_not_swallow_the_next_one() {
+        assert_eq!(parse(&["$g$01x"]), [text("$g"), Owned::Font(1), text("x")]);
+        assert_eq!(parse(&["$1^02"]), [text("$1"), Owned::Style(2)]);
+        assert_eq!(parse(&["$^03"]), [text("$"), Owned::Style(3)]);
+        assert_eq!(parse(&["^$$"]), [text("^$")]);
+        assert_eq!(parse(&["a$"]), [text("a$")]);
+        assert_eq!(parse(&["a^0"]), [text("a^0")]);
+    }
+
+    #[test]
+    fn split_anywhere() {
+        let input = "A$01b^^c$g$02^1z^1F$$";
+        let expected = parse(&[input]);
+        assert_eq!(
+            expected,
+            [
+                text("A"),
+                Owned::Font(1),
+                text("b^c$g"),
+                Owned::Font(2),
+                text("^1z"),
+                Owned::Style(0x1F),
+                text("$")
+            ]
+        );
+        for a in 0..=input.len() {
+            for b in a..=input.len() {
+                let chunks = [&input[..a], &input[a..b], &input[b..]];
+                assert_eq!(parse(&chunks), expected, "split at {a} and {b}");
+            }
+        }
+    }
+}
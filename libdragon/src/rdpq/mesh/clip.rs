@@ -0,0 +1,354 @@
+//! The CPU side of [mesh](super): transform, near-plane clipping, backface culling and viewport
+//! mapping.
+//!
+//! This module only depends on `core`, `alloc` and [math](crate::math). It is shared with
+//! `libdragon-build`, so the clipping tests can be run on a development host.
+
+use alloc::vec::Vec;
+
+use crate::math::{Mat4, Vec2, Vec3, Vec4};
+
+/// A vertex in object space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshVertex {
+    pub position: Vec3,
+    /// RGBA color, each component from 0 to 1. Used if
+    /// [MeshParms::shade](super::MeshParms::shade) is set.
+    pub color:    [f32; 4],
+    /// Texture coordinates in texels. Used if
+    /// [MeshParms::texture](super::MeshParms::texture) is set.
+    pub texcoord: Vec2,
+}
+
+impl Default for MeshVertex {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            color:    [1.0; 4],
+            texcoord: Vec2::ZERO,
+        }
+    }
+}
+
+/// Which triangles are skipped depending on their orientation on screen. Like in GL, front
+/// faces are the triangles whose vertices are counter-clockwise with the Y axis pointing up.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Cull {
+    /// Draw all triangles
+    None,
+    /// Skip the back faces
+    #[default]
+    Back,
+    /// Skip the front faces
+    Front,
+}
+
+/// A vertex in screen space, as passed to [triangle](crate::rdpq::triangle): X, Y, Z (0 to 1),
+/// R, G, B, A, S, T and 1/W. The components not enabled in [MeshParms](super::MeshParms) are
+/// ignored by the RDP.
+pub type ScreenVertex = [f32; 10];
+
+/// Counters of what happened to the triangles of a mesh
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MeshStats {
+    /// Triangles of the mesh
+    pub triangles: usize,
+    /// Triangles skipped because they are entirely outside the frustum
+    pub outside:   usize,
+    /// Triangles cut by the near plane
+    pub clipped:   usize,
+    /// Triangles skipped by backface culling, or because they have no area
+    pub culled:    usize,
+    /// Triangles drawn, including the extra ones created by clipping
+    pub drawn:     usize,
+}
+
+/// Transform the `triangles` (indices of `vertices`) with `transform`, clip them against the near
+/// plane, cull them according to `cull` and map them to `viewport`, calling `emit` with the
+/// screen-space vertices of each triangle to draw.
+///
+/// Panics if an index is out of bounds.
+pub fn process<I, F>(
+    transform: &Mat4,
+    viewport: &[f32; 4],
+    cull: Cull,
+    vertices: &[MeshVertex],
+    triangles: I,
+    mut emit: F,
+) -> MeshStats
+where
+    I: IntoIterator<Item = [usize; 3]>,
+    F: FnMut(&ScreenVertex, &ScreenVertex, &ScreenVertex),
+{
+    let clip: Vec<ClipVertex> = vertices
+        .iter()
+        .map(|v| ClipVertex {
+            position: *transform * v.position.extend(1.0),
+            color:    v.color,
+            texcoord: v.texcoord,
+        })
+        .collect();
+
+    let mut stats = MeshStats::default();
+    for [a, b, c] in triangles {
+        stats.triangles += 1;
+        let triangle = [clip[a], clip[b], clip[c]];
+        let outcodes = triangle.map(|v| v.outcode());
+        if outcodes[0] & outcodes[1] & outcodes[2] != 0 {
+            stats.outside += 1;
+            continue;
+        }
+
+        let mut polygon = [triangle[0]; 4];
+        let count = if (outcodes[0] | outcodes[1] | outcodes[2]) & NEAR != 0 {
+            stats.clipped += 1;
+            clip_near(&triangle, &mut polygon)
+        } else {
+            polygon[..3].copy_from_slice(&triangle);
+            3
+        };
+
+        let screen = polygon.map(|v| v.to_screen(viewport));
+        for i in 1..count.saturating_sub(1) {
+            let (v1, v2, v3) = (&screen[0], &screen[i], &screen[i + 1]);
+            // positive when counter-clockwise with Y pointing down, i.e. a back face
+            let area = (v2[0] - v1[0]) * (v3[1] - v1[1]) - (v3[0] - v1[0]) * (v2[1] - v1[1]);
+            let skip = match cull {
+                Cull::None => area == 0.0,
+                Cull::Back => area >= 0.0,
+                Cull::Front => area <= 0.0,
+            };
+            if skip {
+                stats.culled += 1;
+                continue;
+            }
+            stats.drawn += 1;
+            emit(v1, v2, v3);
+        }
+    }
+    stats
+}
+
+// Outcode bits: the clip planes a vertex is outside of
+const NEAR: u8 = 1 << 4;
+
+#[derive(Debug, Copy, Clone)]
+struct ClipVertex {
+    position: Vec4,
+    color:    [f32; 4],
+    texcoord: Vec2,
+}
+
+impl ClipVertex {
+    fn outcode(&self) -> u8 {
+        let Vec4 { x, y, z, w } = self.position;
+        (x < -w) as u8
+            | ((x > w) as u8) << 1
+            | ((y < -w) as u8) << 2
+            | ((y > w) as u8) << 3
+            | ((z < -w) as u8) << 4
+            | ((z > w) as u8) << 5
+    }
+
+    // signed distance to the near plane, positive inside
+    fn near_distance(&self) -> f32 { self.position.z + self.position.w }
+
+    fn lerp(&self, rhs: &Self, t: f32) -> Self {
+        let mut color = self.color;
+        for (c, r) in color.iter_mut().zip(rhs.color) {
+            *c += (r - *c) * t;
+        }
+        Self {
+            position: self.position.lerp(rhs.position, t),
+            color,
+            texcoord: self.texcoord.lerp(rhs.texcoord, t),
+        }
+    }
+
+    fn to_screen(self, viewport: &[f32; 4]) -> ScreenVertex {
+        let inv_w = 1.0 / self.position.w;
+        let ndc = self.position.truncate() * inv_w;
+        let [x, y, width, height] = *viewport;
+        let [r, g, b, a] = self.color;
+        [
+            x + (ndc.x + 1.0) * 0.5 * width,
+            y + (1.0 - ndc.y) * 0.5 * height,
+            ((ndc.z + 1.0) * 0.5).clamp(0.0, 1.0),
+            r,
+            g,
+            b,
+            a,
+            self.texcoord.x,
+            self.texcoord.y,
+            inv_w,
+        ]
+    }
+}
+
+// Sutherland-Hodgman clipping of a triangle against the near plane, which gives a triangle or
+// a quad. Returns the number of vertices written to `out`.
+fn clip_near(triangle: &[ClipVertex; 3], out: &mut [ClipVertex; 4]) -> usize {
+    let mut count = 0;
+    for i in 0..3 {
+        let (current, next) = (&triangle[i], &triangle[(i + 1) % 3]);
+        let (dc, dn) = (current.near_distance(), next.near_distance());
+        if dc >= 0.0 {
+            out[count] = *current;
+            count += 1;
+        }
+        if (dc >= 0.0) != (dn >= 0.0) {
+            out[count] = current.lerp(next, dc / (dc - dn));
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWPORT: [f32; 4] = [0.0, 0.0, 320.0, 240.0];
+
+    fn vertex(x: f32, y: f32, z: f32) -> MeshVertex {
+        MeshVertex {
+            position: Vec3::new(x, y, z),
+            ..Default::default()
+        }
+    }
+
+    // Process one triangle with an identity transform, so that its vertices are in clip space
+    // with w = 1, returning the stats and the emitted triangles
+    fn run(triangle: [MeshVertex; 3], cull: Cull) -> (MeshStats, Vec<[ScreenVertex; 3]>) {
+        let mut emitted = Vec::new();
+        let stats = process(
+            &Mat4::IDENTITY,
+            &VIEWPORT,
+            cull,
+            &triangle,
+            [[0, 1, 2]],
+            |a, b, c| emitted.push([*a, *b, *c]),
+        );
+        (stats, emitted)
+    }
+
+    #[test]
+    fn near_clipping() {
+        // counter-clockwise with Y up, z = -2 is behind the near plane (z = -w)
+        let (a, b, c) = (
+            vertex(-0.5, -0.5, 0.0),
+            vertex(0.5, -0.5, 0.0),
+            vertex(0.0, 0.5, 0.0),
+        );
+        let behind = |v: MeshVertex| MeshVertex {
+            position: Vec3::new(v.position.x, v.position.y, -2.0),
+            ..v
+        };
+
+        let (stats, emitted) = run([a, b, c], Cull::None);
+        assert_eq!((stats.clipped, stats.drawn), (0, 1));
+        assert_eq!(emitted.len(), 1);
+
+        let (stats, emitted) = run([a, b, behind(c)], Cull::None);
+        assert_eq!((stats.clipped, stats.drawn), (1, 2));
+        assert_eq!(emitted.len(), 2);
+        // the new vertices are on the near plane, at depth 0
+        let depths = emitted.iter().flatten().map(|v| v[2]);
+        assert!(depths.clone().all(|z| (0.0..=0.5).contains(&z)));
+        assert!(depths.filter(|&z| z == 0.0).count() >= 2);
+
+        let (stats, emitted) = run([a, behind(b), behind(c)], Cull::None);
+        assert_eq!((stats.clipped, stats.drawn), (1, 1));
+        assert_eq!(emitted.len(), 1);
+
+        let (stats, emitted) = run([behind(a), behind(b), behind(c)], Cull::None);
+        assert_eq!((stats.outside, stats.clipped, stats.drawn), (1, 0, 0));
+        assert!(emitted.is_empty());
+    }
+
+    #[test]
+    fn outside() {
+        // entirely to the right of the frustum
+        let (stats, _) = run(
+            [
+                vertex(2.0, 0.0, 0.0),
+                vertex(3.0, 0.0, 0.0),
+                vertex(2.0, 1.0, 0.0),
+            ],
+            Cull::None,
+        );
+        assert_eq!((stats.triangles, stats.outside, stats.drawn), (1, 1, 0));
+
+        // partially outside: scissored by the RDP, not skipped
+        let (stats, _) = run(
+            [
+                vertex(0.0, 0.0, 0.0),
+                vertex(3.0, 0.0, 0.0),
+                vertex(0.0, 1.0, 0.0),
+            ],
+            Cull::None,
+        );
+        assert_eq!((stats.outside, stats.drawn), (0, 1));
+    }
+
+    #[test]
+    fn backface_culling() {
+        let front = [
+            vertex(-0.5, -0.5, 0.0),
+            vertex(0.5, -0.5, 0.0),
+            vertex(0.0, 0.5, 0.0),
+        ];
+        let back = [front[0], front[2], front[1]];
+
+        let drawn = |triangle, cull| {
+            let (stats, emitted) = run(triangle, cull);
+            assert_eq!(stats.culled + stats.drawn, 1);
+            assert_eq!(emitted.len(), stats.drawn);
+            stats.drawn == 1
+        };
+        assert!(drawn(front, Cull::Back));
+        assert!(!drawn(back, Cull::Back));
+        assert!(!drawn(front, Cull::Front));
+        assert!(drawn(back, Cull::Front));
+        assert!(drawn(front, Cull::None));
+        assert!(drawn(back, Cull::None));
+
+        // degenerate triangles are always culled
+        let line = [front[0], front[1], vertex(1.5, -0.5, 0.0)];
+        assert!(!drawn(line, Cull::None));
+    }
+
+    #[test]
+    fn viewport_mapping() {
+        let mut triangle = [
+            vertex(-1.0, 1.0, -1.0),
+            vertex(-1.0, -1.0, 1.0),
+            vertex(1.0, -1.0, 0.0),
+        ];
+        triangle[0].color = [1.0, 0.5, 0.25, 0.0];
+        triangle[2].texcoord = Vec2::new(32.0, 16.0);
+        let mut emitted = Vec::new();
+        let viewport = [10.0, 20.0, 320.0, 240.0];
+        // scale the clip space by 2, so that w = 2 and the NDC are the same
+        let transform = Mat4::from_cols(
+            Vec4::new(2.0, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, 2.0, 0.0),
+            Vec4::new(0.0, 0.0, 0.0, 2.0),
+        );
+        process(
+            &transform,
+            &viewport,
+            Cull::None,
+            &triangle,
+            [[0, 1, 2]],
+            |a, b, c| emitted.push([*a, *b, *c]),
+        );
+        assert_eq!(emitted.len(), 1);
+        let [a, b, c] = emitted[0];
+        assert_eq!(a, [10.0, 20.0, 0.0, 1.0, 0.5, 0.25, 0.0, 0.0, 0.0, 0.5]);
+        assert_eq!(b[..3], [10.0, 260.0, 1.0]);
+        assert_eq!(c[..3], [330.0, 260.0, 0.5]);
+        assert_eq!(c[7..], [32.0, 16.0, 0.5]);
+    }
+}
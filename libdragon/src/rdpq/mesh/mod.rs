@@ -0,0 +1,128 @@
+//! Lightweight 3D rendering with rdpq, without the GL state machine.
+//!
+//! [draw] and [draw_indexed] take vertices in object space and a matrix to clip space, and do
+//! on the CPU what GL does on the RSP: transform, near-plane clipping, backface culling and
+//! viewport mapping. Each resulting triangle is drawn with [triangle] using the [TriFmt] of
+//! [MeshParms::tri_fmt]. Render modes, textures and combiners are set up with the usual rdpq
+//! functions beforehand, e.g. [mode_zbuf](super::mode_zbuf) when [MeshParms::z] is enabled.
+//!
+//! ```rust
+//! let projection = math::Mat4::perspective(1.0, 320.0 / 240.0, 1.0, 100.0);
+//! let view = math::Mat4::look_at(eye, math::Vec3::ZERO, math::Vec3::Y);
+//! let viewport = [0.0, 0.0, 320.0, 240.0];
+//! let mut parms = rdpq::mesh::MeshParms::new(projection * view * model, viewport);
+//! parms.shade = true;
+//! rdpq::set_mode_standard();
+//! rdpq::mode_combiner(rdpq::consts::COMBINER_SHADE);
+//! rdpq::mesh::draw_indexed(&parms, &VERTICES, &INDICES);
+//! ```
+//!
+//! The triangles are not clipped against the other planes of the frustum: the RDP scissors
+//! them. Triangles entirely outside the frustum are skipped, but very large triangles crossing
+//! the screen edges can still exceed the coordinate range of the RDP.
+
+use super::{triangle, Tile, TriFmt};
+use crate::*;
+use math::Mat4;
+
+mod clip;
+pub use clip::{Cull, MeshStats, MeshVertex, ScreenVertex};
+
+/// Parameters of [draw] and [draw_indexed]
+#[derive(Debug, Copy, Clone)]
+pub struct MeshParms {
+    /// Transform from object space to clip space (projection * view * model)
+    pub transform:   Mat4,
+    /// Screen rectangle the clip space is mapped to: x, y, width, height
+    pub viewport:    [f32; 4],
+    /// Backface culling (default: [Cull::Back])
+    pub cull:        Cull,
+    /// Pass the depth to the RDP, for z-buffering (default: false)
+    pub z:           bool,
+    /// Pass the vertex colors to the RDP (default: false)
+    pub shade:       bool,
+    /// Use the color of the first vertex for the whole triangle (default: false)
+    pub shade_flat:  bool,
+    /// Pass the texture coordinates to the RDP, with this tile (default: `None`)
+    pub texture:     Option<Tile>,
+    /// Number of mipmaps of the texture (default: 0)
+    pub tex_mipmaps: i32,
+}
+
+impl MeshParms {
+    /// Parameters drawing solid-filled triangles with backface culling
+    pub fn new(transform: Mat4, viewport: [f32; 4]) -> Self {
+        Self {
+            transform,
+            viewport,
+            cull: Cull::Back,
+            z: false,
+            shade: false,
+            shade_flat: false,
+            texture: None,
+            tex_mipmaps: 0,
+        }
+    }
+
+    /// Format of the [ScreenVertex]es passed to [triangle]
+    pub fn tri_fmt(&self) -> TriFmt {
+        let offset = |enabled: bool, offset: i32| if enabled { offset } else { -1 };
+        TriFmt {
+            pos_offset:   0,
+            z_offset:     offset(self.z, 2),
+            shade_offset: offset(self.shade, 3),
+            shade_flat:   self.shade_flat,
+            tex_offset:   offset(self.texture.is_some(), 7),
+            tex_tile:     self.texture.unwrap_or_default(),
+            tex_mipmaps:  self.tex_mipmaps,
+        }
+    }
+}
+
+/// Draw a list of triangles, 3 vertices each, and return what happened to them.
+///
+/// Rust: allocates a buffer for the transformed vertices.
+pub fn draw(parms: &MeshParms, vertices: &[MeshVertex]) -> MeshStats {
+    let triangles = (0..vertices.len() / 3).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]);
+    draw_with(parms, vertices, triangles)
+}
+
+/// Draw a list of indexed triangles, 3 indices each, and return what happened to them.
+///
+/// Rust: allocates a buffer for the transformed vertices. Panics if an index is out of bounds.
+pub fn draw_indexed(parms: &MeshParms, vertices: &[MeshVertex], indices: &[u16]) -> MeshStats {
+    let triangles = indices
+        .chunks_exact(3)
+        .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize]);
+    draw_with(parms, vertices, triangles)
+}
+
+fn draw_with<I>(parms: &MeshParms, vertices: &[MeshVertex], triangles: I) -> MeshStats
+where
+    I: IntoIterator<Item = [usize; 3]>,
+{
+    let fmt = parms.tri_fmt();
+    process(parms, vertices, triangles, |v1, v2, v3| {
+        triangle(&fmt, v1, v2, v3)
+    })
+}
+
+/// Transform, clip and cull the `triangles` (indices of `vertices`), calling `emit` with the
+/// screen-space vertices of each triangle to draw instead of drawing it. [draw] and
+/// [draw_indexed] pass them to [triangle].
+///
+/// Rust: allocates a buffer for the transformed vertices. Panics if an index is out of bounds.
+pub fn process<I, F>(parms: &MeshParms, vertices: &[MeshVertex], triangles: I, emit: F) -> MeshStats
+where
+    I: IntoIterator<Item = [usize; 3]>,
+    F: FnMut(&ScreenVertex, &ScreenVertex, &ScreenVertex),
+{
+    clip::process(
+        &parms.transform,
+        &parms.viewport,
+        parms.cull,
+        vertices,
+        triangles,
+        emit,
+    )
+}
@@ -1,10 +1,18 @@
-use core::cmp::Ordering;
+use core::{
+    cmp::Ordering,
+    sync::atomic::{AtomicBool, Ordering as AtomicOrdering},
+};
 
 use crate::*;
 
+use fixed::{Fx10_2, Fx1_11, S10_5, S16_16, S5_10};
 use sprite::Sprite;
 use surface::Surface;
 
+/// 3D meshes transformed, clipped and culled on the CPU, drawn with [triangle]
+pub(crate) mod escape;
+pub mod mesh;
+
 // rdpq.h
 pub const OVL_ID: u32 = libdragon_sys::RDPQ_OVL_ID;
 
@@ -144,6 +152,33 @@ pub struct TileSTParms {
     shift:  i8,
 }
 
+impl TileParms {
+    /// Parameters using `palette` (for CI4 textures) and the given S and T mapping
+    #[inline]
+    pub fn new(palette: u8, s: TileSTParms, t: TileSTParms) -> Self { Self { palette, s, t } }
+}
+
+impl TileSTParms {
+    /// Mapping parameters of one direction of a tile.
+    ///
+    /// `mask` is the log2 of the number of texels after which the coordinate wraps (0 to 15,
+    /// 0 disables wrapping) and `shift` the log2 of the factor dividing the coordinate (-5 to
+    /// 10, negative values multiply it).
+    ///
+    /// Rust: panics if `mask` or `shift` is out of range.
+    #[inline]
+    pub fn new(clamp: bool, mirror: bool, mask: u8, shift: i8) -> Self {
+        assert!(mask <= 15, "tile mask out of range");
+        assert!((-5..=10).contains(&shift), "tile shift out of range");
+        Self {
+            clamp,
+            mirror,
+            mask,
+            shift,
+        }
+    }
+}
+
 /// Tile descriptor internally used by some RDPQ functions. Avoid using if possible
 pub const TILE_INTERNAL: Tile = Tile(7);
 
@@ -265,23 +300,42 @@ pub fn set_prim_depth_raw(prim_z: u16, prim_dz: i16) {
     }
 }
 
+// Starts are 10.2 coordinates, the exclusive ends can reach 1024 (4096 in 10.2): the RDP
+// receives the inclusive end, 1023.75 at most
+#[inline]
+fn tile_extents(s0: u16, t0: u16, s1: u16, t1: u16) -> [u16; 4] {
+    assert!(
+        s0 < 1024 && t0 < 1024 && s1 <= 1024 && t1 <= 1024,
+        "texture coordinates out of range"
+    );
+    [s0 * 4, t0 * 4, s1 * 4, t1 * 4]
+}
+
 /// Load a portion of a texture into TMEM (RDP command: LOAD_TILE)
 ///
+/// Rust: panics if `s0` or `t0` is not smaller than 1024, or if `s1` or `t1` (the exclusive
+/// end) is larger than 1024.
+///
 /// See [`rdpq_load_tile`](libdragon_sys::rdpq_load_tile) for details.
 #[inline]
 pub fn load_tile(tile: Tile, s0: u16, t0: u16, s1: u16, t1: u16) {
-    assert!(
-        s0 < 1024 && t0 < 1024 && s1 < 1024 && t1 < 1024,
-        "texture coordinates must be smaller than 1024"
-    );
-    load_tile_fx(tile, s0 * 4, t0 * 4, s1 * 4, t1 * 4);
+    let [s0, t0, s1, t1] = tile_extents(s0, t0, s1, t1);
+    load_tile_raw(tile, s0, t0, s1, t1);
 }
 
 /// Load a portion of a texture into TMEM -- fixed point version (RDP command:: LOAD_TILE)
 ///
+/// Rust: `s1` and `t1` are exclusive like in C, so this version cannot load up to coordinate
+/// 1024; use [load_tile] for that.
+///
 /// See [`rdpq_load_tile_fx`](libdragon_sys::rdpq_load_tile_fx) for details.
 #[inline]
-pub fn load_tile_fx(tile: Tile, s0: u16, t0: u16, s1: u16, t1: u16) {
+pub fn load_tile_fx(tile: Tile, s0: Fx10_2, t0: Fx10_2, s1: Fx10_2, t1: Fx10_2) {
+    load_tile_raw(tile, s0.to_bits(), t0.to_bits(), s1.to_bits(), t1.to_bits());
+}
+
+#[inline]
+fn load_tile_raw(tile: Tile, s0: u16, t0: u16, s1: u16, t1: u16) {
     extern "C" {
         fn rdpq_load_tile_fx(
             tile: libdragon_sys::rdpq_tile_t,
@@ -315,17 +369,29 @@ pub fn load_tlut_raw(tile: Tile, color_idx: u8, num_colors: u8) {
 
 /// Configure the extents of a tile descriptor (RDP command: SET_TILE_SIZE)
 ///
+/// Rust: panics if `s0` or `t0` is not smaller than 1024, or if `s1` or `t1` (the exclusive
+/// end) is larger than 1024.
+///
 /// See [`rdpq_set_tile_size`](libdragon_sys::rdpq_set_tile_size) for details.
 #[inline]
 pub fn set_tile_size(tile: Tile, s0: u16, t0: u16, s1: u16, t1: u16) {
-    set_tile_size_fx(tile, s0 * 4, t0 * 4, s1 * 4, t1 * 4);
+    let [s0, t0, s1, t1] = tile_extents(s0, t0, s1, t1);
+    set_tile_size_raw(tile, s0, t0, s1, t1);
 }
 
 /// Configure the extents of a tile descriptor -- fixed point version (RDP command: SET_TILE_SIZE)
 ///
+/// Rust: `s1` and `t1` are exclusive like in C, so this version cannot cover coordinate 1024;
+/// use [set_tile_size] for that.
+///
 /// See [`rdpq_set_tile_size_fx`](libdragon_sys::rdpq_set_tile_size_fx) for details.
 #[inline]
-pub fn set_tile_size_fx(tile: Tile, s0: u16, t0: u16, s1: u16, t1: u16) {
+pub fn set_tile_size_fx(tile: Tile, s0: Fx10_2, t0: Fx10_2, s1: Fx10_2, t1: Fx10_2) {
+    set_tile_size_raw(tile, s0.to_bits(), t0.to_bits(), s1.to_bits(), t1.to_bits());
+}
+
+#[inline]
+fn set_tile_size_raw(tile: Tile, s0: u16, t0: u16, s1: u16, t1: u16) {
     extern "C" {
         fn rdpq_set_tile_size_fx(
             tile: libdragon_sys::rdpq_tile_t,
@@ -342,9 +408,12 @@ pub fn set_tile_size_fx(tile: Tile, s0: u16, t0: u16, s1: u16, t1: u16) {
 
 /// Low level function to load a texture image into TMEM in a single memory transfer
 ///
+/// `dxt` is the increment of T for each 64-bit word of a line, i.e. the reciprocal of the
+/// number of words per line.
+///
 /// See [`rdpq_load_block_fx`](libdragon_sys::rdpq_load_block_fx) for details.
 #[inline]
-pub fn load_block_fx(tile: Tile, s0: u16, t0: u16, num_texels: u16, dxt: u16) {
+pub fn load_block_fx(tile: Tile, s0: Fx10_2, t0: Fx10_2, num_texels: u16, dxt: Fx1_11) {
     extern "C" {
         fn rdpq_load_block_fx(
             tile: libdragon_sys::rdpq_tile_t,
@@ -357,10 +426,10 @@ pub fn load_block_fx(tile: Tile, s0: u16, t0: u16, num_texels: u16, dxt: u16) {
     unsafe {
         rdpq_load_block_fx(
             tile.0 as libdragon_sys::rdpq_tile_t,
-            s0,
-            t0,
+            s0.to_bits(),
+            t0.to_bits(),
             num_texels,
-            dxt,
+            dxt.to_bits(),
         );
     }
 }
@@ -883,11 +952,11 @@ pub fn write(num_rdp_commands: Option<usize>, ovl_id: u32, cmd_id: u32, args: Ve
 
 /// Attach the RDP to a color surface (and optionally a Z buffer)
 ///
-/// See [`rdpq_attach`][(libdragon_sys::rdpq_attach) for details.
+/// See [`rdpq_attach`](libdragon_sys::rdpq_attach) for details.
 pub fn attach(surf_color: &Surface, surf_depth: Option<&Surface>) {
     let depth_null_surface = Surface::from_ptr(::core::ptr::null_mut());
     unsafe {
-        libdragon_sys::rdpq_attach_clear(
+        libdragon_sys::rdpq_attach(
             surf_color.ptr,
             surf_depth.unwrap_or(&depth_null_surface).ptr,
         );
@@ -920,6 +989,9 @@ pub fn clear(color: graphics::Color) {
     }
 }
 
+/// The farthest Z value, which [attach_clear] clears the Z buffer to
+pub const ZBUF_MAX: u16 = 0xFFFC;
+
 /// Clear the current Z buffer to the given value.
 ///
 /// See [`rdpq_clear_z`](libdragon_sys::rdpq_clear_z) for details.
@@ -2629,6 +2701,7 @@ impl Paragraph {
     ///
     /// See [`rdpq_paragraph_build`](libdragon_sys::rdpq_paragraph_build) for details.
     pub fn build(parms: TextParms, initial_font_id: u8, text: &str) -> (Paragraph, usize) {
+        assert_no_text_writer();
         let parms: libdragon_sys::rdpq_textparms_t = parms.into();
         let ctext = CString::new(text).unwrap();
         let mut len = ctext.to_bytes().len(); // does not contain any trailing \0
@@ -2659,7 +2732,7 @@ impl Paragraph {
     pub fn bbox(&self) -> (f32, f32, f32, f32) {
         unsafe {
             let r: &libdragon_sys::rdpq_paragraph_t = &*self.c;
-            (r.bbox.x0, r.bbox.y0, r.bbox.x0, r.bbox.y1)
+            (r.bbox.x0, r.bbox.y0, r.bbox.x1, r.bbox.y1)
         }
     }
 
@@ -2673,6 +2746,16 @@ impl Paragraph {
     pub fn x0(&self) -> f32 { unsafe { (*self.c).x0 } }
     /// Alignment offset of the text (Y coord)
     pub fn y0(&self) -> f32 { unsafe { (*self.c).y0 } }
+    /// Size and line count of the laid out text. See [TextMetrics].
+    pub fn metrics(&self) -> TextMetrics {
+        let (x0, y0, x1, y1) = self.bbox();
+        TextMetrics {
+            width:  x1 - x0,
+            height: y1 - y0,
+            nlines: self.nlines(),
+            nchars: self.nchars(),
+        }
+    }
     /// Array of chars
     pub fn chars(&self) -> &[ParagraphChar] {
         // As long as ParagraphChar wraps only rdpq_paragraph_char_t, this cast should be safe
@@ -2718,6 +2801,7 @@ impl<'a> ParagraphBuilder<'a> {
         initial_font_id: u8,
         layout: Option<&Paragraph>,
     ) -> ParagraphBuilder<'_> {
+        assert_no_text_writer();
         // text parms have to persist throughout the entire builder
         let parms: libdragon_sys::rdpq_textparms_t = parms.into();
         let pinned = Box::pin(parms);
@@ -2992,6 +3076,9 @@ pub fn texture_rectangle_scaled<T>(
 
 /// Draw a textured rectangle with scaling -- raw version (RDP command: TEXTURE_RECTANGLE)
 ///
+/// `s0`/`t0` are the texture coordinates of the top-left corner, and `dsdx`/`dtdy` the number
+/// of texels to advance for each pixel.
+///
 /// See [`rdpq_texture_rectangle_raw`](libdragon_sys::rdpq_texture_rectangle_raw) and `rdpq_rect.h` for details.
 #[inline]
 #[allow(clippy::too_many_arguments)]
@@ -3001,10 +3088,10 @@ pub fn texture_rectangle_raw<T>(
     y0: T,
     x1: T,
     y1: T,
-    s0: i32,
-    t0: i32,
-    dsdx: T,
-    dtdy: T,
+    s0: S10_5,
+    t0: S10_5,
+    dsdx: S5_10,
+    dtdy: S5_10,
 ) where
     T: Into<i32> + From<i32> + core::ops::Mul<Output = T> + Copy,
 {
@@ -3012,9 +3099,17 @@ pub fn texture_rectangle_raw<T>(
     let y0 = Into::<i32>::into(y0 * From::<i32>::from(4));
     let x1 = Into::<i32>::into(x1 * From::<i32>::from(4));
     let y1 = Into::<i32>::into(y1 * From::<i32>::from(4));
-    let dsdx = Into::<i32>::into(dsdx * From::<i32>::from(1024));
-    let dtdy = Into::<i32>::into(dtdy * From::<i32>::from(1024));
-    __rdpq_texture_rectangle_raw_fx(tile, x0, y0, x1, y1, s0 * 32, t0 * 32, dsdx, dtdy);
+    __rdpq_texture_rectangle_raw_fx(
+        tile,
+        x0,
+        y0,
+        x1,
+        y1,
+        s0.to_bits() as i32,
+        t0.to_bits() as i32,
+        dsdx.to_bits() as i32,
+        dtdy.to_bits() as i32,
+    );
 }
 
 /// Draw a textured flipped rectangle (RDP command: TEXTURE_RECTANGLE_FLIP)
@@ -3028,10 +3123,10 @@ pub fn texture_rectangle_flip_raw<T>(
     y0: T,
     x1: T,
     y1: T,
-    s: i32,
-    t: i32,
-    dsdx: T,
-    dtdy: T,
+    s: S10_5,
+    t: S10_5,
+    dsdy: S5_10,
+    dtdx: S5_10,
 ) where
     T: Into<i32> + From<i32> + core::ops::Mul<Output = T> + Copy,
 {
@@ -3039,9 +3134,54 @@ pub fn texture_rectangle_flip_raw<T>(
     let y0 = Into::<i32>::into(y0 * From::<i32>::from(4));
     let x1 = Into::<i32>::into(x1 * From::<i32>::from(4));
     let y1 = Into::<i32>::into(y1 * From::<i32>::from(4));
-    let dsdx = Into::<i32>::into(dsdx * From::<i32>::from(1024));
-    let dtdy = Into::<i32>::into(dtdy * From::<i32>::from(1024));
-    __rdpq_texture_rectangle_flip_raw_fx(tile, x0, y0, x1, y1, s * 32, t * 32, dsdx, dtdy);
+    __rdpq_texture_rectangle_flip_raw_fx(
+        tile,
+        x0,
+        y0,
+        x1,
+        y1,
+        s.to_bits() as i32,
+        t.to_bits() as i32,
+        dsdy.to_bits() as i32,
+        dtdx.to_bits() as i32,
+    );
+}
+
+/// A rectangle in screen coordinates, for [draw_rects]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Rect {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+}
+
+impl Rect {
+    /// Create a rectangle from its top-left and bottom-right (exclusive) corners
+    #[inline]
+    pub const fn new(x0: f32, y0: f32, x1: f32, y1: f32) -> Self { Self { x0, y0, x1, y1 } }
+
+    /// Create a rectangle from its top-left corner and its size
+    #[inline]
+    pub fn with_size(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self::new(x, y, x + width, y + height)
+    }
+}
+
+/// Draw a batch of filled rectangles (RDP command: FILL_RECTANGLE), like calling
+/// [fill_rectangle] for each of them. Coordinates are rounded down to a quarter of a pixel.
+///
+/// Rust: the commands are written directly, without going through the generic conversions of
+/// [fill_rectangle].
+pub fn draw_rects(rects: &[Rect]) {
+    for rect in rects {
+        __rdpq_fill_rectangle_fx(
+            (rect.x0 * 4.0) as i32,
+            (rect.y0 * 4.0) as i32,
+            (rect.x1 * 4.0) as i32,
+            (rect.y1 * 4.0) as i32,
+        );
+    }
 }
 
 // rdpq_sprite.h
@@ -3110,6 +3250,18 @@ pub struct TexParmsST {
     pub mirror:    bool,
 }
 
+impl TexParmsST {
+    /// Set the translation from a 10.5 fixed point value, the precision the RDP uses for
+    /// texture coordinates, so that the translation is not rounded when it is applied
+    #[inline]
+    pub fn set_translate_fx(&mut self, translate: S10_5) { self.translate = translate.to_f32(); }
+
+    /// The translation rounded to the 10.5 fixed point precision of the RDP, or `None` if it
+    /// does not fit
+    #[inline]
+    pub fn translate_fx(&self) -> Option<S10_5> { S10_5::checked_from_f32(self.translate) }
+}
+
 impl From<TexParms> for libdragon_sys::rdpq_texparms_t {
     fn from(v: TexParms) -> Self {
         assert!(::core::mem::size_of::<TexParms>() == ::core::mem::size_of::<Self>());
@@ -3187,17 +3339,25 @@ pub fn tex_upload_sub(
 
 /// Load one or more palettes into TMEM
 ///
+/// `tlut` must be 8-byte aligned and written back from the CPU cache, since the RDP reads it
+/// directly from RDRAM. [palette::Palette::upload] takes care of both.
+///
+/// Rust: panics if `tlut` holds less than `num_colors` entries or if the upload would overflow
+/// the 256 TMEM palette entries.
+///
 /// See [`rdpq_tex_upload_tlut`](libdragon_sys::rdpq_tex_upload_tlut) for details.
 #[inline]
-pub unsafe fn tex_upload_tlut(tlut: TlutPalette, color_idx: i32, num_colors: i32) {
+pub fn tex_upload_tlut(tlut: &[u16], color_idx: i32, num_colors: i32) {
+    assert!(tlut.len() >= num_colors as usize, "palette too small");
+    assert!(
+        color_idx >= 0 && color_idx + num_colors <= 256,
+        "palette out of TMEM bounds"
+    );
     unsafe {
-        libdragon_sys::rdpq_tex_upload_tlut(tlut, color_idx, num_colors);
+        libdragon_sys::rdpq_tex_upload_tlut(tlut.as_ptr() as *mut u16, color_idx, num_colors);
     }
 }
 
-/// TODO Temporary opaque type to cover the palettes returned by `sprite_get_palette`
-pub type TlutPalette<'a> = *mut u16;
-
 /// Reuse a portion of the previously uploaded texture to TMEM
 ///
 /// See [`rdpq_tex_reuse_sub`](libdragon_sys::rdpq_tex_reuse_sub) for details.
@@ -3312,6 +3472,16 @@ pub struct BlitParms {
     pub ny:        i32,
 }
 
+impl BlitParms {
+    /// Set the scale factors from 16.16 fixed point values, for scales computed with integer
+    /// math
+    #[inline]
+    pub fn set_scale_fx(&mut self, scale_x: S16_16, scale_y: S16_16) {
+        self.scale_x = scale_x.to_f32();
+        self.scale_y = scale_y.to_f32();
+    }
+}
+
 impl From<BlitParms> for libdragon_sys::rdpq_blitparms_s {
     fn from(v: BlitParms) -> Self {
         assert!(::core::mem::size_of::<BlitParms>() == ::core::mem::size_of::<Self>());
@@ -3443,11 +3613,23 @@ pub fn text_get_font<'a>(font_id: u8) -> Font<'a> {
 
 /// Layout and render a text in a single cell.
 ///
+/// Rust: the text is passed with its length, so nothing is allocated (see [text_printn]).
+///
 /// See [`rdpq_text_print`](libdragon_sys::rdpq_text_print) for details.
 #[inline]
 pub fn text_print(parms: TextParms, font_id: u8, x0: f32, y0: f32, text: &str) -> i32 {
-    let ctext = CString::new(text).unwrap();
-    let len = ctext.to_bytes().len(); // does not contain any trailing \0
+    text_printn(parms, font_id, x0, y0, text.as_bytes())
+}
+
+/// Layout and render the UTF-8 bytes of a text in a single cell, without allocating.
+///
+/// Rust: panics if a [TextWriter] is active, since the text is laid out with the paragraph
+/// builder.
+///
+/// See [`rdpq_text_printn`](libdragon_sys::rdpq_text_printn) for details.
+#[inline]
+pub fn text_printn(parms: TextParms, font_id: u8, x0: f32, y0: f32, text: &[u8]) -> i32 {
+    assert_no_text_writer();
     unsafe {
         libdragon_sys::rdpq_text_printn(
             &mut Into::<libdragon_sys::rdpq_textparms_t>::into(parms)
@@ -3455,12 +3637,192 @@ pub fn text_print(parms: TextParms, font_id: u8, x0: f32, y0: f32, text: &str) -
             font_id,
             x0,
             y0,
-            ctext.as_ptr(),
-            len as i32,
+            text.as_ptr() as *const _,
+            text.len() as i32,
         )
     }
 }
 
+/// Layout information of a text, as computed by [text_measure] or [Paragraph::metrics]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct TextMetrics {
+    /// Width of the bounding box of the text, in pixels
+    pub width:  f32,
+    /// Height of the bounding box of the text, in pixels
+    pub height: f32,
+    /// Number of lines, after wrapping
+    pub nlines: usize,
+    /// Number of chars laid out (after truncation)
+    pub nchars: usize,
+}
+
+/// Compute the layout of a text without rendering it (no RDP command is emitted).
+///
+/// Useful to center or box a text before drawing it with [text_print].
+pub fn text_measure(parms: TextParms, font_id: u8, text: &str) -> TextMetrics {
+    Paragraph::build(parms, font_id, text).0.metrics()
+}
+
+/// Stream formatted text into a paragraph, without intermediate allocations.
+///
+/// [TextWriter] implements [core::fmt::Write], so it can be used with `write!`. The same
+/// escape codes as [text_print] are parsed inline: `$xx` switches to font `xx` and `^xx`
+/// switches to style `xx` (two hex digits, like [ParagraphBuilder::font] and
+/// [ParagraphBuilder::style]), while `$$` and `^^` emit a literal `$` or `^`.
+///
+/// ```rust
+/// let mut para = None;
+/// loop {
+///     let mut w = rdpq::TextWriter::begin(parms, FONT_ID, para.take());
+///     write!(w, "Score: ^01{}^00", score).unwrap();
+///     let p = w.end();
+///     p.render(10.0, 10.0);
+///     para = Some(p); // reuse the memory next frame
+/// }
+/// ```
+///
+/// Rust: like [ParagraphBuilder], only one [TextWriter] (or [ParagraphBuilder]) can be active
+/// at a time, since LibDragon keeps the builder state in a global. [TextWriter::begin] panics
+/// if another [TextWriter] is active.
+///
+/// See `rdpq_paragraph.h` in LibDragon for details.
+pub struct TextWriter {
+    parser: escape::EscapeParser,
+    active: bool,
+}
+
+// Set while a TextWriter holds LibDragon's paragraph builder
+static TEXT_WRITER_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// LibDragon's paragraph builder cannot be used while a TextWriter holds it
+#[inline]
+fn assert_no_text_writer() {
+    assert!(
+        !TEXT_WRITER_ACTIVE.load(AtomicOrdering::Acquire),
+        "the paragraph builder is in use by a TextWriter"
+    );
+}
+
+// Paragraph reused by every call to text_printf
+static mut PRINTF_LAYOUT: Option<Paragraph> = None;
+
+// Text parms of the active TextWriter, which the paragraph builder points to until it ends
+static mut TEXT_WRITER_PARMS: core::mem::MaybeUninit<libdragon_sys::rdpq_textparms_t> =
+    core::mem::MaybeUninit::uninit();
+
+impl TextWriter {
+    /// Start building a paragraph, optionally reusing the memory of a previous one.
+    ///
+    /// Rust: panics if another [TextWriter] is active.
+    ///
+    /// See [`rdpq_paragraph_builder_begin`](libdragon_sys::rdpq_paragraph_builder_begin) for details.
+    #[allow(static_mut_refs)]
+    pub fn begin(parms: TextParms, initial_font_id: u8, layout: Option<Paragraph>) -> Self {
+        assert!(
+            !TEXT_WRITER_ACTIVE.swap(true, AtomicOrdering::Acquire),
+            "only one TextWriter can be active at a time"
+        );
+        // no other TextWriter can point to the parms slot, and it lives forever
+        let parms = unsafe { TEXT_WRITER_PARMS.write(parms.into()) as *const _ };
+        // ownership of the old paragraph passes to the builder, which may reallocate it
+        let layout_ptr = layout.map_or(core::ptr::null_mut(), |p| {
+            let p = core::mem::ManuallyDrop::new(p);
+            p.c
+        });
+        unsafe {
+            libdragon_sys::rdpq_paragraph_builder_begin(parms, initial_font_id, layout_ptr);
+        }
+        Self {
+            parser: escape::EscapeParser::new(),
+            active: true,
+        }
+    }
+
+    // Forward a token from the escape parser to the paragraph builder
+    fn emit(token: escape::Token) {
+        match token {
+            escape::Token::Text(text) => unsafe {
+                libdragon_sys::rdpq_paragraph_builder_span(
+                    text.as_ptr() as *const _,
+                    text.len() as i32,
+                );
+            },
+            escape::Token::Font(id) => unsafe { libdragon_sys::rdpq_paragraph_builder_font(id) },
+            escape::Token::Style(id) => unsafe { libdragon_sys::rdpq_paragraph_builder_style(id) },
+        }
+    }
+
+    // Finish the paragraph and release the builder
+    fn finish(&mut self) -> Paragraph {
+        self.parser.finish(Self::emit);
+        self.active = false;
+        let ptr = unsafe { libdragon_sys::rdpq_paragraph_builder_end() };
+        TEXT_WRITER_ACTIVE.store(false, AtomicOrdering::Release);
+        Paragraph { c: ptr }
+    }
+
+    /// Finalize the paragraph and return it
+    ///
+    /// See [`rdpq_paragraph_builder_end`](libdragon_sys::rdpq_paragraph_builder_end) for details.
+    pub fn end(mut self) -> Paragraph { self.finish() }
+}
+
+impl core::fmt::Write for TextWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.parser.feed(s.as_bytes(), Self::emit);
+        Ok(())
+    }
+}
+
+impl Drop for TextWriter {
+    /// Finalize and free the paragraph if [TextWriter::end] was not called
+    fn drop(&mut self) {
+        if self.active {
+            drop(self.finish());
+        }
+    }
+}
+
+/// Layout and render formatted text in a single cell. See [text_printf!](crate::text_printf).
+///
+/// Rust: the paragraph memory is kept between calls, so that printing every frame does not
+/// allocate once the longest text has been laid out.
+///
+/// Returns the number of chars rendered.
+#[allow(static_mut_refs)]
+pub fn text_printf(
+    parms: TextParms,
+    font_id: u8,
+    x0: f32,
+    y0: f32,
+    args: core::fmt::Arguments,
+) -> i32 {
+    let mut w = TextWriter::begin(parms, font_id, unsafe { PRINTF_LAYOUT.take() });
+    let _ = core::fmt::Write::write_fmt(&mut w, args);
+    let p = w.end();
+    p.render(x0, y0);
+    let nchars = p.nchars() as i32;
+    unsafe {
+        PRINTF_LAYOUT = Some(p);
+    }
+    nchars
+}
+
+/// Layout and render formatted text, like [text_print](rdpq::text_print) but with
+/// [format_args!] syntax and without building an intermediate [String].
+///
+/// ```rust
+/// text_printf!(parms, FONT_ID, 20.0, 20.0, "FPS: {:.1}", display::get_fps());
+/// ```
+///
+/// See [`rdpq_text_printf`](libdragon_sys::rdpq_text_printf) for details.
+#[macro_export]
+macro_rules! text_printf {
+    ($parms:expr, $font_id:expr, $x0:expr, $y0:expr, $($arg:tt)*) => {
+        $crate::rdpq::text_printf($parms, $font_id, $x0, $y0, ::core::format_args!($($arg)*))
+    };
+}
+
 // rdpq_tri.h
 
 /// Format descriptor of a triangle.
@@ -3578,3 +3940,205 @@ pub fn triangle(fmt: &TriFmt, v1: &[f32], v2: &[f32], v3: &[f32]) {
         libdragon_sys::rdpq_triangle(ptr, v1.as_ptr(), v2.as_ptr(), v3.as_ptr());
     }
 }
+
+/// Where the components read by [triangle] live within a [Vertex], as indices of `f32`s.
+/// `None` means the vertex doesn't have the component.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct VertexLayout {
+    /// Size of a vertex, in `f32`s
+    pub components: usize,
+    /// X and Y in pixels
+    pub position:   Option<usize>,
+    /// R, G, B and A, from 0 to 1
+    pub shade:      Option<usize>,
+    /// S and T in texels, and INV_W (1.0 without perspective correction)
+    pub tex:        Option<usize>,
+    /// Depth, from 0 to 1
+    pub z:          Option<usize>,
+}
+
+impl VertexLayout {
+    /// An empty layout for a vertex of `components` floats
+    #[inline]
+    pub const fn new(components: usize) -> Self {
+        Self {
+            components,
+            position: None,
+            shade: None,
+            tex: None,
+            z: None,
+        }
+    }
+
+    /// Index of a field of a vertex, given pointers to the vertex and the field
+    #[inline]
+    pub fn component_of_field<V, F>(base: *const V, field: *const F) -> usize {
+        (field as usize - base as usize) / core::mem::size_of::<f32>()
+    }
+
+    /// A triangle format reading all the components of this layout, using tile 0 without
+    /// mipmaps for the texture
+    pub fn tri_fmt(&self) -> TriFmt {
+        let offset = |component: Option<usize>| component.map_or(-1, |c| c as i32);
+        TriFmt {
+            pos_offset:   offset(self.position),
+            shade_offset: offset(self.shade),
+            shade_flat:   false,
+            tex_offset:   offset(self.tex),
+            tex_tile:     Tile(0),
+            tex_mipmaps:  0,
+            z_offset:     offset(self.z),
+        }
+    }
+
+    // Layout of `V`, panicking if it doesn't match `V` or if `fmt` reads components outside of it
+    fn checked<V: Vertex>(fmt: &TriFmt) -> Self {
+        let layout = V::layout();
+        assert!(
+            core::mem::size_of::<V>() == layout.components * core::mem::size_of::<f32>(),
+            "vertex layout doesn't match the size of the vertex type"
+        );
+        layout.check(fmt);
+        layout
+    }
+
+    // Panics if `fmt` reads components outside of a vertex with this layout
+    fn check(&self, fmt: &TriFmt) {
+        let fits =
+            |offset: i32, size: usize| offset < 0 || offset as usize + size <= self.components;
+        assert!(
+            fmt.pos_offset >= 0 && fits(fmt.pos_offset, 2),
+            "triangle format must read the position within the vertex"
+        );
+        assert!(
+            fits(fmt.shade_offset, 4) && fits(fmt.tex_offset, 3) && fits(fmt.z_offset, 1),
+            "triangle format reads components outside of the vertex"
+        );
+    }
+}
+
+/// A vertex type that can be drawn with [draw_triangles].
+///
+/// Implement it with [rdpq_vertex!](crate::rdpq_vertex), or use `[f32; N]` for vertices with
+/// only a position at index 0.
+///
+/// # Safety
+///
+/// The type must be made only of `f32`s (`repr(C)` without padding), and be
+/// [VertexLayout::components] `f32`s long.
+pub unsafe trait Vertex: Copy + 'static {
+    /// Components of the vertex
+    fn layout() -> VertexLayout;
+}
+
+unsafe impl<const N: usize> Vertex for [f32; N] {
+    fn layout() -> VertexLayout {
+        VertexLayout {
+            position: Some(0),
+            ..VertexLayout::new(N)
+        }
+    }
+}
+
+/// Implement [rdpq::Vertex](crate::rdpq::Vertex) for a `repr(C)` struct of `f32`s, mapping its
+/// fields to the components read by the RDP (`position`, `shade`, `tex`, `z`).
+///
+/// # Safety
+///
+/// The macro can't check the fields of the struct, so it must be invoked with `unsafe` before
+/// the type: the caller guarantees the requirements of [rdpq::Vertex](crate::rdpq::Vertex), that
+/// the struct is `repr(C)` and made only of `f32`s (or arrays of them).
+///
+/// ```rust
+/// #[repr(C)]
+/// #[derive(Copy, Clone)]
+/// struct UiVertex {
+///     xy:   [f32; 2],
+///     rgba: [f32; 4],
+/// }
+/// // SAFETY: repr(C) and only f32s
+/// rdpq_vertex!(unsafe UiVertex { xy => position, rgba => shade });
+///
+/// let fmt = rdpq::TriFmt::for_vertex::<UiVertex>();
+/// rdpq::draw_triangles(&fmt, &vertices, &[0, 1, 2, 2, 1, 3]);
+/// ```
+#[macro_export]
+macro_rules! rdpq_vertex {
+    (unsafe $ty:ty { $($field:ident => $component:ident),* $(,)? }) => {
+        unsafe impl $crate::rdpq::Vertex for $ty {
+            fn layout() -> $crate::rdpq::VertexLayout {
+                let v = ::core::mem::MaybeUninit::<$ty>::uninit();
+                let base = v.as_ptr();
+                let mut layout = $crate::rdpq::VertexLayout::new(
+                    ::core::mem::size_of::<$ty>() / ::core::mem::size_of::<f32>(),
+                );
+                $(
+                    layout.$component = Some($crate::rdpq::VertexLayout::component_of_field(
+                        base,
+                        unsafe { ::core::ptr::addr_of!((*base).$field) },
+                    ));
+                )*
+                layout
+            }
+        }
+    };
+}
+
+impl TriFmt {
+    /// The format reading all the components of vertex type `V`. Set [TriFmt::tex_tile],
+    /// [TriFmt::tex_mipmaps] and [TriFmt::shade_flat] as needed.
+    #[inline]
+    pub fn for_vertex<V: Vertex>() -> Self { V::layout().tri_fmt() }
+}
+
+/// Draw a batch of indexed triangles (RDP command: TRI_*), 3 indices each, like calling
+/// [triangle] for each of them.
+///
+/// The format and the indices are checked once for the whole batch, instead of at every
+/// triangle. Panics if `fmt` reads components outside of `V` (see [TriFmt::for_vertex]), if the
+/// number of indices is not a multiple of 3 or if an index is out of bounds.
+///
+/// See [`rdpq_triangle`](libdragon_sys::rdpq_triangle) for details.
+pub fn draw_triangles<V: Vertex>(fmt: &TriFmt, vertices: &[V], indices: &[u16]) {
+    let layout = VertexLayout::checked::<V>(fmt);
+    assert!(
+        indices.len() / 3 * 3 == indices.len(),
+        "number of indices must be a multiple of 3"
+    );
+    if let Some(&max) = indices.iter().max() {
+        assert!((max as usize) < vertices.len(), "index out of bounds");
+    }
+
+    let fmt = fmt as *const rdpq::TriFmt as *const libdragon_sys::rdpq_trifmt_s;
+    let base = vertices.as_ptr() as *const f32;
+    for t in indices.chunks_exact(3) {
+        // SAFETY: the indices are within `vertices`, and the components read by `fmt` within a
+        // vertex
+        unsafe {
+            libdragon_sys::rdpq_triangle(
+                fmt,
+                base.add(t[0] as usize * layout.components),
+                base.add(t[1] as usize * layout.components),
+                base.add(t[2] as usize * layout.components),
+            );
+        }
+    }
+}
+
+/// Draw a batch of triangles (RDP command: TRI_*), 3 vertices each. See [draw_triangles].
+pub fn draw_triangle_list<V: Vertex>(fmt: &TriFmt, vertices: &[V]) {
+    VertexLayout::checked::<V>(fmt);
+
+    let fmt = fmt as *const rdpq::TriFmt as *const libdragon_sys::rdpq_trifmt_s;
+    for t in vertices.chunks_exact(3) {
+        // SAFETY: the components read by `fmt` are within a vertex
+        unsafe {
+            libdragon_sys::rdpq_triangle(
+                fmt,
+                &t[0] as *const V as *const f32,
+                &t[1] as *const V as *const f32,
+                &t[2] as *const V as *const f32,
+            );
+        }
+    }
+}
@@ -1,3 +1,6 @@
+use alloc::rc::Rc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use crate::*;
 
 // rspq.h
@@ -266,46 +269,141 @@ struct SyncpointCallbackInternal {
     user_callback: SyncpointCallback,
 }
 
+/// Set while a [BlockRecorder] is active, so that only one recording can happen at a time
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// Set while a GL list opened with [gl::NewList] is being recorded
+static RAW_GL_LIST: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if a block (or a GL display list) is currently being recorded
+#[inline]
+pub fn is_recording() -> bool { RECORDING.load(Ordering::SeqCst) }
+
+// Take the recording state for a GL list opened with gl::NewList, which has no BlockRecorder
+pub(crate) fn begin_raw_gl_list(list: u32) {
+    BlockRecorder::start(RecordTarget::GlList(list));
+    RAW_GL_LIST.store(true, Ordering::SeqCst);
+}
+
+// Release the recording state taken by begin_raw_gl_list
+pub(crate) fn end_raw_gl_list() {
+    assert!(
+        RAW_GL_LIST.swap(false, Ordering::SeqCst),
+        "no list opened with gl::NewList is being recorded"
+    );
+    RECORDING.store(false, Ordering::SeqCst);
+}
+
+extern "C" {
+    // Write pointer into the current rspq buffer, used by the inline functions of rspq.h
+    static rspq_cur_pointer: *mut u32;
+}
+
+// Number of 32-bit words written to the block being recorded since `start` (the beginning of its
+// first chunk). When a chunk is full, rspq allocates the next one, zeroes it and terminates the
+// previous one with a JUMP to it, so the JUMP is the last non-zero word of every chunk but the
+// current one.
+fn recorded_words(start: *const u32) -> usize {
+    let end = unsafe { core::ptr::read_volatile(core::ptr::addr_of!(rspq_cur_pointer)) };
+    let end = end as *const u32;
+    let mut chunk = start;
+    let mut size = consts::BLOCK_MIN_SIZE;
+    let mut words = 0;
+    loop {
+        if (chunk..=chunk.wrapping_add(size)).contains(&end) {
+            return words + (end as usize - chunk as usize) / 4;
+        }
+        let data = unsafe { core::slice::from_raw_parts(chunk, size) };
+        let jump = data
+            .iter()
+            .rposition(|&word| word != 0)
+            .expect("block chunk not terminated by a JUMP");
+        words += jump;
+        // the JUMP argument is the physical address of the next chunk, allocated uncached
+        chunk = ((data[jump] & 0x00FF_FFFF) | 0xA000_0000) as *const u32;
+        // same growth as in rspq_next_buffer
+        if size < consts::BLOCK_MAX_SIZE {
+            size *= 2;
+        }
+    }
+}
+
 /// A preconstructed block of commands
 ///
+/// Rust: blocks are reference counted. Cloning a [Block] is cheap and the underlying RSPQ
+/// block is freed when the last clone is dropped. A block also keeps alive all the blocks
+/// that were called from within it (see [BlockRecorder::call]).
+///
 /// See [`rspq_block_t](libdragon_sys::rspq_block_t) for details.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Block {
-    ptr: *mut libdragon_sys::rspq_block_t,
+    inner: Rc<BlockInner>,
 }
 
-impl Block {
-    /// Begin creating a new block
-    ///
-    /// See [`rspq_block_begin`](libdragon_sys::rspq_block_begin) for details.
-    pub fn begin() {
-        unsafe {
-            libdragon_sys::rspq_block_begin();
-        }
-    }
+#[derive(Debug)]
+struct BlockInner {
+    ptr:           *mut libdragon_sys::rspq_block_t,
+    nesting_level: usize,
+    command_words: usize,
+    _children:     Vec<Block>,
+}
 
-    /// Finish creating a block
+impl Block {
+    /// Record a new block. The closure is given a [BlockRecorder] that can be used to call
+    /// other blocks; any rspq, rdpq or gl command issued within the closure is recorded.
     ///
-    /// Rust: this function returns a [Block] struct that frees the underlying RSPQ block
-    /// when the struct is dropped.
+    /// ```rust
+    /// let block = rspq::Block::record(|rec| {
+    ///     rdpq::set_mode_standard();
+    ///     rec.call(&background);
+    /// });
+    /// block.run();
+    /// ```
     ///
-    /// See [`rspq_block_end`](libdragon_sys::rspq_block_end) for details.
-    pub fn end() -> Self {
-        let block_ptr = unsafe { libdragon_sys::rspq_block_end() };
-        Self { ptr: block_ptr }
+    /// Panics if another block is already being recorded.
+    pub fn record<F>(f: F) -> Self
+    where
+        F: FnOnce(&mut BlockRecorder),
+    {
+        let mut rec = BlockRecorder::begin();
+        f(&mut rec);
+        rec.end()
     }
 
     /// Add to the RSP queue a command that runs a block
     ///
+    /// Rust: running a block while recording another one is not allowed, use
+    /// [BlockRecorder::call] instead so that the nested block is kept alive.
+    ///
     /// See [`rspq_block_run`](libdragon_sys::rspq_block_run) for details.
     pub fn run(&self) {
+        assert!(
+            !is_recording(),
+            "cannot run a block while recording, use BlockRecorder::call()"
+        );
         unsafe {
-            libdragon_sys::rspq_block_run(self.ptr);
+            libdragon_sys::rspq_block_run(self.inner.ptr);
         }
     }
+
+    /// Nesting level of the block: 0 if it does not call any other block, otherwise one
+    /// more than the deepest block it calls.
+    #[inline]
+    pub fn nesting_level(&self) -> usize { self.inner.nesting_level }
+
+    /// Size of the commands recorded in this block, in 32-bit words, for profiling.
+    ///
+    /// Rust: rspq commands have a variable size and their number is not stored anywhere, so the
+    /// size of the command buffers is reported instead. Every command issued while recording is
+    /// accounted for, including the ones issued by LibDragon (rdpq, gl, ...). A call to a nested
+    /// block counts as the size of the call command, not of the nested block. RDP commands that
+    /// rdpq stores in a separate static buffer count as the command that points to that buffer,
+    /// and the final RET command is excluded.
+    #[inline]
+    pub fn command_words(&self) -> usize { self.inner.command_words }
 }
 
-impl Drop for Block {
+impl Drop for BlockInner {
     /// Free a block that is not needed any more.
     ///
     /// See [`rspq_block_free`](libdragon_sys::rspq_block_free) for details.
@@ -316,6 +414,161 @@ impl Drop for Block {
     }
 }
 
+// What a BlockRecorder is recording into
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum RecordTarget {
+    Block,
+    GlList(u32),
+}
+
+/// Guard object for the recording of a [Block] (or of a GL display list, see [gl::record_list]).
+///
+/// Only one recorder can be active at a time: creating a second one panics. Dropping the
+/// recorder without calling [BlockRecorder::end] discards the recorded commands.
+///
+/// See [`rspq_block_begin`](libdragon_sys::rspq_block_begin) for details.
+#[derive(Debug)]
+pub struct BlockRecorder {
+    target:        RecordTarget,
+    start:         *const u32,
+    nesting_level: usize,
+    children:      Vec<Block>,
+    active:        bool,
+    // recording state is global to the rspq engine, keep the recorder on its thread
+    phantom:       core::marker::PhantomData<*const u8>,
+}
+
+impl BlockRecorder {
+    /// Begin creating a new block
+    ///
+    /// Panics if another block is already being recorded.
+    ///
+    /// See [`rspq_block_begin`](libdragon_sys::rspq_block_begin) for details.
+    pub fn begin() -> Self {
+        Self::start(RecordTarget::Block);
+        unsafe {
+            libdragon_sys::rspq_block_begin();
+        }
+        Self::new(RecordTarget::Block)
+    }
+
+    // Start recording a GL display list. Used by gl::record_list
+    pub(crate) fn begin_gl_list(list: u32, mode: gl::ListMode) -> Self {
+        Self::start(RecordTarget::GlList(list));
+        unsafe {
+            libdragon_sys::glNewList(list, mode.into());
+        }
+        Self::new(RecordTarget::GlList(list))
+    }
+
+    fn start(target: RecordTarget) {
+        assert!(
+            !RECORDING.swap(true, Ordering::SeqCst),
+            "a block is already being recorded (starting {:?})",
+            target
+        );
+    }
+
+    // Called once LibDragon started recording, so that rspq writes into the block
+    fn new(target: RecordTarget) -> Self {
+        Self {
+            target,
+            start: unsafe { core::ptr::read_volatile(core::ptr::addr_of!(rspq_cur_pointer)) },
+            nesting_level: 0,
+            children: Vec::new(),
+            active: true,
+            phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// Record a call to another block. The called block is kept alive for as long as the
+    /// block being recorded.
+    ///
+    /// Panics if the maximum nesting level ([consts::MAX_BLOCK_NESTING_LEVEL]) is exceeded.
+    ///
+    /// See [`rspq_block_run`](libdragon_sys::rspq_block_run) for details.
+    pub fn call(&mut self, block: &Block) {
+        let nesting_level = block.nesting_level() + 1;
+        assert!(
+            nesting_level < consts::MAX_BLOCK_NESTING_LEVEL,
+            "reached maximum number of nested block runs ({})",
+            consts::MAX_BLOCK_NESTING_LEVEL
+        );
+        self.nesting_level = core::cmp::max(self.nesting_level, nesting_level);
+        unsafe {
+            libdragon_sys::rspq_block_run(block.inner.ptr);
+        }
+        self.children.push(block.clone());
+    }
+
+    /// Nesting level of the block being recorded (see [Block::nesting_level])
+    #[inline]
+    pub fn nesting_level(&self) -> usize { self.nesting_level }
+
+    /// Size of the commands recorded so far, in 32-bit words (see [Block::command_words])
+    #[inline]
+    pub fn command_words(&self) -> usize { recorded_words(self.start) }
+
+    /// Finish creating a block
+    ///
+    /// Panics if the recorder was created for a GL display list.
+    ///
+    /// See [`rspq_block_end`](libdragon_sys::rspq_block_end) for details.
+    pub fn end(mut self) -> Block {
+        assert!(
+            self.target == RecordTarget::Block,
+            "recording a GL list, use gl::record_list()"
+        );
+        let command_words = self.command_words();
+        let ptr = self.finish();
+        Block {
+            inner: Rc::new(BlockInner {
+                ptr,
+                nesting_level: self.nesting_level,
+                command_words,
+                _children: core::mem::take(&mut self.children),
+            }),
+        }
+    }
+
+    // Finish a GL display list, returning the size of its commands (see Block::command_words)
+    // and the blocks called from within it.
+    pub(crate) fn end_gl_list(mut self) -> (usize, Vec<Block>) {
+        let command_words = self.command_words();
+        let _ = self.finish();
+        (command_words, core::mem::take(&mut self.children))
+    }
+
+    fn finish(&mut self) -> *mut libdragon_sys::rspq_block_t {
+        self.active = false;
+        let ptr = match self.target {
+            RecordTarget::Block => unsafe { libdragon_sys::rspq_block_end() },
+            RecordTarget::GlList(_) => {
+                unsafe {
+                    libdragon_sys::glEndList();
+                }
+                ::core::ptr::null_mut()
+            }
+        };
+        RECORDING.store(false, Ordering::SeqCst);
+        ptr
+    }
+}
+
+impl Drop for BlockRecorder {
+    /// Close and discard the recording if [BlockRecorder::end] was not called.
+    fn drop(&mut self) {
+        if self.active {
+            let ptr = self.finish();
+            if !ptr.is_null() {
+                unsafe {
+                    libdragon_sys::rspq_block_free(ptr);
+                }
+            }
+        }
+    }
+}
+
 /// Start building a high-priority queue.
 ///
 /// Rust: this function requires calling [highpri_end] when finished. It's recommended to use the
@@ -354,7 +607,7 @@ pub fn highpri_sync() {
 /// ```rust
 ///     use libdragon::rspq;
 ///     ...
-///     let block = rspq::Block::end();
+///     let block = rspq::Block::record(|_| { ... });
 ///     ...
 ///     rspq::highpri! {
 ///         block.run();
@@ -556,6 +809,10 @@ impl Default for ProfileSlot {
 }
 
 impl ProfileSlot {
+    /// Returns true if this slot is used, i.e. it has a name
+    #[inline]
+    pub fn is_used(&self) -> bool { !self.name.is_null() }
+
     /// The name of this slot, if it is used.
     /// See [`rspq_profile_slot_t.name`](libdragon_sys::rspq_profile_slot_t::name)
     pub fn name(&self) -> &str {
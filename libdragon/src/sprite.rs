@@ -220,11 +220,49 @@ impl<'a> Sprite<'a> {
         }
     }
 
+    // Number of palette entries implied by the sprite format
+    fn palette_len(&self) -> usize {
+        match self.get_format() {
+            surface::TexFormat::Ci4 => 16,
+            surface::TexFormat::Ci8 => 256,
+            _ => 0,
+        }
+    }
+
     /// Access the sprite palette (if any)
     ///
+    /// Rust: the palette is returned as a slice of packed RGBA16 entries, with 16 entries for
+    /// CI4 sprites and 256 for CI8 sprites. See [palette::Palette] for a higher level interface.
+    ///
     /// See [`sprite_get_palette`](libdragon_sys::sprite_get_palette)
-    pub fn get_palette(&self) -> rdpq::TlutPalette<'a> {
-        unsafe { libdragon_sys::sprite_get_palette(self.ptr) as rdpq::TlutPalette }
+    pub fn get_palette(&self) -> Option<&[u16]> {
+        let ptr = unsafe { libdragon_sys::sprite_get_palette(self.ptr) };
+        if ptr.is_null() || self.palette_len() == 0 {
+            return None;
+        }
+        Some(unsafe { core::slice::from_raw_parts(ptr as *const u16, self.palette_len()) })
+    }
+
+    /// Replace the palette stored in the sprite, so that [rdpq::sprite_upload] and
+    /// [rdpq::sprite_blit] use it from now on.
+    ///
+    /// Rust: panics if the sprite has no palette, if `palette` has more entries than the sprite
+    /// palette or if `palette` does not use [rdpq::Tlut::Rgba16] (entries are copied as-is).
+    pub fn set_palette(&mut self, palette: &palette::Palette) {
+        let ptr = unsafe { libdragon_sys::sprite_get_palette(self.ptr) };
+        assert!(!ptr.is_null(), "sprite has no palette");
+        assert!(
+            palette.tlut() == rdpq::Tlut::Rgba16,
+            "sprite palettes are in RGBA16 format"
+        );
+        assert!(
+            palette.len() <= self.palette_len(),
+            "palette too large for sprite"
+        );
+        let entries = unsafe { core::slice::from_raw_parts_mut(ptr as *mut u16, palette.len()) };
+        entries.copy_from_slice(palette.as_raw());
+        // the palette inside the sprite file is only 8-byte aligned
+        data_cache_hit_writeback(entries);
     }
 
     /// Get a copy of the RDP texparms, optionally stored within the sprite.
@@ -348,6 +348,122 @@ impl<'a> Surface<'a> {
     /// Unsafe access to [`surface_t.buffer`](libdragon_sys::surface_t::buffer)
     pub unsafe fn buffer<T>(&self) -> *const T { unsafe { (*self.ptr).buffer as *const _ } }
 
+    // Number of bytes of the buffer covered by the surface
+    fn buffer_len(&self) -> usize {
+        let (width, height) = (self.width() as i32, self.height() as usize);
+        if width == 0 || height == 0 {
+            return 0;
+        }
+        self.stride() as usize * (height - 1) + self.get_format().pix2bytes(width) as usize
+    }
+
+    /// Typed read-only access to the pixels of the surface.
+    ///
+    /// Rust: panics if the surface format is not `F::FORMAT` or if the surface has no buffer
+    /// (e.g. a placeholder).
+    pub fn pixels<F: pixel::PixelFormat>(&self) -> pixel::Pixels<'_, F> {
+        assert!(self.get_format() == F::FORMAT, "surface format mismatch");
+        let buffer = unsafe { self.buffer::<u8>() };
+        assert!(!buffer.is_null(), "surface has no buffer");
+        let data = unsafe { core::slice::from_raw_parts(buffer, self.buffer_len()) };
+        pixel::Pixels::new(
+            data,
+            self.width() as usize,
+            self.height() as usize,
+            self.stride() as usize,
+        )
+    }
+
+    /// Typed mutable access to the pixels of the surface.
+    ///
+    /// Rust: panics if the surface format is not `F::FORMAT`, if the surface has no buffer or if
+    /// the surface is const.
+    pub fn pixels_mut<F: pixel::PixelFormat>(&mut self) -> pixel::PixelsMut<'_, F> {
+        assert!(self.get_format() == F::FORMAT, "surface format mismatch");
+        let len = self.buffer_len();
+        let buffer = unsafe { self.buffer_mut::<u8>() };
+        assert!(!buffer.is_null(), "surface has no buffer");
+        let data = unsafe { core::slice::from_raw_parts_mut(buffer, len) };
+        pixel::PixelsMut::new(
+            data,
+            self.width() as usize,
+            self.height() as usize,
+            self.stride() as usize,
+        )
+    }
+
+    /// Read a single pixel as a [Color](graphics::Color), whatever the surface format.
+    ///
+    /// Color-indexed surfaces are resolved through `palette`, if given. See
+    /// [pixel::read_color].
+    pub fn get_pixel(&self, x: u32, y: u32, palette: Option<&[u16]>) -> graphics::Color {
+        assert!(
+            x < self.width() as u32 && y < self.height() as u32,
+            "pixel out of bounds"
+        );
+        let buffer = unsafe { self.buffer::<u8>() };
+        assert!(!buffer.is_null(), "surface has no buffer");
+        let data = unsafe { core::slice::from_raw_parts(buffer, self.buffer_len()) };
+        let row = &data[y as usize * self.stride() as usize..];
+        pixel::read_color(self.get_format(), row, x as usize, palette)
+    }
+
+    /// Write a single pixel from a [Color](graphics::Color), whatever the surface format.
+    ///
+    /// Rust: panics on color-indexed surfaces; use [Surface::pixels_mut] to write indices.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: graphics::Color) {
+        assert!(
+            x < self.width() as u32 && y < self.height() as u32,
+            "pixel out of bounds"
+        );
+        let len = self.buffer_len();
+        let stride = self.stride() as usize;
+        let format = self.get_format();
+        let buffer = unsafe { self.buffer_mut::<u8>() };
+        assert!(!buffer.is_null(), "surface has no buffer");
+        let data = unsafe { core::slice::from_raw_parts_mut(buffer, len) };
+        pixel::write_color(format, &mut data[y as usize * stride..], x as usize, color);
+    }
+
+    /// Allocate a new surface with the same size and contents converted to `format`.
+    ///
+    /// This is a software conversion running on the CPU, meant for load-time processing. If
+    /// `dither` is true and `format` is [TexFormat::Rgba16], ordered dithering is applied.
+    /// Color-indexed sources are resolved through `palette`.
+    ///
+    /// Rust: panics if `format` is color-indexed; use [pixel::quantize] instead.
+    pub fn convert(
+        &self,
+        format: TexFormat,
+        palette: Option<&[u16]>,
+        dither: bool,
+    ) -> Surface<'static> {
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        let mut dst = Surface::alloc(format, width as u32, height as u32);
+        let dither = dither && format == TexFormat::Rgba16;
+        let src_format = self.get_format();
+        let (src_stride, dst_stride) = (self.stride() as usize, dst.stride() as usize);
+
+        let src_buffer = unsafe { self.buffer::<u8>() };
+        assert!(!src_buffer.is_null(), "surface has no buffer");
+        let src = unsafe { core::slice::from_raw_parts(src_buffer, self.buffer_len()) };
+        let dst_len = dst.buffer_len();
+        let dst_data = unsafe { core::slice::from_raw_parts_mut(dst.buffer_mut::<u8>(), dst_len) };
+
+        for y in 0..height {
+            let src_row = &src[y * src_stride..];
+            let dst_row = &mut dst_data[y * dst_stride..];
+            for x in 0..width {
+                let mut color = pixel::read_color(src_format, src_row, x, palette);
+                if dither {
+                    color = pixel::dither_rgba16(color, x, y);
+                }
+                pixel::write_color(format, dst_row, x, color);
+            }
+        }
+        dst
+    }
+
     /// Display a buffer on the screen
     ///
     /// See [`display_show`](libdragon_sys::display_show)
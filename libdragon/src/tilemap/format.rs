@@ -0,0 +1,274 @@
+//! Layout of the `.tilemap` file format.
+//!
+//! This module only depends on `core` and `alloc`. It is shared with `libdragon-build`, so the
+//! files written by its Tiled converter are encoded by the same code that reads them back.
+
+use alloc::vec::Vec;
+
+/// Cell value of a layer position without a tile
+pub const EMPTY: u16 = 0xFFFF;
+
+/// Magic bytes at the start of a `.tilemap` file
+pub const MAGIC: &[u8; 4] = b"TMAP";
+/// Version of the `.tilemap` format
+pub const VERSION: u16 = 1;
+
+/// Errors that can happen while parsing a `.tilemap` file
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TilemapError {
+    /// The file does not start with [MAGIC]
+    BadMagic,
+    /// The file was written for another version of the format
+    BadVersion,
+    /// The file is shorter than what its header describes
+    Truncated,
+    /// A layer name is not valid UTF-8
+    InvalidName,
+}
+
+/// Reads big-endian values from the contents of a `.tilemap` file
+pub struct Reader<'d> {
+    data: &'d [u8],
+    pos:  usize,
+}
+
+impl<'d> Reader<'d> {
+    /// Read `data` from the start
+    pub fn new(data: &'d [u8]) -> Self { Self { data, pos: 0 } }
+
+    /// Read the next `len` bytes
+    pub fn bytes(&mut self, len: usize) -> Result<&'d [u8], TilemapError> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or(TilemapError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Read a 16-bit value
+    pub fn u16(&mut self) -> Result<u16, TilemapError> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    /// Read a 32-bit float
+    pub fn f32(&mut self) -> Result<f32, TilemapError> {
+        let b = self.bytes(4)?;
+        Ok(f32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// The start of the file: magic, version, tile size and number of layers and animations
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub tile_width:     u16,
+    pub tile_height:    u16,
+    pub num_layers:     u16,
+    pub num_animations: u16,
+}
+
+impl Header {
+    /// Read the header, checking the magic and version
+    pub fn read(r: &mut Reader) -> Result<Self, TilemapError> {
+        if r.bytes(4).map_err(|_| TilemapError::BadMagic)? != MAGIC {
+            return Err(TilemapError::BadMagic);
+        }
+        if r.u16()? != VERSION {
+            return Err(TilemapError::BadVersion);
+        }
+        Ok(Self {
+            tile_width:     r.u16()?,
+            tile_height:    r.u16()?,
+            num_layers:     r.u16()?,
+            num_animations: r.u16()?,
+        })
+    }
+
+    /// Append the header to `out`
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(MAGIC);
+        for v in [
+            VERSION,
+            self.tile_width,
+            self.tile_height,
+            self.num_layers,
+            self.num_animations,
+        ] {
+            out.extend_from_slice(&v.to_be_bytes());
+        }
+    }
+}
+
+/// The start of a layer, followed by `width * height` cells
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LayerHeader<'a> {
+    pub name:     &'a str,
+    pub width:    u16,
+    pub height:   u16,
+    pub visible:  bool,
+    pub parallax: (f32, f32),
+    pub offset:   (f32, f32),
+}
+
+impl<'a> LayerHeader<'a> {
+    /// Read a layer header
+    pub fn read(r: &mut Reader<'a>) -> Result<Self, TilemapError> {
+        let name_len = r.u16()? as usize;
+        let name =
+            core::str::from_utf8(r.bytes(name_len)?).map_err(|_| TilemapError::InvalidName)?;
+        Ok(Self {
+            name,
+            width: r.u16()?,
+            height: r.u16()?,
+            visible: r.u16()? & 1 != 0,
+            parallax: (r.f32()?, r.f32()?),
+            offset: (r.f32()?, r.f32()?),
+        })
+    }
+
+    /// Append the layer header to `out`.
+    ///
+    /// Panics if the name is longer than 65535 bytes.
+    pub fn write(&self, out: &mut Vec<u8>) {
+        let name_len = u16::try_from(self.name.len()).expect("layer name too long");
+        out.extend_from_slice(&name_len.to_be_bytes());
+        out.extend_from_slice(self.name.as_bytes());
+        for v in [self.width, self.height, self.visible as u16] {
+            out.extend_from_slice(&v.to_be_bytes());
+        }
+        for v in [
+            self.parallax.0,
+            self.parallax.1,
+            self.offset.0,
+            self.offset.1,
+        ] {
+            out.extend_from_slice(&v.to_be_bytes());
+        }
+    }
+
+    /// Number of cells following the header
+    #[inline]
+    pub fn len(&self) -> usize { self.width as usize * self.height as usize }
+    /// Returns true if the layer has no cells
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+}
+
+/// Read `cells.len()` cells into `cells`
+pub fn read_cells(r: &mut Reader, cells: &mut [u16]) -> Result<(), TilemapError> {
+    let bytes = r.bytes(cells.len() * 2)?;
+    for (cell, b) in cells.iter_mut().zip(bytes.chunks_exact(2)) {
+        *cell = u16::from_be_bytes([b[0], b[1]]);
+    }
+    Ok(())
+}
+
+/// Append `cells` to `out`
+pub fn write_cells(cells: &[u16], out: &mut Vec<u8>) {
+    for cell in cells {
+        out.extend_from_slice(&cell.to_be_bytes());
+    }
+}
+
+/// Read an animation: the animated tile and its frames (tile index, duration in milliseconds)
+pub fn read_animation(
+    r: &mut Reader,
+    mut frame: impl FnMut(u16, u16),
+) -> Result<u16, TilemapError> {
+    let tile = r.u16()?;
+    for _ in 0..r.u16()? {
+        let index = r.u16()?;
+        frame(index, r.u16()?);
+    }
+    Ok(tile)
+}
+
+/// Append an animation to `out`.
+///
+/// Panics if there are more than 65535 frames.
+pub fn write_animation(tile: u16, frames: &[(u16, u16)], out: &mut Vec<u8>) {
+    let len = u16::try_from(frames.len()).expect("too many animation frames");
+    out.extend_from_slice(&tile.to_be_bytes());
+    out.extend_from_slice(&len.to_be_bytes());
+    for (index, duration) in frames {
+        out.extend_from_slice(&index.to_be_bytes());
+        out.extend_from_slice(&duration.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn round_trip() {
+        let header = Header {
+            tile_width:     16,
+            tile_height:    8,
+            num_layers:     1,
+            num_animations: 1,
+        };
+        let layer = LayerHeader {
+            name:     "ground",
+            width:    2,
+            height:   1,
+            visible:  false,
+            parallax: (0.5, 1.0),
+            offset:   (-3.0, 4.5),
+        };
+        let mut out = Vec::new();
+        header.write(&mut out);
+        layer.write(&mut out);
+        write_cells(&[7, EMPTY], &mut out);
+        write_animation(7, &[(8, 100), (9, 250)], &mut out);
+        assert_eq!(&out[..14], b"TMAP\0\x01\0\x10\0\x08\0\x01\0\x01");
+
+        let mut r = Reader::new(&out);
+        assert_eq!(Header::read(&mut r), Ok(header));
+        let read = LayerHeader::read(&mut r).unwrap();
+        assert_eq!(read, layer);
+        let mut cells = vec![0; read.len()];
+        read_cells(&mut r, &mut cells).unwrap();
+        assert_eq!(cells, [7, EMPTY]);
+        let mut frames = Vec::new();
+        assert_eq!(read_animation(&mut r, |i, d| frames.push((i, d))), Ok(7));
+        assert_eq!(frames, [(8, 100), (9, 250)]);
+        assert_eq!(r.bytes(1), Err(TilemapError::Truncated));
+    }
+
+    #[test]
+    fn invalid_files() {
+        assert_eq!(
+            Header::read(&mut Reader::new(b"TMA")),
+            Err(TilemapError::BadMagic)
+        );
+        assert_eq!(
+            Header::read(&mut Reader::new(b"PAMT\0\x01")),
+            Err(TilemapError::BadMagic)
+        );
+        assert_eq!(
+            Header::read(&mut Reader::new(b"TMAP\0\x02")),
+            Err(TilemapError::BadVersion)
+        );
+        assert_eq!(
+            Header::read(&mut Reader::new(b"TMAP\0\x01\0")),
+            Err(TilemapError::Truncated)
+        );
+        assert_eq!(
+            LayerHeader::read(&mut Reader::new(b"\0\x02\xFF\xFE")),
+            Err(TilemapError::InvalidName)
+        );
+        assert_eq!(
+            LayerHeader::read(&mut Reader::new(b"\xFF\xFFname")),
+            Err(TilemapError::Truncated)
+        );
+        let mut cells = [0; 3];
+        assert_eq!(
+            read_cells(&mut Reader::new(&[0; 5]), &mut cells),
+            Err(TilemapError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Tile map layers and the camera looking at them, independent of rendering.
+//!
+//! This module only depends on `core` and `alloc`. It is shared with `libdragon-build`, so the
+//! scrolling and culling behind [tilemap](crate::tilemap) can be tested on a development host.
+
+use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+
+use super::format::EMPTY;
+
+#[inline]
+fn floor(x: f32) -> i32 {
+    let i = x as i32;
+    if (i as f32) > x {
+        i - 1
+    } else {
+        i
+    }
+}
+
+/// The part of the world shown on screen.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Camera {
+    /// World X coordinate shown at the left edge of the viewport, in pixels
+    pub x:        f32,
+    /// World Y coordinate shown at the top edge of the viewport, in pixels
+    pub y:        f32,
+    /// Left edge of the viewport on screen
+    pub screen_x: i32,
+    /// Top edge of the viewport on screen
+    pub screen_y: i32,
+    /// Width of the viewport in pixels
+    pub width:    i32,
+    /// Height of the viewport in pixels
+    pub height:   i32,
+}
+
+impl Camera {
+    /// Create a camera at the world origin, with a viewport of `width` x `height` pixels at the
+    /// top-left corner of the screen
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    /// Move the camera so that the world position (`x`, `y`) is at the center of the viewport
+    pub fn center_on(&mut self, x: f32, y: f32) {
+        self.x = x - self.width as f32 / 2.0;
+        self.y = y - self.height as f32 / 2.0;
+    }
+}
+
+/// A grid of tile indices, drawn with a tileset
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    name:         String,
+    width:        u16,
+    height:       u16,
+    cells:        Vec<u16>,
+    /// Scroll speed relative to the camera on each axis: 1.0 moves with the camera, 0.5 at half
+    /// speed (e.g. a background) and 0.0 stays fixed on screen
+    pub parallax: (f32, f32),
+    /// Position of the top-left corner of the layer in the world, in pixels
+    pub offset:   (f32, f32),
+    /// Hidden layers are skipped when drawing the whole map
+    pub visible:  bool,
+}
+
+impl Layer {
+    /// Create a visible layer of `width` x `height` [EMPTY] cells
+    pub fn new(name: &str, width: u16, height: u16) -> Self {
+        Self {
+            name: name.to_owned(),
+            width,
+            height,
+            cells: vec![EMPTY; width as usize * height as usize],
+            parallax: (1.0, 1.0),
+            offset: (0.0, 0.0),
+            visible: true,
+        }
+    }
+
+    /// Name of the layer
+    #[inline]
+    pub fn name(&self) -> &str { &self.name }
+    /// Width of the layer, in tiles
+    #[inline]
+    pub fn width(&self) -> u16 { self.width }
+    /// Height of the layer, in tiles
+    #[inline]
+    pub fn height(&self) -> u16 { self.height }
+    /// The cells of the layer, in row-major order
+    #[inline]
+    pub fn cells(&self) -> &[u16] { &self.cells }
+    /// Mutable access to the cells of the layer, in row-major order
+    #[inline]
+    pub fn cells_mut(&mut self) -> &mut [u16] { &mut self.cells }
+
+    /// Tile at cell (`x`, `y`), or [EMPTY] outside of the layer
+    #[inline]
+    pub fn get(&self, x: i32, y: i32) -> u16 {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return EMPTY;
+        }
+        self.cells[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Set the tile at cell (`x`, `y`)
+    #[inline]
+    pub fn set(&mut self, x: u16, y: u16, tile: u16) {
+        assert!(x < self.width && y < self.height, "cell out of the layer");
+        self.cells[y as usize * self.width as usize + x as usize] = tile;
+    }
+
+    /// Position in layer pixels shown at the top-left corner of the camera viewport
+    pub fn scroll(&self, camera: &Camera) -> (i32, i32) {
+        (
+            floor(camera.x * self.parallax.0 - self.offset.0),
+            floor(camera.y * self.parallax.1 - self.offset.1),
+        )
+    }
+
+    /// Range of cells `(x0, y0, x1, y1)` (exclusive end) at least partially inside the camera
+    /// viewport, for tiles of `tile_width` x `tile_height` pixels. The range is empty when the
+    /// viewport does not overlap the layer.
+    pub fn visible_cells(
+        &self,
+        tile_width: i32,
+        tile_height: i32,
+        camera: &Camera,
+    ) -> (i32, i32, i32, i32) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let (sx, sy) = self.scroll(camera);
+        let x0 = sx.div_euclid(tile_width).clamp(0, width);
+        let y0 = sy.div_euclid(tile_height).clamp(0, height);
+        let x1 = (sx + camera.width + tile_width - 1).div_euclid(tile_width);
+        let y1 = (sy + camera.height + tile_height - 1).div_euclid(tile_height);
+        (x0, y0, x1.clamp(x0, width), y1.clamp(y0, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer() -> Layer { Layer::new("test", 20, 10) }
+
+    #[test]
+    fn cells() {
+        let mut layer = layer();
+        layer.set(19, 9, 3);
+        assert_eq!(layer.get(19, 9), 3);
+        assert_eq!(layer.cells()[199], 3);
+        assert_eq!(layer.get(0, 0), EMPTY);
+        assert_eq!(layer.get(-1, 0), EMPTY);
+        assert_eq!(layer.get(20, 0), EMPTY);
+        assert_eq!(layer.get(0, 10), EMPTY);
+    }
+
+    #[test]
+    #[should_panic(expected = "cell out of the layer")]
+    fn set_outside() { layer().set(20, 0, 1); }
+
+    #[test]
+    fn scroll() {
+        let mut layer = layer();
+        let mut camera = Camera::new(64, 32);
+        camera.center_on(32.5, 16.0);
+        assert_eq!(layer.scroll(&camera), (0, 0));
+        camera.x = -0.5;
+        assert_eq!(layer.scroll(&camera), (-1, 0));
+
+        layer.parallax = (0.5, 0.0);
+        layer.offset = (10.0, 0.0);
+        camera.x = 100.0;
+        camera.y = 100.0;
+        assert_eq!(layer.scroll(&camera), (40, 0));
+    }
+
+    #[test]
+    fn visible_cells() {
+        let layer = layer();
+        let mut camera = Camera::new(64, 32);
+        assert_eq!(layer.visible_cells(16, 16, &camera), (0, 0, 4, 2));
+        // partially visible tiles on both sides
+        camera.x = 8.0;
+        camera.y = 8.0;
+        assert_eq!(layer.visible_cells(16, 16, &camera), (0, 0, 5, 3));
+        // clamped to the layer
+        camera.x = -40.0;
+        camera.y = 150.0;
+        assert_eq!(layer.visible_cells(16, 16, &camera), (0, 9, 2, 10));
+    }
+
+    #[test]
+    fn camera_past_the_edge() {
+        let layer = layer();
+        let mut camera = Camera::new(64, 32);
+        for (x, y) in [(1000.0, 0.0), (0.0, 1000.0), (-1000.0, 0.0), (0.0, -1000.0)] {
+            camera.x = x;
+            camera.y = y;
+            let (x0, y0, x1, y1) = layer.visible_cells(16, 16, &camera);
+            assert!(x0 == x1 || y0 == y1, "{:?}", (x0, y0, x1, y1));
+            assert!((0..=20).contains(&x0) && (x0..=20).contains(&x1));
+            assert!((0..=10).contains(&y0) && (y0..=10).contains(&y1));
+        }
+        camera.x = 320.0;
+        camera.y = 160.0;
+        assert_eq!(layer.visible_cells(16, 16, &camera), (20, 10, 20, 10));
+    }
+}
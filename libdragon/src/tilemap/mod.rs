@@ -0,0 +1,346 @@
+//! Tile map rendering with scrolling, parallax and animated tiles.
+//!
+//! A [Tilemap] is a stack of [Layer]s of tile indices, drawn through a [Tileset] (a sprite sheet
+//! of equally sized tiles, usually CI4, CI8 or RGBA16) as seen by a [Camera]. Only the tiles
+//! inside the camera viewport are drawn, grouped so that each part of the tileset is uploaded
+//! to TMEM once per layer and reused by all the tiles that need it.
+//!
+//! Maps can be built at runtime or loaded from `.tilemap` files, which `libdragon-build`
+//! converts from maps made with the Tiled editor. All values are big-endian:
+//!
+//! | Offset | Field                                                                        |
+//! |--------|------------------------------------------------------------------------------|
+//! | 0      | magic (`"TMAP"`)                                                             |
+//! | 4      | version (16-bit)                                                             |
+//! | 6      | tile width, tile height, number of layers, number of animations (4 x 16-bit) |
+//! | 14     | layers, then animations                                                      |
+//!
+//! Each layer is made of its name length (16-bit) and UTF-8 name, its width, height and flags
+//! (3 x 16-bit, bit 0 of the flags is the visibility), its parallax and offset (4 x 32-bit
+//! floats), then `width * height` cells (16-bit, [EMPTY] for no tile) in row-major order.
+//!
+//! Each animation is made of the animated tile index and its number of frames (2 x 16-bit),
+//! then for every frame the tile index to show and its duration in milliseconds (2 x 16-bit).
+
+use core::cell::RefCell;
+
+use crate::*;
+
+mod format;
+mod layer;
+
+pub use format::{TilemapError, EMPTY, MAGIC, VERSION};
+pub use layer::{Camera, Layer};
+
+use anim::{Clip, PlayMode};
+use rdpq::Tile;
+use sprite::Sprite;
+use surface::TexFormat;
+
+// Tile descriptor used to draw the tiles
+const TILE: Tile = Tile(0);
+
+/// A sprite sheet of equally sized tiles, numbered left to right, top to bottom.
+pub struct Tileset<'a> {
+    sheet:       &'a Sprite<'a>,
+    tile_width:  i32,
+    tile_height: i32,
+    columns:     i32,
+    count:       i32,
+    // size in tiles of the rectangles of the sheet uploaded at once to TMEM
+    band_cols:   i32,
+    band_rows:   i32,
+    // tiles start on a TMEM word, so each can get its own clamped tile descriptor
+    aligned:     bool,
+}
+
+impl<'a> Tileset<'a> {
+    /// Use `sheet` as a tileset of `tile_width` x `tile_height` tiles.
+    ///
+    /// Rust: panics if a single tile does not fit in TMEM (2 KiB for CI textures, since the
+    /// upper half holds the palette, 4 KiB otherwise).
+    pub fn new(sheet: &'a Sprite<'a>, tile_width: i32, tile_height: i32) -> Self {
+        assert!(tile_width > 0 && tile_height > 0, "invalid tile size");
+        let format = sheet.get_format();
+        let columns = (sheet.width() as i32 / tile_width).max(1);
+        let rows = (sheet.height() as i32 / tile_height).max(1);
+
+        let tmem = match format {
+            TexFormat::Ci4 | TexFormat::Ci8 => 2048,
+            _ => 4096,
+        };
+        let band_size = |cols: i32, rows: i32| {
+            ((format.pix2bytes(cols * tile_width) + 7) & !7) * rows * tile_height
+        };
+        assert!(band_size(1, 1) <= tmem, "tiles do not fit in TMEM");
+        let (band_cols, band_rows) = if band_size(columns, 1) <= tmem {
+            (columns, (tmem / band_size(columns, 1)).min(rows))
+        } else {
+            let cols = (1..columns).rev().find(|&c| band_size(c, 1) <= tmem);
+            (cols.unwrap_or(1), 1)
+        };
+
+        Self {
+            sheet,
+            tile_width,
+            tile_height,
+            columns,
+            count: columns * rows,
+            band_cols,
+            band_rows,
+            aligned: format.pix2bytes(tile_width) % 8 == 0,
+        }
+    }
+
+    /// Use the [hslices](Sprite::hslices) and [vslices](Sprite::vslices) of `sheet` to split it
+    /// in tiles
+    pub fn from_slices(sheet: &'a Sprite<'a>) -> Self {
+        let width = sheet.width() as i32 / (sheet.hslices() as i32).max(1);
+        let height = sheet.height() as i32 / (sheet.vslices() as i32).max(1);
+        Self::new(sheet, width, height)
+    }
+
+    /// The sprite sheet
+    #[inline]
+    pub fn sheet(&self) -> &'a Sprite<'a> { self.sheet }
+    /// Size of a tile, in pixels
+    #[inline]
+    pub fn tile_size(&self) -> (i32, i32) { (self.tile_width, self.tile_height) }
+    /// Number of tiles in the sheet
+    #[inline]
+    pub fn len(&self) -> usize { self.count as usize }
+    /// Returns true if the sheet has no tiles
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.count == 0 }
+
+    /// Top-left corner of tile `index` within the sheet
+    #[inline]
+    pub fn tile_origin(&self, index: u16) -> (i32, i32) {
+        let index = index as i32;
+        (
+            (index % self.columns) * self.tile_width,
+            (index / self.columns) * self.tile_height,
+        )
+    }
+
+    fn band_of(&self, index: u16) -> u32 {
+        let col = index as i32 % self.columns;
+        let row = index as i32 / self.columns;
+        let bands_per_row = (self.columns + self.band_cols - 1) / self.band_cols;
+        ((row / self.band_rows) * bands_per_row + col / self.band_cols) as u32
+    }
+
+    fn band_rect(&self, band: u32) -> (i32, i32, i32, i32) {
+        let bands_per_row = (self.columns + self.band_cols - 1) / self.band_cols;
+        let col = (band as i32 % bands_per_row) * self.band_cols;
+        let row = (band as i32 / bands_per_row) * self.band_rows;
+        let cols = self.band_cols.min(self.columns - col);
+        let rows = self.band_rows.min(self.count / self.columns - row);
+        (
+            col * self.tile_width,
+            row * self.tile_height,
+            (col + cols) * self.tile_width,
+            (row + rows) * self.tile_height,
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Cell {
+    // TMEM band in the upper 16 bits, tile index in the lower ones: sorting by key groups the
+    // tiles by upload, then by tile descriptor
+    key: u32,
+    x:   u16,
+    y:   u16,
+}
+
+/// A stack of [Layer]s, with animated tiles.
+///
+/// ```rust
+/// let sheet = Sprite::load(dfs::PathBuf::from("rom:/tiles.sprite"))?;
+/// let tileset = tilemap::Tileset::new(&sheet, 16, 16);
+/// let map = tilemap::Tilemap::load(dfs::PathBuf::from("rom:/level1.tilemap"))?;
+/// let mut camera = tilemap::Camera::new(320, 240);
+/// loop {
+///     camera.center_on(player_x, player_y);
+///     rdpq::attach(&display::get(), None);
+///     rdpq::set_mode_copy(true);
+///     map.draw(&tileset, &camera, time);
+///     rdpq::detach_show();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tilemap {
+    tile_width:  u16,
+    tile_height: u16,
+    layers:      Vec<Layer>,
+    // sorted by tile index
+    animations:  Vec<(u16, Clip)>,
+    batch:       RefCell<Vec<Cell>>,
+}
+
+impl Tilemap {
+    /// Create a map without layers, for tiles of `tile_width` x `tile_height` pixels
+    pub fn new(tile_width: u16, tile_height: u16) -> Self {
+        Self {
+            tile_width,
+            tile_height,
+            layers: Vec::new(),
+            animations: Vec::new(),
+            batch: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Load a `.tilemap` file from a filesystem (eg: ROM)
+    pub fn load<T: AsRef<dfs::Path>>(path: T) -> Result<Self> {
+        let data = asset::load::<u8, _>(path).ok_or(LibDragonError::DfsError {
+            error: dfs::DfsError::NotFound,
+        })?;
+        Ok(Self::parse(&data)?)
+    }
+
+    /// Parse the contents of a `.tilemap` file
+    pub fn parse(data: &[u8]) -> core::result::Result<Self, TilemapError> {
+        let mut r = format::Reader::new(data);
+        let header = format::Header::read(&mut r)?;
+        let mut map = Self::new(header.tile_width, header.tile_height);
+
+        for _ in 0..header.num_layers {
+            let h = format::LayerHeader::read(&mut r)?;
+            let mut layer = Layer::new(h.name, h.width, h.height);
+            layer.visible = h.visible;
+            layer.parallax = h.parallax;
+            layer.offset = h.offset;
+            format::read_cells(&mut r, layer.cells_mut())?;
+            map.layers.push(layer);
+        }
+
+        for _ in 0..header.num_animations {
+            let mut clip = Clip::new(PlayMode::Loop);
+            let tile = format::read_animation(&mut r, |index, duration| {
+                clip.frame(index, duration.max(1) as f32 / 1000.0);
+            })?;
+            map.animate(tile, clip);
+        }
+        Ok(map)
+    }
+
+    /// Size of a tile, in pixels
+    #[inline]
+    pub fn tile_size(&self) -> (u16, u16) { (self.tile_width, self.tile_height) }
+    /// The layers, from back to front
+    #[inline]
+    pub fn layers(&self) -> &[Layer] { &self.layers }
+    /// Mutable access to the layers, from back to front
+    #[inline]
+    pub fn layers_mut(&mut self) -> &mut [Layer] { &mut self.layers }
+
+    /// Add a layer in front of the existing ones, returning its index
+    pub fn add_layer(&mut self, layer: Layer) -> usize {
+        self.layers.push(layer);
+        self.layers.len() - 1
+    }
+
+    /// Find a layer by name
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|l| l.name() == name)
+    }
+
+    /// Animate every cell containing `tile`: at draw time, the cell shows the frame of `clip`
+    /// for the current time instead. Replaces any previous animation of `tile`.
+    pub fn animate(&mut self, tile: u16, clip: Clip) {
+        match self.animations.binary_search_by_key(&tile, |(t, _)| *t) {
+            Ok(i) => self.animations[i].1 = clip,
+            Err(i) => self.animations.insert(i, (tile, clip)),
+        }
+    }
+
+    /// The tile shown in place of `tile` at `time` seconds, after animation
+    #[inline]
+    pub fn resolve(&self, tile: u16, time: f32) -> u16 {
+        match self.animations.binary_search_by_key(&tile, |(t, _)| *t) {
+            Ok(i) => self.animations[i].1.index_at(time),
+            Err(_) => tile,
+        }
+    }
+
+    /// Draw all the visible layers, from back to front.
+    ///
+    /// `time` is the time in seconds used to animate tiles. The render mode must be set up by
+    /// the caller (e.g. [rdpq::set_mode_copy] for 1:1 tiles); for CI tilesets, the palette of the
+    /// sheet is uploaded and the TLUT mode is enabled while drawing, then the previous render
+    /// mode is restored.
+    ///
+    /// Tiles crossing the border of the camera viewport are drawn whole: set a scissor
+    /// ([rdpq::set_scissor]) if the viewport does not cover the whole framebuffer.
+    pub fn draw(&self, tileset: &Tileset, camera: &Camera, time: f32) {
+        for index in 0..self.layers.len() {
+            if self.layers[index].visible {
+                self.draw_layer(index, tileset, camera, time);
+            }
+        }
+    }
+
+    /// Draw a single layer, even if it is hidden. See [Tilemap::draw].
+    pub fn draw_layer(&self, index: usize, tileset: &Tileset, camera: &Camera, time: f32) {
+        let (tw, th) = (self.tile_width as i32, self.tile_height as i32);
+        assert!(
+            tileset.tile_size() == (tw, th),
+            "tileset and tile map have different tile sizes"
+        );
+        let layer = &self.layers[index];
+        let (sx, sy) = layer.scroll(camera);
+        let (x0, y0, x1, y1) = layer.visible_cells(tw, th, camera);
+
+        let mut batch = self.batch.borrow_mut();
+        batch.clear();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let tile = self.resolve(layer.get(x, y), time);
+                if tile == EMPTY || tile as usize >= tileset.len() {
+                    continue;
+                }
+                batch.push(Cell {
+                    key: (tileset.band_of(tile) << 16) | tile as u32,
+                    x:   x as u16,
+                    y:   y as u16,
+                });
+            }
+        }
+        if batch.is_empty() {
+            return;
+        }
+        batch.sort_unstable_by_key(|c| c.key);
+
+        let sheet = tileset.sheet();
+        let palette = sheet.get_palette();
+        if let Some(palette) = palette {
+            rdpq::mode_push();
+            rdpq::mode_tlut(sheet.get_format().into());
+            rdpq::tex_upload_tlut(palette, 0, palette.len() as i32);
+        }
+        let surface = sheet.get_pixels();
+        let (mut band, mut tile) = (u32::MAX, u32::MAX);
+        for cell in batch.iter() {
+            if cell.key >> 16 != band {
+                band = cell.key >> 16;
+                let (s0, t0, s1, t1) = tileset.band_rect(band);
+                rdpq::tex_upload_sub(TILE, &surface, None, s0, t0, s1, t1);
+            }
+            let (s, t) = tileset.tile_origin(cell.key as u16);
+            if cell.key != tile {
+                tile = cell.key;
+                // a descriptor clamped to the tile keeps filtering from sampling its neighbours
+                if tileset.aligned {
+                    rdpq::tex_reuse_sub(TILE, None, s, t, s + tw, t + th);
+                }
+            }
+            let x = camera.screen_x + cell.x as i32 * tw - sx;
+            let y = camera.screen_y + cell.y as i32 * th - sy;
+            rdpq::texture_rectangle(TILE, x, y, x + tw, y + th, s, t);
+        }
+        if palette.is_some() {
+            rdpq::mode_pop();
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Trace events, their JSON serialization and the framing of the USB packets.
+//!
+//! This module only depends on `core` and `alloc`. It is shared with `libdragon-build`, which
+//! reads the packets back, so the format is tested on a development host.
+
+use alloc::{borrow::Cow, collections::VecDeque, string::String, vec::Vec};
+use core::fmt::Write;
+
+/// Start of every USB packet carrying trace data
+pub const TRACE_MAGIC: &[u8; 4] = b"TRC1";
+
+// Largest payload of a USB packet, after the magic
+const USB_CHUNK: usize = 8192;
+
+/// Timeline an event is shown on
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Track {
+    Cpu,
+    Rsp,
+    Rdp,
+}
+
+impl Track {
+    /// All the tracks, in the order they are shown
+    pub const ALL: [Track; 3] = [Track::Cpu, Track::Rsp, Track::Rdp];
+
+    /// Thread id of the track in the trace
+    #[inline]
+    pub fn id(self) -> u32 { self as u32 }
+
+    #[inline]
+    pub fn name(self) -> &'static str {
+        match self {
+            Track::Cpu => "CPU",
+            Track::Rsp => "RSP",
+            Track::Rdp => "RDP",
+        }
+    }
+}
+
+/// What an [Event] records
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EventKind {
+    /// Something that lasted `duration` ticks from the timestamp
+    Span { duration: u64 },
+    /// A point in time
+    Instant,
+    /// The value of a counter, shown as a graph
+    Counter { value: f32 },
+}
+
+/// A trace event. Timestamps and durations are in CPU ticks (see `ticks::per_second`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub name:      Cow<'static, str>,
+    pub track:     Track,
+    pub kind:      EventKind,
+    /// Ticks since the start of the trace
+    pub timestamp: u64,
+}
+
+impl Event {
+    /// Append the event to `out` as a line of the JSON array format, ending with a comma
+    pub fn write_json(&self, out: &mut String, ticks_per_second: u32) {
+        let us = |ticks: u64| ticks as f64 * 1_000_000.0 / ticks_per_second as f64;
+        out.push_str("{\"name\":");
+        write_json_str(out, &self.name);
+        let _ = write!(
+            out,
+            ",\"cat\":\"{}\",\"pid\":1,\"tid\":{},\"ts\":{:.3}",
+            self.track.name(),
+            self.track.id(),
+            us(self.timestamp)
+        );
+        let _ = match self.kind {
+            EventKind::Span { duration } => {
+                write!(out, ",\"ph\":\"X\",\"dur\":{:.3}", us(duration))
+            }
+            EventKind::Instant => write!(out, ",\"ph\":\"i\",\"s\":\"t\""),
+            EventKind::Counter { value } => {
+                write!(out, ",\"ph\":\"C\",\"args\":{{\"value\":{:.3}}}", value)
+            }
+        };
+        out.push_str("},\n");
+    }
+}
+
+/// Append the start of a trace to `out`: the opening bracket and the names of the tracks
+pub fn write_json_header(out: &mut String) {
+    out.push_str("[\n");
+    for track in Track::ALL {
+        let _ = writeln!(
+            out,
+            concat!(
+                "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},",
+                "\"args\":{{\"name\":\"{}\"}}}},"
+            ),
+            track.id(),
+            track.name()
+        );
+    }
+}
+
+fn write_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Ring buffer of [Event]s: once full, the oldest events are dropped
+#[derive(Debug, Clone)]
+pub struct TraceBuffer {
+    events:   VecDeque<Event>,
+    capacity: usize,
+    dropped:  u64,
+}
+
+impl TraceBuffer {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, event: Event) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(event);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize { self.events.len() }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.events.is_empty() }
+
+    /// Number of events dropped because the buffer was full, since the last [Self::take]
+    #[inline]
+    pub fn dropped(&self) -> u64 { self.dropped }
+
+    /// The events, from the oldest to the newest
+    pub fn iter(&self) -> impl Iterator<Item = &Event> { self.events.iter() }
+
+    /// Serialize the events to JSON lines (see [Event::write_json]) and empty the buffer. Dropped
+    /// events are reported with an instant event at `now`.
+    pub fn take(&mut self, now: u64, ticks_per_second: u32) -> String {
+        let mut out = String::new();
+        if self.dropped > 0 {
+            let name = alloc::format!("trace: {} events dropped", self.dropped);
+            Event {
+                name:      Cow::Owned(name),
+                track:     Track::Cpu,
+                kind:      EventKind::Instant,
+                timestamp: now,
+            }
+            .write_json(&mut out, ticks_per_second);
+            self.dropped = 0;
+        }
+        for event in self.events.drain(..) {
+            event.write_json(&mut out, ticks_per_second);
+        }
+        out
+    }
+}
+
+/// Split serialized trace data into the payloads of the USB packets sent by `trace::flush`:
+/// each one starts with [TRACE_MAGIC], followed by up to 8 KiB of data. `f` is called with each
+/// payload, reusing the same buffer.
+pub fn for_each_packet(data: &[u8], mut f: impl FnMut(&[u8])) {
+    let mut packet = Vec::with_capacity(USB_CHUNK + TRACE_MAGIC.len());
+    for chunk in data.chunks(USB_CHUNK) {
+        packet.clear();
+        packet.extend_from_slice(TRACE_MAGIC);
+        packet.extend_from_slice(chunk);
+        f(&packet);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &'static str, kind: EventKind, timestamp: u64) -> Event {
+        Event {
+            name: Cow::Borrowed(name),
+            track: Track::Rsp,
+            kind,
+            timestamp,
+        }
+    }
+
+    fn json(event: &Event) -> String {
+        let mut out = String::new();
+        // 1 tick per microsecond
+        event.write_json(&mut out, 1_000_000);
+        out
+    }
+
+    #[test]
+    fn phases() {
+        let span = event("draw", EventKind::Span { duration: 250 }, 1000);
+        assert_eq!(
+            json(&span),
+            concat!(
+                "{\"name\":\"draw\",\"cat\":\"RSP\",\"pid\":1,\"tid\":1,\"ts\":1000.000,",
+                "\"ph\":\"X\",\"dur\":250.000},\n"
+            )
+        );
+        let instant = json(&event("sync", EventKind::Instant, 0));
+        assert!(instant.ends_with(",\"ts\":0.000,\"ph\":\"i\",\"s\":\"t\"},\n"));
+        let counter = json(&event("busy", EventKind::Counter { value: 12.5 }, 0));
+        assert!(counter.ends_with(",\"ph\":\"C\",\"args\":{\"value\":12.500}},\n"));
+
+        // timestamps are converted to microseconds
+        let mut out = String::new();
+        event("x", EventKind::Instant, 93_750).write_json(&mut out, 46_875_000);
+        assert!(out.contains("\"ts\":2000.000"));
+    }
+
+    #[test]
+    fn escaping() {
+        let mut out = String::new();
+        write_json_str(&mut out, "a\"b\\c\nd\te\u{1}é");
+        assert_eq!(out, "\"a\\\"b\\\\c\\nd\\u0009e\\u0001é\"");
+    }
+
+    #[test]
+    fn header() {
+        let mut out = String::new();
+        write_json_header(&mut out);
+        assert!(out.starts_with("[\n"));
+        assert_eq!(out.lines().count(), 1 + Track::ALL.len());
+        assert!(out.contains("\"tid\":2,\"args\":{\"name\":\"RDP\"}},\n"));
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let mut buffer = TraceBuffer::new(2);
+        for (i, name) in ["a", "b", "c"].into_iter().enumerate() {
+            buffer.push(event(name, EventKind::Instant, i as u64));
+        }
+        assert_eq!((buffer.len(), buffer.dropped()), (2, 1));
+        let names: Vec<_> = buffer.iter().map(|e| e.name.as_ref()).collect();
+        assert_eq!(names, ["b", "c"]);
+
+        let out = buffer.take(5, 1_000_000);
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("{\"name\":\"trace: 1 events dropped\",\"cat\":\"CPU\""));
+        assert!(lines[0].contains("\"ts\":5.000"));
+        assert!(lines[1].starts_with("{\"name\":\"b\""));
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.dropped(), 0);
+        // nothing more to report
+        assert_eq!(buffer.take(6, 1_000_000), "");
+    }
+
+    #[test]
+    fn packets() {
+        let data: Vec<u8> = (0..USB_CHUNK * 2 + 10).map(|i| i as u8).collect();
+        let mut packets = Vec::new();
+        for_each_packet(&data, |p| packets.push(p.to_vec()));
+        assert_eq!(packets.len(), 3);
+        assert!(packets.iter().all(|p| p.starts_with(TRACE_MAGIC)));
+        assert_eq!(packets[2].len(), TRACE_MAGIC.len() + 10);
+        let payload: Vec<u8> = packets
+            .iter()
+            .flat_map(|p| p[4..].iter().copied())
+            .collect();
+        assert_eq!(payload, data);
+
+        for_each_packet(&[], |_| panic!("no packet for no data"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Timeline tracing: records CPU spans, RSP overlay timings and RDP sync points into a ring
+//! buffer, and streams them in the Chrome trace-event JSON format, to be opened in a timeline
+//! viewer such as Perfetto or `chrome://tracing`.
+//!
+//! ```rust
+//! trace::init(4096, trace::Target::Usb);
+//! rspq::profile_start();
+//! loop {
+//!     {
+//!         let _span = trace::span("update");
+//!         world.update();
+//!     }
+//!     let _span = trace::span("draw");
+//!     draw_frame();
+//!     trace::rdp_sync("frame done");
+//!     rspq::profile_next_frame();
+//!     trace::record_profile(&rspq::profile_get_data());
+//!     rspq::profile_reset();
+//!     trace::flush()?;
+//! }
+//! ```
+//!
+//! With [Target::Usb], each chunk is sent as a [DataType::RawBinary](usb::DataType::RawBinary)
+//! packet starting with [TRACE_MAGIC]. Use `libdragon_build::trace` (or its `n64trace` tool)
+//! on a dump of the USB stream to get a `.json` file. With [Target::File] (e.g. on the SD card,
+//! see [debug::init_sdfs]) the file is already a trace in the JSON array format, which viewers
+//! accept without the closing bracket.
+//!
+//! The timestamps are in microseconds since [init], measured with [ticks::get].
+
+use alloc::borrow::Cow;
+
+use crate::*;
+
+mod format;
+pub use format::{
+    for_each_packet, write_json_header, Event, EventKind, TraceBuffer, Track, TRACE_MAGIC,
+};
+
+/// Where [flush] sends the trace
+pub enum Target {
+    /// USB packets, see the [module documentation](self)
+    Usb,
+    /// A file on a writable filesystem
+    File(dfs::File),
+}
+
+struct Tracer {
+    buffer:       TraceBuffer,
+    target:       Target,
+    start:        u64,
+    last_profile: u64,
+    header_sent:  bool,
+}
+
+static mut TRACER: Option<Tracer> = None;
+
+// Run `f` on the tracer, if tracing is enabled, with interrupts disabled: events are also
+// recorded from the RDP interrupt.
+fn with_tracer<R>(f: impl FnOnce(&mut Tracer) -> R) -> Option<R> {
+    interrupts::disable();
+    let result = unsafe { (*core::ptr::addr_of_mut!(TRACER)).as_mut().map(f) };
+    interrupts::enable();
+    result
+}
+
+/// Start tracing into a ring buffer of `capacity` events, flushed to `target`.
+///
+/// Rust: calling it again restarts the trace, discarding the buffered events.
+pub fn init(capacity: usize, target: Target) {
+    let start = ticks::get();
+    let tracer = Tracer {
+        buffer: TraceBuffer::new(capacity),
+        target,
+        start,
+        last_profile: 0,
+        header_sent: false,
+    };
+    interrupts::disable();
+    unsafe {
+        TRACER = Some(tracer);
+    }
+    interrupts::enable();
+}
+
+/// Flush the remaining events and stop tracing
+pub fn close() -> Result<()> {
+    let result = flush();
+    interrupts::disable();
+    unsafe {
+        TRACER = None;
+    }
+    interrupts::enable();
+    result
+}
+
+/// Returns true between [init] and [close]
+pub fn is_enabled() -> bool { with_tracer(|_| ()).is_some() }
+
+// Ticks since the start of the trace
+fn now(tracer: &Tracer) -> u64 { ticks::get().saturating_sub(tracer.start) }
+
+/// Record an event. Does nothing if tracing is not enabled.
+pub fn record(name: impl Into<Cow<'static, str>>, track: Track, kind: EventKind) {
+    let name = name.into();
+    with_tracer(|tracer| {
+        let timestamp = now(tracer);
+        tracer.buffer.push(Event {
+            name,
+            track,
+            kind,
+            timestamp,
+        });
+    });
+}
+
+/// Record a point in time on the CPU track
+pub fn instant(name: &'static str) { record(name, Track::Cpu, EventKind::Instant); }
+
+/// Record the value of a counter
+pub fn counter(name: &'static str, value: f32) {
+    record(name, Track::Cpu, EventKind::Counter { value });
+}
+
+/// A CPU span, recorded when dropped. See [span].
+pub struct Span {
+    name:  &'static str,
+    start: u64,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let end = ticks::get();
+        let (name, start) = (self.name, self.start);
+        with_tracer(|tracer| {
+            let timestamp = start.saturating_sub(tracer.start);
+            tracer.buffer.push(Event {
+                name: Cow::Borrowed(name),
+                track: Track::Cpu,
+                kind: EventKind::Span {
+                    duration: end.saturating_sub(start),
+                },
+                timestamp,
+            });
+        });
+    }
+}
+
+/// Start a CPU span, which lasts until the returned guard is dropped
+#[must_use = "the span ends when the guard is dropped"]
+pub fn span(name: &'static str) -> Span {
+    Span {
+        name,
+        start: ticks::get(),
+    }
+}
+
+/// Run `f` inside a CPU span
+pub fn scope<R>(name: &'static str, f: impl FnOnce() -> R) -> R {
+    let _span = span(name);
+    f()
+}
+
+/// Record on the RDP track the time when the RDP reaches this point of the command stream.
+///
+/// Rust: this enqueues a [rdpq::sync_full], whose callback records the event from the RDP
+/// interrupt. Like any full sync, it stalls the RDP pipeline for a moment.
+pub fn rdp_sync(name: &'static str) {
+    if !is_enabled() {
+        return;
+    }
+    rdpq::sync_full(Some(Box::new(move || {
+        record(name, Track::Rdp, EventKind::Instant)
+    })));
+}
+
+/// Record the RSP overlay timings of `data`, which covers the time since the previous call
+/// (or since [init]). The profiler only records totals, so the overlays are laid out one after
+/// the other on the RSP track: their durations are exact, their positions are not. Also
+/// records the RSP and RDP busy percentages as counters.
+pub fn record_profile(data: &rspq::ProfileData) {
+    let summary = debug::hud::ProfileSummary::from_data(data);
+    with_tracer(|tracer| {
+        let end = now(tracer);
+        let interval = end.saturating_sub(tracer.last_profile);
+        let mut timestamp = tracer.last_profile;
+        tracer.last_profile = end;
+        for slot in summary.slots.iter().filter(|slot| !slot.control) {
+            let duration = (slot.busy as f64 * interval as f64) as u64;
+            if duration == 0 {
+                continue;
+            }
+            tracer.buffer.push(Event {
+                name: Cow::Owned(slot.name.clone()),
+                track: Track::Rsp,
+                kind: EventKind::Span { duration },
+                timestamp,
+            });
+            timestamp += duration;
+        }
+        for (name, track, busy) in [
+            ("RSP busy %", Track::Rsp, summary.rsp_busy),
+            ("RDP busy %", Track::Rdp, summary.rdp_busy),
+        ] {
+            tracer.buffer.push(Event {
+                name: Cow::Borrowed(name),
+                track,
+                kind: EventKind::Counter {
+                    value: busy * 100.0,
+                },
+                timestamp: end,
+            });
+        }
+    });
+}
+
+/// Send the buffered events to the [Target] and empty the buffer. Does nothing if tracing is
+/// not enabled.
+pub fn flush() -> Result<()> {
+    // serialize with interrupts disabled, then write without holding them
+    let Some((json, usb)) = with_tracer(|tracer| {
+        let mut json = String::new();
+        if !tracer.header_sent {
+            write_json_header(&mut json);
+            tracer.header_sent = true;
+        }
+        json.push_str(&tracer.buffer.take(now(tracer), ticks::per_second()));
+        (json, matches!(tracer.target, Target::Usb))
+    }) else {
+        return Ok(());
+    };
+    if json.is_empty() {
+        return Ok(());
+    }
+
+    if usb {
+        for_each_packet(json.as_bytes(), |packet| {
+            usb::write_bytes(usb::DataType::RawBinary, packet)
+        });
+        return Ok(());
+    }
+    // take the file out while writing, so that interrupts are not disabled during the I/O
+    let target = with_tracer(|tracer| core::mem::replace(&mut tracer.target, Target::Usb));
+    let Some(Target::File(mut file)) = target else {
+        return Ok(());
+    };
+    let result = {
+        use embedded_io::Write;
+        file.write_all(json.as_bytes())
+            .map_err(|error| LibDragonError::DfsError { error })
+    };
+    with_tracer(|tracer| tracer.target = Target::File(file));
+    result
+}
@@ -35,6 +35,9 @@ pub enum DataType {
     Screenshot,
     Heartbeat,
     RdbPacket,
+    /// Rust: an encoded image file sent by [debug::screenshot]. See
+    /// [debug::DATATYPE_SCREENSHOT_FILE].
+    ScreenshotFile,
 }
 
 impl From<u32> for DataType {
@@ -46,6 +49,7 @@ impl From<u32> for DataType {
             libdragon_sys::DATATYPE_SCREENSHOT => DataType::Screenshot,
             libdragon_sys::DATATYPE_HEARTBEAT => DataType::Heartbeat,
             libdragon_sys::DATATYPE_RDBPACKET => DataType::RdbPacket,
+            debug::DATATYPE_SCREENSHOT_FILE => DataType::ScreenshotFile,
             _ => panic!("invalid value"),
         }
     }
@@ -60,6 +64,7 @@ impl From<DataType> for u32 {
             DataType::Screenshot => libdragon_sys::DATATYPE_SCREENSHOT,
             DataType::Heartbeat => libdragon_sys::DATATYPE_HEARTBEAT,
             DataType::RdbPacket => libdragon_sys::DATATYPE_RDBPACKET,
+            DataType::ScreenshotFile => debug::DATATYPE_SCREENSHOT_FILE,
         }
     }
 }
@@ -94,6 +99,23 @@ pub fn write<T>(datatype: DataType, t: &T) {
     }
 }
 
+/// Writes a slice of bytes to the USB
+///
+/// Rust: unlike [write], the size sent is the length of the slice rather than the size of `T`.
+///
+/// See [`usb_write`](libdragon_sys::usb_write) for details.
+#[inline]
+pub fn write_bytes(datatype: DataType, data: &[u8]) {
+    let datatype: u32 = datatype.into();
+    unsafe {
+        libdragon_sys::usb_write(
+            datatype as i32,
+            data.as_ptr() as *const _,
+            data.len() as i32,
+        );
+    }
+}
+
 /// Returns the header of data being received via USB
 ///
 /// See [`usb_poll`](libdragon_sys::usb_poll) for details.
@@ -0,0 +1,101 @@
+//! Zoom and alignment of the frames drawn by the FMV blitter.
+//!
+//! This module only depends on `core`. It is shared with `libdragon-build`, so the layout math
+//! can be tested on a development host.
+
+/// YUV blitter zoom configuration
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Zoom {
+    /// Zoom the frame, keeping the aspect ratio
+    KeepAspect,
+    /// Zoom the frame, irrespective of aspect ratio
+    Full,
+    /// Do not zoom the frame to fit the output buffer
+    None,
+}
+
+/// YUV blitter output buffer alignment
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Align {
+    /// Align to the center of the output buffer
+    Center,
+    /// Align to left/top of the output buffer
+    Min,
+    /// Align to right/bottom of the output buffer
+    Max,
+}
+
+/// Where a frame of `frame_width` x `frame_height` pixels is drawn in an output buffer of
+/// `out_width` x `out_height` with `zoom` and alignment, as `[x, y, width, height]`. The rest
+/// of the buffer is filled with the background color.
+pub fn frame_rect(
+    frame_width: u32,
+    frame_height: u32,
+    out_width: u32,
+    out_height: u32,
+    zoom: Zoom,
+    halign: Align,
+    valign: Align,
+) -> [u32; 4] {
+    let (width, height) = match zoom {
+        Zoom::Full => (out_width, out_height),
+        Zoom::None => (frame_width.min(out_width), frame_height.min(out_height)),
+        Zoom::KeepAspect => {
+            let scale = (out_width as f32 / frame_width as f32)
+                .min(out_height as f32 / frame_height as f32);
+            (
+                ((frame_width as f32 * scale) as u32).clamp(1, out_width),
+                ((frame_height as f32 * scale) as u32).clamp(1, out_height),
+            )
+        }
+    };
+    let align = |align: Align, free: u32| match align {
+        Align::Min => 0,
+        Align::Center => free / 2,
+        Align::Max => free,
+    };
+    [
+        align(halign, out_width - width),
+        align(valign, out_height - height),
+        width,
+        height,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Align::{Center, Max, Min};
+
+    #[test]
+    fn keep_aspect() {
+        // 4:3 into 16:9: pillarbox
+        let rect = frame_rect(320, 240, 640, 360, Zoom::KeepAspect, Center, Center);
+        assert_eq!(rect, [80, 0, 480, 360]);
+        // 16:9 into 4:3: letterbox
+        let rect = frame_rect(320, 180, 320, 240, Zoom::KeepAspect, Center, Center);
+        assert_eq!(rect, [0, 30, 320, 180]);
+        let rect = frame_rect(320, 180, 320, 240, Zoom::KeepAspect, Min, Max);
+        assert_eq!(rect, [0, 60, 320, 180]);
+        // a frame smaller than the buffer is zoomed in
+        let rect = frame_rect(160, 90, 320, 240, Zoom::KeepAspect, Center, Min);
+        assert_eq!(rect, [0, 0, 320, 180]);
+    }
+
+    #[test]
+    fn no_zoom() {
+        let rect = frame_rect(160, 120, 320, 240, Zoom::None, Center, Center);
+        assert_eq!(rect, [80, 60, 160, 120]);
+        let rect = frame_rect(160, 120, 320, 240, Zoom::None, Max, Min);
+        assert_eq!(rect, [160, 0, 160, 120]);
+        // a frame larger than the buffer is cropped
+        let rect = frame_rect(640, 120, 320, 240, Zoom::None, Center, Center);
+        assert_eq!(rect, [0, 60, 320, 120]);
+    }
+
+    #[test]
+    fn full() {
+        let rect = frame_rect(160, 90, 320, 240, Zoom::Full, Max, Max);
+        assert_eq!(rect, [0, 0, 320, 240]);
+    }
+}
@@ -2,6 +2,9 @@ use crate::*;
 
 use surface::Surface;
 
+mod layout;
+pub use layout::{frame_rect, Align, Zoom};
+
 /// Initialize the YUV conversion library
 #[inline]
 pub fn init() {
@@ -136,16 +139,6 @@ pub trait ColorspaceImpl {
 
 impl ColorspaceImpl for Colorspace {}
 
-/// YUV blitter zoom configuration
-pub enum Zoom {
-    /// Zoom the frame, keeping the aspect ratio
-    KeepAspect,
-    /// Zoom the frame, irrespective of aspect ratio
-    Full,
-    /// Do not zoom the frame to fit the output buffer
-    None,
-}
-
 impl From<Zoom> for u32 {
     fn from(v: Zoom) -> Self {
         match v {
@@ -156,16 +149,6 @@ impl From<Zoom> for u32 {
     }
 }
 
-/// YUV blitter output buffer alignment
-pub enum Align {
-    /// Align to the center of the output buffer
-    Center,
-    /// Align to left/top of the output buffer
-    Min,
-    /// Align to right/bottom of the output buffer
-    Max,
-}
-
 impl From<Align> for u32 {
     fn from(v: Align) -> Self {
         match v {
@@ -247,6 +230,12 @@ pub fn blitter_new_fmv(
 }
 
 impl Blitter<'_> {
+    // Give up the ownership of the blitter, to hand it over to libdragon
+    pub(crate) fn into_raw(self) -> libdragon_sys::yuv_blitter_t {
+        let this = core::mem::ManuallyDrop::new(self);
+        unsafe { core::ptr::read(&this.b) }
+    }
+
     /// Perform a YUV blit using a blitter, with the specified surfaces
     ///
     /// See [`yuv_blitter_run`](libdragon_sys::yuv_blitter_run) for details.